@@ -5,4 +5,7 @@
 
 pub mod normalize;
 
-pub use normalize::{CacheManager, CacheConfig, CacheEntry, CacheStats};
+pub use normalize::{
+    CacheConfig, CacheEntry, CacheEntryInfo, CacheManager, CachePin, CacheStats, EvictionPolicy,
+    PrewarmResult, PrewarmStatus, VerifyReport,
+};
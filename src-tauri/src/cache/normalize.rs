@@ -3,14 +3,21 @@
 //! Caches normalized versions of media files to avoid repeated transcoding.
 //! Files are identified by a hash of (source path + normalize config).
 
+use crate::db::schema::AppSettings;
 use crate::merge::normalize::{normalize_to_file, NormalizeConfig};
+use crate::scanner::metadata::{probe_file, MediaMetadata, ProbeError};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use sqlx::sqlite::SqlitePool;
 use sqlx::FromRow;
+use std::collections::HashMap;
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use thiserror::Error;
 use tokio::fs;
+use tokio::sync::Semaphore;
 
 #[derive(Debug, Error)]
 pub enum CacheError {
@@ -22,6 +29,40 @@ pub enum CacheError {
     Normalize(#[from] crate::merge::normalize::NormalizeError),
     #[error("Cache directory not found")]
     NoCacheDir,
+    #[error("Source file not found: {0}")]
+    SourceNotFound(String),
+    #[error("FFprobe not found")]
+    ProbeUnavailable,
+    #[error("Cache entry not found: {0}")]
+    EntryNotFound(String),
+    #[error("Cache entry is in use: {0}")]
+    InUse(String),
+}
+
+/// Which entries go first when the cache is over its size limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictionPolicy {
+    /// Least recently accessed first
+    #[default]
+    Lru,
+    /// Biggest files first, least recently accessed among equals
+    LargestFirst,
+    /// Lowest value first, where value weighs what re-normalizing would
+    /// cost (size) by how often the entry is used, decaying with idle time
+    LeastValuable,
+}
+
+impl EvictionPolicy {
+    /// Policy named by the `cache_eviction_policy` setting
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "lru" => Some(Self::Lru),
+            "largest_first" => Some(Self::LargestFirst),
+            "least_valuable" => Some(Self::LeastValuable),
+            _ => None,
+        }
+    }
 }
 
 /// Cache configuration
@@ -33,6 +74,30 @@ pub struct CacheConfig {
     pub max_age_days: u32,
     /// Warn when cache usage exceeds this percentage
     pub warn_threshold_percent: u32,
+    /// Eviction order used by `enforce_size_limit`
+    #[serde(default)]
+    pub eviction_policy: EvictionPolicy,
+    /// Normalize jobs allowed to run at once; further requests wait
+    #[serde(default = "default_max_concurrent_jobs")]
+    pub max_concurrent_jobs: usize,
+}
+
+fn default_max_concurrent_jobs() -> usize {
+    8
+}
+
+impl CacheConfig {
+    /// Limits from app settings; normalizing competes with CPU transcodes
+    /// for the same cores, so it shares their limit
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            max_size_bytes: settings.cache_max_size_gb as u64 * 1024 * 1024 * 1024,
+            max_age_days: settings.cache_max_age_days,
+            max_concurrent_jobs: settings.max_transcode_cpu as usize,
+            eviction_policy: EvictionPolicy::parse(&settings.cache_eviction_policy).unwrap_or_default(),
+            ..Default::default()
+        }
+    }
 }
 
 impl Default for CacheConfig {
@@ -41,6 +106,8 @@ impl Default for CacheConfig {
             max_size_bytes: 50 * 1024 * 1024 * 1024, // 50 GB
             max_age_days: 30,
             warn_threshold_percent: 80,
+            eviction_policy: EvictionPolicy::Lru,
+            max_concurrent_jobs: default_max_concurrent_jobs(),
         }
     }
 }
@@ -56,13 +123,102 @@ pub struct CacheEntry {
     pub normalize_config: String,
     pub created_at: String,
     pub last_accessed: String,
+    /// Cache hits since the entry was created
+    pub access_count: i64,
+}
+
+/// Cache entry joined with its source's filename
+#[derive(FromRow)]
+struct CacheEntryRow {
+    #[sqlx(flatten)]
+    entry: CacheEntry,
+    filename: Option<String>,
 }
 
+/// Cache entry with details of its source, for display
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntryInfo {
+    pub id: String,
+    pub source_file_id: String,
+    /// Source filename, if the source is still in the library
+    pub filename: Option<String>,
+    /// Preset label derived from the stored normalize config
+    pub preset_label: String,
+    pub cache_path: String,
+    pub size_bytes: i64,
+    pub created_at: String,
+    pub last_accessed: String,
+    pub access_count: i64,
+    pub in_use: bool,
+}
+
+/// Outcome of pre-warming one normalize config
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrewarmStatus {
+    /// Normalized and recorded now
+    Cached,
+    /// Entry already existed; nothing done
+    AlreadyCached,
+    /// Normalization failed (see `error`)
+    Failed,
+}
+
+/// Per-config result of `CacheManager::prewarm`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrewarmResult {
+    pub cache_key: String,
+    pub status: PrewarmStatus,
+    pub error: Option<String>,
+}
+
+/// Cache files currently being read, with a reader count per path
+type InUseSet = Arc<std::sync::Mutex<HashMap<PathBuf, usize>>>;
+
+/// Keeps a cache file from being removed by cleanup while held
+pub struct CachePin {
+    path: PathBuf,
+    in_use: InUseSet,
+}
+
+impl CachePin {
+    /// The pinned cache file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for CachePin {
+    fn drop(&mut self) {
+        // A panic elsewhere must not leave the file pinned or panic again here
+        let mut in_use = self.in_use.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = in_use.get_mut(&self.path) {
+            *count -= 1;
+            if *count == 0 {
+                in_use.remove(&self.path);
+            }
+        }
+    }
+}
+
+/// One lock per cache key being produced, so concurrent requests for the
+/// same entry normalize it once
+type KeyLocks = Arc<std::sync::Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>;
+
 /// Cache manager handles pre-normalized file storage
+#[derive(Clone)]
 pub struct CacheManager {
     cache_dir: PathBuf,
     db: SqlitePool,
     config: CacheConfig,
+    in_use: InUseSet,
+    /// `get_or_normalize` lookups served from cache since startup
+    hits: Arc<AtomicU64>,
+    /// `get_or_normalize` lookups that had to normalize since startup
+    misses: Arc<AtomicU64>,
+    /// Slots for normalize jobs, `max_concurrent_jobs` in total
+    jobs: Arc<Semaphore>,
+    key_locks: KeyLocks,
 }
 
 impl CacheManager {
@@ -71,13 +227,48 @@ impl CacheManager {
         Self {
             cache_dir,
             db,
-            config,
+            config: config.clone(),
+            in_use: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+            jobs: Arc::new(Semaphore::new(config.max_concurrent_jobs.max(1))),
+            key_locks: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Apply new limits. Size and age take effect at the next cleanup;
+    /// the job limit is shared with every clone of this manager.
+    pub fn update_config(&mut self, config: CacheConfig) {
+        let old = self.config.max_concurrent_jobs.max(1);
+        let new = config.max_concurrent_jobs.max(1);
+        if new > old {
+            self.jobs.add_permits(new - old);
+        } else if new < old {
+            // Running jobs keep their slots; take them back as they finish
+            let jobs = self.jobs.clone();
+            let excess = (old - new) as u32;
+            tokio::spawn(async move {
+                if let Ok(permits) = jobs.acquire_many(excess).await {
+                    permits.forget();
+                }
+            });
         }
+        self.config = config;
     }
 
-    /// Initialize cache directory
+    /// Initialize cache directory, clearing output of normalize jobs that
+    /// were interrupted
     pub async fn init(&self) -> Result<(), CacheError> {
         fs::create_dir_all(&self.cache_dir).await?;
+
+        let mut dir = fs::read_dir(&self.cache_dir).await?;
+        while let Some(file) = dir.next_entry().await? {
+            let path = file.path();
+            if path.extension().is_some_and(|ext| ext == "tmp") {
+                println!("[Cache] Removing unfinished {}", path.display());
+                let _ = fs::remove_file(&path).await;
+            }
+        }
         Ok(())
     }
 
@@ -88,36 +279,270 @@ impl CacheManager {
         source_path: &Path,
         config: &NormalizeConfig,
     ) -> Result<PathBuf, CacheError> {
+        let normalize = |source: PathBuf, config: NormalizeConfig, output: PathBuf| async move {
+            // FFmpeg runs to completion here; keep it off the async workers
+            tokio::task::spawn_blocking(move || normalize_to_file(&source, &config, &output))
+                .await
+                .map_err(std::io::Error::other)??;
+            Ok(())
+        };
+        self.get_or_normalize_with(source_file_id, source_path, config, normalize)
+            .await
+    }
+
+    /// `get_or_normalize`, pinned so cleanup leaves the file alone until the
+    /// pin is dropped. Streams hold one for as long as FFmpeg reads the file.
+    pub async fn checkout(
+        &self,
+        source_file_id: &str,
+        source_path: &Path,
+        config: &NormalizeConfig,
+    ) -> Result<CachePin, CacheError> {
+        let cache_path = self.get_or_normalize(source_file_id, source_path, config).await?;
+        let pin = self.pin(&cache_path);
+        // Cleanup may have removed it before the pin was taken; the path is
+        // fixed by the key, so producing it again lands under the same pin
+        if !cache_path.exists() {
+            self.get_or_normalize(source_file_id, source_path, config).await?;
+        }
+        Ok(pin)
+    }
+
+    /// `get_or_normalize` with the normalize step supplied by the caller
+    async fn get_or_normalize_with<F, Fut>(
+        &self,
+        source_file_id: &str,
+        source_path: &Path,
+        config: &NormalizeConfig,
+        normalize: F,
+    ) -> Result<PathBuf, CacheError>
+    where
+        F: FnOnce(PathBuf, NormalizeConfig, PathBuf) -> Fut,
+        Fut: Future<Output = Result<(), CacheError>>,
+    {
         let cache_key = compute_cache_key(source_path, config);
 
-        // Check if cached
-        if let Some(entry) = self.get_cached(&cache_key).await? {
-            // Update last accessed time
-            self.touch_entry(&entry.id).await?;
-            return Ok(PathBuf::from(&entry.cache_path));
+        if let Some(path) = self.lookup(&cache_key).await? {
+            return Ok(path);
         }
 
-        // Not cached - normalize
-        let cache_path = self.cache_dir.join(format!("{}.ts", cache_key));
-        normalize_to_file(source_path, config, &cache_path)?;
+        // Requests for the same key wait here, then find the entry cached
+        let lock = self.key_lock(&cache_key);
+        let result = {
+            let _guard = lock.lock().await;
+            match self.lookup(&cache_key).await {
+                Ok(Some(path)) => Ok(path),
+                Ok(None) => {
+                    self.normalize_entry(source_file_id, source_path, config, &cache_key, normalize)
+                        .await
+                }
+                Err(e) => Err(e),
+            }
+        };
+        self.release_key_lock(&cache_key, lock);
+        result
+    }
 
-        // Get file size
-        let metadata = fs::metadata(&cache_path).await?;
-        let size_bytes = metadata.len() as i64;
+    /// Cached file for a key, counted as a hit and touched
+    async fn lookup(&self, cache_key: &str) -> Result<Option<PathBuf>, CacheError> {
+        let Some(entry) = self.get_cached(cache_key).await? else {
+            return Ok(None);
+        };
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        // Update last accessed time
+        self.touch_entry(&entry.id).await?;
+        Ok(Some(PathBuf::from(&entry.cache_path)))
+    }
 
-        // Record in database
-        self.record_cache(
-            source_file_id,
-            &cache_key,
-            &cache_path,
-            size_bytes,
-            config,
-        )
-        .await?;
+    /// Normalize into the cache once a job slot is free, and record the entry
+    async fn normalize_entry<F, Fut>(
+        &self,
+        source_file_id: &str,
+        source_path: &Path,
+        config: &NormalizeConfig,
+        cache_key: &str,
+        normalize: F,
+    ) -> Result<PathBuf, CacheError>
+    where
+        F: FnOnce(PathBuf, NormalizeConfig, PathBuf) -> Fut,
+        Fut: Future<Output = Result<(), CacheError>>,
+    {
+        let _permit = self.jobs.acquire().await.expect("job semaphore is never closed");
 
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let cache_path = self.cache_dir.join(format!("{}.ts", cache_key));
+        let tmp_path = cache_path.with_extension("tmp");
+        if let Err(e) = normalize(source_path.to_path_buf(), config.clone(), tmp_path.clone()).await {
+            let _ = fs::remove_file(&tmp_path).await;
+            return Err(e);
+        }
+
+        self.finish_entry(source_file_id, cache_key, &tmp_path, &cache_path, config)
+            .await?;
         Ok(cache_path)
     }
 
+    /// Move finished normalize output from `tmp_path` into place, then
+    /// record it. A crash before the rename leaves only a `.tmp` file,
+    /// which is never served and is swept by `init`.
+    async fn finish_entry(
+        &self,
+        source_file_id: &str,
+        cache_key: &str,
+        tmp_path: &Path,
+        cache_path: &Path,
+        config: &NormalizeConfig,
+    ) -> Result<(), CacheError> {
+        let finished = async {
+            fs::rename(tmp_path, cache_path).await?;
+            let size_bytes = fs::metadata(cache_path).await?.len() as i64;
+            self.record_cache(source_file_id, cache_key, cache_path, size_bytes, config)
+                .await
+        }
+        .await;
+
+        if finished.is_err() {
+            let _ = fs::remove_file(tmp_path).await;
+            let _ = fs::remove_file(cache_path).await;
+        }
+        finished
+    }
+
+    /// Normalize a source file for each config ahead of time, skipping
+    /// configs that are already cached. Results are in `configs` order.
+    pub async fn prewarm(
+        &self,
+        source_file_id: &str,
+        configs: &[NormalizeConfig],
+    ) -> Result<Vec<PrewarmResult>, CacheError> {
+        let (source_path,): (String,) =
+            sqlx::query_as("SELECT path FROM media_files WHERE id = ?")
+                .bind(source_file_id)
+                .fetch_optional(&self.db)
+                .await?
+                .ok_or_else(|| CacheError::SourceNotFound(source_file_id.to_string()))?;
+        let source_path = PathBuf::from(source_path);
+
+        let mut results = Vec::with_capacity(configs.len());
+
+        for config in configs {
+            let cache_key = compute_cache_key(&source_path, config);
+            let lock = self.key_lock(&cache_key);
+            let result = {
+                let _guard = lock.lock().await;
+                self.prewarm_config(source_file_id, &source_path, config, cache_key.clone())
+                    .await
+            };
+            self.release_key_lock(&cache_key, lock);
+            results.push(result?);
+        }
+
+        Ok(results)
+    }
+
+    /// Pre-warm one config; the caller holds its key lock
+    async fn prewarm_config(
+        &self,
+        source_file_id: &str,
+        source_path: &Path,
+        config: &NormalizeConfig,
+        cache_key: String,
+    ) -> Result<PrewarmResult, CacheError> {
+        if self.get_cached(&cache_key).await?.is_some() {
+            return Ok(PrewarmResult {
+                cache_key,
+                status: PrewarmStatus::AlreadyCached,
+                error: None,
+            });
+        }
+
+        let cache_path = self.cache_dir.join(format!("{}.ts", cache_key));
+        let tmp_path = cache_path.with_extension("tmp");
+        let normalized = {
+            let _permit = self.jobs.acquire().await.expect("job semaphore is never closed");
+            let source_path = source_path.to_path_buf();
+            let config = config.clone();
+            let tmp_path = tmp_path.clone();
+            // FFmpeg runs to completion here; keep it off the async workers
+            tokio::task::spawn_blocking(move || {
+                normalize_to_file(&source_path, &config, &tmp_path)
+            })
+            .await
+        };
+
+        let outcome = match normalized {
+            Ok(Ok(())) => self
+                .finish_entry(source_file_id, &cache_key, &tmp_path, &cache_path, config)
+                .await
+                .map_err(|e| e.to_string()),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        match outcome {
+            Ok(()) => {
+                println!("[Cache] Pre-warmed {} ({})", source_path.display(), cache_key);
+                Ok(PrewarmResult {
+                    cache_key,
+                    status: PrewarmStatus::Cached,
+                    error: None,
+                })
+            }
+            Err(error) => {
+                eprintln!("[Cache] Pre-warm failed for {}: {}", source_path.display(), error);
+                let _ = fs::remove_file(&tmp_path).await;
+                Ok(PrewarmResult {
+                    cache_key,
+                    status: PrewarmStatus::Failed,
+                    error: Some(error),
+                })
+            }
+        }
+    }
+
+    /// Lock for producing one cache key, shared by everyone asking for it
+    fn key_lock(&self, cache_key: &str) -> Arc<tokio::sync::Mutex<()>> {
+        self.key_locks
+            .lock()
+            .unwrap()
+            .entry(cache_key.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Forget a key's lock once nobody else is waiting on it
+    fn release_key_lock(&self, cache_key: &str, lock: Arc<tokio::sync::Mutex<()>>) {
+        let mut locks = self.key_locks.lock().unwrap();
+        // The map and `lock` itself are the only references left
+        if Arc::strong_count(&lock) <= 2 {
+            locks.remove(cache_key);
+        }
+    }
+
+    /// Protect a cache file from cleanup until the pin is dropped.
+    /// Hold one for as long as the file is being streamed.
+    pub fn pin(&self, cache_path: &Path) -> CachePin {
+        *self
+            .in_use
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(cache_path.to_path_buf())
+            .or_insert(0) += 1;
+
+        CachePin {
+            path: cache_path.to_path_buf(),
+            in_use: self.in_use.clone(),
+        }
+    }
+
+    /// Whether a cache file is pinned
+    fn is_in_use(&self, cache_path: &str) -> bool {
+        self.in_use
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(Path::new(cache_path))
+    }
+
     /// Get cached entry by key
     async fn get_cached(&self, cache_key: &str) -> Result<Option<CacheEntry>, CacheError> {
         let entry = sqlx::query_as::<_, CacheEntry>(
@@ -172,10 +597,10 @@ impl CacheManager {
         Ok(())
     }
 
-    /// Update last accessed time for an entry
+    /// Update last accessed time and access count for an entry
     async fn touch_entry(&self, id: &str) -> Result<(), CacheError> {
         let now = chrono::Utc::now().to_rfc3339();
-        sqlx::query("UPDATE cache_files SET last_accessed = ? WHERE id = ?")
+        sqlx::query("UPDATE cache_files SET last_accessed = ?, access_count = access_count + 1 WHERE id = ?")
             .bind(&now)
             .bind(id)
             .execute(&self.db)
@@ -209,6 +634,9 @@ impl CacheManager {
         let mut files_removed = 0u32;
 
         for entry in old_entries {
+            if self.is_in_use(&entry.cache_path) {
+                continue;
+            }
             if let Ok(()) = fs::remove_file(&entry.cache_path).await {
                 freed_bytes += entry.size_bytes as u64;
                 files_removed += 1;
@@ -222,7 +650,7 @@ impl CacheManager {
         })
     }
 
-    /// Clear cache entries exceeding size limit (LRU)
+    /// Clear cache entries exceeding size limit, in the configured eviction order
     pub async fn enforce_size_limit(&self) -> Result<CacheCleanupResult, CacheError> {
         let current_size = self.get_total_size().await?;
 
@@ -236,12 +664,10 @@ impl CacheManager {
         let target_size = (self.config.max_size_bytes as f64 * 0.8) as u64; // Clean to 80%
         let mut to_free = current_size - target_size;
 
-        // Get entries ordered by last accessed (oldest first)
-        let entries = sqlx::query_as::<_, CacheEntry>(
-            "SELECT * FROM cache_files ORDER BY last_accessed ASC",
-        )
-        .fetch_all(&self.db)
-        .await?;
+        let mut entries = sqlx::query_as::<_, CacheEntry>("SELECT * FROM cache_files")
+            .fetch_all(&self.db)
+            .await?;
+        sort_for_eviction(&mut entries, self.config.eviction_policy, chrono::Utc::now());
 
         let mut freed_bytes = 0u64;
         let mut files_removed = 0u32;
@@ -250,6 +676,9 @@ impl CacheManager {
             if freed_bytes >= to_free {
                 break;
             }
+            if self.is_in_use(&entry.cache_path) {
+                continue;
+            }
 
             if let Ok(()) = fs::remove_file(&entry.cache_path).await {
                 freed_bytes += entry.size_bytes as u64;
@@ -264,6 +693,169 @@ impl CacheManager {
         })
     }
 
+    /// All cache entries with source details, most recently used first
+    pub async fn list_entries(&self) -> Result<Vec<CacheEntryInfo>, CacheError> {
+        let rows = sqlx::query_as::<_, CacheEntryRow>(
+            r#"
+            SELECT c.*, m.filename
+            FROM cache_files c
+            LEFT JOIN media_files m ON m.id = c.source_file_id
+            ORDER BY c.last_accessed DESC
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let entry = row.entry;
+                let preset_label =
+                    serde_json::from_str::<NormalizeConfig>(&entry.normalize_config)
+                        .map(|config| config.label())
+                        .unwrap_or_else(|_| "Unknown".to_string());
+
+                CacheEntryInfo {
+                    in_use: self.is_in_use(&entry.cache_path),
+                    id: entry.id,
+                    source_file_id: entry.source_file_id,
+                    filename: row.filename,
+                    preset_label,
+                    cache_path: entry.cache_path,
+                    size_bytes: entry.size_bytes,
+                    created_at: entry.created_at,
+                    last_accessed: entry.last_accessed,
+                    access_count: entry.access_count,
+                }
+            })
+            .collect())
+    }
+
+    /// Remove a single cache entry and its file
+    pub async fn delete_cache_entry(&self, id: &str) -> Result<CacheCleanupResult, CacheError> {
+        let entry = sqlx::query_as::<_, CacheEntry>("SELECT * FROM cache_files WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.db)
+            .await?
+            .ok_or_else(|| CacheError::EntryNotFound(id.to_string()))?;
+
+        if self.is_in_use(&entry.cache_path) {
+            return Err(CacheError::InUse(entry.cache_path));
+        }
+
+        let mut result = CacheCleanupResult {
+            freed_bytes: 0,
+            files_removed: 0,
+        };
+        if let Ok(()) = fs::remove_file(&entry.cache_path).await {
+            result.freed_bytes = entry.size_bytes as u64;
+            result.files_removed = 1;
+        }
+        self.delete_entry(&entry.id).await?;
+
+        Ok(result)
+    }
+
+    /// Age-based cleanup followed by the size limit, as one pass
+    pub async fn run_maintenance(&self) -> Result<CacheCleanupResult, CacheError> {
+        let aged = self.clear_old_cache().await?;
+        let sized = self.enforce_size_limit().await?;
+
+        Ok(CacheCleanupResult {
+            freed_bytes: aged.freed_bytes + sized.freed_bytes,
+            files_removed: aged.files_removed + sized.files_removed,
+        })
+    }
+
+    /// Check every cache file: it must exist, match its recorded size and
+    /// probe with a non-zero duration. Invalid entries are removed; files
+    /// currently pinned are left alone.
+    pub async fn verify(&self) -> Result<VerifyReport, CacheError> {
+        self.verify_with(|path| async move { probe_file(&path).await })
+            .await
+    }
+
+    /// `verify` with a pluggable probe
+    async fn verify_with<F, Fut>(&self, probe: F) -> Result<VerifyReport, CacheError>
+    where
+        F: Fn(PathBuf) -> Fut,
+        Fut: Future<Output = Result<MediaMetadata, ProbeError>>,
+    {
+        let entries = sqlx::query_as::<_, CacheEntry>("SELECT * FROM cache_files")
+            .fetch_all(&self.db)
+            .await?;
+
+        let mut report = VerifyReport::default();
+
+        for entry in entries {
+            if self.is_in_use(&entry.cache_path) {
+                report.skipped += 1;
+                continue;
+            }
+            report.checked += 1;
+
+            let path = PathBuf::from(&entry.cache_path);
+            if self.source_changed(&entry).await? {
+                eprintln!("[Cache] Removing stale cache file {} (source changed)", entry.cache_path);
+                if fs::remove_file(&path).await.is_ok() {
+                    report.freed_bytes += entry.size_bytes.max(0) as u64;
+                }
+                self.delete_entry(&entry.id).await?;
+                report.removed += 1;
+                report.stale += 1;
+                continue;
+            }
+
+            let size_ok = match fs::metadata(&path).await {
+                Ok(metadata) => metadata.len() > 0 && metadata.len() as i64 == entry.size_bytes,
+                Err(_) => false,
+            };
+
+            let valid = size_ok
+                && match probe(path.clone()).await {
+                    Ok(meta) => meta.duration_secs.unwrap_or(0.0) > 0.0,
+                    // Can't tell good from bad without ffprobe; don't guess
+                    Err(ProbeError::NotFound) => return Err(CacheError::ProbeUnavailable),
+                    Err(_) => false,
+                };
+
+            if valid {
+                report.valid += 1;
+                continue;
+            }
+
+            eprintln!("[Cache] Removing invalid cache file {}", entry.cache_path);
+            if fs::remove_file(&path).await.is_ok() {
+                report.freed_bytes += entry.size_bytes.max(0) as u64;
+            }
+            self.delete_entry(&entry.id).await?;
+            report.removed += 1;
+        }
+
+        Ok(report)
+    }
+
+    /// Whether the entry's source file was edited or replaced since it was cached.
+    /// Entries whose source is missing or whose config can't be read are left alone.
+    async fn source_changed(&self, entry: &CacheEntry) -> Result<bool, CacheError> {
+        let source: Option<(String,)> = sqlx::query_as("SELECT path FROM media_files WHERE id = ?")
+            .bind(&entry.source_file_id)
+            .fetch_optional(&self.db)
+            .await?;
+        let Some((source_path,)) = source else {
+            return Ok(false);
+        };
+        let source_path = PathBuf::from(source_path);
+        if !source_path.exists() {
+            return Ok(false);
+        }
+        let Ok(config) = serde_json::from_str::<NormalizeConfig>(&entry.normalize_config) else {
+            return Ok(false);
+        };
+
+        Ok(compute_cache_key(&source_path, &config) != entry.cache_key)
+    }
+
     /// Get total cache size in bytes
     pub async fn get_total_size(&self) -> Result<u64, CacheError> {
         let result: (i64,) = sqlx::query_as(
@@ -288,12 +880,24 @@ impl CacheManager {
             0
         };
 
+        let hit_count = self.hits.load(Ordering::Relaxed);
+        let miss_count = self.misses.load(Ordering::Relaxed);
+        let lookups = hit_count + miss_count;
+        let hit_ratio = if lookups > 0 {
+            hit_count as f64 / lookups as f64
+        } else {
+            0.0
+        };
+
         Ok(CacheStats {
             total_size_bytes: total_size,
             file_count: count.0 as u32,
             max_size_bytes: self.config.max_size_bytes,
             usage_percent,
             warning: usage_percent >= self.config.warn_threshold_percent,
+            hit_count,
+            miss_count,
+            hit_ratio,
         })
     }
 
@@ -307,6 +911,9 @@ impl CacheManager {
         let mut files_removed = 0u32;
 
         for entry in entries {
+            if self.is_in_use(&entry.cache_path) {
+                continue;
+            }
             if let Ok(()) = fs::remove_file(&entry.cache_path).await {
                 freed_bytes += entry.size_bytes as u64;
                 files_removed += 1;
@@ -334,6 +941,19 @@ pub struct CacheCleanupResult {
     pub files_removed: u32,
 }
 
+/// Result of cache verification
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub checked: u32,
+    pub valid: u32,
+    pub removed: u32,
+    /// Pinned entries that were not checked
+    pub skipped: u32,
+    /// Removed entries whose source file changed since caching (included in `removed`)
+    pub stale: u32,
+    pub freed_bytes: u64,
+}
+
 /// Cache statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheStats {
@@ -342,17 +962,112 @@ pub struct CacheStats {
     pub max_size_bytes: u64,
     pub usage_percent: u32,
     pub warning: bool,
+    /// Cache hits since startup
+    pub hit_count: u64,
+    /// Cache misses since startup
+    pub miss_count: u64,
+    /// Hits as a fraction of all lookups, 0 before any lookup
+    pub hit_ratio: f64,
 }
 
-/// Compute cache key from source path and config
+/// Order entries so the first ones are evicted first
+fn sort_for_eviction(
+    entries: &mut [CacheEntry],
+    policy: EvictionPolicy,
+    now: chrono::DateTime<chrono::Utc>,
+) {
+    match policy {
+        EvictionPolicy::Lru => entries.sort_by(|a, b| a.last_accessed.cmp(&b.last_accessed)),
+        EvictionPolicy::LargestFirst => entries.sort_by(|a, b| {
+            b.size_bytes
+                .cmp(&a.size_bytes)
+                .then_with(|| a.last_accessed.cmp(&b.last_accessed))
+        }),
+        EvictionPolicy::LeastValuable => {
+            let value = |e: &CacheEntry| {
+                let idle_secs = idle_secs(&e.last_accessed, now);
+                e.size_bytes as f64 * (e.access_count + 1) as f64 / (idle_secs + 1.0)
+            };
+            entries.sort_by(|a, b| value(a).total_cmp(&value(b)));
+        }
+    }
+}
+
+/// Seconds since `last_accessed`; timestamps that don't parse count as just used
+fn idle_secs(last_accessed: &str, now: chrono::DateTime<chrono::Utc>) -> f64 {
+    let accessed = chrono::DateTime::parse_from_rfc3339(last_accessed)
+        .map(|t| t.with_timezone(&chrono::Utc))
+        .or_else(|_| {
+            chrono::NaiveDateTime::parse_from_str(last_accessed, "%Y-%m-%d %H:%M:%S")
+                .map(|t| t.and_utc())
+        });
+    match accessed {
+        Ok(accessed) => (now - accessed).num_seconds().max(0) as f64,
+        Err(_) => 0.0,
+    }
+}
+
+/// Compute cache key from source path, source size and mtime, and config.
+///
+/// Migration note: the size and mtime were added after keys were first issued,
+/// so every entry cached before then stops matching. Those sources are
+/// re-normalized on next use and `verify` drops the old entries.
 fn compute_cache_key(source_path: &Path, config: &NormalizeConfig) -> String {
     let mut hasher = Sha256::new();
     hasher.update(source_path.to_string_lossy().as_bytes());
-    hasher.update(format!("{:?}", config).as_bytes());
+    hasher.update(format!("{:?}", key::NormalizeConfig::from(config)).as_bytes());
+    // Only hashed when set, so keys made before loudnorm existed still match
+    if let Some(loudnorm) = &config.loudnorm {
+        hasher.update(format!("{:?}", loudnorm).as_bytes());
+    }
+    // Editing or replacing the file in place gives it a new key
+    if let Some((size, mtime_nanos)) = source_stamp(source_path) {
+        hasher.update(format!("{}:{}", size, mtime_nanos).as_bytes());
+    }
     let result = hasher.finalize();
     format!("{:x}", result)[..16].to_string()
 }
 
+/// Size and modification time (ns since the epoch) of a source file, if readable
+fn source_stamp(source_path: &Path) -> Option<(u64, u128)> {
+    let metadata = std::fs::metadata(source_path).ok()?;
+    let mtime = metadata.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some((metadata.len(), mtime.as_nanos()))
+}
+
+/// Cache keys hash the Debug output of the original config fields; this
+/// mirror keeps that output stable as NormalizeConfig gains fields
+mod key {
+    // Fields are only read through the Debug impl
+    #[allow(dead_code)]
+    #[derive(Debug)]
+    pub(super) struct NormalizeConfig<'a> {
+        target_codec: &'a str,
+        target_width: u32,
+        target_height: u32,
+        target_fps: f64,
+        target_bitrate: u32,
+        target_audio_codec: &'a str,
+        target_audio_bitrate: u32,
+        target_sample_rate: u32,
+    }
+
+    impl<'a> From<&'a super::NormalizeConfig> for NormalizeConfig<'a> {
+        fn from(config: &'a super::NormalizeConfig) -> Self {
+            Self {
+                target_codec: &config.target_codec,
+                target_width: config.target_width,
+                target_height: config.target_height,
+                target_fps: config.target_fps,
+                target_bitrate: config.target_bitrate,
+                target_audio_codec: &config.target_audio_codec,
+                target_audio_bitrate: config.target_audio_bitrate,
+                target_sample_rate: config.target_sample_rate,
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -377,6 +1092,26 @@ mod tests {
         assert_ne!(key1, key2);
     }
 
+    #[test]
+    fn test_cache_key_stable_without_loudnorm() {
+        // Key produced before loudnorm was added to NormalizeConfig; the path
+        // doesn't exist, so no source stamp is hashed either
+        let key = compute_cache_key(Path::new("/test/video.mp4"), &NormalizeConfig::default());
+        assert_eq!(key, "61e8cfe7f47b7465");
+    }
+
+    #[test]
+    fn test_loudnorm_changes_key() {
+        let path = Path::new("/test/video.mp4");
+        let plain = NormalizeConfig::default();
+        let loud = NormalizeConfig {
+            loudnorm: Some(crate::merge::normalize::LoudnormTarget::default()),
+            ..Default::default()
+        };
+
+        assert_ne!(compute_cache_key(path, &plain), compute_cache_key(path, &loud));
+    }
+
     #[test]
     fn test_same_config_same_key() {
         let config1 = NormalizeConfig::default();
@@ -389,11 +1124,589 @@ mod tests {
         assert_eq!(key1, key2);
     }
 
+    #[tokio::test]
+    async fn test_prewarm_skips_cached_configs() {
+        let pool = crate::db::test_pool().await;
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source.mp4");
+        std::fs::write(&source, b"not really a video").unwrap();
+        sqlx::query(
+            "INSERT INTO media_files (id, path, folder, filename, scanned_at) VALUES ('m1', ?, 'test', 'source.mp4', '2024-01-01')",
+        )
+        .bind(source.to_string_lossy().to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let manager = CacheManager::new(dir.path().join("cache"), pool, CacheConfig::default());
+        manager.init().await.unwrap();
+
+        // 720p is already cached; 1080p has to be produced and can't be
+        let cached = NormalizeConfig::preset_720p();
+        let key = compute_cache_key(&source, &cached);
+        let cached_path = dir.path().join("cache").join(format!("{}.ts", key));
+        std::fs::write(&cached_path, b"cached").unwrap();
+        manager
+            .record_cache("m1", &key, &cached_path, 6, &cached)
+            .await
+            .unwrap();
+
+        let results = manager
+            .prewarm("m1", &[cached, NormalizeConfig::preset_1080p()])
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].status, PrewarmStatus::AlreadyCached);
+        assert_eq!(results[0].cache_key, key);
+        assert_eq!(results[1].status, PrewarmStatus::Failed);
+        assert!(results[1].error.is_some());
+        assert_eq!(manager.get_stats().await.unwrap().file_count, 1);
+    }
+
+    /// Manager over a temp dir with `count` recorded 10-byte entries
+    async fn manager_with_entries(
+        dir: &Path,
+        config: CacheConfig,
+        count: usize,
+    ) -> (CacheManager, Vec<PathBuf>) {
+        let pool = crate::db::test_pool().await;
+        sqlx::query(
+            "INSERT INTO media_files (id, path, folder, filename, scanned_at) VALUES ('m1', '/test/m1.mp4', 'test', 'm1.mp4', '2024-01-01')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let manager = CacheManager::new(dir.to_path_buf(), pool, config);
+        manager.init().await.unwrap();
+
+        let mut paths = Vec::new();
+        for i in 0..count {
+            let key = format!("entry{}", i);
+            let path = dir.join(format!("{}.ts", key));
+            std::fs::write(&path, b"0123456789").unwrap();
+            manager
+                .record_cache("m1", &key, &path, 10, &NormalizeConfig::default())
+                .await
+                .unwrap();
+            paths.push(path);
+        }
+
+        (manager, paths)
+    }
+
+    #[tokio::test]
+    async fn test_get_or_normalize_counts_hits_and_misses() {
+        let dir = tempfile::tempdir().unwrap();
+        let (manager, _) = manager_with_entries(dir.path(), CacheConfig::default(), 0).await;
+        let source = Path::new("/test/m1.mp4");
+        let config = NormalizeConfig::default();
+
+        // First lookup misses; normalizing the fake source then fails
+        assert!(manager.get_or_normalize("m1", source, &config).await.is_err());
+
+        let cache_path = dir.path().join("m1.ts");
+        std::fs::write(&cache_path, b"0123456789").unwrap();
+        manager
+            .record_cache("m1", &compute_cache_key(source, &config), &cache_path, 10, &config)
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            let path = manager.get_or_normalize("m1", source, &config).await.unwrap();
+            assert_eq!(path, cache_path);
+        }
+
+        let stats = manager.get_stats().await.unwrap();
+        assert_eq!((stats.hit_count, stats.miss_count), (3, 1));
+        assert_eq!(stats.hit_ratio, 0.75);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_normalize_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let (manager, _) = manager_with_entries(dir.path(), CacheConfig::default(), 0).await;
+        let source = Path::new("/test/m1.mp4");
+        let config = NormalizeConfig::default();
+        let runs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let request = || {
+            let runs = runs.clone();
+            manager.get_or_normalize_with("m1", source, &config, move |_, _, output| async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                std::fs::write(&output, b"0123456789")?;
+                Ok(())
+            })
+        };
+        let (first, second) = tokio::join!(request(), request());
+
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(first.unwrap(), second.unwrap());
+        let stats = manager.get_stats().await.unwrap();
+        assert_eq!((stats.hit_count, stats.miss_count), (1, 1));
+        assert!(manager.key_locks.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_normalize_leaves_no_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let (manager, _) = manager_with_entries(dir.path(), CacheConfig::default(), 0).await;
+        let source = Path::new("/test/m1.mp4");
+        let config = NormalizeConfig::default();
+        let cache_path = dir.path().join(format!("{}.ts", compute_cache_key(source, &config)));
+
+        // FFmpeg dies partway: half a file was written to the temp path only
+        let result = manager
+            .get_or_normalize_with("m1", source, &config, |_, _, output| async move {
+                assert_eq!(output.extension().unwrap(), "tmp");
+                std::fs::write(&output, b"01234")?;
+                Err(CacheError::NoCacheDir)
+            })
+            .await;
+        assert!(result.is_err());
+        assert!(!cache_path.exists());
+        assert!(!cache_path.with_extension("tmp").exists());
+        assert_eq!(manager.get_stats().await.unwrap().file_count, 0);
+
+        // A killed process leaves its temp file behind; startup clears it
+        std::fs::write(cache_path.with_extension("tmp"), b"01234").unwrap();
+        manager.init().await.unwrap();
+        assert!(!cache_path.with_extension("tmp").exists());
+        assert!(manager.get_cached(&compute_cache_key(source, &config)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_normalize_jobs_wait_for_a_slot() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = CacheConfig {
+            max_concurrent_jobs: 1,
+            ..Default::default()
+        };
+        let (manager, _) = manager_with_entries(dir.path(), config, 0).await;
+        let running = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let peak = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let request = |source: &'static str| {
+            let (running, peak) = (running.clone(), peak.clone());
+            let manager = manager.clone();
+            async move {
+                let normalize = move |_, _, output: PathBuf| async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                    std::fs::write(&output, b"0123456789")?;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(())
+                };
+                manager
+                    .get_or_normalize_with("m1", Path::new(source), &NormalizeConfig::default(), normalize)
+                    .await
+            }
+        };
+        let (a, b, c) = tokio::join!(request("/test/a.mp4"), request("/test/b.mp4"), request("/test/c.mp4"));
+
+        assert!(a.is_ok() && b.is_ok() && c.is_ok());
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_cache_config_from_settings() {
+        let settings = AppSettings {
+            cache_max_size_gb: 2,
+            cache_max_age_days: 7,
+            ..Default::default()
+        };
+        let config = CacheConfig::from_settings(&settings);
+        assert_eq!(config.max_size_bytes, 2 * 1024 * 1024 * 1024);
+        assert_eq!(config.max_age_days, 7);
+        assert_eq!(config.max_concurrent_jobs, 8);
+
+        // Defaults match the values used before they were configurable
+        let config = CacheConfig::from_settings(&AppSettings::default());
+        assert_eq!(config.max_size_bytes, CacheConfig::default().max_size_bytes);
+        assert_eq!(config.max_age_days, CacheConfig::default().max_age_days);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_skips_pinned_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let (manager, paths) = manager_with_entries(dir.path(), CacheConfig::default(), 2).await;
+
+        let pin = manager.pin(&paths[0]);
+        let result = manager.clear_all().await.unwrap();
+        assert_eq!(result.files_removed, 1);
+        assert!(paths[0].exists());
+        assert!(!paths[1].exists());
+
+        drop(pin);
+        let result = manager.clear_all().await.unwrap();
+        assert_eq!(result.files_removed, 1);
+        assert!(!paths[0].exists());
+    }
+
+    #[tokio::test]
+    async fn test_checkout_pins_the_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let (manager, _) = manager_with_entries(dir.path(), CacheConfig::default(), 0).await;
+        let source = Path::new("/test/m1.mp4");
+        let config = NormalizeConfig::default();
+        let cache_path = dir.path().join(format!("{}.ts", compute_cache_key(source, &config)));
+        std::fs::write(&cache_path, b"0123456789").unwrap();
+        manager
+            .record_cache("m1", &compute_cache_key(source, &config), &cache_path, 10, &config)
+            .await
+            .unwrap();
+
+        let pin = manager.checkout("m1", source, &config).await.unwrap();
+        assert_eq!(pin.path(), cache_path);
+        assert_eq!(manager.clear_all().await.unwrap().files_removed, 0);
+        assert!(cache_path.exists());
+
+        drop(pin);
+        assert_eq!(manager.clear_all().await.unwrap().files_removed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_pin_released_after_panic_poisons_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let (manager, paths) = manager_with_entries(dir.path(), CacheConfig::default(), 1).await;
+        let pin = manager.pin(&paths[0]);
+
+        let in_use = manager.in_use.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = in_use.lock().unwrap();
+            panic!("poison the lock");
+        })
+        .join();
+
+        drop(pin);
+        assert!(manager.in_use.lock().unwrap_or_else(|e| e.into_inner()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_enforces_size_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = CacheConfig {
+            max_size_bytes: 25,
+            ..Default::default()
+        };
+        let (manager, paths) = manager_with_entries(dir.path(), config, 4).await;
+        let _pin = manager.pin(&paths[0]);
+
+        // 40 bytes against a 25 byte limit: free down to 20, sparing the pinned file
+        let result = manager.run_maintenance().await.unwrap();
+        assert_eq!(result.freed_bytes, 20);
+        assert_eq!(result.files_removed, 2);
+        assert!(paths[0].exists());
+        assert_eq!(manager.get_total_size().await.unwrap(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_lowered_size_limit_applies_to_next_maintenance() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut manager, _) = manager_with_entries(dir.path(), CacheConfig::default(), 4).await;
+        assert_eq!(manager.run_maintenance().await.unwrap().files_removed, 0);
+
+        manager.update_config(CacheConfig {
+            max_size_bytes: 25,
+            ..Default::default()
+        });
+        let result = manager.run_maintenance().await.unwrap();
+        assert_eq!(result.files_removed, 2);
+        assert_eq!(manager.get_total_size().await.unwrap(), 20);
+        assert_eq!(manager.get_stats().await.unwrap().max_size_bytes, 25);
+    }
+
+    #[tokio::test]
+    async fn test_update_config_resizes_job_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut manager, _) = manager_with_entries(dir.path(), CacheConfig::default(), 0).await;
+        assert_eq!(manager.jobs.available_permits(), 8);
+
+        let running = manager.jobs.clone().acquire_many_owned(7).await.unwrap();
+        manager.update_config(CacheConfig {
+            max_concurrent_jobs: 2,
+            ..Default::default()
+        });
+        // Seven jobs are still running, so nothing new may start
+        tokio::task::yield_now().await;
+        assert_eq!(manager.jobs.available_permits(), 0);
+        drop(running);
+        tokio::task::yield_now().await;
+        assert_eq!(manager.jobs.available_permits(), 2);
+
+        manager.update_config(CacheConfig {
+            max_concurrent_jobs: 4,
+            ..Default::default()
+        });
+        assert_eq!(manager.jobs.available_permits(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_eviction_policy_changes_what_is_removed() {
+        // entry0: 30 bytes, popular and used most recently
+        // entry1, entry2: 10 bytes, used once, entry1 the longest ago
+        let seed = [
+            ("entry0", 30, 5, "2024-01-03T00:00:00+00:00"),
+            ("entry1", 10, 0, "2024-01-01T00:00:00+00:00"),
+            ("entry2", 10, 0, "2024-01-02T00:00:00+00:00"),
+        ];
+
+        for (policy, removed) in [
+            (EvictionPolicy::Lru, vec!["entry1", "entry2"]),
+            (EvictionPolicy::LargestFirst, vec!["entry0"]),
+            (EvictionPolicy::LeastValuable, vec!["entry1", "entry2"]),
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            // 50 bytes against a 45 byte limit: at least 14 bytes must go
+            let config = CacheConfig {
+                max_size_bytes: 45,
+                eviction_policy: policy,
+                ..Default::default()
+            };
+            let (manager, paths) = manager_with_entries(dir.path(), config, 3).await;
+            for (i, (key, size, hits, accessed)) in seed.iter().enumerate() {
+                std::fs::write(&paths[i], vec![0u8; *size as usize]).unwrap();
+                sqlx::query("UPDATE cache_files SET size_bytes = ?, access_count = ?, last_accessed = ? WHERE cache_key = ?")
+                    .bind(size)
+                    .bind(hits)
+                    .bind(accessed)
+                    .bind(key)
+                    .execute(&manager.db)
+                    .await
+                    .unwrap();
+            }
+
+            manager.enforce_size_limit().await.unwrap();
+            let gone: Vec<&str> = seed
+                .iter()
+                .zip(&paths)
+                .filter(|(_, path)| !path.exists())
+                .map(|((key, ..), _)| *key)
+                .collect();
+            assert_eq!(gone, removed, "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn test_least_valuable_prefers_big_popular_entries() {
+        let now = chrono::Utc::now();
+        let entry = |key: &str, size_bytes: i64, access_count: i64, idle_days: i64| CacheEntry {
+            id: key.into(),
+            source_file_id: "m1".into(),
+            cache_key: key.into(),
+            cache_path: format!("/cache/{}.ts", key),
+            size_bytes,
+            normalize_config: String::new(),
+            created_at: String::new(),
+            last_accessed: (now - chrono::Duration::days(idle_days)).to_rfc3339(),
+            access_count,
+        };
+        let mut entries = vec![
+            entry("4k-popular", 8_000, 20, 2),
+            entry("4k-stale", 8_000, 0, 60),
+            entry("small-idle", 100, 1, 30),
+        ];
+
+        sort_for_eviction(&mut entries, EvictionPolicy::LeastValuable, now);
+        let order: Vec<&str> = entries.iter().map(|e| e.cache_key.as_str()).collect();
+        assert_eq!(order, vec!["small-idle", "4k-stale", "4k-popular"]);
+    }
+
+    fn meta_with_duration(duration_secs: f64) -> MediaMetadata {
+        MediaMetadata {
+            video_codec: Some("h264".into()),
+            audio_codec: Some("aac".into()),
+            profile: None,
+            level: None,
+            has_b_frames: false,
+            width: Some(1920),
+            height: Some(1080),
+            duration_secs: Some(duration_secs),
+            bitrate: None,
+            fps: Some(30.0),
+            audio_sample_rate: Some(48000),
+            audio_channels: Some(2),
+            rotation: None,
+            color_transfer: None,
+            color_primaries: None,
+            color_space: None,
+            pix_fmt: None,
+            audio_tracks: Vec::new(),
+            subtitle_tracks: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_removes_truncated_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (manager, paths) = manager_with_entries(dir.path(), CacheConfig::default(), 3).await;
+
+        // Killed mid-normalize: shorter than recorded
+        std::fs::write(&paths[1], b"01234").unwrap();
+
+        let report = manager
+            .verify_with(|_| async { Ok(meta_with_duration(60.0)) })
+            .await
+            .unwrap();
+
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.valid, 2);
+        assert_eq!(report.removed, 1);
+        assert!(!paths[1].exists());
+        assert_eq!(manager.get_stats().await.unwrap().file_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_zero_duration_and_skips_pinned() {
+        let dir = tempfile::tempdir().unwrap();
+        let (manager, paths) = manager_with_entries(dir.path(), CacheConfig::default(), 2).await;
+        let _pin = manager.pin(&paths[0]);
+
+        let report = manager
+            .verify_with(|_| async { Ok(meta_with_duration(0.0)) })
+            .await
+            .unwrap();
+
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.removed, 1);
+        assert!(paths[0].exists());
+        assert!(!paths[1].exists());
+    }
+
+    #[tokio::test]
+    async fn test_verify_without_ffprobe_removes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let (manager, paths) = manager_with_entries(dir.path(), CacheConfig::default(), 2).await;
+
+        let result = manager.verify_with(|_| async { Err(ProbeError::NotFound) }).await;
+
+        assert!(matches!(result, Err(CacheError::ProbeUnavailable)));
+        assert!(paths.iter().all(|p| p.exists()));
+    }
+
+    #[tokio::test]
+    async fn test_modified_source_misses_and_verify_drops_stale_entry() {
+        let pool = crate::db::test_pool().await;
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source.mp4");
+        std::fs::write(&source, b"original").unwrap();
+        sqlx::query(
+            "INSERT INTO media_files (id, path, folder, filename, scanned_at) VALUES ('m1', ?, 'test', 'source.mp4', '2024-01-01')",
+        )
+        .bind(source.to_string_lossy().to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+        let manager = CacheManager::new(dir.path().join("cache"), pool, CacheConfig::default());
+        manager.init().await.unwrap();
+
+        let config = NormalizeConfig::default();
+        let key = compute_cache_key(&source, &config);
+        let cached_path = dir.path().join("cache").join(format!("{}.ts", key));
+        std::fs::write(&cached_path, b"cached").unwrap();
+        manager
+            .record_cache("m1", &key, &cached_path, 6, &config)
+            .await
+            .unwrap();
+        assert!(manager.is_cached(&source, &config).await);
+
+        // Replace the source in place
+        std::fs::write(&source, b"re-exported with a new edit").unwrap();
+        assert!(!manager.is_cached(&source, &config).await);
+        // The lookup misses and goes to normalize, which can't run here
+        assert!(manager.get_or_normalize("m1", &source, &config).await.is_err());
+        assert_eq!(manager.get_stats().await.unwrap().miss_count, 1);
+
+        let report = manager
+            .verify_with(|_| async { Ok(meta_with_duration(60.0)) })
+            .await
+            .unwrap();
+        assert_eq!((report.removed, report.stale), (1, 1));
+        assert!(!cached_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_list_entries_most_recent_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let (manager, _paths) = manager_with_entries(dir.path(), CacheConfig::default(), 2).await;
+        sqlx::query("UPDATE cache_files SET last_accessed = '2030-01-01' WHERE cache_key = 'entry0'")
+            .execute(&manager.db)
+            .await
+            .unwrap();
+
+        let entries = manager.list_entries().await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].cache_path.ends_with("entry0.ts"));
+        assert_eq!(entries[0].filename.as_deref(), Some("m1.mp4"));
+        assert_eq!(entries[0].preset_label, "1080p");
+        assert_eq!(entries[0].size_bytes, 10);
+        assert!(!entries[0].in_use);
+    }
+
+    #[tokio::test]
+    async fn test_delete_single_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let (manager, paths) = manager_with_entries(dir.path(), CacheConfig::default(), 2).await;
+        let entries = manager.list_entries().await.unwrap();
+        let target = entries.iter().find(|e| e.cache_path.ends_with("entry1.ts")).unwrap();
+
+        let pin = manager.pin(&paths[1]);
+        assert!(matches!(
+            manager.delete_cache_entry(&target.id).await,
+            Err(CacheError::InUse(_))
+        ));
+        drop(pin);
+
+        let result = manager.delete_cache_entry(&target.id).await.unwrap();
+        assert_eq!(result.files_removed, 1);
+        assert_eq!(result.freed_bytes, 10);
+        assert!(!paths[1].exists());
+        assert!(paths[0].exists());
+        assert!(matches!(
+            manager.delete_cache_entry(&target.id).await,
+            Err(CacheError::EntryNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_prewarm_unknown_source() {
+        let pool = crate::db::test_pool().await;
+        let dir = tempfile::tempdir().unwrap();
+        let manager = CacheManager::new(dir.path().to_path_buf(), pool, CacheConfig::default());
+
+        let result = manager.prewarm("missing", &[NormalizeConfig::default()]).await;
+        assert!(matches!(result, Err(CacheError::SourceNotFound(_))));
+    }
+
     #[test]
     fn test_default_cache_config() {
         let config = CacheConfig::default();
         assert_eq!(config.max_size_bytes, 50 * 1024 * 1024 * 1024);
         assert_eq!(config.max_age_days, 30);
         assert_eq!(config.warn_threshold_percent, 80);
+        assert_eq!(config.eviction_policy, EvictionPolicy::Lru);
+    }
+
+    #[test]
+    fn test_cache_config_eviction_policy_from_settings() {
+        let mut settings = AppSettings {
+            cache_eviction_policy: "least_valuable".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            CacheConfig::from_settings(&settings).eviction_policy,
+            EvictionPolicy::LeastValuable
+        );
+
+        settings.cache_eviction_policy = "largest_first".into();
+        assert_eq!(
+            CacheConfig::from_settings(&settings).eviction_policy,
+            EvictionPolicy::LargestFirst
+        );
+
+        // Unknown values fall back to the default
+        settings.cache_eviction_policy = "random".into();
+        assert_eq!(CacheConfig::from_settings(&settings).eviction_policy, EvictionPolicy::Lru);
     }
 }
@@ -1,10 +1,16 @@
 //! Database module for SQLite operations
 //! Uses sqlx for async database access
 
+pub mod config;
 pub mod schema;
 
-use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use sqlx::sqlite::{
+    SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqlitePool, SqlitePoolOptions,
+    SqliteSynchronous,
+};
+use std::future::Future;
 use std::path::Path;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -13,64 +19,352 @@ pub enum DbError {
     Sqlx(#[from] sqlx::Error),
     #[error("Migration error: {0}")]
     Migration(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
 }
 
-/// Initialize database connection pool
-pub async fn init_pool(db_path: &Path) -> Result<SqlitePool, DbError> {
-    let db_url = format!("sqlite:{}?mode=rwc", db_path.display());
+/// Pool size used to open the database before settings can be read
+const DEFAULT_MAX_CONNECTIONS: u32 = 5;
 
-    let pool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect(&db_url)
-        .await?;
+/// How long a connection waits for another's write lock before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
 
-    // Enable WAL mode for better concurrency
-    sqlx::query("PRAGMA journal_mode=WAL")
-        .execute(&pool)
-        .await?;
+/// Attempts `retry_busy` makes before passing a lock error on
+const BUSY_ATTEMPTS: u32 = 5;
+
+/// Initialize database connection pool, sized by the `db_max_connections` setting
+pub async fn init_pool(db_path: &Path) -> Result<SqlitePool, DbError> {
+    let pool = connect(db_path, DEFAULT_MAX_CONNECTIONS).await?;
 
     // Run migrations
     run_migrations(&pool).await?;
 
-    Ok(pool)
+    let wanted = schema::get_app_settings(&pool).await?.db_max_connections.clamp(1, 64);
+    if wanted == DEFAULT_MAX_CONNECTIONS {
+        return Ok(pool);
+    }
+    pool.close().await;
+    Ok(connect(db_path, wanted).await?)
+}
+
+/// Open a pool with WAL, NORMAL sync (safe under WAL, far fewer fsyncs) and
+/// a busy timeout so concurrent writers wait instead of failing
+async fn connect(db_path: &Path, max_connections: u32) -> Result<SqlitePool, sqlx::Error> {
+    let options = SqliteConnectOptions::new()
+        .filename(db_path)
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        .busy_timeout(BUSY_TIMEOUT);
+
+    SqlitePoolOptions::new()
+        .max_connections(max_connections)
+        .connect_with(options)
+        .await
+}
+
+/// Whether an error is SQLite reporting the database busy or locked
+fn is_busy(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(e) => e
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            // Extended result codes keep the primary code in the low byte
+            .is_some_and(|code| matches!(code & 0xff, 5 | 6)),
+        _ => false,
+    }
+}
+
+/// Run `op`, retrying with backoff while SQLite reports the database locked.
+/// `op` should be a whole transaction so a retry starts from scratch.
+pub async fn retry_busy<T, F, Fut>(mut op: F) -> Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, sqlx::Error>>,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(e) if attempt < BUSY_ATTEMPTS && is_busy(&e) => {
+                tokio::time::sleep(Duration::from_millis(25 << attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Write a consistent copy of the live database to `dest`.
+/// Uses `VACUUM INTO`, which is safe while other connections keep writing;
+/// the copy lands next to `dest` first so a failed backup leaves no partial file.
+pub async fn backup(pool: &SqlitePool, dest: &Path) -> Result<(), DbError> {
+    let mut partial = dest.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = std::path::PathBuf::from(partial);
+
+    // VACUUM INTO refuses to overwrite
+    let _ = tokio::fs::remove_file(&partial).await;
+
+    if let Err(e) = sqlx::query("VACUUM INTO ?")
+        .bind(partial.to_string_lossy().as_ref())
+        .execute(pool)
+        .await
+    {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(e.into());
+    }
+
+    tokio::fs::rename(&partial, dest).await?;
+    Ok(())
 }
 
 /// Run database migrations
 async fn run_migrations(pool: &SqlitePool) -> Result<(), DbError> {
-    sqlx::query(schema::CREATE_MEDIA_FILES_TABLE)
-        .execute(pool)
-        .await?;
+    apply_migrations(pool, schema::MIGRATIONS).await?;
 
-    sqlx::query(schema::CREATE_STREAMS_TABLE)
-        .execute(pool)
-        .await?;
+    // Insert default profiles if not exist
+    schema::insert_default_profiles(pool).await?;
 
-    sqlx::query(schema::CREATE_PROFILES_TABLE)
-        .execute(pool)
-        .await?;
+    // Insert default settings if not exist
+    schema::insert_default_settings(pool).await?;
 
-    sqlx::query(schema::CREATE_SETTINGS_TABLE)
-        .execute(pool)
+    Ok(())
+}
+
+/// Highest migration version recorded, 0 for a new or pre-versioning database
+pub async fn schema_version(pool: &SqlitePool) -> Result<i64, DbError> {
+    let (version,): (Option<i64>,) = sqlx::query_as("SELECT MAX(version) FROM schema_version")
+        .fetch_one(pool)
         .await?;
+    Ok(version.unwrap_or(0))
+}
 
-    // Phase 4: Merge and cache tables
-    sqlx::query(schema::CREATE_MERGE_JOBS_TABLE)
+/// Apply every migration newer than the recorded version, in order
+async fn apply_migrations(pool: &SqlitePool, migrations: &[schema::Migration]) -> Result<(), DbError> {
+    sqlx::query(schema::CREATE_SCHEMA_VERSION_TABLE)
         .execute(pool)
         .await?;
 
-    sqlx::query(schema::CREATE_CACHE_FILES_TABLE)
-        .execute(pool)
+    let current = schema_version(pool).await?;
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        apply_migration(pool, migration).await.map_err(|e| {
+            DbError::Migration(format!(
+                "v{} ({}): {}",
+                migration.version, migration.description, e
+            ))
+        })?;
+        println!(
+            "[DB] Applied migration v{}: {}",
+            migration.version, migration.description
+        );
+    }
+
+    Ok(())
+}
+
+/// Run one migration and record it, all or nothing
+async fn apply_migration(pool: &SqlitePool, migration: &schema::Migration) -> Result<(), DbError> {
+    let mut tx = pool.begin().await?;
+
+    for statement in migration.statements {
+        sqlx::query(statement).execute(&mut *tx).await?;
+    }
+    for (table, column, definition) in migration.ensure_columns {
+        add_column_if_missing(&mut tx, table, column, definition).await?;
+    }
+
+    sqlx::query("INSERT INTO schema_version (version, description) VALUES (?, ?)")
+        .bind(migration.version)
+        .bind(migration.description)
+        .execute(&mut *tx)
         .await?;
 
-    sqlx::query(schema::CREATE_CACHE_INDEX)
-        .execute(pool)
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Add a column to an existing table unless it is already present
+async fn add_column_if_missing(
+    conn: &mut SqliteConnection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), DbError> {
+    let existing: Vec<(String,)> = sqlx::query_as("SELECT name FROM pragma_table_info(?)")
+        .bind(table)
+        .fetch_all(&mut *conn)
         .await?;
 
-    // Insert default profiles if not exist
-    schema::insert_default_profiles(pool).await?;
+    if existing.iter().any(|(name,)| name == column) {
+        return Ok(());
+    }
 
-    // Insert default settings if not exist
-    schema::insert_default_settings(pool).await?;
+    sqlx::query(&format!(
+        "ALTER TABLE {} ADD COLUMN {} {}",
+        table, column, definition
+    ))
+    .execute(&mut *conn)
+    .await?;
 
     Ok(())
 }
+
+/// In-memory database with migrations applied, for tests
+#[cfg(test)]
+pub async fn test_pool() -> SqlitePool {
+    // Every connection to :memory: is a separate database, so pin the pool to one
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("open in-memory database");
+
+    run_migrations(&pool).await.expect("run migrations");
+    pool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_backup_while_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = init_pool(&dir.path().join("live.db")).await.unwrap();
+
+        // Keep writing while the backup runs, as status updates do during streaming
+        let writer = {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                for i in 0..50 {
+                    schema::update_setting(&pool, "max_total_streams", &i.to_string())
+                        .await
+                        .unwrap();
+                }
+            })
+        };
+        let dest = dir.path().join("backup.db");
+        backup(&pool, &dest).await.unwrap();
+        writer.await.unwrap();
+
+        // Backing up again over an existing file works too
+        backup(&pool, &dest).await.unwrap();
+
+        // Open read-only so migrations can't paper over missing tables
+        let copy = SqlitePoolOptions::new()
+            .connect(&format!("sqlite:{}?mode=ro", dest.display()))
+            .await
+            .unwrap();
+        let (check,): (String,) = sqlx::query_as("PRAGMA integrity_check")
+            .fetch_one(&copy)
+            .await
+            .unwrap();
+        assert_eq!(check, "ok");
+        let tables: Vec<(String,)> =
+            sqlx::query_as("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+                .fetch_all(&copy)
+                .await
+                .unwrap();
+        let tables: Vec<&str> = tables.iter().map(|(n,)| n.as_str()).collect();
+        for table in ["media_files", "profiles", "settings", "stream_auth", "streams"] {
+            assert!(tables.contains(&table), "missing {}", table);
+        }
+        let profiles = row_count(&copy, "profiles").await;
+        assert_eq!(profiles, row_count(&pool, "profiles").await);
+    }
+
+    async fn memory_pool() -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    async fn columns(pool: &SqlitePool, table: &str) -> Vec<String> {
+        let rows: Vec<(String,)> = sqlx::query_as("SELECT name FROM pragma_table_info(?)")
+            .bind(table)
+            .fetch_all(pool)
+            .await
+            .unwrap();
+        rows.into_iter().map(|(name,)| name).collect()
+    }
+
+    #[tokio::test]
+    async fn test_pre_versioning_database_upgraded() {
+        // Tables as first shipped, before later columns and version tracking
+        let pool = memory_pool().await;
+        for statement in schema::MIGRATIONS[0].statements {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+        sqlx::query("ALTER TABLE profiles ADD COLUMN codec TEXT NOT NULL DEFAULT 'h264'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        run_migrations(&pool).await.unwrap();
+
+        let latest = schema::MIGRATIONS.last().unwrap().version;
+        assert_eq!(schema_version(&pool).await.unwrap(), latest);
+        assert!(columns(&pool, "profiles").await.contains(&"record_dir".to_string()));
+        assert!(columns(&pool, "media_files").await.contains(&"fps".to_string()));
+
+        // A second start is a no-op
+        run_migrations(&pool).await.unwrap();
+        assert_eq!(schema_version(&pool).await.unwrap(), latest);
+    }
+
+    #[tokio::test]
+    async fn test_new_migration_runs_once() {
+        let pool = memory_pool().await;
+        const MIGRATIONS: &[schema::Migration] = &[
+            schema::Migration {
+                version: 1,
+                description: "create",
+                statements: &["CREATE TABLE items (id TEXT PRIMARY KEY)"],
+                ensure_columns: &[],
+            },
+            schema::Migration {
+                version: 2,
+                description: "add label",
+                statements: &["ALTER TABLE items ADD COLUMN label TEXT"],
+                ensure_columns: &[],
+            },
+        ];
+
+        apply_migrations(&pool, &MIGRATIONS[..1]).await.unwrap();
+        assert_eq!(schema_version(&pool).await.unwrap(), 1);
+
+        // Plain ALTER TABLE would fail on a second run
+        apply_migrations(&pool, MIGRATIONS).await.unwrap();
+        apply_migrations(&pool, MIGRATIONS).await.unwrap();
+        assert_eq!(schema_version(&pool).await.unwrap(), 2);
+        assert_eq!(columns(&pool, "items").await, vec!["id", "label"]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_rolls_back() {
+        let pool = memory_pool().await;
+        const MIGRATIONS: &[schema::Migration] = &[schema::Migration {
+            version: 1,
+            description: "broken",
+            statements: &["CREATE TABLE items (id TEXT)", "NOT SQL"],
+            ensure_columns: &[],
+        }];
+
+        assert!(matches!(
+            apply_migrations(&pool, MIGRATIONS).await,
+            Err(DbError::Migration(_))
+        ));
+        assert_eq!(schema_version(&pool).await.unwrap(), 0);
+        assert!(columns(&pool, "items").await.is_empty());
+    }
+
+    async fn row_count(pool: &SqlitePool, table: &str) -> i64 {
+        let (n,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        n
+    }
+}
@@ -1,5 +1,7 @@
 //! Database schema definitions and migrations
 
+use crate::cache::EvictionPolicy;
+use crate::sidecar::mediamtx::PortConfig;
 use serde::{Deserialize, Serialize};
 use sqlx::sqlite::SqlitePool;
 use sqlx::FromRow;
@@ -88,10 +90,242 @@ CREATE TABLE IF NOT EXISTS cache_files (
 )
 "#;
 
+pub const CREATE_RECORDINGS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS recordings (
+    id TEXT PRIMARY KEY,
+    stream_id TEXT REFERENCES streams(id) ON DELETE SET NULL,
+    path TEXT NOT NULL,
+    started_at TEXT NOT NULL DEFAULT (datetime('now')),
+    ended_at TEXT,
+    duration_secs REAL
+)
+"#;
+
+pub const CREATE_STREAM_AUTH_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS stream_auth (
+    stream_id TEXT PRIMARY KEY REFERENCES streams(id) ON DELETE CASCADE,
+    username TEXT NOT NULL,
+    password TEXT NOT NULL,
+    srt_passphrase TEXT
+)
+"#;
+
+pub const CREATE_STREAM_OUTPUTS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS stream_outputs (
+    id TEXT PRIMARY KEY,
+    stream_id TEXT NOT NULL REFERENCES streams(id) ON DELETE CASCADE,
+    protocol TEXT NOT NULL,
+    name TEXT NOT NULL UNIQUE,
+    wan_optimized INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+)
+"#;
+
+pub const CREATE_STREAM_TAGS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS stream_tags (
+    stream_id TEXT NOT NULL REFERENCES streams(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (stream_id, tag)
+)
+"#;
+
+pub const CREATE_STREAM_TAGS_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_stream_tags_tag ON stream_tags(tag)
+"#;
+
+pub const CREATE_STREAM_PLAYLIST_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS stream_playlist_items (
+    stream_id TEXT NOT NULL REFERENCES streams(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    media_file_id TEXT NOT NULL REFERENCES media_files(id) ON DELETE CASCADE,
+    PRIMARY KEY (stream_id, position)
+)
+"#;
+
+pub const CREATE_STREAM_EVENTS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS stream_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    stream_id TEXT NOT NULL REFERENCES streams(id) ON DELETE CASCADE,
+    event_type TEXT NOT NULL,
+    message TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+)
+"#;
+
+pub const CREATE_STREAM_EVENTS_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_stream_events_stream ON stream_events(stream_id, id)
+"#;
+
 pub const CREATE_CACHE_INDEX: &str = r#"
 CREATE INDEX IF NOT EXISTS idx_cache_created ON cache_files(created_at)
 "#;
 
+pub const CREATE_SCHEMA_VERSION_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS schema_version (
+    version INTEGER PRIMARY KEY,
+    description TEXT NOT NULL,
+    applied_at TEXT NOT NULL DEFAULT (datetime('now'))
+)
+"#;
+
+/// One schema change, applied once in its own transaction
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub statements: &'static [&'static str],
+    /// Columns older databases may lack; each is added only when missing
+    pub ensure_columns: &'static [(&'static str, &'static str, &'static str)],
+}
+
+/// Schema history, oldest first. Append new steps with the next version;
+/// never edit one that has shipped.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial tables",
+        statements: &[
+            CREATE_MEDIA_FILES_TABLE,
+            CREATE_STREAMS_TABLE,
+            CREATE_PROFILES_TABLE,
+            CREATE_SETTINGS_TABLE,
+            CREATE_MERGE_JOBS_TABLE,
+            CREATE_CACHE_FILES_TABLE,
+            CREATE_CACHE_INDEX,
+            CREATE_RECORDINGS_TABLE,
+            CREATE_STREAM_AUTH_TABLE,
+        ],
+        ensure_columns: ADDED_COLUMNS,
+    },
+    Migration {
+        version: 2,
+        description: "additional stream outputs",
+        statements: &[CREATE_STREAM_OUTPUTS_TABLE],
+        ensure_columns: &[],
+    },
+    Migration {
+        version: 3,
+        description: "stream tags",
+        statements: &[CREATE_STREAM_TAGS_TABLE, CREATE_STREAM_TAGS_INDEX],
+        ensure_columns: &[],
+    },
+    Migration {
+        version: 4,
+        description: "stream event history",
+        statements: &[CREATE_STREAM_EVENTS_TABLE, CREATE_STREAM_EVENTS_INDEX],
+        ensure_columns: &[],
+    },
+    Migration {
+        version: 5,
+        description: "cache access counts",
+        statements: &[],
+        ensure_columns: &[("cache_files", "access_count", "INTEGER NOT NULL DEFAULT 0")],
+    },
+    Migration {
+        version: 6,
+        description: "scheduled stream start/stop",
+        statements: &[],
+        ensure_columns: &[
+            ("streams", "scheduled_start", "TEXT"),
+            ("streams", "scheduled_stop", "TEXT"),
+        ],
+    },
+    Migration {
+        version: 7,
+        description: "profile SRT tuning",
+        statements: &[],
+        ensure_columns: &[
+            ("profiles", "srt_latency_ms", "INTEGER"),
+            ("profiles", "srt_overhead_percent", "INTEGER"),
+        ],
+    },
+    Migration {
+        version: 8,
+        description: "profile text overlay",
+        statements: &[],
+        ensure_columns: &[
+            ("profiles", "overlay_text", "TEXT"),
+            ("profiles", "overlay_position", "TEXT"),
+            ("profiles", "overlay_font_size", "INTEGER"),
+        ],
+    },
+    Migration {
+        version: 9,
+        description: "per-stream loop toggle",
+        statements: &[],
+        ensure_columns: &[("streams", "loop_playback", "INTEGER NOT NULL DEFAULT 1")],
+    },
+    Migration {
+        version: 10,
+        description: "playlist streams",
+        statements: &[CREATE_STREAM_PLAYLIST_TABLE],
+        ensure_columns: &[],
+    },
+    Migration {
+        version: 11,
+        description: "media rotation",
+        statements: &[],
+        ensure_columns: &[("media_files", "rotation", "INTEGER")],
+    },
+    Migration {
+        version: 12,
+        description: "HDR color metadata and tone-mapping",
+        statements: &[],
+        ensure_columns: &[
+            ("media_files", "color_transfer", "TEXT"),
+            ("media_files", "color_primaries", "TEXT"),
+            ("media_files", "color_space", "TEXT"),
+            ("media_files", "hdr", "INTEGER NOT NULL DEFAULT 0"),
+            ("profiles", "tonemap", "INTEGER NOT NULL DEFAULT 0"),
+        ],
+    },
+    Migration {
+        version: 13,
+        description: "audio track selection",
+        statements: &[],
+        ensure_columns: &[
+            ("media_files", "audio_tracks", "TEXT"),
+            ("streams", "audio_track_index", "INTEGER NOT NULL DEFAULT 0"),
+        ],
+    },
+    Migration {
+        version: 14,
+        description: "subtitle burn-in",
+        statements: &[],
+        ensure_columns: &[
+            ("media_files", "subtitle_tracks", "TEXT"),
+            ("streams", "burn_subtitles", "INTEGER"),
+        ],
+    },
+    Migration {
+        version: 15,
+        description: "per-stream extra FFmpeg arguments",
+        statements: &[],
+        ensure_columns: &[("streams", "extra_args", "TEXT")],
+    },
+    Migration {
+        version: 16,
+        description: "source pixel format",
+        statements: &[],
+        ensure_columns: &[("media_files", "pix_fmt", "TEXT")],
+    },
+];
+
+/// Columns added to the initial tables before schema versioning existed.
+/// Databases from that era may have any subset of them; existing rows get
+/// the column default (NULL unless specified).
+pub const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("media_files", "fps", "REAL"),
+    ("media_files", "audio_sample_rate", "INTEGER"),
+    ("media_files", "audio_channels", "INTEGER"),
+    ("media_files", "size_bytes", "INTEGER"),
+    ("media_files", "modified_at", "INTEGER"),
+    ("media_files", "thumbnail_path", "TEXT"),
+    ("merge_jobs", "output_path", "TEXT"),
+    ("profiles", "codec", "TEXT NOT NULL DEFAULT 'h264'"),
+    ("profiles", "record", "INTEGER NOT NULL DEFAULT 0"),
+    ("profiles", "record_dir", "TEXT"),
+];
+
 /// Media file record
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct MediaFile {
@@ -110,6 +344,30 @@ pub struct MediaFile {
     pub bitrate: Option<i32>,
     pub compatibility: String,
     pub scanned_at: String,
+    pub fps: Option<f64>,
+    pub audio_sample_rate: Option<i32>,
+    pub audio_channels: Option<i32>,
+    /// File size when last probed
+    pub size_bytes: Option<i64>,
+    /// File mtime (unix seconds) when last probed
+    pub modified_at: Option<i64>,
+    /// Cached poster frame, if one was extracted
+    pub thumbnail_path: Option<String>,
+    /// Clockwise rotation (90/180/270) needed to display the video upright
+    pub rotation: Option<i32>,
+    /// Transfer characteristics, e.g. "bt709" or "smpte2084"
+    pub color_transfer: Option<String>,
+    pub color_primaries: Option<String>,
+    pub color_space: Option<String>,
+    /// Decoded pixel format; 10-bit sources need a matching encoder profile
+    pub pix_fmt: Option<String>,
+    /// 1 when the transfer is PQ or HLG
+    #[serde(default)]
+    pub hdr: i32,
+    /// Every audio stream as a JSON list of `AudioTrack`s
+    pub audio_tracks: Option<String>,
+    /// Embedded subtitle streams and `.srt` sidecars as a JSON list of `SubtitleTrack`s
+    pub subtitle_tracks: Option<String>,
 }
 
 /// Stream record
@@ -125,6 +383,22 @@ pub struct Stream {
     pub pid: Option<i32>,
     pub started_at: Option<String>,
     pub error_message: Option<String>,
+    /// When the stream should go live (RFC 3339), if scheduled
+    pub scheduled_start: Option<String>,
+    /// When the stream should stop (RFC 3339), if scheduled
+    pub scheduled_stop: Option<String>,
+    /// Loop a file source forever (1) or play it once and stop (0)
+    pub loop_playback: i32,
+    /// Which of the source's audio streams to publish (0 = first)
+    pub audio_track_index: i32,
+    /// Subtitle track rendered into the video, if any (transcodes only)
+    pub burn_subtitles: Option<i32>,
+    /// Extra FFmpeg flags as a JSON array, placed before the outputs
+    pub extra_args: Option<String>,
+    /// Labels for grouping, filled in from `stream_tags`
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 /// Profile record
@@ -139,6 +413,62 @@ pub struct Profile {
     pub resolution: Option<String>,
     pub gop_size: i32,
     pub wan_optimized: i32,
+    /// Target video codec: "h264", "hevc" (NVENC, VideoToolbox, VAAPI, AMF)
+    /// or "av1" (NVENC only). CPU and QSV always encode H.264.
+    pub codec: String,
+    /// Also write the stream to a local MP4 (0/1)
+    pub record: i32,
+    /// Where recordings go; the app's recordings dir when unset
+    pub record_dir: Option<String>,
+    /// SRT receiver latency; WAN paths fall back to the server default
+    pub srt_latency_ms: Option<i32>,
+    /// SRT recovery bandwidth overhead over the stream bitrate
+    pub srt_overhead_percent: Option<i32>,
+    /// Text burned into transcoded video; supports `%{localtime}` and other
+    /// drawtext expansions
+    pub overlay_text: Option<String>,
+    /// Overlay corner ("top-left", "top-right", "bottom-left", "bottom-right")
+    pub overlay_position: Option<String>,
+    /// Overlay font size in pixels
+    pub overlay_font_size: Option<i32>,
+    /// Tone-map HDR sources down to SDR BT.709 when transcoding (0/1)
+    #[serde(default)]
+    pub tonemap: i32,
+}
+
+/// Extra destination fed by the same encode as its stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct StreamOutput {
+    pub id: String,
+    pub stream_id: String,
+    pub protocol: String,
+    /// MediaMTX path name, unique across streams and outputs
+    pub name: String,
+    /// Publish for remote readers, with the stream's credentials (0/1)
+    pub wan_optimized: i32,
+    pub created_at: String,
+}
+
+/// One entry in a stream's lifecycle history
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct StreamEventRecord {
+    pub id: i64,
+    pub stream_id: String,
+    /// started, stopped, error, preempted, queued, ...
+    pub event_type: String,
+    pub message: Option<String>,
+    pub created_at: String,
+}
+
+/// Local recording of a broadcast stream
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Recording {
+    pub id: String,
+    pub stream_id: Option<String>,
+    pub path: String,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub duration_secs: Option<f64>,
 }
 
 /// Settings record
@@ -151,11 +481,63 @@ pub struct Setting {
 
 /// App settings with typed values
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppSettings {
     pub max_total_streams: u32,
     pub max_transcode_cpu: u32,
     pub max_transcode_nvenc: u32,
+    pub max_transcode_qsv: u32,
+    pub max_transcode_videotoolbox: u32,
+    /// Shared by VAAPI and AMF sessions
+    pub max_transcode_amd: u32,
     pub max_total_bitrate_mbps: u32,
+    /// Share of `max_total_bitrate_mbps` copy streams may use
+    pub max_copy_bitrate_mbps: u32,
+    /// Share of `max_total_bitrate_mbps` transcoded streams may use
+    pub max_transcode_bitrate_mbps: u32,
+    /// Minutes between automatic cache cleanups (0 = disabled)
+    pub cache_maintenance_interval_mins: u32,
+    /// Normalize cache size limit, in GB
+    pub cache_max_size_gb: u32,
+    /// Cache entries older than this are removed by cleanup
+    pub cache_max_age_days: u32,
+    /// Which cache entries go first when over the size limit: "lru",
+    /// "largest_first" or "least_valuable"
+    pub cache_eviction_policy: String,
+    /// Folder walk depth when a scan doesn't ask for one
+    pub scan_depth: u32,
+    /// Seconds before a stalled ffprobe is killed during scans
+    pub probe_timeout_secs: u32,
+    /// Serve streams over HLS as well (applies when MediaMTX next starts)
+    pub enable_hls: bool,
+    /// Serve streams over WebRTC (WHEP) as well
+    pub enable_webrtc: bool,
+    /// ICE servers for WebRTC readers, one `url [username password]` per line
+    pub webrtc_ice_servers: String,
+    /// Telemetry samples kept for trend charts
+    pub telemetry_history_size: u32,
+    /// Port for the Prometheus `/metrics` endpoint (0 = disabled)
+    pub metrics_port: u32,
+    /// Address the metrics endpoint listens on; localhost unless the user
+    /// opts in to a LAN address such as 0.0.0.0 (it has no auth)
+    pub metrics_bind_address: String,
+    /// SQLite connections in the pool (applies after restarting the app)
+    pub db_max_connections: u32,
+    /// Let pinned or higher-priority starts stop a lower-priority stream
+    /// when a hardware/CPU transcode limit is full
+    pub allow_preemption: bool,
+    /// MediaMTX listener ports (apply when MediaMTX next starts)
+    pub rtsp_port: u16,
+    pub rtmp_port: u16,
+    pub srt_port: u16,
+    pub hls_port: u16,
+    pub webrtc_port: u16,
+    /// UDP ports for RTSP media (RTP/RTCP) and WebRTC media
+    pub rtp_port: u16,
+    pub rtcp_port: u16,
+    pub webrtc_udp_port: u16,
+    /// MediaMTX control API port, bound to localhost
+    pub api_port: u16,
 }
 
 /// Merge job record
@@ -170,6 +552,8 @@ pub struct MergeJob {
     pub error_message: Option<String>,
     pub created_at: String,
     pub completed_at: Option<String>,
+    /// Merged file, once the job has completed
+    pub output_path: Option<String>,
 }
 
 impl Default for AppSettings {
@@ -178,8 +562,54 @@ impl Default for AppSettings {
             max_total_streams: 50,
             max_transcode_cpu: 8,
             max_transcode_nvenc: 6, // Conservative default
+            max_transcode_qsv: 4,
+            max_transcode_videotoolbox: 4,
+            max_transcode_amd: 4,
             max_total_bitrate_mbps: 500,
+            max_copy_bitrate_mbps: 500,
+            max_transcode_bitrate_mbps: 500,
+            cache_maintenance_interval_mins: 60,
+            cache_max_size_gb: 50,
+            cache_max_age_days: 30,
+            cache_eviction_policy: "lru".to_string(),
+            scan_depth: 2,
+            probe_timeout_secs: 15,
+            enable_hls: false,
+            enable_webrtc: false,
+            webrtc_ice_servers: String::new(),
+            telemetry_history_size: 300,
+            metrics_port: 0,
+            metrics_bind_address: "127.0.0.1".to_string(),
+            db_max_connections: 5,
+            allow_preemption: false,
+            rtsp_port: 8554,
+            rtmp_port: 1935,
+            srt_port: 8890,
+            hls_port: 8888,
+            webrtc_port: 8889,
+            rtp_port: 8000,
+            rtcp_port: 8001,
+            webrtc_udp_port: 8189,
+            api_port: 9997,
+        }
+    }
+}
+
+impl AppSettings {
+    /// Check the values that can't be stored as given: ports, the metrics
+    /// bind address and the cache eviction policy
+    pub fn validate(&self) -> Result<(), String> {
+        PortConfig::from_settings(self).validate().map_err(|e| e.to_string())?;
+        if self.metrics_bind_address.parse::<std::net::IpAddr>().is_err() {
+            return Err(format!(
+                "metrics bind address '{}' is not an IP address",
+                self.metrics_bind_address
+            ));
+        }
+        if EvictionPolicy::parse(&self.cache_eviction_policy).is_none() {
+            return Err(format!("unknown cache eviction policy '{}'", self.cache_eviction_policy));
         }
+        Ok(())
     }
 }
 
@@ -196,6 +626,15 @@ pub async fn insert_default_profiles(pool: &SqlitePool) -> Result<(), sqlx::Erro
             resolution: None,
             gop_size: 30,
             wan_optimized: 0,
+            codec: "h264".into(),
+            record: 0,
+            record_dir: None,
+            srt_latency_ms: None,
+            srt_overhead_percent: None,
+            overlay_text: None,
+            overlay_position: None,
+            overlay_font_size: None,
+            tonemap: 0,
         },
         Profile {
             id: "lan-high".into(),
@@ -207,6 +646,15 @@ pub async fn insert_default_profiles(pool: &SqlitePool) -> Result<(), sqlx::Erro
             resolution: None,
             gop_size: 30,
             wan_optimized: 0,
+            codec: "h264".into(),
+            record: 0,
+            record_dir: None,
+            srt_latency_ms: None,
+            srt_overhead_percent: None,
+            overlay_text: None,
+            overlay_position: None,
+            overlay_font_size: None,
+            tonemap: 0,
         },
         Profile {
             id: "wan-stable".into(),
@@ -218,6 +666,15 @@ pub async fn insert_default_profiles(pool: &SqlitePool) -> Result<(), sqlx::Erro
             resolution: None,
             gop_size: 60,
             wan_optimized: 1,
+            codec: "h264".into(),
+            record: 0,
+            record_dir: None,
+            srt_latency_ms: None,
+            srt_overhead_percent: None,
+            overlay_text: None,
+            overlay_position: None,
+            overlay_font_size: None,
+            tonemap: 0,
         },
         Profile {
             id: "wan-low".into(),
@@ -229,6 +686,15 @@ pub async fn insert_default_profiles(pool: &SqlitePool) -> Result<(), sqlx::Erro
             resolution: Some("1280x720".into()),
             gop_size: 60,
             wan_optimized: 1,
+            codec: "h264".into(),
+            record: 0,
+            record_dir: None,
+            srt_latency_ms: None,
+            srt_overhead_percent: None,
+            overlay_text: None,
+            overlay_position: None,
+            overlay_font_size: None,
+            tonemap: 0,
         },
         Profile {
             id: "rtmp-copy".into(),
@@ -240,6 +706,15 @@ pub async fn insert_default_profiles(pool: &SqlitePool) -> Result<(), sqlx::Erro
             resolution: None,
             gop_size: 30,
             wan_optimized: 0,
+            codec: "h264".into(),
+            record: 0,
+            record_dir: None,
+            srt_latency_ms: None,
+            srt_overhead_percent: None,
+            overlay_text: None,
+            overlay_position: None,
+            overlay_font_size: None,
+            tonemap: 0,
         },
         Profile {
             id: "rtmp-high".into(),
@@ -251,6 +726,15 @@ pub async fn insert_default_profiles(pool: &SqlitePool) -> Result<(), sqlx::Erro
             resolution: None,
             gop_size: 30,
             wan_optimized: 0,
+            codec: "h264".into(),
+            record: 0,
+            record_dir: None,
+            srt_latency_ms: None,
+            srt_overhead_percent: None,
+            overlay_text: None,
+            overlay_position: None,
+            overlay_font_size: None,
+            tonemap: 0,
         },
         Profile {
             id: "rtmp-stream".into(),
@@ -262,14 +746,23 @@ pub async fn insert_default_profiles(pool: &SqlitePool) -> Result<(), sqlx::Erro
             resolution: Some("1280x720".into()),
             gop_size: 60,
             wan_optimized: 1,
+            codec: "h264".into(),
+            record: 0,
+            record_dir: None,
+            srt_latency_ms: None,
+            srt_overhead_percent: None,
+            overlay_text: None,
+            overlay_position: None,
+            overlay_font_size: None,
+            tonemap: 0,
         },
     ];
 
     for p in profiles {
         sqlx::query(
             r#"
-            INSERT OR IGNORE INTO profiles (id, name, protocol, mode, video_bitrate, audio_bitrate, resolution, gop_size, wan_optimized)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT OR IGNORE INTO profiles (id, name, protocol, mode, video_bitrate, audio_bitrate, resolution, gop_size, wan_optimized, codec)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&p.id)
@@ -281,6 +774,7 @@ pub async fn insert_default_profiles(pool: &SqlitePool) -> Result<(), sqlx::Erro
         .bind(&p.resolution)
         .bind(p.gop_size)
         .bind(p.wan_optimized)
+        .bind(&p.codec)
         .execute(pool)
         .await?;
     }
@@ -295,7 +789,44 @@ pub async fn insert_default_settings(pool: &SqlitePool) -> Result<(), sqlx::Erro
         ("max_total_streams", defaults.max_total_streams.to_string()),
         ("max_transcode_cpu", defaults.max_transcode_cpu.to_string()),
         ("max_transcode_nvenc", defaults.max_transcode_nvenc.to_string()),
+        ("max_transcode_qsv", defaults.max_transcode_qsv.to_string()),
+        (
+            "max_transcode_videotoolbox",
+            defaults.max_transcode_videotoolbox.to_string(),
+        ),
+        ("max_transcode_amd", defaults.max_transcode_amd.to_string()),
         ("max_total_bitrate_mbps", defaults.max_total_bitrate_mbps.to_string()),
+        ("max_copy_bitrate_mbps", defaults.max_copy_bitrate_mbps.to_string()),
+        (
+            "max_transcode_bitrate_mbps",
+            defaults.max_transcode_bitrate_mbps.to_string(),
+        ),
+        (
+            "cache_maintenance_interval_mins",
+            defaults.cache_maintenance_interval_mins.to_string(),
+        ),
+        ("cache_max_size_gb", defaults.cache_max_size_gb.to_string()),
+        ("cache_max_age_days", defaults.cache_max_age_days.to_string()),
+        ("cache_eviction_policy", defaults.cache_eviction_policy.clone()),
+        ("scan_depth", defaults.scan_depth.to_string()),
+        ("probe_timeout_secs", defaults.probe_timeout_secs.to_string()),
+        ("enable_hls", defaults.enable_hls.to_string()),
+        ("enable_webrtc", defaults.enable_webrtc.to_string()),
+        ("webrtc_ice_servers", defaults.webrtc_ice_servers.clone()),
+        ("telemetry_history_size", defaults.telemetry_history_size.to_string()),
+        ("metrics_port", defaults.metrics_port.to_string()),
+        ("metrics_bind_address", defaults.metrics_bind_address.clone()),
+        ("db_max_connections", defaults.db_max_connections.to_string()),
+        ("allow_preemption", defaults.allow_preemption.to_string()),
+        ("rtsp_port", defaults.rtsp_port.to_string()),
+        ("rtmp_port", defaults.rtmp_port.to_string()),
+        ("srt_port", defaults.srt_port.to_string()),
+        ("hls_port", defaults.hls_port.to_string()),
+        ("webrtc_port", defaults.webrtc_port.to_string()),
+        ("rtp_port", defaults.rtp_port.to_string()),
+        ("rtcp_port", defaults.rtcp_port.to_string()),
+        ("webrtc_udp_port", defaults.webrtc_udp_port.to_string()),
+        ("api_port", defaults.api_port.to_string()),
     ];
 
     for (key, value) in settings {
@@ -317,26 +848,118 @@ pub async fn get_app_settings(pool: &SqlitePool) -> Result<AppSettings, sqlx::Er
         .fetch_all(pool)
         .await?;
 
+    Ok(settings_from_pairs(
+        rows.iter().map(|row| (row.key.as_str(), row.value.as_str())),
+    ))
+}
+
+/// Typed settings from key/value pairs; later pairs win, unknown keys are
+/// ignored and unreadable values fall back to their defaults
+pub fn settings_from_pairs<'a>(pairs: impl IntoIterator<Item = (&'a str, &'a str)>) -> AppSettings {
     let mut settings = AppSettings::default();
-    for row in rows {
-        match row.key.as_str() {
+    for (key, value) in pairs {
+        match key {
             "max_total_streams" => {
-                settings.max_total_streams = row.value.parse().unwrap_or(50);
+                settings.max_total_streams = value.parse().unwrap_or(50);
             }
             "max_transcode_cpu" => {
-                settings.max_transcode_cpu = row.value.parse().unwrap_or(8);
+                settings.max_transcode_cpu = value.parse().unwrap_or(8);
             }
             "max_transcode_nvenc" => {
-                settings.max_transcode_nvenc = row.value.parse().unwrap_or(6);
+                settings.max_transcode_nvenc = value.parse().unwrap_or(6);
+            }
+            "max_transcode_qsv" => {
+                settings.max_transcode_qsv = value.parse().unwrap_or(4);
+            }
+            "max_transcode_videotoolbox" => {
+                settings.max_transcode_videotoolbox = value.parse().unwrap_or(4);
+            }
+            "max_transcode_amd" => {
+                settings.max_transcode_amd = value.parse().unwrap_or(4);
             }
             "max_total_bitrate_mbps" => {
-                settings.max_total_bitrate_mbps = row.value.parse().unwrap_or(500);
+                settings.max_total_bitrate_mbps = value.parse().unwrap_or(500);
+            }
+            "max_copy_bitrate_mbps" => {
+                settings.max_copy_bitrate_mbps = value.parse().unwrap_or(500);
+            }
+            "max_transcode_bitrate_mbps" => {
+                settings.max_transcode_bitrate_mbps = value.parse().unwrap_or(500);
+            }
+            "cache_maintenance_interval_mins" => {
+                settings.cache_maintenance_interval_mins = value.parse().unwrap_or(60);
+            }
+            "cache_max_size_gb" => {
+                settings.cache_max_size_gb = value.parse().unwrap_or(50);
+            }
+            "cache_max_age_days" => {
+                settings.cache_max_age_days = value.parse().unwrap_or(30);
+            }
+            "cache_eviction_policy" => {
+                settings.cache_eviction_policy = value.to_string();
+            }
+            "scan_depth" => {
+                settings.scan_depth = value.parse().unwrap_or(2);
+            }
+            "probe_timeout_secs" => {
+                settings.probe_timeout_secs = value.parse().unwrap_or(15);
+            }
+            "enable_hls" => {
+                settings.enable_hls = value.parse().unwrap_or(false);
+            }
+            "enable_webrtc" => {
+                settings.enable_webrtc = value.parse().unwrap_or(false);
+            }
+            "webrtc_ice_servers" => {
+                settings.webrtc_ice_servers = value.to_string();
+            }
+            "telemetry_history_size" => {
+                settings.telemetry_history_size = value.parse().unwrap_or(300);
+            }
+            "metrics_port" => {
+                settings.metrics_port = value.parse().unwrap_or(0);
+            }
+            "metrics_bind_address" => {
+                settings.metrics_bind_address = value.to_string();
+            }
+            "allow_preemption" => {
+                settings.allow_preemption = value.parse().unwrap_or(false);
+            }
+            "rtsp_port" => {
+                settings.rtsp_port = value.parse().unwrap_or(8554);
+            }
+            "rtmp_port" => {
+                settings.rtmp_port = value.parse().unwrap_or(1935);
+            }
+            "srt_port" => {
+                settings.srt_port = value.parse().unwrap_or(8890);
+            }
+            "hls_port" => {
+                settings.hls_port = value.parse().unwrap_or(8888);
+            }
+            "webrtc_port" => {
+                settings.webrtc_port = value.parse().unwrap_or(8889);
+            }
+            "rtp_port" => {
+                settings.rtp_port = value.parse().unwrap_or(8000);
+            }
+            "rtcp_port" => {
+                settings.rtcp_port = value.parse().unwrap_or(8001);
+            }
+            "webrtc_udp_port" => {
+                settings.webrtc_udp_port = value.parse().unwrap_or(8189);
+            }
+            "api_port" => {
+                settings.api_port = value.parse().unwrap_or(9997);
+            }
+            "db_max_connections" => {
+                settings.db_max_connections = value.parse().unwrap_or(5);
             }
             _ => {}
         }
     }
 
-    Ok(settings)
+    settings
 }
 
 /// Update a single setting
@@ -0,0 +1,64 @@
+//! One-call health summary of the app's subsystems, for status pages
+
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqlitePool;
+
+/// Subsystem status at a glance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub mediamtx_running: bool,
+    pub running_streams: usize,
+    pub max_streams: usize,
+    pub queued_streams: usize,
+    pub gpu_available: bool,
+    /// Cache is near its size limit; None if its stats couldn't be read
+    pub cache_warning: Option<bool>,
+    pub database_ok: bool,
+    /// MediaMTX is up, the database answers and the cache isn't near full
+    pub healthy: bool,
+}
+
+impl HealthReport {
+    /// Fill in `healthy` from the subsystem fields
+    pub fn with_overall(mut self) -> Self {
+        self.healthy = self.mediamtx_running && self.database_ok && self.cache_warning == Some(false);
+        self
+    }
+}
+
+/// Whether the database answers a trivial query
+pub async fn ping_db(pool: &SqlitePool) -> bool {
+    sqlx::query("SELECT 1").execute(pool).await.is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_health_report() {
+        let pool = crate::db::test_pool().await;
+        let report = HealthReport {
+            mediamtx_running: true,
+            running_streams: 2,
+            max_streams: 50,
+            queued_streams: 0,
+            gpu_available: false,
+            cache_warning: Some(false),
+            database_ok: ping_db(&pool).await,
+            healthy: false,
+        }
+        .with_overall();
+        assert!(report.database_ok);
+        assert!(report.healthy);
+
+        pool.close().await;
+        assert!(!ping_db(&pool).await);
+
+        let down = HealthReport {
+            mediamtx_running: false,
+            ..report
+        };
+        assert!(!down.with_overall().healthy);
+    }
+}
@@ -0,0 +1,273 @@
+//! Error type returned by Tauri commands
+//! Serializes as `{ code, message }` so the UI can branch on `code`;
+//! `message` is the same text the module error displays
+
+use crate::cache::normalize::CacheError;
+use crate::db::config::ConfigError;
+use crate::db::DbError;
+use crate::diagnostics::DiagnosticsError;
+use crate::merge::job::MergeJobError;
+use crate::merge::normalize::NormalizeError;
+use crate::scanner::metadata::ProbeError;
+use crate::scanner::ScannerError;
+use crate::sidecar::SidecarError;
+use crate::stream::StreamError;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AppError {
+    /// A stream, file, profile, job or cache entry doesn't exist
+    #[error("{0}")]
+    NotFound(String),
+    /// The request itself is invalid (bad name, URL, schedule, config file)
+    #[error("{0}")]
+    InvalidInput(String),
+    /// A stream or transcode limit is full; the start was queued or refused
+    #[error("{0}")]
+    LimitReached(String),
+    /// FFmpeg or FFprobe isn't installed or can't be found
+    #[error("{0}")]
+    FfmpegMissing(String),
+    /// Already exists, already running, or still in use
+    #[error("{0}")]
+    Conflict(String),
+    /// MediaMTX isn't running or didn't answer
+    #[error("{0}")]
+    Unavailable(String),
+    #[error("{0}")]
+    Database(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl AppError {
+    /// Stable identifier the frontend matches on
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "not_found",
+            Self::InvalidInput(_) => "invalid_input",
+            Self::LimitReached(_) => "limit_reached",
+            Self::FfmpegMissing(_) => "ffmpeg_missing",
+            Self::Conflict(_) => "conflict",
+            Self::Unavailable(_) => "unavailable",
+            Self::Database(_) => "database",
+            Self::Internal(_) => "internal",
+        }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("AppError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+/// Ad-hoc messages from command code
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::Internal(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        Self::Internal(message.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => Self::NotFound(e.to_string()),
+            _ => Self::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::RowNotFound => Self::NotFound(e.to_string()),
+            _ => Self::Database(e.to_string()),
+        }
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        Self::Internal(e.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for AppError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        Self::Internal(e.to_string())
+    }
+}
+
+impl From<StreamError> for AppError {
+    fn from(e: StreamError) -> Self {
+        let message = e.to_string();
+        match e {
+            StreamError::Db(_) => Self::Database(message),
+            StreamError::NotFound(_) => Self::NotFound(message),
+            StreamError::AlreadyExists(_)
+            | StreamError::AlreadyRunning
+            | StreamError::ProfileInUse(_) => Self::Conflict(message),
+            StreamError::NotPublishing(_) => Self::Unavailable(message),
+            StreamError::Process(_) => Self::Internal(message),
+            StreamError::FfmpegNotFound => Self::FfmpegMissing(message),
+            StreamError::InvalidProfile(_)
+            | StreamError::InvalidOutput(_)
+            | StreamError::InvalidTag(_)
+            | StreamError::InvalidName(_)
+            | StreamError::InvalidSchedule(_)
+            | StreamError::InvalidPlaylist(_)
+            | StreamError::InvalidAudioTrack(_)
+            | StreamError::InvalidSubtitles(_)
+            | StreamError::InvalidExtraArgs(_) => Self::InvalidInput(message),
+        }
+    }
+}
+
+impl From<ScannerError> for AppError {
+    fn from(e: ScannerError) -> Self {
+        let message = e.to_string();
+        match e {
+            ScannerError::Db(_) => Self::Database(message),
+            ScannerError::NotFound(_) => Self::NotFound(message),
+            ScannerError::InvalidUrl(_) => Self::InvalidInput(message),
+            ScannerError::Io(_) | ScannerError::Probe(_) => Self::Internal(message),
+        }
+    }
+}
+
+impl From<ProbeError> for AppError {
+    fn from(e: ProbeError) -> Self {
+        let message = e.to_string();
+        match e {
+            ProbeError::NotFound => Self::FfmpegMissing(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<NormalizeError> for AppError {
+    fn from(e: NormalizeError) -> Self {
+        let message = e.to_string();
+        match e {
+            NormalizeError::FfmpegNotFound => Self::FfmpegMissing(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<MergeJobError> for AppError {
+    fn from(e: MergeJobError) -> Self {
+        let message = e.to_string();
+        match e {
+            MergeJobError::Db(_) => Self::Database(message),
+            MergeJobError::NotFound(_) | MergeJobError::FileNotFound(_) => Self::NotFound(message),
+            MergeJobError::InvalidState(..) => Self::Conflict(message),
+            MergeJobError::NoFiles => Self::InvalidInput(message),
+            MergeJobError::FfmpegNotFound => Self::FfmpegMissing(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<CacheError> for AppError {
+    fn from(e: CacheError) -> Self {
+        let message = e.to_string();
+        match e {
+            CacheError::Database(_) => Self::Database(message),
+            CacheError::SourceNotFound(_) | CacheError::EntryNotFound(_) => Self::NotFound(message),
+            CacheError::InUse(_) => Self::Conflict(message),
+            CacheError::ProbeUnavailable | CacheError::Normalize(NormalizeError::FfmpegNotFound) => {
+                Self::FfmpegMissing(message)
+            }
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<SidecarError> for AppError {
+    fn from(e: SidecarError) -> Self {
+        let message = e.to_string();
+        match e {
+            SidecarError::AlreadyRunning | SidecarError::Conflict(_) => Self::Conflict(message),
+            SidecarError::NotRunning | SidecarError::NotReady(_) | SidecarError::ApiError(_) => {
+                Self::Unavailable(message)
+            }
+            SidecarError::ConfigError(_) => Self::InvalidInput(message),
+            SidecarError::SpawnError(_) | SidecarError::IoError(_) => Self::Internal(message),
+        }
+    }
+}
+
+impl From<DbError> for AppError {
+    fn from(e: DbError) -> Self {
+        match e {
+            DbError::Io(_) => Self::Internal(e.to_string()),
+            _ => Self::Database(e.to_string()),
+        }
+    }
+}
+
+impl From<ConfigError> for AppError {
+    fn from(e: ConfigError) -> Self {
+        let message = e.to_string();
+        match e {
+            ConfigError::Db(_) => Self::Database(message),
+            ConfigError::Json(_) | ConfigError::UnsupportedVersion { .. } | ConfigError::Invalid(_) => {
+                Self::InvalidInput(message)
+            }
+            ConfigError::Io(_) => Self::Internal(message),
+        }
+    }
+}
+
+impl From<DiagnosticsError> for AppError {
+    fn from(e: DiagnosticsError) -> Self {
+        let message = e.to_string();
+        match e {
+            DiagnosticsError::Db(_) | DiagnosticsError::Sqlx(_) => Self::Database(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_code_and_message() {
+        let error = AppError::from(StreamError::NotFound("s1".into()));
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["code"], "not_found");
+        assert_eq!(json["message"], "Stream not found: s1");
+        // Display is unchanged from the module error
+        assert_eq!(error.to_string(), "Stream not found: s1");
+    }
+
+    #[test]
+    fn test_module_errors_map_to_codes() {
+        assert_eq!(AppError::from(StreamError::InvalidName("x".into())).code(), "invalid_input");
+        assert_eq!(AppError::from(StreamError::ProfileInUse(2)).code(), "conflict");
+        assert_eq!(AppError::from(StreamError::AlreadyRunning).to_string(), "Stream already running");
+        assert_eq!(AppError::from(ProbeError::NotFound).code(), "ffmpeg_missing");
+        assert_eq!(AppError::from(StreamError::FfmpegNotFound).code(), "ffmpeg_missing");
+        assert_eq!(
+            AppError::from(CacheError::Normalize(NormalizeError::FfmpegNotFound)).code(),
+            "ffmpeg_missing"
+        );
+        assert_eq!(AppError::from(SidecarError::NotRunning).code(), "unavailable");
+        assert_eq!(AppError::from(sqlx::Error::RowNotFound).code(), "not_found");
+        assert_eq!(AppError::from("boom".to_string()).code(), "internal");
+    }
+}
@@ -1,6 +1,12 @@
 //! GPU capability detection module
-//! Detects NVENC availability and manages encoder selection
+//! Detects NVENC/QSV/VideoToolbox/AMD availability and manages encoder selection
 
+pub mod amd;
 pub mod nvenc;
+pub mod qsv;
+pub mod videotoolbox;
 
+pub use amd::{detect_amd, AmdCapability};
 pub use nvenc::{detect_nvenc, NvencCapability};
+pub use qsv::{detect_qsv, QsvCapability};
+pub use videotoolbox::{detect_videotoolbox, VideoToolboxCapability};
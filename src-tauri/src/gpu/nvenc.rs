@@ -1,10 +1,33 @@
 //! NVENC detection and capability probing
 //! Detects NVIDIA encoder availability and estimates max sessions
 
+use super::QsvCapability;
 use serde::{Deserialize, Serialize};
-use std::process::Command;
+use std::future::Future;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
 use std::time::Duration;
-use tokio::time::timeout;
+use tokio::time::{timeout_at, Instant};
+
+/// Upper bound for the session probe, keeps its runtime bounded
+const MAX_PROBE_SESSIONS: u32 = 16;
+
+/// Time allowed for detection
+const DETECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Time allowed for the session probe, whose batches each run for about a
+/// second so their sessions overlap
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Session ceiling found by the latest `probe_max_sessions`
+static PROBED_MAX_SESSIONS: Mutex<Option<u32>> = Mutex::new(None);
+
+/// An NVIDIA GPU as reported by nvidia-smi
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GpuInfo {
+    pub index: u32,
+    pub name: String,
+}
 
 /// NVENC capability information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,7 +36,10 @@ pub struct NvencCapability {
     pub max_sessions: u32,
     pub h264_nvenc: bool,
     pub hevc_nvenc: bool,
+    pub av1_nvenc: bool,
+    /// Name of the first GPU
     pub gpu_name: Option<String>,
+    pub gpus: Vec<GpuInfo>,
 }
 
 impl Default for NvencCapability {
@@ -23,21 +49,48 @@ impl Default for NvencCapability {
             max_sessions: 0,
             h264_nvenc: false,
             hevc_nvenc: false,
+            av1_nvenc: false,
             gpu_name: None,
+            gpus: Vec::new(),
         }
     }
 }
 
 /// Detect NVENC capabilities
-/// Returns capability info within 3s timeout
-pub async fn detect_nvenc() -> NvencCapability {
-    match timeout(Duration::from_secs(3), detect_nvenc_internal()).await {
+/// Detection is bounded by a 3s timeout. When NVENC works and `probe_sessions`
+/// is set, the session limit is probed again, bounded by `PROBE_TIMEOUT`;
+/// callers only set it while no NVENC stream is live, since live sessions
+/// would be counted against the limit. Otherwise the last probed limit (or
+/// the estimate) is used.
+pub async fn detect_nvenc(probe_sessions: bool) -> NvencCapability {
+    let mut cap = match timeout_at(Instant::now() + DETECT_TIMEOUT, detect_nvenc_internal()).await {
         Ok(cap) => cap,
         Err(_) => {
             println!("[GPU] NVENC detection timed out");
-            NvencCapability::default()
+            return NvencCapability::default();
         }
+    };
+    if !cap.available {
+        return cap;
     }
+
+    let probed = if probe_sessions {
+        // Test encodes still running when it times out are killed on drop
+        match timeout_at(Instant::now() + PROBE_TIMEOUT, probe_max_sessions()).await {
+            Ok(max) => max,
+            Err(_) => {
+                println!("[GPU] NVENC session probe timed out");
+                None
+            }
+        }
+    } else {
+        None
+    };
+    if let Some(max) = probed.or_else(cached_max_sessions) {
+        cap.max_sessions = max;
+    }
+
+    cap
 }
 
 async fn detect_nvenc_internal() -> NvencCapability {
@@ -58,23 +111,16 @@ async fn detect_nvenc_internal() -> NvencCapability {
 
     let h264_nvenc = encoder_output.contains("h264_nvenc");
     let hevc_nvenc = encoder_output.contains("hevc_nvenc");
+    let av1_nvenc = encoder_output.contains("av1_nvenc");
 
     if !h264_nvenc {
         println!("[GPU] h264_nvenc encoder not found in FFmpeg");
         return NvencCapability::default();
     }
 
-    // Step 2: Get GPU name from nvidia-smi
-    let gpu_name = tokio::task::spawn_blocking(|| {
-        Command::new("nvidia-smi")
-            .args(["--query-gpu=name", "--format=csv,noheader"])
-            .output()
-    })
-    .await
-    .ok()
-    .and_then(|r| r.ok())
-    .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
-    .filter(|s| !s.is_empty());
+    // Step 2: Enumerate GPUs with nvidia-smi
+    let gpus = list_gpus().await;
+    let gpu_name = gpus.first().map(|g| g.name.clone());
 
     // Step 3: Test actual NVENC encoding
     let test_result = tokio::task::spawn_blocking(|| {
@@ -110,7 +156,9 @@ async fn detect_nvenc_internal() -> NvencCapability {
             max_sessions: 0,
             h264_nvenc,
             hevc_nvenc,
+            av1_nvenc,
             gpu_name,
+            gpus,
         };
     }
 
@@ -118,8 +166,10 @@ async fn detect_nvenc_internal() -> NvencCapability {
     let max_sessions = estimate_max_sessions(gpu_name.as_deref());
 
     println!(
-        "[GPU] NVENC available: GPU={:?}, max_sessions={}",
-        gpu_name, max_sessions
+        "[GPU] NVENC available: GPU={:?}, gpus={}, max_sessions={}",
+        gpu_name,
+        gpus.len(),
+        max_sessions
     );
 
     NvencCapability {
@@ -127,7 +177,136 @@ async fn detect_nvenc_internal() -> NvencCapability {
         max_sessions,
         h264_nvenc,
         hevc_nvenc,
+        av1_nvenc,
         gpu_name,
+        gpus,
+    }
+}
+
+/// List NVIDIA GPUs; empty when nvidia-smi is missing or fails
+pub async fn list_gpus() -> Vec<GpuInfo> {
+    let output = tokio::task::spawn_blocking(|| {
+        Command::new("nvidia-smi")
+            .args(["--query-gpu=index,name", "--format=csv,noheader"])
+            .output()
+    })
+    .await
+    .ok()
+    .and_then(|r| r.ok())
+    .filter(|o| o.status.success());
+
+    output
+        .map(|o| parse_gpu_list(&String::from_utf8_lossy(&o.stdout)))
+        .unwrap_or_default()
+}
+
+/// Parse `index, name` lines from nvidia-smi CSV output
+fn parse_gpu_list(output: &str) -> Vec<GpuInfo> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (index, name) = line.split_once(',')?;
+            Some(GpuInfo {
+                index: index.trim().parse().ok()?,
+                name: name.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Result of one test encode during session probing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProbeOutcome {
+    Ok,
+    SessionLimit,
+    Failed,
+}
+
+/// Session ceiling found by the latest probe, if any
+pub fn cached_max_sessions() -> Option<u32> {
+    *PROBED_MAX_SESSIONS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Probe the real NVENC session limit by running increasing numbers of
+/// concurrent test encodes until the driver refuses one, and remember it
+/// Returns None when the probe is inconclusive (e.g. encodes fail for other reasons)
+async fn probe_max_sessions() -> Option<u32> {
+    let max = probe_max_sessions_with(MAX_PROBE_SESSIONS, run_test_encodes).await?;
+    println!("[GPU] Probed NVENC session limit: {}", max);
+    *PROBED_MAX_SESSIONS.lock().unwrap_or_else(|e| e.into_inner()) = Some(max);
+    Some(max)
+}
+
+async fn probe_max_sessions_with<F, Fut>(limit: u32, run_batch: F) -> Option<u32>
+where
+    F: Fn(u32) -> Fut,
+    Fut: Future<Output = Vec<ProbeOutcome>>,
+{
+    for n in 1..=limit {
+        let outcomes = run_batch(n).await;
+        if outcomes.contains(&ProbeOutcome::SessionLimit) {
+            // Refused at the first session means something else holds them;
+            // one is the least the GPU can do
+            return Some((n - 1).max(1));
+        }
+        if outcomes.len() as u32 != n || outcomes.contains(&ProbeOutcome::Failed) {
+            return None;
+        }
+    }
+    Some(limit)
+}
+
+/// Run `count` concurrent NVENC test encodes
+/// Each runs in real time for ~1s so that all sessions overlap. They are
+/// killed if the probe is dropped before they finish.
+async fn run_test_encodes(count: u32) -> Vec<ProbeOutcome> {
+    let children: Vec<_> = (0..count)
+        .filter_map(|_| {
+            tokio::process::Command::new("ffmpeg")
+                .args([
+                    "-hide_banner",
+                    "-loglevel",
+                    "error",
+                    "-re",
+                    "-f",
+                    "lavfi",
+                    "-i",
+                    "color=c=black:s=256x144:d=1",
+                    "-c:v",
+                    "h264_nvenc",
+                    "-f",
+                    "null",
+                    "-",
+                ])
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .ok()
+        })
+        .collect();
+
+    let mut outcomes = Vec::with_capacity(children.len());
+    for child in children {
+        outcomes.push(match child.wait_with_output().await {
+            Ok(output) if output.status.success() => ProbeOutcome::Ok,
+            Ok(output) => classify_failure(&String::from_utf8_lossy(&output.stderr)),
+            Err(_) => ProbeOutcome::Failed,
+        });
+    }
+    outcomes
+}
+
+/// Distinguish "too many sessions" from other encode failures
+fn classify_failure(stderr: &str) -> ProbeOutcome {
+    let stderr = stderr.to_lowercase();
+    if stderr.contains("openencodesessionex failed")
+        || stderr.contains("incompatible client key")
+        || stderr.contains("maximum number")
+    {
+        ProbeOutcome::SessionLimit
+    } else {
+        ProbeOutcome::Failed
     }
 }
 
@@ -171,30 +350,52 @@ fn estimate_max_sessions(gpu_name: Option<&str>) -> u32 {
 }
 
 /// Select encoder based on preference and availability
+/// Returns the encoder name and whether it is hardware accelerated.
+/// NVENC encodes `codec`; the other encoders are H.264.
 pub fn select_encoder(
     preferred: &str,
+    codec: &str,
     nvenc_cap: &NvencCapability,
     nvenc_used: u32,
+    qsv_cap: &QsvCapability,
 ) -> (String, bool) {
+    let nvenc = match codec {
+        "h264" if nvenc_cap.h264_nvenc => Some("h264_nvenc"),
+        "hevc" if nvenc_cap.hevc_nvenc => Some("hevc_nvenc"),
+        "av1" if nvenc_cap.av1_nvenc => Some("av1_nvenc"),
+        _ => None,
+    }
+    .filter(|_| nvenc_cap.available && nvenc_used < nvenc_cap.max_sessions);
+
     match preferred {
         "auto" => {
-            if nvenc_cap.available && nvenc_used < nvenc_cap.max_sessions {
-                ("h264_nvenc".into(), true)
+            if let Some(encoder) = nvenc {
+                (encoder.into(), true)
+            } else if qsv_cap.available {
+                ("h264_qsv".into(), true)
             } else {
                 ("libx264".into(), false)
             }
         }
         "nvenc" => {
-            if nvenc_cap.available && nvenc_used < nvenc_cap.max_sessions {
-                ("h264_nvenc".into(), true)
+            if let Some(encoder) = nvenc {
+                (encoder.into(), true)
             } else {
                 println!(
-                    "[GPU] NVENC requested but unavailable/saturated (used={}/max={}), falling back to CPU",
-                    nvenc_used, nvenc_cap.max_sessions
+                    "[GPU] NVENC {} requested but unavailable/saturated (used={}/max={}), falling back to CPU",
+                    codec, nvenc_used, nvenc_cap.max_sessions
                 );
                 ("libx264".into(), false)
             }
         }
+        "qsv" => {
+            if qsv_cap.available {
+                ("h264_qsv".into(), true)
+            } else {
+                println!("[GPU] QSV requested but unavailable, falling back to CPU");
+                ("libx264".into(), false)
+            }
+        }
         "cpu" => ("libx264".into(), false),
         "copy" => ("copy".into(), false),
         _ => ("libx264".into(), false),
@@ -221,25 +422,116 @@ mod tests {
             max_sessions: 8,
             h264_nvenc: true,
             hevc_nvenc: true,
+            av1_nvenc: false,
             gpu_name: Some("RTX 4090".into()),
+            gpus: Vec::new(),
         };
 
-        let (encoder, is_nvenc) = select_encoder("auto", &cap, 0);
+        let (encoder, is_nvenc) = select_encoder("auto", "h264", &cap, 0, &QsvCapability::default());
         assert_eq!(encoder, "h264_nvenc");
         assert!(is_nvenc);
 
         // Saturated
-        let (encoder, is_nvenc) = select_encoder("auto", &cap, 8);
+        let (encoder, is_nvenc) = select_encoder("auto", "h264", &cap, 8, &QsvCapability::default());
         assert_eq!(encoder, "libx264");
         assert!(!is_nvenc);
     }
 
+    #[test]
+    fn test_select_encoder_by_codec() {
+        let cap = NvencCapability {
+            available: true,
+            max_sessions: 8,
+            h264_nvenc: true,
+            hevc_nvenc: true,
+            av1_nvenc: true,
+            gpu_name: Some("RTX 4090".into()),
+            gpus: Vec::new(),
+        };
+        let qsv = QsvCapability::default();
+
+        assert_eq!(select_encoder("nvenc", "hevc", &cap, 0, &qsv), ("hevc_nvenc".into(), true));
+        assert_eq!(select_encoder("nvenc", "av1", &cap, 0, &qsv), ("av1_nvenc".into(), true));
+
+        // Older GPUs without AV1 fall back
+        let no_av1 = NvencCapability { av1_nvenc: false, ..cap };
+        assert_eq!(select_encoder("nvenc", "av1", &no_av1, 0, &qsv), ("libx264".into(), false));
+    }
+
     #[test]
     fn test_select_encoder_fallback() {
         let cap = NvencCapability::default();
 
-        let (encoder, is_nvenc) = select_encoder("nvenc", &cap, 0);
+        let (encoder, is_nvenc) = select_encoder("nvenc", "h264", &cap, 0, &QsvCapability::default());
         assert_eq!(encoder, "libx264");
         assert!(!is_nvenc);
     }
+
+    #[test]
+    fn test_select_encoder_qsv() {
+        let nvenc = NvencCapability::default();
+        let qsv = QsvCapability {
+            available: true,
+            h264_qsv: true,
+            hevc_qsv: false,
+        };
+
+        assert_eq!(select_encoder("qsv", "h264", &nvenc, 0, &qsv), ("h264_qsv".into(), true));
+        // Auto uses QSV when NVENC is missing
+        assert_eq!(select_encoder("auto", "h264", &nvenc, 0, &qsv), ("h264_qsv".into(), true));
+        assert_eq!(
+            select_encoder("qsv", "h264", &nvenc, 0, &QsvCapability::default()),
+            ("libx264".into(), false)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_stops_at_session_limit() {
+        // Driver allows 5 concurrent sessions
+        let max = probe_max_sessions_with(16, |n| async move {
+            (0..n)
+                .map(|i| if i < 5 { ProbeOutcome::Ok } else { ProbeOutcome::SessionLimit })
+                .collect()
+        })
+        .await;
+        assert_eq!(max, Some(5));
+
+        // Never reported as zero sessions
+        let max = probe_max_sessions_with(16, |n| async move {
+            vec![ProbeOutcome::SessionLimit; n as usize]
+        })
+        .await;
+        assert_eq!(max, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_probe_capped_and_inconclusive() {
+        let max = probe_max_sessions_with(16, |n| async move { vec![ProbeOutcome::Ok; n as usize] }).await;
+        assert_eq!(max, Some(16));
+
+        let max = probe_max_sessions_with(16, |n| async move { vec![ProbeOutcome::Failed; n as usize] }).await;
+        assert_eq!(max, None);
+    }
+
+    #[test]
+    fn test_parse_gpu_list() {
+        let gpus = parse_gpu_list("0, NVIDIA GeForce RTX 4090\n1, NVIDIA RTX A4000\n\n");
+        assert_eq!(
+            gpus,
+            vec![
+                GpuInfo { index: 0, name: "NVIDIA GeForce RTX 4090".into() },
+                GpuInfo { index: 1, name: "NVIDIA RTX A4000".into() },
+            ]
+        );
+        assert!(parse_gpu_list("NVIDIA-SMI has failed").is_empty());
+    }
+
+    #[test]
+    fn test_classify_failure() {
+        assert_eq!(
+            classify_failure("[h264_nvenc] OpenEncodeSessionEx failed: incompatible client key (21)"),
+            ProbeOutcome::SessionLimit
+        );
+        assert_eq!(classify_failure("Cannot load libcuda.so.1"), ProbeOutcome::Failed);
+    }
 }
@@ -0,0 +1,96 @@
+//! Apple VideoToolbox detection
+//! VideoToolbox only exists on macOS; other platforms always report unavailable
+
+use serde::{Deserialize, Serialize};
+
+/// VideoToolbox capability information
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VideoToolboxCapability {
+    pub available: bool,
+    pub h264_videotoolbox: bool,
+    pub hevc_videotoolbox: bool,
+}
+
+/// Detect VideoToolbox encoders
+/// Returns capability info within 3s timeout
+#[cfg(target_os = "macos")]
+pub async fn detect_videotoolbox() -> VideoToolboxCapability {
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    match timeout(Duration::from_secs(3), detect_videotoolbox_internal()).await {
+        Ok(cap) => cap,
+        Err(_) => {
+            println!("[GPU] VideoToolbox detection timed out");
+            VideoToolboxCapability::default()
+        }
+    }
+}
+
+/// Detect VideoToolbox encoders (never available off macOS)
+#[cfg(not(target_os = "macos"))]
+pub async fn detect_videotoolbox() -> VideoToolboxCapability {
+    VideoToolboxCapability::default()
+}
+
+#[cfg(target_os = "macos")]
+async fn detect_videotoolbox_internal() -> VideoToolboxCapability {
+    use std::process::Command;
+
+    // Step 1: Check if ffmpeg was built with VideoToolbox encoders
+    let encoder_output = tokio::task::spawn_blocking(|| {
+        Command::new("ffmpeg")
+            .args(["-hide_banner", "-encoders"])
+            .output()
+    })
+    .await
+    .ok()
+    .and_then(|r| r.ok())
+    .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+    .unwrap_or_default();
+
+    let h264_videotoolbox = encoder_output.contains("h264_videotoolbox");
+    let hevc_videotoolbox = encoder_output.contains("hevc_videotoolbox");
+
+    if !h264_videotoolbox {
+        println!("[GPU] h264_videotoolbox encoder not found in FFmpeg");
+        return VideoToolboxCapability::default();
+    }
+
+    // Step 2: Test actual encoding
+    let test_result = tokio::task::spawn_blocking(|| {
+        Command::new("ffmpeg")
+            .args([
+                "-hide_banner",
+                "-loglevel",
+                "error",
+                "-f",
+                "lavfi",
+                "-i",
+                "color=c=black:s=320x240:d=0.1",
+                "-c:v",
+                "h264_videotoolbox",
+                "-f",
+                "null",
+                "-",
+            ])
+            .output()
+    })
+    .await
+    .ok()
+    .and_then(|r| r.ok());
+
+    let available = test_result.map(|o| o.status.success()).unwrap_or(false);
+
+    if available {
+        println!("[GPU] VideoToolbox available: hevc={}", hevc_videotoolbox);
+    } else {
+        println!("[GPU] VideoToolbox test encode failed");
+    }
+
+    VideoToolboxCapability {
+        available,
+        h264_videotoolbox,
+        hevc_videotoolbox,
+    }
+}
@@ -4,6 +4,7 @@
 mod cache;
 mod db;
 mod diagnostics;
+mod error;
 mod gpu;
 mod merge;
 mod scanner;
@@ -13,19 +14,24 @@ mod sidecar;
 mod stream;
 mod telemetry;
 
-use cache::{CacheConfig, CacheManager, CacheStats};
-use db::schema::{AppSettings, MediaFile, MergeJob, Profile, Stream};
+use cache::{CacheConfig, CacheManager, CachePin, CacheStats};
+use db::schema::{
+    AppSettings, MediaFile, MergeJob, Profile, Recording, Stream, StreamEventRecord, StreamOutput,
+};
+use error::AppError;
 use merge::check_merge_compatibility;
 use scheduler::limits::CapacitySummary;
 use scheduler::{create_shared_scheduler, BatchResult, SharedScheduler, StreamInfo};
 use sidecar::mediamtx;
 use sqlx::sqlite::SqlitePool;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
-use stream::supervisor::{create_shared_supervisor, SharedSupervisor};
-use tauri::{Manager, State};
-use telemetry::{create_shared_telemetry, SharedTelemetry, TelemetryMetrics};
-use tokio::sync::Mutex;
+use stream::supervisor::{create_shared_supervisor, SharedSupervisor, StreamEvent};
+use tauri::{Emitter, Manager, State};
+use telemetry::history::TelemetrySample;
+use telemetry::{create_shared_telemetry, SharedTelemetry, StreamStats, TelemetryMetrics};
+use tokio::sync::{mpsc, Mutex, Notify};
 
 /// Application state
 pub struct AppState {
@@ -35,24 +41,43 @@ pub struct AppState {
     pub scheduler: SharedScheduler,
     pub telemetry: SharedTelemetry,
     pub cache_manager: Arc<Mutex<CacheManager>>,
+    pub merge_jobs: merge::MergeRegistry,
+    /// Default location for stream recordings
+    pub recordings_dir: PathBuf,
+    /// Concat lists of running playlist streams
+    pub playlists_dir: PathBuf,
+    /// Cache files each running stream plays, kept from cleanup until it stops
+    pub cache_pins: Mutex<HashMap<String, Vec<CachePin>>>,
 }
 
 // ============ MediaMTX Commands ============
 
 #[tauri::command]
-async fn start_mediamtx(state: State<'_, AppState>) -> Result<(), String> {
+async fn start_mediamtx(state: State<'_, AppState>) -> Result<(), AppError> {
+    let settings = db::schema::get_app_settings(&state.db)
+        .await?;
+
     let mut manager = state.mediamtx.lock().await;
-    manager.start().await.map_err(|e| e.to_string())
+    // A running server keeps the ports it was started with
+    if !manager.is_running() {
+        manager.set_ports(mediamtx::PortConfig::from_settings(&settings));
+    }
+    manager.set_delivery_options(mediamtx::DeliveryOptions {
+        enable_hls: settings.enable_hls,
+        enable_webrtc: settings.enable_webrtc,
+        webrtc_ice_servers: mediamtx::parse_ice_servers(&settings.webrtc_ice_servers),
+    });
+    manager.start().await.map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn stop_mediamtx(state: State<'_, AppState>) -> Result<(), String> {
+async fn stop_mediamtx(state: State<'_, AppState>) -> Result<(), AppError> {
     let mut manager = state.mediamtx.lock().await;
-    manager.stop().await.map_err(|e| e.to_string())
+    manager.stop().await.map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn get_mediamtx_status(state: State<'_, AppState>) -> Result<String, String> {
+async fn get_mediamtx_status(state: State<'_, AppState>) -> Result<String, AppError> {
     let mut manager = state.mediamtx.lock().await;
     Ok(if manager.is_running() { "running" } else { "stopped" }.to_string())
 }
@@ -60,35 +85,164 @@ async fn get_mediamtx_status(state: State<'_, AppState>) -> Result<String, Strin
 // ============ Scanner Commands ============
 
 #[tauri::command]
-async fn scan_folder(state: State<'_, AppState>, folder_path: String) -> Result<Vec<MediaFile>, String> {
+async fn scan_folder(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    folder_path: String,
+    depth: Option<usize>,
+    recursive: Option<bool>,
+    max_workers: Option<usize>,
+) -> Result<Vec<MediaFile>, AppError> {
     let path = PathBuf::from(&folder_path);
     if !path.exists() {
-        return Err(format!("Folder not found: {}", folder_path));
+        return Err(AppError::NotFound(format!("Folder not found: {}", folder_path)));
     }
-    scanner::scan_folder(&state.db, &path)
+    let settings = db::schema::get_app_settings(&state.db)
+        .await?;
+    let options = scanner::ScanOptions {
+        depth: scanner::resolve_depth(depth, recursive.unwrap_or(false), settings.scan_depth as usize),
+        max_workers: max_workers.unwrap_or_else(scanner::default_workers),
+        thumbs_dir: Some(thumbs_dir(&app)?),
+        probe_timeout: probe_timeout(&settings),
+    };
+
+    // Forward per-file progress to the frontend while the scan runs
+    let (progress_tx, mut progress_rx) = tokio::sync::mpsc::channel(64);
+    let progress_app = app.clone();
+    let forwarder = tauri::async_runtime::spawn(async move {
+        while let Some(progress) = progress_rx.recv().await {
+            let _ = progress_app.emit("scan-progress", progress);
+        }
+    });
+
+    let result = scanner::scan_folder(&state.db, &path, &options, Some(progress_tx)).await;
+    let _ = forwarder.await;
+
+    let _ = app.emit(
+        "scan-complete",
+        ScanComplete {
+            folder_path,
+            added: result.as_ref().map(|files| files.len()).unwrap_or(0),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        },
+    );
+
+    result.map_err(AppError::from)
+}
+
+/// Per-file ffprobe limit from settings, at least one second
+fn probe_timeout(settings: &AppSettings) -> std::time::Duration {
+    std::time::Duration::from_secs(settings.probe_timeout_secs.max(1) as u64)
+}
+
+/// Payload of the `scan-complete` event
+#[derive(Debug, Clone, serde::Serialize)]
+struct ScanComplete {
+    folder_path: String,
+    added: usize,
+    error: Option<String>,
+}
+
+#[tauri::command]
+async fn rescan_folder(
+    state: State<'_, AppState>,
+    folder_path: String,
+    depth: Option<usize>,
+    recursive: Option<bool>,
+) -> Result<scanner::RescanResult, AppError> {
+    let path = PathBuf::from(&folder_path);
+    if !path.exists() {
+        return Err(AppError::NotFound(format!("Folder not found: {}", folder_path)));
+    }
+    let settings = db::schema::get_app_settings(&state.db)
+        .await?;
+    let depth = scanner::resolve_depth(depth, recursive.unwrap_or(false), settings.scan_depth as usize);
+    let result = scanner::rescan_folder(&state.db, &path, depth, probe_timeout(&settings))
+        .await?;
+
+    if result.updated > 0 {
+        let ids = stream::get_all_streams(&state.db)
+            .await?
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        resize_streams(&state, ids).await;
+    }
+    Ok(result)
+}
+
+/// Re-probe a single media file and refresh its compatibility
+#[tauri::command]
+async fn rescan_file(state: State<'_, AppState>, media_id: String) -> Result<MediaFile, AppError> {
+    let settings = db::schema::get_app_settings(&state.db)
+        .await?;
+    let file = scanner::rescan_file(&state.db, &media_id, probe_timeout(&settings))
+        .await?;
+
+    let ids = stream::streams_using_media(&state.db, &media_id).await?;
+    resize_streams(&state, ids).await;
+    Ok(file)
+}
+
+/// Re-size streams whose sources were re-probed, so limits count them in
+/// the mode and bitrate they will start with
+async fn resize_streams(state: &AppState, ids: Vec<String>) {
+    for id in ids {
+        // Streams left without a source can't start, so there's nothing to size
+        let Ok(plan) = load_launch_plan(&state.db, &id).await else {
+            continue;
+        };
+        state.scheduler.lock().await.resize_stream(&id, launch_size(&plan));
+    }
+}
+
+/// Add a live RTSP/HTTP/SRT source URL that streams can restream
+#[tauri::command]
+async fn add_url_source(state: State<'_, AppState>, url: String) -> Result<MediaFile, AppError> {
+    scanner::add_url_source(&state.db, &url)
         .await
+        .map_err(AppError::from)
+}
+
+/// Poster thumbnails live alongside the normalize cache
+fn thumbs_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("cache").join("thumbs"))
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn get_media_files(state: State<'_, AppState>) -> Result<Vec<MediaFile>, String> {
+async fn get_thumbnail(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    media_id: String,
+) -> Result<Option<String>, AppError> {
+    scanner::thumbnail::get_thumbnail(&state.db, &media_id, &thumbs_dir(&app)?)
+        .await
+        .map(|p| p.map(|p| p.to_string_lossy().to_string()))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn get_media_files(state: State<'_, AppState>) -> Result<Vec<MediaFile>, AppError> {
     scanner::get_all_media_files(&state.db)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn get_media_file(state: State<'_, AppState>, id: String) -> Result<Option<MediaFile>, String> {
+async fn get_media_file(state: State<'_, AppState>, id: String) -> Result<Option<MediaFile>, AppError> {
     scanner::get_media_file(&state.db, &id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn delete_media_file(state: State<'_, AppState>, id: String) -> Result<(), String> {
+async fn delete_media_file(state: State<'_, AppState>, id: String) -> Result<(), AppError> {
     scanner::delete_media_file(&state.db, &id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 // ============ Stream Commands ============
@@ -99,275 +253,1629 @@ async fn create_stream(
     media_file_id: String,
     name: String,
     profile_id: String,
-) -> Result<Stream, String> {
+) -> Result<Stream, AppError> {
     let stream = stream::create_stream(&state.db, &media_file_id, &name, &profile_id)
+        .await?;
+
+    register_with_scheduler(&state, &stream.id).await?;
+
+    Ok(stream)
+}
+
+/// Create a stream that plays several files back to back
+#[tauri::command]
+async fn create_playlist_stream(
+    state: State<'_, AppState>,
+    media_file_ids: Vec<String>,
+    name: String,
+    profile_id: String,
+) -> Result<Stream, AppError> {
+    let stream = stream::playlist::create_playlist_stream(&state.db, &name, &media_file_ids, &profile_id)
+        .await?;
+
+    register_with_scheduler(&state, &stream.id).await?;
+
+    Ok(stream)
+}
+
+/// Files of a playlist stream in play order
+#[tauri::command]
+async fn get_stream_playlist(state: State<'_, AppState>, id: String) -> Result<Vec<MediaFile>, AppError> {
+    stream::playlist::get_playlist(&state.db, &id)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(AppError::from)
+}
 
-    // Register with scheduler
+/// Register a stream with the scheduler, sized from its profile and source
+async fn register_with_scheduler(state: &AppState, id: &str) -> Result<(), AppError> {
+    let plan = load_launch_plan(&state.db, id).await?;
+    let size = launch_size(&plan);
     let mut scheduler = state.scheduler.lock().await;
     scheduler.register_stream(StreamInfo {
-        id: stream.id.clone(),
-        mode: stream.mode.clone(),
-        bitrate_mbps: 10, // Default estimate
+        id: plan.stream.id.clone(),
+        mode: size.mode,
+        bitrate_mbps: size.bitrate_mbps,
         priority: 50,
         pinned: false,
+        run_secs: size.run_secs,
     });
+    Ok(())
+}
 
-    Ok(stream)
+/// Capacity a stream takes from its profile and source
+fn launch_size(plan: &LaunchPlan) -> scheduler::StreamSize {
+    scheduler::StreamSize {
+        // Counted in the mode it will really run in, e.g. copy upgraded to cpu
+        mode: if plan.playlist.is_empty() {
+            stream::command::effective_mode(&plan.profile, &plan.media).to_string()
+        } else {
+            stream::playlist::playlist_mode(&plan.profile, &plan.playlist).to_string()
+        },
+        bitrate_mbps: scheduler::estimate_bitrate_mbps(&plan.profile, &plan.media),
+        run_secs: scheduler::estimate_run_secs(
+            if plan.playlist.is_empty() {
+                std::slice::from_ref(&plan.media)
+            } else {
+                &plan.playlist
+            },
+            plan.stream.loop_playback == 1,
+        ),
+    }
 }
 
 #[tauri::command]
-async fn get_streams(state: State<'_, AppState>) -> Result<Vec<Stream>, String> {
+async fn get_streams(state: State<'_, AppState>) -> Result<Vec<Stream>, AppError> {
     stream::get_all_streams(&state.db)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn get_stream(state: State<'_, AppState>, id: String) -> Result<Option<Stream>, String> {
+async fn get_stream(state: State<'_, AppState>, id: String) -> Result<Option<Stream>, AppError> {
     stream::get_stream(&state.db, &id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn delete_stream(state: State<'_, AppState>, id: String) -> Result<(), String> {
-    // Stop if running
+async fn delete_stream(state: State<'_, AppState>, id: String) -> Result<(), AppError> {
     let mut supervisor = state.supervisor.lock().await;
-    let _ = supervisor.stop_stream(&id);
-    drop(supervisor);
-
-    // Unregister from scheduler
     let mut scheduler = state.scheduler.lock().await;
-    scheduler.unregister_stream(&id);
+    retire_stream(&state, &mut supervisor, &mut scheduler, &id).await;
     drop(scheduler);
+    drop(supervisor);
 
     stream::delete_stream(&state.db, &id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
+}
+
+/// Stop a stream if running, unregister it and drop its MediaMTX paths,
+/// credentials and other resources, ahead of deleting its record
+async fn retire_stream(
+    state: &AppState,
+    supervisor: &mut stream::supervisor::Supervisor,
+    scheduler: &mut scheduler::Scheduler,
+    id: &str,
+) {
+    let _ = supervisor.stop_stream(id).await;
+    scheduler.unregister_stream(id);
+    release_stream_resources(state, id).await;
 }
 
 #[tauri::command]
-async fn start_stream(state: State<'_, AppState>, id: String) -> Result<String, String> {
-    // Get stream
-    let stream_record = stream::get_stream(&state.db, &id)
+async fn add_stream_output(
+    state: State<'_, AppState>,
+    stream_id: String,
+    protocol: String,
+    name: String,
+    wan_optimized: bool,
+) -> Result<StreamOutput, AppError> {
+    stream::outputs::add_output(&state.db, &stream_id, &protocol, &name, wan_optimized)
+        .await
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn remove_stream_output(state: State<'_, AppState>, id: String) -> Result<(), AppError> {
+    stream::outputs::remove_output(&state.db, &id)
+        .await
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn get_stream_outputs(
+    state: State<'_, AppState>,
+    stream_id: String,
+) -> Result<Vec<StreamOutput>, AppError> {
+    stream::outputs::get_outputs(&state.db, &stream_id)
+        .await
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn add_stream_tag(state: State<'_, AppState>, stream_id: String, tag: String) -> Result<(), AppError> {
+    stream::tags::add_tag(&state.db, &stream_id, &tag)
+        .await
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn remove_stream_tag(
+    state: State<'_, AppState>,
+    stream_id: String,
+    tag: String,
+) -> Result<(), AppError> {
+    stream::tags::remove_tag(&state.db, &stream_id, &tag)
         .await
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| "Stream not found".to_string())?;
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn get_streams_by_tag(state: State<'_, AppState>, tag: String) -> Result<Vec<Stream>, AppError> {
+    stream::tags::get_streams_by_tag(&state.db, &tag)
+        .await
+        .map_err(AppError::from)
+}
+
+/// Reader URL of every path a stream publishes to, its own first
+#[tauri::command]
+async fn get_stream_urls(state: State<'_, AppState>, id: String) -> Result<Vec<String>, AppError> {
+    let plan = load_launch_plan(&state.db, &id).await?;
+    let auth = plan_credentials(&state.db, &plan).await?;
+    let ports = state.mediamtx.lock().await.ports();
+    Ok(output_targets(&plan, auth.as_ref(), ports)
+        .iter()
+        .map(|t| stream::command::get_stream_url(t.protocol, t.name, t.auth, &t.ports))
+        .collect())
+}
+
+/// Everything needed to spawn FFmpeg for a stream
+struct LaunchPlan {
+    stream: Stream,
+    media: MediaFile,
+    profile: Profile,
+    /// Extra destinations fed by the same encode
+    outputs: Vec<StreamOutput>,
+    /// Files played in order, for playlist streams
+    playlist: Vec<MediaFile>,
+}
+
+impl LaunchPlan {
+    /// Whether any destination is reachable from outside and needs credentials
+    fn publishes_to_wan(&self) -> bool {
+        self.profile.wan_optimized == 1 || self.outputs.iter().any(|o| o.wan_optimized == 1)
+    }
+}
+
+/// Resolve the stream, its media file, and its profile
+async fn load_launch_plan(db: &SqlitePool, id: &str) -> Result<LaunchPlan, AppError> {
+    // Get stream
+    let stream_record = stream::get_stream(db, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Stream not found".to_string()))?;
 
     // Get media file
     let media_file_id = stream_record.media_file_id.as_ref()
-        .ok_or_else(|| "No media file associated".to_string())?;
+        .ok_or_else(|| AppError::NotFound("No media file associated".to_string()))?;
 
-    let media = scanner::get_media_file(&state.db, media_file_id)
-        .await
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| "Media file not found".to_string())?;
+    let media = scanner::get_media_file(db, media_file_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Media file not found".to_string()))?;
 
     // Get profile
     let profile_id = stream_record.profile_id.as_ref()
-        .ok_or_else(|| "No profile associated".to_string())?;
+        .ok_or_else(|| AppError::NotFound("No profile associated".to_string()))?;
 
     let profile: Profile = sqlx::query_as::<_, Profile>("SELECT * FROM profiles WHERE id = ?")
         .bind(profile_id)
-        .fetch_optional(&state.db)
-        .await
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| "Profile not found".to_string())?;
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Profile not found".to_string()))?;
+
+    let outputs = stream::outputs::get_outputs(db, id).await?;
+    let playlist = stream::playlist::get_playlist(db, id).await?;
+
+    Ok(LaunchPlan {
+        stream: stream_record,
+        media,
+        profile,
+        outputs,
+        playlist,
+    })
+}
+
+/// Spawn FFmpeg for a stream the scheduler has already admitted
+async fn launch_stream(state: &AppState, plan: &LaunchPlan) -> Result<String, AppError> {
+    // Normalizing can take minutes, so it happens before any lock is taken
+    let sources = playlist_sources(state, plan).await?;
+
+    // Lock order everywhere: supervisor, then scheduler, then MediaMTX
+    let mut supervisor = state.supervisor.lock().await;
+    let mut scheduler = state.scheduler.lock().await;
+    launch_locked(state, plan, sources, &mut supervisor, &mut scheduler).await
+}
+
+/// Files a playlist plays, in order, and pins on any that come from the
+/// cache. Mixed playlists play copies normalized to one format, which the
+/// concat demuxer can join and loop; empty for single-file streams.
+async fn playlist_sources(
+    state: &AppState,
+    plan: &LaunchPlan,
+) -> Result<(Vec<PathBuf>, Vec<CachePin>), AppError> {
+    if !stream::playlist::needs_normalize(&plan.playlist) {
+        let paths = plan.playlist.iter().map(|f| PathBuf::from(&f.path)).collect();
+        return Ok((paths, Vec::new()));
+    }
+
+    let cache = state.cache_manager.lock().await.clone();
+    let config = stream::playlist::normalize_config(&plan.profile);
+    let mut pins = Vec::with_capacity(plan.playlist.len());
+    for file in &plan.playlist {
+        pins.push(
+            cache
+                .checkout(&file.id, std::path::Path::new(&file.path), &config)
+                .await?,
+        );
+    }
+    let paths = pins.iter().map(|pin| pin.path().to_path_buf()).collect();
+    Ok((paths, pins))
+}
+
+/// `launch_stream` for callers already holding the supervisor and scheduler.
+/// `sources` come from `playlist_sources`; its pins are held until the
+/// stream's resources are released.
+async fn launch_locked(
+    state: &AppState,
+    plan: &LaunchPlan,
+    sources: (Vec<PathBuf>, Vec<CachePin>),
+    supervisor: &mut stream::supervisor::Supervisor,
+    scheduler: &mut scheduler::Scheduler,
+) -> Result<String, AppError> {
+    let (sources, pins) = sources;
+    let id = &plan.stream.id;
+
+    let recording = recording_target(state, plan);
+    if let Some(dir) = recording.as_deref().and_then(|p| p.parent()) {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| AppError::Internal(format!("Failed to create recordings dir: {}", e)))?;
+    }
+
+    let auth = plan_credentials(&state.db, plan).await?;
+    let ports = state.mediamtx.lock().await.ports();
+    let targets = output_targets(plan, auth.as_ref(), ports);
+
+    // MediaMTX must know the credentials before FFmpeg tries to publish
+    let protected: Vec<&str> = targets
+        .iter()
+        .filter(|t| t.auth.is_some())
+        .map(|t| t.name)
+        .collect();
+    for name in &protected {
+        let mut mediamtx = state.mediamtx.lock().await;
+        mediamtx
+            .add_path(name, auth.clone(), Some(mediamtx::wan_srt_latency_ms(&plan.profile)))
+            .await?;
+    }
+
+    if plan.playlist.is_empty() && stream::command::effective_mode(&plan.profile, &plan.media) == "copy" {
+        if let Some(rotation) = plan.media.rotation {
+            eprintln!(
+                "[Stream] {} is rotated {} degrees; copy mode can't turn it upright, use a transcode profile",
+                plan.stream.name, rotation
+            );
+        }
+        if plan.stream.burn_subtitles.is_some() {
+            eprintln!(
+                "[Stream] {} runs in copy mode; subtitles can't be burned in, use a transcode profile",
+                plan.stream.name
+            );
+        }
+    }
+
+    let list_path = playlist_list_path(state, id);
+    if !plan.playlist.is_empty() {
+        std::fs::create_dir_all(&state.playlists_dir)
+            .map_err(|e| AppError::Internal(format!("Failed to create playlists dir: {}", e)))?;
+        stream::playlist::write_playlist_list(&sources, &list_path)?;
+    }
+
+    let gpu = scheduler.assigned_gpu(id);
+    let (args, duration_secs) = launch_args(plan, &list_path, &targets, recording.as_deref(), gpu)?;
+
+    // Start stream
+    let started = supervisor.start_stream(id, args, duration_secs).await;
+
+    let pid = match started {
+        Ok(pid) => pid,
+        Err(e) => {
+            let mut mediamtx = state.mediamtx.lock().await;
+            for name in &protected {
+                let _ = mediamtx.remove_path(name).await;
+            }
+            return Err(e.into());
+        }
+    };
+
+    if let Some(path) = &recording {
+        if let Err(e) = stream::recording::start_recording(&state.db, id, path).await {
+            eprintln!("[Stream] Failed to track recording for {}: {}", id, e);
+        }
+    }
+
+    scheduler.on_process_started(id, pid);
+    if !pins.is_empty() {
+        state.cache_pins.lock().await.insert(id.clone(), pins);
+    }
+
+    // Update status
+    stream::update_stream_status(&state.db, id, "running", Some(pid as i32), None).await?;
+
+    Ok(stream::command::get_stream_url(
+        &plan.profile.protocol,
+        &plan.stream.name,
+        auth.as_ref(),
+        &targets[0].ports,
+    ))
+}
+
+/// WAN streams are reachable from outside, so they publish with credentials
+async fn plan_credentials(
+    db: &SqlitePool,
+    plan: &LaunchPlan,
+) -> Result<Option<security::StreamAuth>, AppError> {
+    if !plan.publishes_to_wan() {
+        return Ok(None);
+    }
+    Ok(Some(security::get_or_create_credentials(db, &plan.stream.id).await?))
+}
+
+/// Recording file for a stream whose profile asks for one; playlists aren't
+/// recorded
+fn recording_target(state: &AppState, plan: &LaunchPlan) -> Option<PathBuf> {
+    if plan.profile.record != 1 || !plan.playlist.is_empty() {
+        return None;
+    }
+    let dir = plan
+        .profile
+        .record_dir
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| state.recordings_dir.clone());
+    Some(stream::recording::recording_path(&dir, &plan.stream.name))
+}
+
+/// FFmpeg args for a stream and its expected duration, on `gpu` for NVENC.
+/// Playlists read the concat list `launch_locked` writes to `list_path`.
+fn launch_args(
+    plan: &LaunchPlan,
+    list_path: &std::path::Path,
+    targets: &[stream::command::OutputTarget<'_>],
+    recording: Option<&std::path::Path>,
+    gpu: Option<u32>,
+) -> Result<(Vec<String>, Option<f64>), AppError> {
+    let extra_args = stream::command::parse_extra_args(plan.stream.extra_args.as_deref());
+    let playback = stream::command::PlaybackOptions {
+        loop_input: plan.stream.loop_playback == 1,
+        audio_track: plan.stream.audio_track_index as u32,
+        burn_subtitles: plan.stream.burn_subtitles.map(|index| index as u32),
+        extra_args: &extra_args,
+        concat_list: None,
+    };
+    if plan.playlist.is_empty() {
+        let args = stream::command::build_fanout_args(
+            &plan.media,
+            &plan.profile,
+            targets,
+            recording,
+            gpu,
+            playback,
+        );
+        Ok((args, plan.media.duration_secs))
+    } else {
+        let args = stream::playlist::build_playlist_args(
+            &plan.playlist,
+            list_path,
+            targets,
+            &plan.profile,
+            gpu,
+            playback,
+        )?;
+        Ok((args, Some(merge::compute_total_duration(&plan.playlist))))
+    }
+}
+
+/// Concat list a playlist stream plays from while it runs
+fn playlist_list_path(state: &AppState, id: &str) -> PathBuf {
+    state.playlists_dir.join(format!("{}.txt", id))
+}
+
+/// The FFmpeg command a stream would run, built exactly as a start would
+/// but without spawning, normalizing or writing anything
+#[tauri::command]
+async fn preview_stream_command(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<stream::command::CommandPreview, AppError> {
+    let plan = load_launch_plan(&state.db, &id).await?;
+    let auth = preview_credentials(&state.db, &plan).await?;
+    let ports = state.mediamtx.lock().await.ports();
+    let targets = output_targets(&plan, auth.as_ref(), ports);
+    let recording = recording_target(&state, &plan);
+    let gpu = state.scheduler.lock().await.assigned_gpu(&id);
+    let list_path = playlist_list_path(&state, &id);
+    let (args, _) = launch_args(&plan, &list_path, &targets, recording.as_deref(), gpu)?;
+    Ok(stream::command::CommandPreview::new(args))
+}
+
+/// `plan_credentials` without storing anything: a WAN stream that has never
+/// started shows freshly generated credentials, which its first start replaces
+async fn preview_credentials(
+    db: &SqlitePool,
+    plan: &LaunchPlan,
+) -> Result<Option<security::StreamAuth>, AppError> {
+    if !plan.publishes_to_wan() {
+        return Ok(None);
+    }
+    let stored = security::load_credentials(db, &plan.stream.id).await?;
+    Ok(Some(stored.unwrap_or_else(|| {
+        security::auth::generate_credentials(&plan.stream.id)
+    })))
+}
+
+/// The stream's own path followed by its extra outputs; WAN ones carry `auth`
+fn output_targets<'a>(
+    plan: &'a LaunchPlan,
+    auth: Option<&'a security::StreamAuth>,
+    ports: mediamtx::PortConfig,
+) -> Vec<stream::command::OutputTarget<'a>> {
+    let primary_wan = plan.profile.wan_optimized == 1;
+    let mut targets = vec![stream::command::OutputTarget {
+        protocol: &plan.profile.protocol,
+        name: &plan.stream.name,
+        auth: if primary_wan { auth } else { None },
+        wan_mode: primary_wan,
+        ports,
+    }];
+    targets.extend(plan.outputs.iter().map(|o| stream::command::OutputTarget {
+        protocol: &o.protocol,
+        name: &o.name,
+        auth: if o.wan_optimized == 1 { auth } else { None },
+        wan_mode: o.wan_optimized == 1,
+        ports,
+    }));
+    targets
+}
+
+/// Release the slot of an admitted stream that could not be launched
+async fn fail_admitted_stream(state: &AppState, id: &str, message: &str) {
+    let mut scheduler = state.scheduler.lock().await;
+    scheduler.on_stream_error(id, message);
+    drop(scheduler);
+
+    record_launch_failure(state, id, message).await;
+}
+
+/// Record a failed launch in the stream's history and status
+async fn record_launch_failure(state: &AppState, id: &str, message: &str) {
+    let _ = stream::events::record_event(&state.db, id, "error", Some(message)).await;
+
+    let _ = stream::update_stream_status(&state.db, id, "error", None, Some(message)).await;
+}
+
+#[tauri::command]
+async fn start_stream(state: State<'_, AppState>, id: String) -> Result<String, AppError> {
+    begin_stream(&state, &id).await
+}
+
+/// Ask the scheduler to start a stream and launch it if admitted
+async fn begin_stream(state: &AppState, id: &str) -> Result<String, AppError> {
+    let plan = load_launch_plan(&state.db, id).await?;
 
     // Check scheduler
     let mut scheduler = state.scheduler.lock().await;
-    let result = scheduler.request_start(&id);
+    let result = scheduler.request_start(id);
 
     if result.queued {
-        // Update status to queued
-        stream::update_stream_status(&state.db, &id, "queued", None, result.message.as_deref())
-            .await
-            .map_err(|e| e.to_string())?;
-        return Err(result.message.unwrap_or_else(|| "Queued for resources".to_string()));
+        return Err(AppError::LimitReached(mark_queued(state, id, result).await));
     }
 
     if result.status == "error" {
-        return Err(result.message.unwrap_or_else(|| "Cannot start stream".to_string()));
+        let message = result.message.unwrap_or_else(|| "Cannot start stream".to_string());
+        if !scheduler.is_registered(id) {
+            return Err(AppError::NotFound(message));
+        }
+        return Err(AppError::LimitReached(message));
+    }
+    // Already running or starting: leave the live stream's state alone
+    if result.status != "starting" {
+        return Err(AppError::Conflict(refusal_message(result)));
     }
     drop(scheduler);
 
-    // Build FFmpeg args
-    let args = stream::command::build_ffmpeg_args(&media, &profile, &stream_record.name);
+    // The scheduler already moved the preempted stream's slot to this one
+    if let Some(victim) = result.preempted {
+        let mut supervisor = state.supervisor.lock().await;
+        let stream = &plan.stream;
+        stop_preempted(state, &mut supervisor, &victim, &stream.id, &stream.name).await;
+    }
 
-    // Start stream
+    match launch_stream(state, &plan).await {
+        Ok(url) => Ok(url),
+        Err(e) => {
+            fail_admitted_stream(state, id, &e.to_string()).await;
+            Err(e)
+        }
+    }
+}
+
+/// Why the scheduler didn't admit a start that wasn't queued
+fn refusal_message(result: scheduler::ScheduleResult) -> String {
+    result
+        .message
+        .unwrap_or_else(|| format!("Stream is already {}", result.status))
+}
+
+/// Record that a start was queued; returns the message for the caller
+async fn mark_queued(state: &AppState, id: &str, result: scheduler::ScheduleResult) -> String {
+    let _ = stream::update_stream_status(&state.db, id, "queued", None, result.message.as_deref()).await;
+    let _ = stream::events::record_event(&state.db, id, "queued", result.message.as_deref()).await;
+    let message = result.message.unwrap_or_else(|| "Queued for resources".to_string());
+    match result.eta_secs.filter(|secs| *secs > 0) {
+        Some(secs) => format!("{} (estimated wait ~{} min)", message, secs.div_ceil(60)),
+        None => message,
+    }
+}
+
+/// Stop a stream the scheduler preempted for `plan`'s stream
+async fn stop_preempted(
+    state: &AppState,
+    supervisor: &mut stream::supervisor::Supervisor,
+    victim: &str,
+    by_id: &str,
+    by_name: &str,
+) {
+    println!("[Scheduler] Stream {} preempted by {}", victim, by_id);
+    if let Err(e) = supervisor.preempt_stream(victim, by_id).await {
+        eprintln!("[Scheduler] Failed to stop preempted stream {}: {}", victim, e);
+    }
+    release_stream_resources(state, victim).await;
+    let reason = format!("Preempted by {}", by_name);
+    let _ = stream::update_stream_status(&state.db, victim, "stopped", None, Some(&reason)).await;
+}
+
+/// Periodically clear aged cache entries and enforce the size limit.
+/// The interval is re-read from settings each round so changes apply
+/// without a restart.
+async fn run_cache_maintenance(app: tauri::AppHandle) {
+    loop {
+        let state = app.state::<AppState>();
+        let interval_mins = db::schema::get_app_settings(&state.db)
+            .await
+            .map(|s| s.cache_maintenance_interval_mins)
+            .unwrap_or_default();
+
+        if interval_mins == 0 {
+            // Disabled; check again later in case it gets turned on
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            continue;
+        }
+
+        tokio::time::sleep(std::time::Duration::from_secs(interval_mins as u64 * 60)).await;
+
+        let cache = state.cache_manager.lock().await.clone();
+        match cache.run_maintenance().await {
+            Ok(result) => {
+                if result.files_removed > 0 {
+                    println!(
+                        "[Cache] Maintenance freed {} bytes ({} files)",
+                        result.freed_bytes, result.files_removed
+                    );
+                }
+                let _ = app.emit("cache-maintenance", result);
+            }
+            Err(e) => eprintln!("[Cache] Maintenance failed: {}", e),
+        }
+    }
+}
+
+/// Where the metrics endpoint listens, or None when it's disabled. Only a
+/// valid `metrics_bind_address` moves it off localhost.
+fn metrics_addr(settings: &AppSettings) -> Option<std::net::SocketAddr> {
+    let port = u16::try_from(settings.metrics_port).ok().filter(|port| *port > 0)?;
+    let ip = settings.metrics_bind_address.parse().unwrap_or_else(|_| {
+        eprintln!(
+            "[Metrics] Invalid bind address '{}', using localhost",
+            settings.metrics_bind_address
+        );
+        std::net::IpAddr::from([127, 0, 0, 1])
+    });
+    Some(std::net::SocketAddr::new(ip, port))
+}
+
+/// Serve Prometheus metrics on `addr` for central monitoring
+async fn run_metrics_server(app: tauri::AppHandle, addr: std::net::SocketAddr) {
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("[Metrics] Failed to bind {}: {}", addr, e);
+            return;
+        }
+    };
+    println!("[Metrics] Serving /metrics on {}", addr);
+
+    telemetry::prometheus::serve(listener, move || {
+        let app = app.clone();
+        async move {
+            let state = app.state::<AppState>();
+            let capacity = state.scheduler.lock().await.capacity_summary();
+            let pids = state.supervisor.lock().await.stream_pids();
+            let mut telemetry = state.telemetry.lock().await;
+            let metrics = telemetry.collect();
+            let streams = telemetry.stream_stats(&pids);
+            telemetry::prometheus::render(&metrics, &capacity, &streams)
+        }
+    })
+    .await;
+}
+
+/// How often the MediaMTX monitor checks the server is still alive
+const MEDIAMTX_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Payload of the `mediamtx-restarted` event
+#[derive(Debug, Clone, serde::Serialize)]
+struct MediaMtxRestarted {
+    attempt: u32,
+    paths: usize,
+}
+
+/// Relaunch MediaMTX with backoff if it dies while it should be running
+async fn run_mediamtx_monitor(app: tauri::AppHandle) {
+    loop {
+        tokio::time::sleep(MEDIAMTX_CHECK_INTERVAL).await;
+        let state = app.state::<AppState>();
+
+        let delay = {
+            let mut manager = state.mediamtx.lock().await;
+            if !manager.needs_restart() {
+                continue;
+            }
+            match manager.next_restart_delay() {
+                Some(delay) => delay,
+                None => {
+                    eprintln!("[MediaMTX] Giving up after repeated crashes");
+                    continue;
+                }
+            }
+        };
+        tokio::time::sleep(delay).await;
+
+        let mut manager = state.mediamtx.lock().await;
+        // Stopped on purpose while we were backing off
+        if !manager.needs_restart() {
+            continue;
+        }
+        match manager.restart().await {
+            Ok(()) => {
+                println!("[MediaMTX] Restarted after crash");
+                let _ = app.emit(
+                    "mediamtx-restarted",
+                    MediaMtxRestarted {
+                        attempt: manager.restart_attempts(),
+                        paths: manager.path_count(),
+                    },
+                );
+            }
+            Err(e) => eprintln!("[MediaMTX] Restart failed: {}", e),
+        }
+    }
+}
+
+/// Payload of the `stream-dequeued` event
+#[derive(Debug, Clone, serde::Serialize)]
+struct StreamDequeued {
+    stream_id: String,
+    url: String,
+}
+
+/// Start queued streams whenever the scheduler frees a slot
+async fn run_dequeue_loop(app: tauri::AppHandle, slot_freed: Arc<Notify>) {
+    loop {
+        slot_freed.notified().await;
+
+        let state = app.state::<AppState>();
+        let ready = state.scheduler.lock().await.drain_ready();
+
+        for scheduler::Dequeued { stream_id: id, preempted } in ready {
+            // Media file or profile may have been deleted while queued
+            let plan = load_launch_plan(&state.db, &id).await;
+
+            // The scheduler already moved the preempted stream's slot to this one
+            if let Some(victim) = preempted {
+                let name = plan.as_ref().map_or(id.as_str(), |p| p.stream.name.as_str());
+                let mut supervisor = state.supervisor.lock().await;
+                stop_preempted(&state, &mut supervisor, &victim, &id, name).await;
+            }
+
+            let launched = match plan {
+                Ok(plan) => launch_stream(&state, &plan).await,
+                Err(e) => Err(e),
+            };
+
+            match launched {
+                Ok(url) => {
+                    let _ = app.emit("stream-dequeued", StreamDequeued { stream_id: id, url });
+                }
+                Err(e) => {
+                    eprintln!("[Scheduler] Failed to start dequeued stream {}: {}", id, e);
+                    fail_admitted_stream(&state, &id, &e.to_string()).await;
+                }
+            }
+        }
+    }
+}
+
+#[tauri::command]
+async fn stop_stream(state: State<'_, AppState>, id: String) -> Result<(), AppError> {
+    end_stream(&state, &id).await
+}
+
+/// Stop a stream and settle it in the scheduler and database
+async fn end_stream(state: &AppState, id: &str) -> Result<(), AppError> {
     let mut supervisor = state.supervisor.lock().await;
-    let pid = supervisor.start_stream(&id, args).await?;
+    supervisor.stop_stream(id).await?;
+    drop(supervisor);
 
     // Notify scheduler
     let mut scheduler = state.scheduler.lock().await;
-    scheduler.on_process_started(&id, pid);
+    scheduler.on_stream_stopped(id);
     drop(scheduler);
 
-    // Update status
-    stream::update_stream_status(&state.db, &id, "running", Some(pid as i32), None)
+    release_stream_resources(state, id).await;
+
+    stream::update_stream_status(&state.db, id, "stopped", None, None)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(AppError::from)
+}
 
-    let url = stream::command::get_stream_url(&profile.protocol, &stream_record.name);
-    Ok(url)
+/// Stop a stream and start it again from its current profile and media,
+/// keeping its id and credentials. The start goes through the scheduler,
+/// so it may queue like any other.
+#[tauri::command]
+async fn restart_stream(state: State<'_, AppState>, id: String) -> Result<String, AppError> {
+    let running = state.supervisor.lock().await.has_stream(&id);
+    if running {
+        end_stream(&state, &id).await?;
+    } else {
+        // A pending queued start would otherwise launch the old settings
+        let _ = state.scheduler.lock().await.cancel_queued(&id);
+    }
+
+    // Re-sized from the profile as it is now, once the old slot is released
+    register_with_scheduler(&state, &id).await?;
+    begin_stream(&state, &id).await
+}
+
+/// Close a stopped stream's recordings, unpin its cache files, remove its
+/// playlist's concat list and drop its MediaMTX path
+async fn release_stream_resources(state: &AppState, id: &str) {
+    state.cache_pins.lock().await.remove(id);
+    // Only playlists have one
+    let _ = tokio::fs::remove_file(playlist_list_path(state, id)).await;
+
+    if let Err(e) = stream::recording::finish_recordings(&state.db, id).await {
+        eprintln!("[Stream] Failed to close recordings for {}: {}", id, e);
+    }
+
+    // Drop any per-stream credentials from the MediaMTX config
+    if let Ok(Some(record)) = stream::get_stream(&state.db, id).await {
+        let _ = state.mediamtx.lock().await.remove_path(&record.name).await;
+    }
+    for output in stream::outputs::get_outputs(&state.db, id).await.unwrap_or_default() {
+        let _ = state.mediamtx.lock().await.remove_path(&output.name).await;
+    }
 }
 
+/// Events returned by `get_stream_events` when no limit is given
+const STREAM_EVENTS_LIMIT: i64 = 100;
+
+/// A stream's lifecycle history, newest first
 #[tauri::command]
-async fn stop_stream(state: State<'_, AppState>, id: String) -> Result<(), String> {
+async fn get_stream_events(
+    state: State<'_, AppState>,
+    stream_id: String,
+    limit: Option<i64>,
+) -> Result<Vec<StreamEventRecord>, AppError> {
+    stream::events::get_events(&state.db, &stream_id, limit.unwrap_or(STREAM_EVENTS_LIMIT))
+        .await
+        .map_err(AppError::from)
+}
+
+/// Set or clear a stream's scheduled start and stop (RFC 3339 timestamps)
+#[tauri::command]
+async fn schedule_stream(
+    state: State<'_, AppState>,
+    id: String,
+    start: Option<String>,
+    stop: Option<String>,
+) -> Result<Stream, AppError> {
+    let parse = |value: Option<String>| value.as_deref().map(stream::schedule::parse_time).transpose();
+    let start = parse(start).map_err(AppError::from)?;
+    let stop = parse(stop).map_err(AppError::from)?;
+
+    stream::schedule::set_schedule(&state.db, &id, start, stop, chrono::Utc::now())
+        .await?;
+    stream::get_stream(&state.db, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Stream not found: {}", id)))
+}
+
+/// Loop the stream's file forever or play it once; applies from the next start
+#[tauri::command]
+async fn set_stream_loop(state: State<'_, AppState>, id: String, enabled: bool) -> Result<(), AppError> {
+    stream::set_loop_playback(&state.db, &id, enabled)
+        .await
+        .map_err(AppError::from)
+}
+
+/// Set extra FFmpeg flags for a stream; an empty list clears them
+#[tauri::command]
+async fn set_stream_extra_args(
+    state: State<'_, AppState>,
+    id: String,
+    args: Vec<String>,
+) -> Result<(), AppError> {
+    stream::set_extra_args(&state.db, &id, &args)
+        .await
+        .map_err(AppError::from)
+}
+
+/// Choose a subtitle track to burn into a stream's video, or none
+#[tauri::command]
+async fn set_stream_subtitles(
+    state: State<'_, AppState>,
+    id: String,
+    index: Option<i32>,
+) -> Result<(), AppError> {
+    stream::set_burn_subtitles(&state.db, &id, index)
+        .await
+        .map_err(AppError::from)
+}
+
+/// Choose the audio track a stream publishes; applies from the next start
+#[tauri::command]
+async fn set_stream_audio_track(state: State<'_, AppState>, id: String, index: i32) -> Result<(), AppError> {
+    stream::set_audio_track(&state.db, &id, index)
+        .await
+        .map_err(AppError::from)
+}
+
+/// Record telemetry on a fixed interval, so the history stays evenly
+/// spaced no matter how often it's read
+async fn run_telemetry_sampler(app: tauri::AppHandle) {
+    let mut interval = tokio::time::interval(telemetry::SAMPLE_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let state = app.state::<AppState>();
+        // nvidia-smi blocks, so the GPU is queried on the blocking pool
+        // without holding the telemetry lock
+        let gpu = state.telemetry.lock().await.gpu();
+        let gpu = tokio::task::spawn_blocking(move || gpu.collect())
+            .await
+            .unwrap_or_default();
+        state.telemetry.lock().await.record(gpu);
+    }
+}
+
+/// How often scheduled starts and stops are checked
+const SCHEDULE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Payload of the `stream-schedule` event
+#[derive(Debug, Clone, serde::Serialize)]
+struct ScheduledRun {
+    stream_id: String,
+    action: stream::schedule::ScheduleAction,
+    error: Option<String>,
+}
+
+/// Start and stop streams when their scheduled times arrive
+async fn run_schedule_loop(app: tauri::AppHandle) {
+    use stream::schedule::ScheduleAction;
+
+    loop {
+        tokio::time::sleep(SCHEDULE_CHECK_INTERVAL).await;
+        let state = app.state::<AppState>();
+
+        let due = match stream::schedule::take_due_actions(&state.db, chrono::Utc::now()).await {
+            Ok(due) => due,
+            Err(e) => {
+                eprintln!("[Schedule] Failed to check schedules: {}", e);
+                continue;
+            }
+        };
+
+        for stream::schedule::DueAction { stream_id, action } in due {
+            println!("[Schedule] Scheduled {:?} for stream {}", action, stream_id);
+            let result = match action {
+                ScheduleAction::Start => start_stream(state, stream_id.clone()).await.map(|_| ()),
+                ScheduleAction::Stop => match stop_stream(state, stream_id.clone()).await {
+                    // Never got a slot; withdraw the start request instead
+                    Err(e) => cancel_queued_stream(state, stream_id.clone())
+                        .await
+                        .map_err(|_| e),
+                    ok => ok,
+                },
+            };
+            if let Err(e) = &result {
+                eprintln!("[Schedule] Scheduled {:?} of {} failed: {}", action, stream_id, e);
+            }
+
+            let _ = app.emit(
+                "stream-schedule",
+                ScheduledRun {
+                    stream_id,
+                    action,
+                    error: result.err().map(|e| e.to_string()),
+                },
+            );
+        }
+    }
+}
+
+/// Forward supervisor events to the frontend as `stream-event`, keep them in
+/// each stream's history, and settle streams whose FFmpeg exited on its own
+async fn run_stream_event_loop(
+    app: tauri::AppHandle,
+    mut events: mpsc::UnboundedReceiver<StreamEvent>,
+) {
+    while let Some(event) = events.recv().await {
+        let _ = app.emit("stream-event", &event);
+
+        let state = app.state::<AppState>();
+        if let Err(e) = stream::events::record_stream_event(&state.db, &event).await {
+            eprintln!("[Stream] Failed to record event: {}", e);
+        }
+
+        if let StreamEvent::Exited { stream_id, generation, last_error } = event {
+            // Deliberate stops are already settled by whoever stopped the stream
+            let reaped = stream::supervisor::wait_reaped(&state.supervisor, &stream_id, generation);
+            let Some(status) = reaped.await else {
+                continue;
+            };
+            let live_source = has_live_source(&state.db, &stream_id).await;
+            let failure = stream::supervisor::exit_failure(&status, last_error, live_source);
+            settle_exited_stream(&state, &stream_id, failure.as_deref()).await;
+        }
+    }
+}
+
+/// Whether a stream restreams a camera or server rather than a file
+async fn has_live_source(db: &SqlitePool, id: &str) -> bool {
+    let Ok(Some(stream)) = stream::get_stream(db, id).await else {
+        return false;
+    };
+    let Some(media_id) = stream.media_file_id else {
+        return false;
+    };
+    matches!(
+        scanner::get_media_file(db, &media_id).await,
+        Ok(Some(file)) if scanner::metadata::is_url(&file.path)
+    )
+}
+
+/// Release an exited stream's slot and resources and record how it ended.
+/// `last_error` is None when FFmpeg finished cleanly, e.g. a play-once file.
+async fn settle_exited_stream(state: &AppState, id: &str, last_error: Option<&str>) {
+    let mut scheduler = state.scheduler.lock().await;
+    match last_error {
+        Some(message) => scheduler.on_stream_error(id, message),
+        None => scheduler.on_stream_stopped(id),
+    }
+    drop(scheduler);
+
+    release_stream_resources(state, id).await;
+
+    let (status, event_type) = match last_error {
+        Some(_) => ("error", "failed"),
+        None => ("stopped", "ended"),
+    };
+    println!("[Stream] Stream {} exited on its own ({})", id, event_type);
+    let _ = stream::events::record_event(&state.db, id, event_type, last_error).await;
+    let _ = stream::update_stream_status(&state.db, id, status, None, last_error).await;
+}
+
+/// Lines returned by `get_stream_log` when no count is given
+const STREAM_LOG_TAIL_LINES: usize = 500;
+
+/// Tail of a stream's captured FFmpeg output
+#[tauri::command]
+async fn get_stream_log(
+    app: tauri::AppHandle,
+    id: String,
+    lines: Option<usize>,
+) -> Result<String, AppError> {
+    let app_dir = app.path().app_data_dir().map_err(AppError::from)?;
+    let logs_dir = diagnostics::logs::logs_dir(&app_dir);
+    let lines = lines.unwrap_or(STREAM_LOG_TAIL_LINES);
+    tokio::task::spawn_blocking(move || diagnostics::logs::read_stream_log(&logs_dir, &id, lines))
+        .await?
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn get_stream_status(state: State<'_, AppState>, id: String) -> Result<String, AppError> {
     let mut supervisor = state.supervisor.lock().await;
-    supervisor.stop_stream(&id)?;
+    let scheduler = state.scheduler.lock().await;
+    let status = stream::batch::stream_status(&mut supervisor, &scheduler, &id);
+    drop(scheduler);
     drop(supervisor);
 
-    // Notify scheduler
-    let mut scheduler = state.scheduler.lock().await;
-    scheduler.on_stream_stopped(&id);
-    drop(scheduler);
+    Ok(live_status(&state, &id, status).await)
+}
+
+/// A running stream's status as "publishing" or "connecting", depending on
+/// whether MediaMTX sees its publisher yet; other statuses pass through
+async fn live_status(state: &AppState, id: &str, status: String) -> String {
+    if status != "running" {
+        return status;
+    }
+    let Ok(Some(stream)) = stream::get_stream(&state.db, id).await else {
+        return status;
+    };
+
+    let mut mediamtx = state.mediamtx.lock().await;
+    let publishing = if mediamtx.is_running() {
+        mediamtx.is_publishing(&stream.name).await.ok()
+    } else {
+        // Nowhere to publish to
+        Some(false)
+    };
+    stream::batch::publish_status(status, publishing)
+}
+
+// ============ Batch Commands ============
+
+/// Start several streams, admitting them under one hold of the supervisor
+/// and scheduler locks and launching the admitted ones under another.
+/// Higher-ranked streams are admitted first; the rest queue.
+#[tauri::command]
+async fn batch_start_streams(
+    state: State<'_, AppState>,
+    stream_ids: Vec<String>,
+) -> Result<BatchResult, AppError> {
+    let mut succeeded = Vec::new();
+    let mut failed = Vec::new();
+
+    // Plans are read before locking so no lock waits on the database
+    let mut plans = HashMap::new();
+    let mut ids = Vec::new();
+    for id in stream_ids {
+        if plans.contains_key(&id) {
+            continue;
+        }
+        match load_launch_plan(&state.db, &id).await {
+            Ok(plan) => {
+                ids.push(id.clone());
+                plans.insert(id, plan);
+            }
+            Err(e) => failed.push((id, e.to_string())),
+        }
+    }
+
+    let mut admitted = Vec::new();
+    {
+        let mut supervisor = state.supervisor.lock().await;
+        let mut scheduler = state.scheduler.lock().await;
+        for result in scheduler.request_start_batch(&ids) {
+            let id = result.stream_id.clone();
+            let Some(plan) = plans.remove(&id) else {
+                continue;
+            };
+
+            if result.queued {
+                failed.push((id.clone(), mark_queued(&state, &id, result).await));
+                continue;
+            }
+            if result.status != "starting" {
+                failed.push((id, refusal_message(result)));
+                continue;
+            }
+            if let Some(victim) = &result.preempted {
+                let stream = &plan.stream;
+                stop_preempted(&state, &mut supervisor, victim, &stream.id, &stream.name).await;
+            }
+            admitted.push(plan);
+        }
+    }
 
-    stream::update_stream_status(&state.db, &id, "stopped", None, None)
-        .await
-        .map_err(|e| e.to_string())
-}
+    // Only admitted playlists are normalized, which can take minutes, so it
+    // happens outside the locks as in `launch_stream`
+    let mut prepared = Vec::with_capacity(admitted.len());
+    for plan in admitted {
+        match playlist_sources(&state, &plan).await {
+            Ok(sources) => prepared.push((plan, sources)),
+            Err(e) => {
+                let message = e.to_string();
+                fail_admitted_stream(&state, &plan.stream.id, &message).await;
+                failed.push((plan.stream.id.clone(), message));
+            }
+        }
+    }
 
-#[tauri::command]
-async fn get_stream_status(state: State<'_, AppState>, id: String) -> Result<String, String> {
     let mut supervisor = state.supervisor.lock().await;
-    if supervisor.is_running(&id) {
-        Ok("running".to_string())
-    } else {
-        // Check scheduler state
-        let scheduler = state.scheduler.lock().await;
-        if let Some(stream_state) = scheduler.get_state(&id) {
-            Ok(stream_state.as_str().to_string())
-        } else {
-            Ok("stopped".to_string())
+    let mut scheduler = state.scheduler.lock().await;
+    for (plan, sources) in prepared {
+        let id = plan.stream.id.clone();
+        match launch_locked(&state, &plan, sources, &mut supervisor, &mut scheduler).await {
+            Ok(_) => succeeded.push(id),
+            Err(e) => {
+                let message = e.to_string();
+                scheduler.on_stream_error(&id, &message);
+                record_launch_failure(&state, &id, &message).await;
+                failed.push((id, message));
+            }
         }
     }
-}
 
-// ============ Batch Commands ============
+    Ok(BatchResult { succeeded, failed })
+}
 
+/// Stop several streams under one hold of the supervisor and scheduler locks
 #[tauri::command]
-async fn batch_start_streams(
+async fn batch_stop_streams(
     state: State<'_, AppState>,
     stream_ids: Vec<String>,
-) -> Result<BatchResult, String> {
+) -> Result<BatchResult, AppError> {
     let mut succeeded = Vec::new();
     let mut failed = Vec::new();
 
+    let mut supervisor = state.supervisor.lock().await;
+    let mut scheduler = state.scheduler.lock().await;
     for id in stream_ids {
-        match start_stream(state.clone(), id.clone()).await {
-            Ok(_) => succeeded.push(id),
-            Err(e) => failed.push((id, e)),
+        if let Err(e) = supervisor.stop_stream(&id).await {
+            failed.push((id, e));
+            continue;
+        }
+        scheduler.on_stream_stopped(&id);
+        release_stream_resources(&state, &id).await;
+
+        match stream::update_stream_status(&state.db, &id, "stopped", None, None).await {
+            Ok(()) => succeeded.push(id),
+            Err(e) => failed.push((id, e.to_string())),
         }
     }
 
     Ok(BatchResult { succeeded, failed })
 }
 
+/// Delete several streams, holding the supervisor and scheduler locks once
 #[tauri::command]
-async fn batch_stop_streams(
+async fn batch_delete_streams(
     state: State<'_, AppState>,
     stream_ids: Vec<String>,
-) -> Result<BatchResult, String> {
+) -> Result<BatchResult, AppError> {
     let mut succeeded = Vec::new();
     let mut failed = Vec::new();
 
+    let mut supervisor = state.supervisor.lock().await;
+    let mut scheduler = state.scheduler.lock().await;
     for id in stream_ids {
-        match stop_stream(state.clone(), id.clone()).await {
-            Ok(_) => succeeded.push(id),
-            Err(e) => failed.push((id, e)),
+        retire_stream(&state, &mut supervisor, &mut scheduler, &id).await;
+        match stream::delete_stream(&state.db, &id).await {
+            Ok(()) => succeeded.push(id),
+            Err(e) => failed.push((id, e.to_string())),
         }
     }
 
     Ok(BatchResult { succeeded, failed })
 }
 
+/// Live status of several streams, by id
+#[tauri::command]
+async fn batch_get_status(
+    state: State<'_, AppState>,
+    stream_ids: Vec<String>,
+) -> Result<HashMap<String, String>, AppError> {
+    let mut supervisor = state.supervisor.lock().await;
+    let scheduler = state.scheduler.lock().await;
+    let statuses = stream::batch::statuses(&mut supervisor, &scheduler, &stream_ids);
+    drop(scheduler);
+    drop(supervisor);
+
+    if !statuses.values().any(|status| status == "running") {
+        return Ok(statuses);
+    }
+    let names: HashMap<String, String> = stream::get_all_streams(&state.db)
+        .await?
+        .into_iter()
+        .map(|stream| (stream.id, stream.name))
+        .collect();
+
+    let mut mediamtx = state.mediamtx.lock().await;
+    let publishing = if mediamtx.is_running() {
+        mediamtx.publishing_paths().await.ok()
+    } else {
+        // Nowhere to publish to
+        Some(HashSet::new())
+    };
+    drop(mediamtx);
+
+    Ok(stream::batch::publish_statuses(statuses, &names, publishing.as_ref()))
+}
+
+// ============ Queue Commands ============
+
+#[tauri::command]
+async fn reprioritize_stream(
+    state: State<'_, AppState>,
+    id: String,
+    priority: u8,
+) -> Result<(), AppError> {
+    state
+        .scheduler
+        .lock()
+        .await
+        .set_priority(&id, priority)
+        .map_err(AppError::NotFound)
+}
+
+#[tauri::command]
+async fn pin_stream(state: State<'_, AppState>, id: String, pinned: bool) -> Result<(), AppError> {
+    state
+        .scheduler
+        .lock()
+        .await
+        .set_pinned(&id, pinned)
+        .map_err(AppError::NotFound)
+}
+
+/// Withdraw a queued start request; running streams are unaffected
+#[tauri::command]
+async fn cancel_queued_stream(state: State<'_, AppState>, id: String) -> Result<(), AppError> {
+    state.scheduler.lock().await.cancel_queued(&id).map_err(AppError::Conflict)?;
+    let _ = stream::events::record_event(&state.db, &id, "cancelled", None).await;
+
+    stream::update_stream_status(&state.db, &id, "stopped", None, None)
+        .await
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn get_recordings(
+    state: State<'_, AppState>,
+    stream_id: Option<String>,
+) -> Result<Vec<Recording>, AppError> {
+    stream::recording::get_recordings(&state.db, stream_id.as_deref())
+        .await
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn capture_snapshot(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    stream_name: String,
+) -> Result<String, AppError> {
+    let out_dir = app
+        .path()
+        .app_data_dir()?
+        .join("snapshots");
+    let auth = snapshot_credentials(&state.db, &stream_name).await?;
+    let ports = state.mediamtx.lock().await.ports();
+
+    stream::snapshot::capture_snapshot(&stream_name, &out_dir, auth.as_ref(), &ports)
+        .await
+        .map(|p| p.to_string_lossy().to_string())
+        .map_err(AppError::from)
+}
+
+/// Read credentials for the MediaMTX path `name`: those of the stream that
+/// owns it, directly or through one of its outputs
+async fn snapshot_credentials(
+    db: &SqlitePool,
+    name: &str,
+) -> Result<Option<security::StreamAuth>, AppError> {
+    let stream_id = match stream::get_stream_by_name(db, name).await? {
+        Some(stream) => stream.id,
+        None => match stream::outputs::get_output_by_name(db, name).await? {
+            Some(output) => output.stream_id,
+            None => return Ok(None),
+        },
+    };
+    Ok(security::load_credentials(db, &stream_id).await?)
+}
+
 // ============ Profile Commands ============
 
 #[tauri::command]
-async fn get_profiles(state: State<'_, AppState>) -> Result<Vec<Profile>, String> {
+async fn get_profiles(state: State<'_, AppState>) -> Result<Vec<Profile>, AppError> {
     stream::get_all_profiles(&state.db)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn create_profile(state: State<'_, AppState>, profile: Profile) -> Result<Profile, AppError> {
+    stream::create_profile(&state.db, profile)
+        .await
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn update_profile(state: State<'_, AppState>, profile: Profile) -> Result<Profile, AppError> {
+    stream::update_profile(&state.db, profile)
+        .await
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn delete_profile(state: State<'_, AppState>, id: String) -> Result<(), AppError> {
+    stream::delete_profile(&state.db, &id)
+        .await
+        .map_err(AppError::from)
 }
 
 // ============ Settings Commands ============
 
 #[tauri::command]
-async fn get_settings(state: State<'_, AppState>) -> Result<AppSettings, String> {
+async fn get_settings(state: State<'_, AppState>) -> Result<AppSettings, AppError> {
     db::schema::get_app_settings(&state.db)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn update_settings(state: State<'_, AppState>, settings: AppSettings) -> Result<(), String> {
+async fn update_settings(state: State<'_, AppState>, settings: AppSettings) -> Result<(), AppError> {
+    settings.validate().map_err(AppError::InvalidInput)?;
+    let ports = mediamtx::PortConfig::from_settings(&settings);
+
     // Update database
     db::schema::update_setting(&state.db, "max_total_streams", &settings.max_total_streams.to_string())
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     db::schema::update_setting(&state.db, "max_transcode_cpu", &settings.max_transcode_cpu.to_string())
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     db::schema::update_setting(&state.db, "max_transcode_nvenc", &settings.max_transcode_nvenc.to_string())
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    db::schema::update_setting(&state.db, "max_transcode_qsv", &settings.max_transcode_qsv.to_string())
+        .await?;
+    db::schema::update_setting(
+        &state.db,
+        "max_transcode_videotoolbox",
+        &settings.max_transcode_videotoolbox.to_string(),
+    )
+    .await?;
+    db::schema::update_setting(&state.db, "max_transcode_amd", &settings.max_transcode_amd.to_string())
+        .await?;
     db::schema::update_setting(&state.db, "max_total_bitrate_mbps", &settings.max_total_bitrate_mbps.to_string())
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    db::schema::update_setting(&state.db, "max_copy_bitrate_mbps", &settings.max_copy_bitrate_mbps.to_string())
+        .await?;
+    db::schema::update_setting(
+        &state.db,
+        "max_transcode_bitrate_mbps",
+        &settings.max_transcode_bitrate_mbps.to_string(),
+    )
+    .await?;
+    db::schema::update_setting(
+        &state.db,
+        "cache_maintenance_interval_mins",
+        &settings.cache_maintenance_interval_mins.to_string(),
+    )
+    .await?;
+    db::schema::update_setting(&state.db, "cache_max_size_gb", &settings.cache_max_size_gb.to_string())
+        .await?;
+    db::schema::update_setting(&state.db, "cache_max_age_days", &settings.cache_max_age_days.to_string())
+        .await?;
+    db::schema::update_setting(&state.db, "cache_eviction_policy", &settings.cache_eviction_policy)
+        .await?;
+    db::schema::update_setting(&state.db, "scan_depth", &settings.scan_depth.to_string())
+        .await?;
+    db::schema::update_setting(&state.db, "probe_timeout_secs", &settings.probe_timeout_secs.to_string())
+        .await?;
+    db::schema::update_setting(&state.db, "enable_hls", &settings.enable_hls.to_string())
+        .await?;
+    db::schema::update_setting(&state.db, "enable_webrtc", &settings.enable_webrtc.to_string())
+        .await?;
+    db::schema::update_setting(&state.db, "webrtc_ice_servers", &settings.webrtc_ice_servers)
+        .await?;
+    db::schema::update_setting(
+        &state.db,
+        "telemetry_history_size",
+        &settings.telemetry_history_size.to_string(),
+    )
+    .await?;
+    db::schema::update_setting(&state.db, "metrics_port", &settings.metrics_port.to_string())
+        .await?;
+    db::schema::update_setting(&state.db, "metrics_bind_address", &settings.metrics_bind_address)
+        .await?;
+    db::schema::update_setting(&state.db, "db_max_connections", &settings.db_max_connections.to_string())
+        .await?;
+    db::schema::update_setting(&state.db, "allow_preemption", &settings.allow_preemption.to_string())
+        .await?;
+    for (key, port) in [
+        ("rtsp_port", settings.rtsp_port),
+        ("rtmp_port", settings.rtmp_port),
+        ("srt_port", settings.srt_port),
+        ("hls_port", settings.hls_port),
+        ("webrtc_port", settings.webrtc_port),
+        ("rtp_port", settings.rtp_port),
+        ("rtcp_port", settings.rtcp_port),
+        ("webrtc_udp_port", settings.webrtc_udp_port),
+        ("api_port", settings.api_port),
+    ] {
+        db::schema::update_setting(&state.db, key, &port.to_string())
+            .await?;
+    }
+
+    // A running server keeps its ports until it restarts
+    let mut manager = state.mediamtx.lock().await;
+    if !manager.is_running() {
+        manager.set_ports(ports);
+    }
+    drop(manager);
 
     // Update scheduler
     let mut scheduler = state.scheduler.lock().await;
     scheduler.update_settings(&settings);
+    drop(scheduler);
+
+    state.telemetry.lock().await.update_settings(&settings);
+    state
+        .cache_manager
+        .lock()
+        .await
+        .update_config(CacheConfig::from_settings(&settings));
 
     Ok(())
 }
 
+// ============ Config Backup Commands ============
+
+#[tauri::command]
+async fn export_config(state: State<'_, AppState>, path: String) -> Result<(), AppError> {
+    db::config::export_config(&state.db, std::path::Path::new(&path))
+        .await
+        .map(|_| ())
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn import_config(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<db::config::ConfigImportResult, AppError> {
+    let result = db::config::import_config(&state.db, std::path::Path::new(&path))
+        .await?;
+
+    // Apply imported limits to the running app
+    let settings = db::schema::get_app_settings(&state.db)
+        .await?;
+    state.scheduler.lock().await.update_settings(&settings);
+    state.telemetry.lock().await.update_settings(&settings);
+    state
+        .cache_manager
+        .lock()
+        .await
+        .update_config(CacheConfig::from_settings(&settings));
+
+    // Streams whose source is in this library can be started right away
+    let streams = stream::get_all_streams(&state.db)
+        .await?;
+    for s in streams.iter().filter(|s| s.media_file_id.is_some()) {
+        if state.scheduler.lock().await.get_state(&s.id).is_some() {
+            continue;
+        }
+        if let Err(e) = register_with_scheduler(&state, &s.id).await {
+            eprintln!("[Config] Could not schedule imported stream {}: {}", s.name, e);
+        }
+    }
+
+    println!(
+        "[Config] Imported {} profiles, {} settings, {} streams",
+        result.profiles, result.settings, result.streams
+    );
+    Ok(result)
+}
+
 // ============ Telemetry Commands ============
 
 #[tauri::command]
-async fn get_telemetry(state: State<'_, AppState>) -> Result<TelemetryMetrics, String> {
+async fn get_telemetry(state: State<'_, AppState>) -> Result<TelemetryMetrics, AppError> {
     let mut telemetry = state.telemetry.lock().await;
     Ok(telemetry.collect())
 }
 
+/// CPU and memory used by each running stream
+#[tauri::command]
+async fn get_stream_stats(state: State<'_, AppState>) -> Result<Vec<StreamStats>, AppError> {
+    let pids = state.supervisor.lock().await.stream_pids();
+    let mut telemetry = state.telemetry.lock().await;
+    Ok(telemetry.stream_stats(&pids))
+}
+
+/// Recent telemetry samples for trend charts, oldest first
+#[tauri::command]
+async fn get_telemetry_history(state: State<'_, AppState>) -> Result<Vec<TelemetrySample>, AppError> {
+    let telemetry = state.telemetry.lock().await;
+    Ok(telemetry.history())
+}
+
 #[tauri::command]
-async fn get_capacity(state: State<'_, AppState>) -> Result<CapacitySummary, String> {
+async fn get_capacity(state: State<'_, AppState>) -> Result<CapacitySummary, AppError> {
     let scheduler = state.scheduler.lock().await;
     Ok(scheduler.capacity_summary())
 }
 
+/// Capacity, queue contents and running streams in one call
+#[tauri::command]
+async fn get_scheduler_snapshot(
+    state: State<'_, AppState>,
+) -> Result<scheduler::SchedulerSnapshot, AppError> {
+    let scheduler = state.scheduler.lock().await;
+    Ok(scheduler.snapshot())
+}
+
 // ============ GPU Commands ============
 
+/// Detect NVENC, probing its session limit again when no NVENC stream is
+/// live. The scheduler stays locked meanwhile so none starts mid-probe.
+#[tauri::command]
+async fn detect_nvenc(state: State<'_, AppState>) -> Result<gpu::NvencCapability, AppError> {
+    let idle = state.scheduler.lock().await.capacity_summary().nvenc_transcoding == 0;
+    Ok(gpu::detect_nvenc(idle).await)
+}
+
+#[tauri::command]
+async fn detect_qsv() -> Result<gpu::QsvCapability, AppError> {
+    Ok(gpu::detect_qsv().await)
+}
+
+#[tauri::command]
+async fn detect_videotoolbox() -> Result<gpu::VideoToolboxCapability, AppError> {
+    Ok(gpu::detect_videotoolbox().await)
+}
+
 #[tauri::command]
-async fn detect_nvenc() -> Result<gpu::NvencCapability, String> {
-    Ok(gpu::detect_nvenc().await)
+async fn detect_amd() -> Result<gpu::AmdCapability, AppError> {
+    Ok(gpu::detect_amd().await)
 }
 
 // ============ Security Commands ============
 
 #[tauri::command]
 async fn generate_stream_url(
+    state: State<'_, AppState>,
     stream_name: String,
     protocol: String,
     host: String,
     include_auth: bool,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     let auth = if include_auth {
-        Some(security::generate_credentials(&stream_name))
+        let record = stream::get_stream_by_name(&state.db, &stream_name)
+            .await?
+            .ok_or_else(|| format!("Stream not found: {}", stream_name))?;
+        Some(
+            security::get_or_create_credentials(&state.db, &record.id)
+                .await?,
+        )
     } else {
         None
     };
 
-    let url = security::auth::build_reader_url(&protocol, &stream_name, auth.as_ref(), &host);
+    let ports = state.mediamtx.lock().await.ports();
+    let url = security::auth::build_reader_url(&protocol, &stream_name, auth.as_ref(), &host, &ports);
     Ok(url)
 }
 
 #[tauri::command]
-async fn get_stream_credentials(stream_id: String) -> Result<security::StreamAuth, String> {
-    Ok(security::generate_credentials(&stream_id))
+async fn get_stream_credentials(
+    state: State<'_, AppState>,
+    stream_id: String,
+) -> Result<security::StreamAuth, AppError> {
+    security::get_or_create_credentials(&state.db, &stream_id)
+        .await
+        .map_err(AppError::from)
 }
 
 // ============ Merge Commands ============
@@ -377,24 +1885,25 @@ async fn get_stream_credentials(stream_id: String) -> Result<security::StreamAut
 async fn check_merge_files(
     state: State<'_, AppState>,
     file_ids: Vec<String>,
-) -> Result<MergeCheckResult, String> {
+) -> Result<MergeCheckResult, AppError> {
     let mut files = Vec::new();
 
     for id in &file_ids {
         let file = scanner::get_media_file(&state.db, id)
-            .await
-            .map_err(|e| e.to_string())?
+            .await?
             .ok_or_else(|| format!("File not found: {}", id))?;
         files.push(file);
     }
 
     let strategy = check_merge_compatibility(&files);
+    let detailed_issues = merge::compatibility::get_detailed_issues(&files);
     let issues = merge::compatibility::get_compatibility_issues(&files);
     let total_duration = merge::compatibility::compute_total_duration(&files);
 
     Ok(MergeCheckResult {
         strategy: strategy.as_str().to_string(),
         issues,
+        detailed_issues,
         total_duration_secs: total_duration,
         file_count: files.len(),
     })
@@ -405,6 +1914,8 @@ async fn check_merge_files(
 pub struct MergeCheckResult {
     pub strategy: String,
     pub issues: Vec<String>,
+    /// Structured form of `issues`, one entry per differing field
+    pub detailed_issues: Vec<merge::CompatIssue>,
     pub total_duration_secs: f64,
     pub file_count: usize,
 }
@@ -416,13 +1927,12 @@ async fn create_merge_job(
     file_ids: Vec<String>,
     _stream_name: String,
     _profile_id: String,
-) -> Result<MergeJob, String> {
+) -> Result<MergeJob, AppError> {
     // Check compatibility
     let mut files = Vec::new();
     for id in &file_ids {
         let file = scanner::get_media_file(&state.db, id)
-            .await
-            .map_err(|e| e.to_string())?
+            .await?
             .ok_or_else(|| format!("File not found: {}", id))?;
         files.push(file);
     }
@@ -445,41 +1955,86 @@ async fn create_merge_job(
     .bind(&file_ids_json)
     .bind(&now)
     .execute(&state.db)
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
     // Return the created job
     let job = sqlx::query_as::<_, MergeJob>("SELECT * FROM merge_jobs WHERE id = ?")
         .bind(&job_id)
         .fetch_one(&state.db)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     Ok(job)
 }
 
+/// Start running a pending merge job in the background.
+/// Progress is reported through `merge-progress` events keyed by job id.
+#[tauri::command]
+async fn start_merge_job(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    job_id: String,
+) -> Result<(), AppError> {
+    let job = sqlx::query_as::<_, MergeJob>("SELECT * FROM merge_jobs WHERE id = ?")
+        .bind(&job_id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| format!("Merge job not found: {}", job_id))?;
+
+    if job.status != "pending" {
+        return Err(AppError::Conflict(format!("Merge job {} is already {}", job_id, job.status)));
+    }
+
+    let output_dir = app
+        .path()
+        .app_data_dir()?
+        .join("merged");
+    let db = state.db.clone();
+    let registry = state.merge_jobs.clone();
+
+    let (progress_tx, mut progress_rx) = tokio::sync::mpsc::channel(64);
+    let progress_app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(progress) = progress_rx.recv().await {
+            let _ = progress_app.emit("merge-progress", progress);
+        }
+    });
+
+    tauri::async_runtime::spawn(async move {
+        // Failures are recorded on the job row and reported via events
+        let _ = merge::run_merge_job(&db, &registry, &job_id, &output_dir, Some(progress_tx)).await;
+    });
+
+    Ok(())
+}
+
+/// Cancel a pending or running merge job, discarding any partial output
+#[tauri::command]
+async fn cancel_merge_job(state: State<'_, AppState>, job_id: String) -> Result<(), AppError> {
+    merge::cancel_merge_job(&state.db, &state.merge_jobs, &job_id)
+        .await
+        .map_err(AppError::from)
+}
+
 /// Get merge job status
 #[tauri::command]
 async fn get_merge_job(
     state: State<'_, AppState>,
     job_id: String,
-) -> Result<Option<MergeJob>, String> {
+) -> Result<Option<MergeJob>, AppError> {
     let job = sqlx::query_as::<_, MergeJob>("SELECT * FROM merge_jobs WHERE id = ?")
         .bind(&job_id)
         .fetch_optional(&state.db)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     Ok(job)
 }
 
 /// Get all merge jobs
 #[tauri::command]
-async fn get_merge_jobs(state: State<'_, AppState>) -> Result<Vec<MergeJob>, String> {
+async fn get_merge_jobs(state: State<'_, AppState>) -> Result<Vec<MergeJob>, AppError> {
     let jobs = sqlx::query_as::<_, MergeJob>("SELECT * FROM merge_jobs ORDER BY created_at DESC")
         .fetch_all(&state.db)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     Ok(jobs)
 }
@@ -489,12 +2044,11 @@ async fn get_merge_jobs(state: State<'_, AppState>) -> Result<Vec<MergeJob>, Str
 async fn delete_merge_job(
     state: State<'_, AppState>,
     job_id: String,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     sqlx::query("DELETE FROM merge_jobs WHERE id = ?")
         .bind(&job_id)
         .execute(&state.db)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     Ok(())
 }
@@ -503,16 +2057,16 @@ async fn delete_merge_job(
 
 /// Get cache statistics
 #[tauri::command]
-async fn get_cache_stats(state: State<'_, AppState>) -> Result<CacheStats, String> {
+async fn get_cache_stats(state: State<'_, AppState>) -> Result<CacheStats, AppError> {
     let cache = state.cache_manager.lock().await;
-    cache.get_stats().await.map_err(|e| e.to_string())
+    cache.get_stats().await.map_err(AppError::from)
 }
 
 /// Clear old cache entries
 #[tauri::command]
-async fn clear_old_cache(state: State<'_, AppState>) -> Result<CacheCleanupResult, String> {
+async fn clear_old_cache(state: State<'_, AppState>) -> Result<CacheCleanupResult, AppError> {
     let cache = state.cache_manager.lock().await;
-    let result = cache.clear_old_cache().await.map_err(|e| e.to_string())?;
+    let result = cache.clear_old_cache().await.map_err(AppError::from)?;
     Ok(CacheCleanupResult {
         freed_bytes: result.freed_bytes,
         files_removed: result.files_removed,
@@ -521,9 +2075,9 @@ async fn clear_old_cache(state: State<'_, AppState>) -> Result<CacheCleanupResul
 
 /// Clear all cache
 #[tauri::command]
-async fn clear_all_cache(state: State<'_, AppState>) -> Result<CacheCleanupResult, String> {
+async fn clear_all_cache(state: State<'_, AppState>) -> Result<CacheCleanupResult, AppError> {
     let cache = state.cache_manager.lock().await;
-    let result = cache.clear_all().await.map_err(|e| e.to_string())?;
+    let result = cache.clear_all().await.map_err(AppError::from)?;
     Ok(CacheCleanupResult {
         freed_bytes: result.freed_bytes,
         files_removed: result.files_removed,
@@ -537,9 +2091,101 @@ pub struct CacheCleanupResult {
     pub files_removed: u32,
 }
 
+/// List individual cache entries, most recently used first
+#[tauri::command]
+async fn get_cache_entries(state: State<'_, AppState>) -> Result<Vec<cache::CacheEntryInfo>, AppError> {
+    let cache = state.cache_manager.lock().await;
+    cache.list_entries().await.map_err(AppError::from)
+}
+
+/// Evict a single cache entry
+#[tauri::command]
+async fn delete_cache_entry(state: State<'_, AppState>, id: String) -> Result<CacheCleanupResult, AppError> {
+    let cache = state.cache_manager.lock().await;
+    let result = cache.delete_cache_entry(&id).await.map_err(AppError::from)?;
+    Ok(CacheCleanupResult {
+        freed_bytes: result.freed_bytes,
+        files_removed: result.files_removed,
+    })
+}
+
+/// Check cache files and drop entries that are missing, truncated or unplayable
+#[tauri::command]
+async fn verify_cache(state: State<'_, AppState>) -> Result<cache::VerifyReport, AppError> {
+    let cache = state.cache_manager.lock().await.clone();
+    cache.verify().await.map_err(AppError::from)
+}
+
+/// Start normalizing a media file for each preset ahead of time, in the
+/// background. Progress is reported through `prewarm-progress` events, one
+/// per preset in order.
+#[tauri::command]
+async fn prewarm_cache(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    media_file_id: String,
+    preset_ids: Vec<String>,
+) -> Result<(), AppError> {
+    let configs = preset_ids
+        .iter()
+        .map(|id| {
+            merge::NormalizeConfig::from_preset(id)
+                .ok_or_else(|| AppError::InvalidInput(format!("Unknown preset: {}", id)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if scanner::get_media_file(&state.db, &media_file_id).await?.is_none() {
+        return Err(AppError::NotFound(format!("Media file not found: {}", media_file_id)));
+    }
+
+    // Work on a copy so the shared manager isn't locked while transcoding
+    let cache = state.cache_manager.lock().await.clone();
+    tauri::async_runtime::spawn(async move {
+        let total = configs.len();
+        for (completed, (preset_id, config)) in preset_ids.into_iter().zip(configs).enumerate() {
+            let result = match cache.prewarm(&media_file_id, std::slice::from_ref(&config)).await {
+                Ok(mut results) => results.remove(0),
+                Err(e) => cache::PrewarmResult {
+                    cache_key: String::new(),
+                    status: cache::PrewarmStatus::Failed,
+                    error: Some(e.to_string()),
+                },
+            };
+            let _ = app.emit(
+                "prewarm-progress",
+                PrewarmProgress {
+                    media_file_id: media_file_id.clone(),
+                    completed: completed + 1,
+                    total,
+                    result: PrewarmPresetResult { preset_id, result },
+                },
+            );
+        }
+    });
+
+    Ok(())
+}
+
+/// Payload of `prewarm-progress`: one preset finished; the last event of a
+/// pre-warm has `completed == total`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PrewarmProgress {
+    pub media_file_id: String,
+    pub completed: usize,
+    pub total: usize,
+    pub result: PrewarmPresetResult,
+}
+
+/// Pre-warm result for one preset
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrewarmPresetResult {
+    pub preset_id: String,
+    #[serde(flatten)]
+    pub result: cache::PrewarmResult,
+}
+
 /// Get normalize config presets
 #[tauri::command]
-async fn get_normalize_presets() -> Result<Vec<NormalizePreset>, String> {
+async fn get_normalize_presets() -> Result<Vec<NormalizePreset>, AppError> {
     Ok(vec![
         NormalizePreset {
             id: "720p".into(),
@@ -581,16 +2227,50 @@ pub struct NormalizePreset {
 #[tauri::command]
 async fn export_diagnostics_zip(
     app: tauri::AppHandle,
+    state: State<'_, AppState>,
     output_path: String,
-) -> Result<diagnostics::DiagnosticsResult, String> {
-    let app_dir = app.path().app_data_dir()
-        .map_err(|e| e.to_string())?;
+    include_database: Option<bool>,
+) -> Result<diagnostics::DiagnosticsResult, AppError> {
+    let app_dir = app.path().app_data_dir()?;
 
     let output = std::path::PathBuf::from(&output_path);
+    let database = include_database.unwrap_or(false).then_some(&state.db);
 
-    diagnostics::export_diagnostics(&output, &app_dir)
+    diagnostics::export_diagnostics(&output, &app_dir, database)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
+}
+
+/// Write a consistent copy of the database, safe while streams are running
+#[tauri::command]
+async fn backup_database(state: State<'_, AppState>, path: String) -> Result<(), AppError> {
+    db::backup(&state.db, std::path::Path::new(&path))
+        .await
+        .map_err(AppError::from)
+}
+
+/// Status of MediaMTX, the scheduler, GPU, cache and database in one call
+#[tauri::command]
+async fn get_health(state: State<'_, AppState>) -> Result<diagnostics::health::HealthReport, AppError> {
+    let mediamtx_running = state.mediamtx.lock().await.is_running();
+    let (capacity, queued_streams) = {
+        let scheduler = state.scheduler.lock().await;
+        (scheduler.capacity_summary(), scheduler.queue_info().len())
+    };
+    let gpu_available = state.telemetry.lock().await.gpu_available();
+    let cache_warning = state.cache_manager.lock().await.get_stats().await.ok().map(|s| s.warning);
+
+    Ok(diagnostics::health::HealthReport {
+        mediamtx_running,
+        running_streams: capacity.total_streams,
+        max_streams: capacity.max_streams,
+        queued_streams,
+        gpu_available,
+        cache_warning,
+        database_ok: diagnostics::health::ping_db(&state.db).await,
+        healthy: false,
+    }
+    .with_overall())
 }
 
 /// Get system info for diagnostics
@@ -601,23 +2281,22 @@ fn get_system_info() -> diagnostics::SystemInfo {
 
 /// Check if first run (onboarding needed)
 #[tauri::command]
-async fn check_first_run(state: State<'_, AppState>) -> Result<bool, String> {
+async fn check_first_run(state: State<'_, AppState>) -> Result<bool, AppError> {
     let result = sqlx::query_scalar::<_, String>(
         "SELECT value FROM settings WHERE key = 'onboarding_completed'"
     )
     .fetch_optional(&state.db)
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
     Ok(result.map(|v| v != "true").unwrap_or(true))
 }
 
 /// Mark onboarding as completed
 #[tauri::command]
-async fn complete_onboarding(state: State<'_, AppState>) -> Result<(), String> {
+async fn complete_onboarding(state: State<'_, AppState>) -> Result<(), AppError> {
     db::schema::update_setting(&state.db, "onboarding_completed", "true")
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 // ============ App Setup ============
@@ -637,6 +2316,12 @@ pub fn run() {
                     .expect("Failed to get app data directory");
                 std::fs::create_dir_all(&app_dir).expect("Failed to create app data directory");
 
+                // Keep the app's console output on disk for diagnostics
+                let logs_dir = diagnostics::logs::logs_dir(&app_dir);
+                if let Err(e) = diagnostics::logs::capture_app_output(&logs_dir) {
+                    eprintln!("[App] Warning: Failed to capture app log: {}", e);
+                }
+
                 let db_path = app_dir.join("c-video.db");
                 println!("[App] Database path: {:?}", db_path);
 
@@ -645,6 +2330,14 @@ pub fn run() {
                     .await
                     .expect("Failed to initialize database");
 
+                // Streams marked active by a run that crashed have no process
+                // behind them anymore
+                match stream::reconcile_stale_streams(&pool, stream::supervisor::kill_orphaned_ffmpeg).await {
+                    Ok(0) => {}
+                    Ok(count) => println!("[App] Reset {} stream(s) left active by the last run", count),
+                    Err(e) => eprintln!("[App] Warning: Failed to reconcile stream states: {}", e),
+                }
+
                 // Load settings
                 let settings = db::schema::get_app_settings(&pool)
                     .await
@@ -652,16 +2345,37 @@ pub fn run() {
 
                 // Create supervisor with event channel
                 let supervisor = create_shared_supervisor();
+                let (event_tx, event_rx) = mpsc::unbounded_channel();
+                {
+                    let mut supervisor = supervisor.lock().await;
+                    supervisor.set_log_dir(logs_dir);
+                    supervisor.set_event_channel(event_tx);
+                }
 
                 // Create scheduler
                 let scheduler = create_shared_scheduler(&settings);
+                let slot_freed = scheduler.lock().await.slot_notifier();
+
+                // Spread NVENC streams over every NVIDIA GPU
+                let gpu_scheduler = scheduler.clone();
+                tauri::async_runtime::spawn(async move {
+                    let gpus = gpu::nvenc::list_gpus().await;
+                    if !gpus.is_empty() {
+                        println!("[App] Found {} NVIDIA GPU(s)", gpus.len());
+                    }
+                    gpu_scheduler.lock().await.set_nvenc_gpu_count(gpus.len());
+                });
+
+                // URLs are built from the ports MediaMTX will listen on
+                let mut mediamtx_manager = mediamtx::MediaMTXManager::new();
+                mediamtx_manager.set_ports(mediamtx::PortConfig::from_settings(&settings));
 
                 // Create telemetry collector
-                let telemetry = create_shared_telemetry();
+                let telemetry = create_shared_telemetry(&settings);
 
                 // Create cache manager
                 let cache_dir = app_dir.join("cache");
-                let cache_config = CacheConfig::default();
+                let cache_config = CacheConfig::from_settings(&settings);
                 let cache_manager = CacheManager::new(cache_dir, pool.clone(), cache_config);
 
                 // Initialize cache directory
@@ -672,14 +2386,42 @@ pub fn run() {
                 // Create state
                 let state = AppState {
                     db: pool,
-                    mediamtx: Arc::new(Mutex::new(mediamtx::MediaMTXManager::new())),
+                    mediamtx: Arc::new(Mutex::new(mediamtx_manager)),
                     supervisor,
                     scheduler,
                     telemetry,
                     cache_manager: Arc::new(Mutex::new(cache_manager)),
+                    merge_jobs: merge::create_merge_registry(),
+                    recordings_dir: app_dir.join("recordings"),
+                    playlists_dir: app_dir.join("playlists"),
+                    cache_pins: Mutex::new(HashMap::new()),
                 };
 
                 app_handle.manage(state);
+
+                // Keep a history of stream lifecycle events
+                tauri::async_runtime::spawn(run_stream_event_loop(app_handle.clone(), event_rx));
+
+                // Start queued streams as capacity frees up
+                tauri::async_runtime::spawn(run_dequeue_loop(app_handle.clone(), slot_freed));
+
+                // Keep the cache within its age and size limits
+                tauri::async_runtime::spawn(run_cache_maintenance(app_handle.clone()));
+
+                // Sample telemetry for the trend charts
+                tauri::async_runtime::spawn(run_telemetry_sampler(app_handle.clone()));
+
+                // Bring MediaMTX back if it crashes
+                tauri::async_runtime::spawn(run_mediamtx_monitor(app_handle.clone()));
+
+                // Start and stop streams at their scheduled times
+                tauri::async_runtime::spawn(run_schedule_loop(app_handle.clone()));
+
+                // Optional Prometheus endpoint, on localhost unless a LAN
+                // address is configured; changes apply on restart
+                if let Some(addr) = metrics_addr(&settings) {
+                    tauri::async_runtime::spawn(run_metrics_server(app_handle.clone(), addr));
+                }
             });
 
             Ok(())
@@ -691,36 +2433,80 @@ pub fn run() {
             get_mediamtx_status,
             // Scanner
             scan_folder,
+            rescan_folder,
+            rescan_file,
+            add_url_source,
+            get_thumbnail,
             get_media_files,
             get_media_file,
             delete_media_file,
             // Streams
             create_stream,
+            create_playlist_stream,
+            get_stream_playlist,
             get_streams,
             get_stream,
             delete_stream,
             start_stream,
+            restart_stream,
             stop_stream,
             get_stream_status,
+            get_stream_log,
+            get_stream_events,
+            schedule_stream,
+            set_stream_loop,
+            set_stream_audio_track,
+            set_stream_subtitles,
+            set_stream_extra_args,
             // Batch operations
             batch_start_streams,
             batch_stop_streams,
+            batch_delete_streams,
+            batch_get_status,
+            // Queue
+            reprioritize_stream,
+            pin_stream,
+            cancel_queued_stream,
+            get_recordings,
+            capture_snapshot,
             // Profiles
             get_profiles,
+            create_profile,
+            update_profile,
+            delete_profile,
             // Settings
             get_settings,
             update_settings,
+            // Config backup
+            export_config,
+            import_config,
             // Telemetry
             get_telemetry,
+            get_telemetry_history,
+            get_stream_stats,
             get_capacity,
+            get_scheduler_snapshot,
             // GPU
             detect_nvenc,
+            detect_qsv,
+            detect_videotoolbox,
+            detect_amd,
             // Security
             generate_stream_url,
+            add_stream_output,
+            remove_stream_output,
+            get_stream_outputs,
+            get_stream_urls,
+            preview_stream_command,
+            add_stream_tag,
+            remove_stream_tag,
+            get_streams_by_tag,
             get_stream_credentials,
             // Merge
             check_merge_files,
             create_merge_job,
+            start_merge_job,
+            cancel_merge_job,
             get_merge_job,
             get_merge_jobs,
             delete_merge_job,
@@ -728,13 +2514,95 @@ pub fn run() {
             get_cache_stats,
             clear_old_cache,
             clear_all_cache,
+            get_cache_entries,
+            delete_cache_entry,
+            prewarm_cache,
+            verify_cache,
             get_normalize_presets,
             // Diagnostics
             export_diagnostics_zip,
+            backup_database,
             get_system_info,
+            get_health,
             check_first_run,
             complete_onboarding,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn pool_with_stream(profile_id: &str) -> (SqlitePool, String) {
+        let pool = db::test_pool().await;
+        sqlx::query("INSERT INTO media_files (id, path, folder, filename, scanned_at) VALUES ('m1', '/test/m1.mp4', 'test', 'm1.mp4', '2024-01-01')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let stream = stream::create_stream(&pool, "m1", "cam", profile_id).await.unwrap();
+        (pool, stream.id)
+    }
+
+    #[tokio::test]
+    async fn test_preview_credentials_store_nothing() {
+        let (pool, id) = pool_with_stream("wan-stable").await;
+        let plan = load_launch_plan(&pool, &id).await.unwrap();
+
+        assert!(preview_credentials(&pool, &plan).await.unwrap().is_some());
+        assert_eq!(security::load_credentials(&pool, &id).await.unwrap(), None);
+
+        // Once a start has stored them, the preview shows the real ones
+        let stored = plan_credentials(&pool, &plan).await.unwrap();
+        assert_eq!(preview_credentials(&pool, &plan).await.unwrap(), stored);
+    }
+
+    /// Args a start would run for `id`, built the way `launch_locked` does
+    async fn start_args(pool: &SqlitePool, id: &str) -> (Vec<String>, Option<security::StreamAuth>) {
+        let plan = load_launch_plan(pool, id).await.unwrap();
+        let auth = plan_credentials(pool, &plan).await.unwrap();
+        let targets = output_targets(&plan, auth.as_ref(), mediamtx::PortConfig::default());
+        let (args, _) =
+            launch_args(&plan, std::path::Path::new("/unused.txt"), &targets, None, None).unwrap();
+        (args, auth)
+    }
+
+    #[tokio::test]
+    async fn test_restart_picks_up_edited_profile() {
+        let (pool, id) = pool_with_stream("wan-stable").await;
+        let (before, auth_before) = start_args(&pool, &id).await;
+        assert!(before.windows(2).any(|w| w[0] == "-b:v" && w[1] == "4000k"));
+
+        let mut profile: Profile = sqlx::query_as("SELECT * FROM profiles WHERE id = 'wan-stable'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        profile.video_bitrate = Some(2500);
+        stream::update_profile(&pool, profile).await.unwrap();
+
+        // A restart reloads the plan: same stream and credentials, new encode
+        let (after, auth_after) = start_args(&pool, &id).await;
+        assert!(after.windows(2).any(|w| w[0] == "-b:v" && w[1] == "2500k"));
+        assert!(!after.contains(&"4000k".to_string()));
+        assert!(auth_before.is_some());
+        assert_eq!(auth_after, auth_before);
+        assert_eq!(load_launch_plan(&pool, &id).await.unwrap().stream.id, id);
+        assert_eq!(before.last(), after.last());
+    }
+
+    #[test]
+    fn test_metrics_bind_localhost_unless_opted_in() {
+        let mut settings = AppSettings::default();
+        assert_eq!(metrics_addr(&settings), None);
+
+        settings.metrics_port = 9100;
+        assert_eq!(metrics_addr(&settings), Some("127.0.0.1:9100".parse().unwrap()));
+
+        settings.metrics_bind_address = "0.0.0.0".into();
+        assert_eq!(metrics_addr(&settings), Some("0.0.0.0:9100".parse().unwrap()));
+
+        settings.metrics_bind_address = "lan".into();
+        assert_eq!(metrics_addr(&settings), Some("127.0.0.1:9100".parse().unwrap()));
+    }
+}
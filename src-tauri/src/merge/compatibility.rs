@@ -14,7 +14,10 @@ pub struct FileCompatibility {
     pub width: u32,
     pub height: u32,
     pub fps: f64,
-    pub sample_rate: u32,
+    /// Audio sample rate in Hz, None if unknown or no audio
+    pub sample_rate: Option<u32>,
+    /// Audio channel count, None if unknown or no audio
+    pub channels: Option<u32>,
     pub bitrate: u32,
 }
 
@@ -39,6 +42,9 @@ impl MergeStrategy {
     }
 }
 
+/// Assumed frame rate for files scanned before fps was probed
+const DEFAULT_FPS: f64 = 30.0;
+
 /// Extract compatibility info from a media file
 pub fn extract_compatibility(file: &MediaFile) -> FileCompatibility {
     FileCompatibility {
@@ -46,19 +52,13 @@ pub fn extract_compatibility(file: &MediaFile) -> FileCompatibility {
         audio_codec: file.audio_codec.clone().unwrap_or_default(),
         width: file.width.unwrap_or(0) as u32,
         height: file.height.unwrap_or(0) as u32,
-        fps: estimate_fps(file),
-        sample_rate: 48000, // Default; would need ffprobe for accurate value
+        fps: file.fps.unwrap_or(DEFAULT_FPS),
+        sample_rate: file.audio_sample_rate.map(|rate| rate as u32),
+        channels: file.audio_channels.map(|channels| channels as u32),
         bitrate: file.bitrate.unwrap_or(0) as u32,
     }
 }
 
-/// Estimate FPS from media file (default 30 if unknown)
-fn estimate_fps(_file: &MediaFile) -> f64 {
-    // In a full implementation, ffprobe would provide this
-    // For now, default to common value
-    30.0
-}
-
 /// Check if files can be merged via concat-copy
 pub fn check_merge_compatibility(files: &[MediaFile]) -> MergeStrategy {
     if files.is_empty() {
@@ -84,6 +84,12 @@ pub fn check_merge_compatibility(files: &[MediaFile]) -> MergeStrategy {
     }
 }
 
+/// Whether two values are both known and differ; files scanned before a
+/// property was probed aren't held against the others
+fn known_differ(a: Option<u32>, b: Option<u32>) -> bool {
+    matches!((a, b), (Some(a), Some(b)) if a != b)
+}
+
 /// Check if two files are compatible for concat-copy
 fn is_compatible(a: &FileCompatibility, b: &FileCompatibility) -> bool {
     // Video codec must match
@@ -107,15 +113,79 @@ fn is_compatible(a: &FileCompatibility, b: &FileCompatibility) -> bool {
     }
 
     // Sample rate should match for seamless audio
-    if a.sample_rate != b.sample_rate {
+    if known_differ(a.sample_rate, b.sample_rate) {
+        return false;
+    }
+
+    // Channel layout changes mid-stream break most players
+    if known_differ(a.channels, b.channels) {
         return false;
     }
 
     true
 }
 
-/// Get a summary of compatibility issues between files
-pub fn get_compatibility_issues(files: &[MediaFile]) -> Vec<String> {
+/// A single property of one file that differs from the first file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompatIssue {
+    /// Property name: video_codec, audio_codec, resolution, fps, sample_rate or channels
+    pub field: String,
+    /// Index of the offending file in the input list (0-based)
+    pub file_index: usize,
+    /// Value of the first file, which the others are compared against
+    pub expected: String,
+    /// Value of the offending file
+    pub actual: String,
+}
+
+impl CompatIssue {
+    fn new(field: &str, file_index: usize, expected: String, actual: String) -> Self {
+        Self {
+            field: field.to_string(),
+            file_index,
+            expected,
+            actual,
+        }
+    }
+
+    /// Human-readable summary of the issue
+    pub fn message(&self) -> String {
+        let n = self.file_index + 1;
+        match self.field.as_str() {
+            "video_codec" => format!(
+                "File {} has different video codec: {} vs {}",
+                n, self.actual, self.expected
+            ),
+            "audio_codec" => format!(
+                "File {} has different audio codec: {} vs {}",
+                n, self.actual, self.expected
+            ),
+            "resolution" => format!(
+                "File {} has different resolution: {} vs {}",
+                n, self.actual, self.expected
+            ),
+            "fps" => format!(
+                "File {} has different FPS: {} vs {}",
+                n, self.actual, self.expected
+            ),
+            "sample_rate" => format!(
+                "File {} has sample rate mismatch: {} Hz vs {} Hz",
+                n, self.actual, self.expected
+            ),
+            "channels" => format!(
+                "File {} has different audio channels: {} vs {}",
+                n, self.actual, self.expected
+            ),
+            field => format!(
+                "File {} has different {}: {} vs {}",
+                n, field, self.actual, self.expected
+            ),
+        }
+    }
+}
+
+/// Per-field differences between each file and the first one
+pub fn get_detailed_issues(files: &[MediaFile]) -> Vec<CompatIssue> {
     let mut issues = Vec::new();
 
     if files.len() < 2 {
@@ -128,47 +198,75 @@ pub fn get_compatibility_issues(files: &[MediaFile]) -> Vec<String> {
         let c = extract_compatibility(file);
 
         if c.video_codec != first.video_codec {
-            issues.push(format!(
-                "File {} has different video codec: {} vs {}",
-                i + 1,
-                c.video_codec,
-                first.video_codec
+            issues.push(CompatIssue::new(
+                "video_codec",
+                i,
+                first.video_codec.clone(),
+                c.video_codec.clone(),
             ));
         }
 
         if c.audio_codec != first.audio_codec {
-            issues.push(format!(
-                "File {} has different audio codec: {} vs {}",
-                i + 1,
-                c.audio_codec,
-                first.audio_codec
+            issues.push(CompatIssue::new(
+                "audio_codec",
+                i,
+                first.audio_codec.clone(),
+                c.audio_codec.clone(),
             ));
         }
 
         if c.width != first.width || c.height != first.height {
-            issues.push(format!(
-                "File {} has different resolution: {}x{} vs {}x{}",
-                i + 1,
-                c.width,
-                c.height,
-                first.width,
-                first.height
+            issues.push(CompatIssue::new(
+                "resolution",
+                i,
+                format!("{}x{}", first.width, first.height),
+                format!("{}x{}", c.width, c.height),
             ));
         }
 
         if (c.fps - first.fps).abs() > 0.1 {
-            issues.push(format!(
-                "File {} has different FPS: {:.2} vs {:.2}",
-                i + 1,
-                c.fps,
-                first.fps
+            issues.push(CompatIssue::new(
+                "fps",
+                i,
+                format!("{:.2}", first.fps),
+                format!("{:.2}", c.fps),
             ));
         }
+
+        if let (Some(expected), Some(actual)) = (first.sample_rate, c.sample_rate) {
+            if expected != actual {
+                issues.push(CompatIssue::new(
+                    "sample_rate",
+                    i,
+                    expected.to_string(),
+                    actual.to_string(),
+                ));
+            }
+        }
+
+        if let (Some(expected), Some(actual)) = (first.channels, c.channels) {
+            if expected != actual {
+                issues.push(CompatIssue::new(
+                    "channels",
+                    i,
+                    expected.to_string(),
+                    actual.to_string(),
+                ));
+            }
+        }
     }
 
     issues
 }
 
+/// Get a summary of compatibility issues between files
+pub fn get_compatibility_issues(files: &[MediaFile]) -> Vec<String> {
+    get_detailed_issues(files)
+        .iter()
+        .map(CompatIssue::message)
+        .collect()
+}
+
 /// Compute estimated duration for merged files
 pub fn compute_total_duration(files: &[MediaFile]) -> f64 {
     files
@@ -204,6 +302,20 @@ mod tests {
             bitrate: Some(8_000_000),
             compatibility: "copy".to_string(),
             scanned_at: "2024-01-01".to_string(),
+            fps: Some(30.0),
+            audio_sample_rate: Some(48000),
+            audio_channels: Some(2),
+            size_bytes: Some(1_000_000),
+            modified_at: Some(1_700_000_000),
+            thumbnail_path: None,
+            rotation: None,
+            color_transfer: None,
+            color_primaries: None,
+            color_space: None,
+            pix_fmt: None,
+            hdr: 0,
+            audio_tracks: None,
+            subtitle_tracks: None,
         }
     }
 
@@ -251,6 +363,65 @@ mod tests {
         assert_eq!(result, MergeStrategy::TranscodeNormalize);
     }
 
+    #[test]
+    fn test_different_fps() {
+        let mut second = make_media_file("2", "h264", "aac", 1920, 1080);
+        second.fps = Some(24.0);
+        let files = vec![make_media_file("1", "h264", "aac", 1920, 1080), second];
+        let result = check_merge_compatibility(&files);
+        assert_eq!(result, MergeStrategy::TranscodeNormalize);
+
+        let issues = get_compatibility_issues(&files);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].contains("FPS"));
+    }
+
+    #[test]
+    fn test_ntsc_fps_within_tolerance() {
+        let mut first = make_media_file("1", "h264", "aac", 1920, 1080);
+        first.fps = Some(30000.0 / 1001.0);
+        let mut second = make_media_file("2", "h264", "aac", 1920, 1080);
+        second.fps = Some(29.97);
+        let result = check_merge_compatibility(&[first, second]);
+        assert_eq!(result, MergeStrategy::ConcatCopy);
+    }
+
+    #[test]
+    fn test_different_sample_rate() {
+        let mut second = make_media_file("2", "h264", "aac", 1920, 1080);
+        second.audio_sample_rate = Some(44100);
+        let files = vec![make_media_file("1", "h264", "aac", 1920, 1080), second];
+        assert_eq!(
+            check_merge_compatibility(&files),
+            MergeStrategy::TranscodeNormalize
+        );
+
+        let issues = get_compatibility_issues(&files);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].contains("sample rate mismatch"));
+    }
+
+    #[test]
+    fn test_unknown_sample_rate_is_not_a_mismatch() {
+        let mut second = make_media_file("2", "h264", "aac", 1920, 1080);
+        second.audio_sample_rate = None;
+        second.audio_channels = None;
+        let files = vec![make_media_file("1", "h264", "aac", 1920, 1080), second];
+        assert_eq!(check_merge_compatibility(&files), MergeStrategy::ConcatCopy);
+        assert!(get_compatibility_issues(&files).is_empty());
+    }
+
+    #[test]
+    fn test_different_channels() {
+        let mut second = make_media_file("2", "h264", "aac", 1920, 1080);
+        second.audio_channels = Some(6);
+        let files = vec![make_media_file("1", "h264", "aac", 1920, 1080), second];
+        assert_eq!(
+            check_merge_compatibility(&files),
+            MergeStrategy::TranscodeNormalize
+        );
+    }
+
     #[test]
     fn test_different_audio() {
         let files = vec![
@@ -269,6 +440,29 @@ mod tests {
         ];
         let issues = get_compatibility_issues(&files);
         assert_eq!(issues.len(), 3); // codec, audio, resolution
+        assert_eq!(issues[0], "File 2 has different video codec: hevc vs h264");
+        assert_eq!(issues[2], "File 2 has different resolution: 1280x720 vs 1920x1080");
+    }
+
+    #[test]
+    fn test_detailed_issues() {
+        let mut third = make_media_file("3", "h264", "aac", 1920, 1080);
+        third.fps = Some(25.0);
+        let files = vec![
+            make_media_file("1", "h264", "aac", 1920, 1080),
+            make_media_file("2", "h264", "aac", 1280, 720),
+            third,
+        ];
+
+        let issues = get_detailed_issues(&files);
+        assert_eq!(
+            issues,
+            vec![
+                CompatIssue::new("resolution", 1, "1920x1080".into(), "1280x720".into()),
+                CompatIssue::new("fps", 2, "30.00".into(), "25.00".into()),
+            ]
+        );
+        assert_eq!(issues[1].message(), "File 3 has different FPS: 25.00 vs 30.00");
     }
 
     #[test]
@@ -3,10 +3,10 @@
 //! Uses FFmpeg's concat demuxer to seamlessly join files
 //! without re-encoding (fast path).
 
-use crate::sidecar::get_sidecar_path;
+use crate::sidecar::find_binary;
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::{Child, Command, Stdio};
+use std::process::{Command, Stdio};
 use tempfile::NamedTempFile;
 use thiserror::Error;
 
@@ -24,41 +24,11 @@ pub enum ConcatError {
 
 /// Find FFmpeg binary
 fn find_ffmpeg() -> Result<PathBuf, ConcatError> {
-    // Try system ffmpeg first
-    if let Ok(output) = Command::new("which").arg("ffmpeg").output() {
-        if output.status.success() {
-            let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !path.is_empty() {
-                return Ok(PathBuf::from(path));
-            }
-        }
-    }
-
-    // Check common locations
-    let paths = [
-        "/opt/homebrew/bin/ffmpeg",
-        "/usr/local/bin/ffmpeg",
-        "/usr/bin/ffmpeg",
-    ];
-
-    for path in paths {
-        let p = PathBuf::from(path);
-        if p.exists() {
-            return Ok(p);
-        }
-    }
-
-    // Bundled sidecar
-    let sidecar_path = get_sidecar_path("ffmpeg");
-    if sidecar_path.exists() {
-        return Ok(sidecar_path);
-    }
-
-    Err(ConcatError::FfmpegNotFound)
+    find_binary("ffmpeg").ok_or(ConcatError::FfmpegNotFound)
 }
 
 /// Create a concat list file for FFmpeg
-fn create_concat_list(files: &[PathBuf]) -> Result<NamedTempFile, ConcatError> {
+pub(crate) fn create_concat_list(files: &[PathBuf]) -> Result<NamedTempFile, ConcatError> {
     let mut list_file = NamedTempFile::new()?;
 
     for path in files {
@@ -71,22 +41,9 @@ fn create_concat_list(files: &[PathBuf]) -> Result<NamedTempFile, ConcatError> {
     Ok(list_file)
 }
 
-/// Stream concatenated files via concat-copy to RTSP/SRT
-///
-/// This is the fast path for compatible files - no transcoding needed.
-pub fn concat_copy_stream(
-    files: &[PathBuf],
-    stream_name: &str,
-    protocol: &str,
-    loop_playback: bool,
-) -> Result<Child, ConcatError> {
-    if files.is_empty() {
-        return Err(ConcatError::NoFiles);
-    }
-
-    let ffmpeg = find_ffmpeg()?;
-    let list_file = create_concat_list(files)?;
-
+/// Input arguments that read a concat list in real time. Codec and output
+/// arguments are left to the caller.
+pub fn build_concat_input_args(list: &Path, loop_playback: bool) -> Vec<String> {
     let mut args = vec![
         "-re".to_string(), // Real-time mode
     ];
@@ -103,62 +60,10 @@ pub fn concat_copy_stream(
         "-safe".to_string(),
         "0".to_string(),
         "-i".to_string(),
-        list_file.path().to_string_lossy().to_string(),
-    ]);
-
-    // Copy codecs (no transcode)
-    args.extend([
-        "-c:v".to_string(),
-        "copy".to_string(),
-        "-c:a".to_string(),
-        "copy".to_string(),
+        list.to_string_lossy().to_string(),
     ]);
 
-    // Output format and destination
-    match protocol {
-        "rtsp" => {
-            args.extend([
-                "-f".to_string(),
-                "rtsp".to_string(),
-                "-rtsp_transport".to_string(),
-                "tcp".to_string(),
-                format!("rtsp://localhost:8554/{}", stream_name),
-            ]);
-        }
-        "srt" => {
-            args.extend([
-                "-f".to_string(),
-                "mpegts".to_string(),
-                format!(
-                    "srt://localhost:8890?streamid=publish:{}&pkt_size=1316",
-                    stream_name
-                ),
-            ]);
-        }
-        _ => {
-            // Default to RTSP
-            args.extend([
-                "-f".to_string(),
-                "rtsp".to_string(),
-                "-rtsp_transport".to_string(),
-                "tcp".to_string(),
-                format!("rtsp://localhost:8554/{}", stream_name),
-            ]);
-        }
-    }
-
-    let child = Command::new(&ffmpeg)
-        .args(&args)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| ConcatError::SpawnError(e.to_string()))?;
-
-    // Keep the temp file alive by leaking it
-    // In production, we'd manage this lifecycle better
-    std::mem::forget(list_file);
-
-    Ok(child)
+    args
 }
 
 /// Concat files to a single output file (for caching)
@@ -218,7 +123,7 @@ mod tests {
 
     #[test]
     fn test_empty_files_error() {
-        let result = concat_copy_stream(&[], "test", "rtsp", false);
+        let result = concat_copy_to_file(&[], Path::new("/tmp/out.ts"));
         assert!(matches!(result, Err(ConcatError::NoFiles)));
     }
 
@@ -0,0 +1,599 @@
+//! Merge job runner
+//!
+//! Executes a stored merge job to a single output file, tracking
+//! FFmpeg progress in the `merge_jobs` table.
+
+use super::compatibility::compute_total_duration;
+use super::concat::create_concat_list;
+use super::normalize::{build_normalize_file_args, NormalizeConfig, NormalizeInput};
+use crate::db::schema::{MediaFile, MergeJob};
+use crate::sidecar::find_binary;
+use serde::Serialize;
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Error)]
+pub enum MergeJobError {
+    #[error("Database error: {0}")]
+    Db(#[from] sqlx::Error),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Merge job not found: {0}")]
+    NotFound(String),
+    #[error("Merge job {0} is already {1}")]
+    InvalidState(String, String),
+    #[error("Media file not found: {0}")]
+    FileNotFound(String),
+    #[error("Nothing to merge")]
+    NoFiles,
+    #[error("FFmpeg not found")]
+    FfmpegNotFound,
+    #[error("FFmpeg failed: {0}")]
+    Failed(String),
+    #[error("Merge job cancelled")]
+    Cancelled,
+}
+
+/// FFmpeg children of running merge jobs, keyed by job id.
+/// Whoever removes a child owns its outcome: the runner on exit,
+/// or `cancel_merge_job` on cancel.
+pub type MergeRegistry = Arc<Mutex<HashMap<String, Child>>>;
+
+pub fn create_merge_registry() -> MergeRegistry {
+    Arc::new(Mutex::new(HashMap::new()))
+}
+
+/// Progress update for a running merge job
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeProgress {
+    pub job_id: String,
+    pub status: String,
+    /// Percent complete (0-100)
+    pub progress: f64,
+    pub error: Option<String>,
+}
+
+/// Run a pending merge job, writing the merged file into `output_dir`.
+/// Status moves pending -> running -> completed/failed; progress updates
+/// are stored on the job row and sent on `progress_tx` when given.
+pub async fn run_merge_job(
+    pool: &SqlitePool,
+    registry: &MergeRegistry,
+    job_id: &str,
+    output_dir: &Path,
+    progress_tx: Option<Sender<MergeProgress>>,
+) -> Result<PathBuf, MergeJobError> {
+    let ffmpeg = find_binary("ffmpeg").ok_or(MergeJobError::FfmpegNotFound)?;
+    run_merge_job_with(pool, registry, job_id, &ffmpeg, output_dir, progress_tx).await
+}
+
+/// `run_merge_job` with an explicit FFmpeg binary
+async fn run_merge_job_with(
+    pool: &SqlitePool,
+    registry: &MergeRegistry,
+    job_id: &str,
+    ffmpeg: &Path,
+    output_dir: &Path,
+    progress_tx: Option<Sender<MergeProgress>>,
+) -> Result<PathBuf, MergeJobError> {
+    let job = sqlx::query_as::<_, MergeJob>("SELECT * FROM merge_jobs WHERE id = ?")
+        .bind(job_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| MergeJobError::NotFound(job_id.to_string()))?;
+
+    // Claim the job atomically so two starts can't both run it
+    let claimed = sqlx::query(
+        "UPDATE merge_jobs SET status = 'running', progress = 0 WHERE id = ? AND status = 'pending'",
+    )
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+
+    if claimed.rows_affected() == 0 {
+        return Err(MergeJobError::InvalidState(job.id, job.status));
+    }
+    send_progress(&progress_tx, job_id, "running", 0.0, None).await;
+
+    let result = execute_job(pool, registry, &job, ffmpeg, output_dir, &progress_tx).await;
+
+    // Final updates only apply while still running, so a cancel that
+    // raced with FFmpeg exiting takes precedence
+    let result = match result {
+        Ok(output) => {
+            let completed = sqlx::query(
+                "UPDATE merge_jobs SET status = 'completed', progress = 100, output_path = ?, completed_at = ? WHERE id = ? AND status = 'running'",
+            )
+            .bind(output.to_string_lossy().to_string())
+            .bind(chrono::Utc::now().to_rfc3339())
+            .bind(job_id)
+            .execute(pool)
+            .await?;
+
+            if completed.rows_affected() == 0 {
+                let _ = std::fs::remove_file(&output);
+                Err(MergeJobError::Cancelled)
+            } else {
+                Ok(output)
+            }
+        }
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(output) => {
+            send_progress(&progress_tx, job_id, "completed", 100.0, None).await;
+            println!("[Merge] Job {} completed: {}", job_id, output.display());
+            Ok(output)
+        }
+        Err(MergeJobError::Cancelled) => {
+            sqlx::query("UPDATE merge_jobs SET status = 'cancelled', completed_at = ? WHERE id = ?")
+                .bind(chrono::Utc::now().to_rfc3339())
+                .bind(job_id)
+                .execute(pool)
+                .await?;
+            send_progress(&progress_tx, job_id, "cancelled", 0.0, None).await;
+
+            println!("[Merge] Job {} cancelled", job_id);
+            Err(MergeJobError::Cancelled)
+        }
+        Err(e) => {
+            let message = e.to_string();
+            sqlx::query(
+                "UPDATE merge_jobs SET status = 'failed', error_message = ?, completed_at = ? WHERE id = ? AND status = 'running'",
+            )
+            .bind(&message)
+            .bind(chrono::Utc::now().to_rfc3339())
+            .bind(job_id)
+            .execute(pool)
+            .await?;
+            send_progress(&progress_tx, job_id, "failed", 0.0, Some(message)).await;
+
+            eprintln!("[Merge] Job {} failed: {}", job_id, e);
+            Err(e)
+        }
+    }
+}
+
+/// Resolve the job's files and run FFmpeg for its strategy
+async fn execute_job(
+    pool: &SqlitePool,
+    registry: &MergeRegistry,
+    job: &MergeJob,
+    ffmpeg: &Path,
+    output_dir: &Path,
+    progress_tx: &Option<Sender<MergeProgress>>,
+) -> Result<PathBuf, MergeJobError> {
+    let file_ids: Vec<String> = serde_json::from_str(&job.file_ids).unwrap_or_default();
+    if file_ids.is_empty() {
+        return Err(MergeJobError::NoFiles);
+    }
+
+    let mut files = Vec::new();
+    for id in &file_ids {
+        let file = sqlx::query_as::<_, MediaFile>("SELECT * FROM media_files WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| MergeJobError::FileNotFound(id.clone()))?;
+        if !Path::new(&file.path).exists() {
+            return Err(MergeJobError::FileNotFound(file.path));
+        }
+        files.push(file);
+    }
+
+    let paths: Vec<PathBuf> = files.iter().map(|f| PathBuf::from(&f.path)).collect();
+    let total_duration = compute_total_duration(&files);
+    std::fs::create_dir_all(output_dir)?;
+
+    // The concat list (if any) must outlive the FFmpeg run
+    let (output, args, _concat_list) = if job.strategy == "concat_copy" {
+        let output = output_dir.join(format!("merge-{}.mkv", job.id));
+        let list = create_concat_list(&paths).map_err(|e| MergeJobError::Failed(e.to_string()))?;
+        let args = vec![
+            "-y".to_string(),
+            "-f".to_string(),
+            "concat".to_string(),
+            "-safe".to_string(),
+            "0".to_string(),
+            "-i".to_string(),
+            list.path().to_string_lossy().to_string(),
+            "-c".to_string(),
+            "copy".to_string(),
+            output.to_string_lossy().to_string(),
+        ];
+        (output, args, Some(list))
+    } else {
+        let output = output_dir.join(format!("merge-{}.mp4", job.id));
+        let inputs: Vec<NormalizeInput> = files.iter().map(NormalizeInput::from_media).collect();
+        let args = build_normalize_file_args(&inputs, &NormalizeConfig::default(), &output);
+        (output, args, None)
+    };
+
+    let result = run_ffmpeg_with_progress(
+        pool,
+        registry,
+        &job.id,
+        ffmpeg,
+        &args,
+        total_duration,
+        progress_tx,
+    )
+    .await;
+
+    if result.is_err() {
+        // Don't leave a partial merge behind
+        let _ = std::fs::remove_file(&output);
+    }
+
+    result.map(|_| output)
+}
+
+/// Run FFmpeg with `-progress pipe:1`, recording progress as it goes
+async fn run_ffmpeg_with_progress(
+    pool: &SqlitePool,
+    registry: &MergeRegistry,
+    job_id: &str,
+    ffmpeg: &Path,
+    args: &[String],
+    total_duration: f64,
+    progress_tx: &Option<Sender<MergeProgress>>,
+) -> Result<(), MergeJobError> {
+    let mut child = Command::new(ffmpeg)
+        .args(["-progress", "pipe:1", "-nostats", "-loglevel", "error"])
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| MergeJobError::Failed(e.to_string()))?;
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    // Register the child unless a cancel already landed while spawning
+    {
+        let mut children = registry.lock().await;
+        let (status,): (String,) = sqlx::query_as("SELECT status FROM merge_jobs WHERE id = ?")
+            .bind(job_id)
+            .fetch_one(pool)
+            .await?;
+        if status != "running" {
+            let _ = child.kill().await;
+            return Err(MergeJobError::Cancelled);
+        }
+        children.insert(job_id.to_string(), child);
+    }
+
+    // Drain stderr so FFmpeg never blocks on a full pipe
+    let stderr_task = tokio::spawn(async move {
+        let mut output = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut output).await;
+        }
+        output
+    });
+
+    let mut last_percent = 0.0;
+    if let Some(stdout) = stdout {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let Some(secs) = parse_progress_time(&line) else {
+                continue;
+            };
+            if total_duration <= 0.0 {
+                continue;
+            }
+
+            // Hold 100 back until FFmpeg actually exits cleanly
+            let percent = (secs / total_duration * 100.0).min(99.0);
+            if percent.floor() > last_percent {
+                last_percent = percent.floor();
+                sqlx::query("UPDATE merge_jobs SET progress = ? WHERE id = ?")
+                    .bind(last_percent)
+                    .bind(job_id)
+                    .execute(pool)
+                    .await?;
+                send_progress(progress_tx, job_id, "running", last_percent, None).await;
+            }
+        }
+    }
+
+    // Output ended: FFmpeg exited or was killed by a cancel
+    let Some(mut child) = registry.lock().await.remove(job_id) else {
+        return Err(MergeJobError::Cancelled);
+    };
+    let status = child.wait().await?;
+    let stderr = stderr_task.await.unwrap_or_default();
+
+    if status.success() {
+        Ok(())
+    } else {
+        let detail = stderr.lines().last().unwrap_or("").trim().to_string();
+        Err(MergeJobError::Failed(if detail.is_empty() {
+            format!("exited with {}", status)
+        } else {
+            detail
+        }))
+    }
+}
+
+/// Cancel a pending or running merge job. A job that already finished
+/// (including one that completes while the cancel is in flight) is left
+/// as is and reported as an invalid state.
+pub async fn cancel_merge_job(
+    pool: &SqlitePool,
+    registry: &MergeRegistry,
+    job_id: &str,
+) -> Result<(), MergeJobError> {
+    // Held throughout so the runner can't register or reap a child meanwhile
+    let mut children = registry.lock().await;
+
+    if let Some(mut child) = children.remove(job_id) {
+        let _ = child.kill().await;
+    }
+
+    // A running job without a child is either still spawning (the runner
+    // sees this status before registering) or just exited (its final
+    // update only applies to running jobs); either way the cancel wins
+    let cancelled = sqlx::query(
+        "UPDATE merge_jobs SET status = 'cancelled', completed_at = ? WHERE id = ? AND status IN ('pending', 'running')",
+    )
+    .bind(chrono::Utc::now().to_rfc3339())
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+
+    if cancelled.rows_affected() > 0 {
+        println!("[Merge] Cancelled job {}", job_id);
+        return Ok(());
+    }
+
+    let status: Option<(String,)> = sqlx::query_as("SELECT status FROM merge_jobs WHERE id = ?")
+        .bind(job_id)
+        .fetch_optional(pool)
+        .await?;
+
+    match status {
+        Some((status,)) => Err(MergeJobError::InvalidState(job_id.to_string(), status)),
+        None => Err(MergeJobError::NotFound(job_id.to_string())),
+    }
+}
+
+/// Output position in seconds from an FFmpeg `-progress` line
+fn parse_progress_time(line: &str) -> Option<f64> {
+    let (key, value) = line.trim().split_once('=')?;
+    match key {
+        // Both are microseconds; out_time_ms is misnamed in FFmpeg
+        "out_time_us" | "out_time_ms" => value.parse::<i64>().ok().map(|us| us as f64 / 1_000_000.0),
+        _ => None,
+    }
+}
+
+async fn send_progress(
+    progress_tx: &Option<Sender<MergeProgress>>,
+    job_id: &str,
+    status: &str,
+    progress: f64,
+    error: Option<String>,
+) {
+    if let Some(tx) = progress_tx {
+        let _ = tx
+            .send(MergeProgress {
+                job_id: job_id.to_string(),
+                status: status.to_string(),
+                progress,
+                error,
+            })
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+
+    #[test]
+    fn test_parse_progress_time() {
+        assert_eq!(parse_progress_time("out_time_us=2500000"), Some(2.5));
+        assert_eq!(parse_progress_time("out_time_ms=1000000"), Some(1.0));
+        assert_eq!(parse_progress_time("out_time_us=N/A"), None);
+        assert_eq!(parse_progress_time("frame=42"), None);
+        assert_eq!(parse_progress_time("progress=end"), None);
+    }
+
+    async fn insert_job(pool: &SqlitePool, dir: &Path) -> String {
+        let mut ids = Vec::new();
+        for name in ["a", "b"] {
+            let path = dir.join(format!("{}.mp4", name));
+            std::fs::write(&path, b"data").unwrap();
+            sqlx::query(
+                "INSERT INTO media_files (id, path, folder, filename, duration_secs, scanned_at) VALUES (?, ?, 'test', ?, 2.0, '2024-01-01')",
+            )
+            .bind(name)
+            .bind(path.to_string_lossy().to_string())
+            .bind(format!("{}.mp4", name))
+            .execute(pool)
+            .await
+            .unwrap();
+            ids.push(name.to_string());
+        }
+
+        sqlx::query(
+            "INSERT INTO merge_jobs (id, strategy, file_ids, status) VALUES ('job1', 'concat_copy', ?, 'pending')",
+        )
+        .bind(serde_json::to_string(&ids).unwrap())
+        .execute(pool)
+        .await
+        .unwrap();
+
+        "job1".to_string()
+    }
+
+    /// Shell script standing in for FFmpeg
+    #[cfg(unix)]
+    fn fake_ffmpeg(dir: &Path, body: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.join("ffmpeg");
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_job_runs_to_completion() {
+        let pool = test_pool().await;
+        let registry = create_merge_registry();
+        let dir = tempfile::tempdir().unwrap();
+        let job_id = insert_job(&pool, dir.path()).await;
+
+        // Reports 1s, 3s and end of a 4s merge, then writes the output (last arg)
+        let ffmpeg = fake_ffmpeg(
+            dir.path(),
+            "echo out_time_us=1000000\necho progress=continue\necho out_time_us=3000000\necho progress=end\nfor last; do :; done\n: > \"$last\"",
+        );
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let output_dir = dir.path().join("merged");
+        let output = run_merge_job_with(&pool, &registry, &job_id, &ffmpeg, &output_dir, Some(tx))
+            .await
+            .unwrap();
+        assert!(output.exists());
+
+        let mut updates = Vec::new();
+        while let Some(update) = rx.recv().await {
+            updates.push(update);
+        }
+
+        let statuses: Vec<_> = updates.iter().map(|u| u.status.as_str()).collect();
+        assert_eq!(statuses.first(), Some(&"running"));
+        assert_eq!(statuses.last(), Some(&"completed"));
+        assert!(updates.windows(2).all(|w| w[0].progress <= w[1].progress));
+        assert!(updates.iter().any(|u| u.progress == 25.0));
+        assert!(updates.iter().any(|u| u.progress == 75.0));
+
+        let job = sqlx::query_as::<_, MergeJob>("SELECT * FROM merge_jobs WHERE id = ?")
+            .bind(&job_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(job.status, "completed");
+        assert_eq!(job.progress, Some(100.0));
+        assert!(job.completed_at.is_some());
+        assert_eq!(job.output_path, Some(output.to_string_lossy().to_string()));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_job_failure_is_recorded() {
+        let pool = test_pool().await;
+        let registry = create_merge_registry();
+        let dir = tempfile::tempdir().unwrap();
+        let job_id = insert_job(&pool, dir.path()).await;
+        let ffmpeg = fake_ffmpeg(dir.path(), "echo 'Invalid data found' >&2\nexit 1");
+
+        let result = run_merge_job_with(&pool, &registry, &job_id, &ffmpeg, dir.path(), None).await;
+        assert!(matches!(result, Err(MergeJobError::Failed(ref m)) if m.contains("Invalid data")));
+
+        let job = sqlx::query_as::<_, MergeJob>("SELECT * FROM merge_jobs WHERE id = ?")
+            .bind(&job_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(job.status, "failed");
+        assert!(job.error_message.unwrap().contains("Invalid data"));
+
+        // A finished job can't be started again
+        let again = run_merge_job_with(&pool, &registry, &job_id, &ffmpeg, dir.path(), None).await;
+        assert!(matches!(again, Err(MergeJobError::InvalidState(_, _))));
+    }
+
+    async fn job_status(pool: &SqlitePool, job_id: &str) -> String {
+        let (status,): (String,) = sqlx::query_as("SELECT status FROM merge_jobs WHERE id = ?")
+            .bind(job_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        status
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancel_running_job() {
+        let pool = test_pool().await;
+        let registry = create_merge_registry();
+        let dir = tempfile::tempdir().unwrap();
+        let job_id = insert_job(&pool, dir.path()).await;
+        let output_dir = dir.path().join("merged");
+
+        // Writes some output, reports progress, then hangs
+        let ffmpeg = fake_ffmpeg(
+            dir.path(),
+            "for last; do :; done\n: > \"$last\"\necho out_time_us=1000000\nexec sleep 30",
+        );
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let runner = {
+            let pool = pool.clone();
+            let registry = registry.clone();
+            let job_id = job_id.clone();
+            tokio::spawn(async move {
+                run_merge_job_with(&pool, &registry, &job_id, &ffmpeg, &output_dir, Some(tx)).await
+            })
+        };
+
+        // Wait for FFmpeg to get going
+        while let Some(update) = rx.recv().await {
+            if update.progress > 0.0 {
+                break;
+            }
+        }
+
+        cancel_merge_job(&pool, &registry, &job_id).await.unwrap();
+        assert_eq!(job_status(&pool, &job_id).await, "cancelled");
+
+        let result = tokio::time::timeout(std::time::Duration::from_secs(5), runner)
+            .await
+            .expect("runner should stop promptly")
+            .unwrap();
+        assert!(matches!(result, Err(MergeJobError::Cancelled)));
+        assert!(!dir.path().join("merged").join(format!("merge-{}.mkv", job_id)).exists());
+        assert!(registry.lock().await.is_empty());
+        assert_eq!(job_status(&pool, &job_id).await, "cancelled");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_pending_and_finished_jobs() {
+        let pool = test_pool().await;
+        let registry = create_merge_registry();
+        let dir = tempfile::tempdir().unwrap();
+        let job_id = insert_job(&pool, dir.path()).await;
+
+        cancel_merge_job(&pool, &registry, &job_id).await.unwrap();
+        assert_eq!(job_status(&pool, &job_id).await, "cancelled");
+
+        // Already finished: left alone
+        sqlx::query("UPDATE merge_jobs SET status = 'completed' WHERE id = ?")
+            .bind(&job_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let result = cancel_merge_job(&pool, &registry, &job_id).await;
+        assert!(matches!(result, Err(MergeJobError::InvalidState(_, ref s)) if s == "completed"));
+
+        let missing = cancel_merge_job(&pool, &registry, "nope").await;
+        assert!(matches!(missing, Err(MergeJobError::NotFound(_))));
+    }
+}
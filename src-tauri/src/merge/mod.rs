@@ -6,11 +6,12 @@
 
 pub mod compatibility;
 pub mod concat;
+pub mod job;
 pub mod normalize;
 
 pub use compatibility::{
     check_merge_compatibility, compute_total_duration, get_compatibility_issues,
-    FileCompatibility, MergeStrategy,
+    CompatIssue, FileCompatibility, MergeStrategy,
 };
-pub use concat::concat_copy_stream;
-pub use normalize::{normalize_and_concat, NormalizeConfig, NormalizeProgress};
+pub use job::{cancel_merge_job, create_merge_registry, run_merge_job, MergeRegistry};
+pub use normalize::NormalizeConfig;
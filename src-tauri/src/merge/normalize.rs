@@ -3,10 +3,10 @@
 //! When files have different codecs, resolutions, or frame rates,
 //! we need to transcode them to a common format before merging.
 
-use crate::sidecar::get_sidecar_path;
+use crate::sidecar::find_binary;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use std::process::{Child, Command, Stdio};
+use std::process::{Command, Stdio};
 use thiserror::Error;
 use tokio::sync::mpsc::Sender;
 
@@ -16,8 +16,6 @@ pub enum NormalizeError {
     Io(#[from] std::io::Error),
     #[error("FFmpeg not found")]
     FfmpegNotFound,
-    #[error("No files provided")]
-    NoFiles,
     #[error("Spawn error: {0}")]
     SpawnError(String),
 }
@@ -41,6 +39,40 @@ pub struct NormalizeConfig {
     pub target_audio_bitrate: u32,
     /// Audio sample rate
     pub target_sample_rate: u32,
+    /// Loudness normalization targets (off when None)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loudnorm: Option<LoudnormTarget>,
+}
+
+/// EBU R128 targets for FFmpeg's `loudnorm` filter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoudnormTarget {
+    /// Integrated loudness in LUFS
+    pub integrated: f64,
+    /// Loudness range in LU
+    pub lra: f64,
+    /// True peak in dBTP
+    pub true_peak: f64,
+}
+
+impl Default for LoudnormTarget {
+    fn default() -> Self {
+        Self {
+            integrated: -16.0,
+            lra: 11.0,
+            true_peak: -1.5,
+        }
+    }
+}
+
+impl LoudnormTarget {
+    /// The `loudnorm` filter for these targets
+    pub fn filter(&self) -> String {
+        format!(
+            "loudnorm=I={}:LRA={}:TP={}",
+            self.integrated, self.lra, self.true_peak
+        )
+    }
 }
 
 impl Default for NormalizeConfig {
@@ -54,6 +86,7 @@ impl Default for NormalizeConfig {
             target_audio_codec: "aac".into(),
             target_audio_bitrate: 128,
             target_sample_rate: 48000,
+            loudnorm: None,
         }
     }
 }
@@ -74,6 +107,32 @@ impl NormalizeConfig {
         Self::default()
     }
 
+    /// Look up a preset by id ("720p", "1080p" or "4k")
+    pub fn from_preset(id: &str) -> Option<Self> {
+        match id {
+            "720p" => Some(Self::preset_720p()),
+            "1080p" => Some(Self::preset_1080p()),
+            "4k" => Some(Self::preset_4k()),
+            _ => None,
+        }
+    }
+
+    /// Short human-readable label, e.g. "1080p" or "1280x544 + loudnorm"
+    pub fn label(&self) -> String {
+        let size = match (self.target_width, self.target_height) {
+            (1280, 720) => "720p".to_string(),
+            (1920, 1080) => "1080p".to_string(),
+            (3840, 2160) => "4K".to_string(),
+            (w, h) => format!("{}x{}", w, h),
+        };
+
+        if self.loudnorm.is_some() {
+            format!("{} + loudnorm", size)
+        } else {
+            size
+        }
+    }
+
     /// Create a 4K preset
     pub fn preset_4k() -> Self {
         Self {
@@ -85,6 +144,26 @@ impl NormalizeConfig {
     }
 }
 
+/// A file fed into the normalize graph
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizeInput {
+    pub path: PathBuf,
+    pub has_audio: bool,
+    /// Sizes the silent track that stands in for missing audio
+    pub duration_secs: Option<f64>,
+}
+
+impl NormalizeInput {
+    /// Describe a scanned media file
+    pub fn from_media(file: &crate::db::schema::MediaFile) -> Self {
+        Self {
+            path: PathBuf::from(&file.path),
+            has_audio: file.audio_codec.is_some(),
+            duration_secs: file.duration_secs,
+        }
+    }
+}
+
 /// Progress update during normalization
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NormalizeProgress {
@@ -104,45 +183,33 @@ pub struct NormalizeProgress {
 
 /// Find FFmpeg binary
 fn find_ffmpeg() -> Result<PathBuf, NormalizeError> {
-    // Try system ffmpeg first
-    if let Ok(output) = Command::new("which").arg("ffmpeg").output() {
-        if output.status.success() {
-            let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !path.is_empty() {
-                return Ok(PathBuf::from(path));
-            }
-        }
-    }
-
-    // Check common locations
-    let paths = [
-        "/opt/homebrew/bin/ffmpeg",
-        "/usr/local/bin/ffmpeg",
-        "/usr/bin/ffmpeg",
-    ];
-
-    for path in paths {
-        let p = PathBuf::from(path);
-        if p.exists() {
-            return Ok(p);
-        }
-    }
+    find_binary("ffmpeg").ok_or(NormalizeError::FfmpegNotFound)
+}
 
-    // Bundled sidecar
-    let sidecar_path = get_sidecar_path("ffmpeg");
-    if sidecar_path.exists() {
-        return Ok(sidecar_path);
+/// Audio filter chain: optional loudness normalization, then resampling.
+/// loudnorm upsamples internally, so it has to run before aresample.
+fn build_audio_filter(config: &NormalizeConfig) -> String {
+    let resample = format!("aresample={}", config.target_sample_rate);
+    match &config.loudnorm {
+        Some(target) => format!("{},{}", target.filter(), resample),
+        None => resample,
     }
+}
 
-    Err(NormalizeError::FfmpegNotFound)
+/// Whether the normalized output carries audio: only if some input has it
+fn has_any_audio(inputs: &[NormalizeInput]) -> bool {
+    inputs.iter().any(|input| input.has_audio)
 }
 
-/// Build FFmpeg filter graph for multi-input normalization
-fn build_filter_graph(file_count: usize, config: &NormalizeConfig) -> String {
+/// Build FFmpeg filter graph for multi-input normalization.
+/// Inputs without audio get a silent track of their length so concat
+/// segments stay aligned; if no input has audio the output is video-only.
+fn build_filter_graph(inputs: &[NormalizeInput], config: &NormalizeConfig) -> String {
     let mut filter_parts = Vec::new();
+    let with_audio = has_any_audio(inputs);
 
     // Scale and fps filter for each input
-    for i in 0..file_count {
+    for (i, input) in inputs.iter().enumerate() {
         filter_parts.push(format!(
             "[{i}:v]scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,fps={fps}[v{i}]",
             i = i,
@@ -150,138 +217,109 @@ fn build_filter_graph(file_count: usize, config: &NormalizeConfig) -> String {
             h = config.target_height,
             fps = config.target_fps as u32,
         ));
-        filter_parts.push(format!(
-            "[{i}:a]aresample={sr},aformat=sample_fmts=fltp:channel_layouts=stereo[a{i}]",
-            i = i,
-            sr = config.target_sample_rate,
-        ));
+        if !with_audio {
+            continue;
+        }
+        if input.has_audio {
+            filter_parts.push(format!(
+                "[{i}:a]{audio},aformat=sample_fmts=fltp:channel_layouts=stereo[a{i}]",
+                i = i,
+                audio = build_audio_filter(config),
+            ));
+        } else {
+            // Trimmed to the input's length; a zero-length trim would drop
+            // the silence, so an unknown length leaves it untrimmed
+            let trim = input
+                .duration_secs
+                .map(|secs| format!("atrim=duration={},", secs))
+                .unwrap_or_default();
+            filter_parts.push(format!(
+                "anullsrc=r={rate}:cl=stereo,{trim}aformat=sample_fmts=fltp:channel_layouts=stereo[a{i}]",
+                i = i,
+                rate = config.target_sample_rate,
+                trim = trim,
+            ));
+        }
     }
 
     // Build concat input strings
-    let concat_v: String = (0..file_count).map(|i| format!("[v{i}]")).collect();
-    let concat_a: String = (0..file_count).map(|i| format!("[a{i}]")).collect();
+    let concat_v: String = (0..inputs.len()).map(|i| format!("[v{i}]")).collect();
 
     // Final concat filter
-    filter_parts.push(format!(
-        "{}{}concat=n={}:v=1:a=1[outv][outa]",
-        concat_v, concat_a, file_count
-    ));
+    if with_audio {
+        let concat_a: String = (0..inputs.len()).map(|i| format!("[a{i}]")).collect();
+        filter_parts.push(format!(
+            "{}{}concat=n={}:v=1:a=1[outv][outa]",
+            concat_v,
+            concat_a,
+            inputs.len()
+        ));
+    } else {
+        filter_parts.push(format!("{}concat=n={}:v=1:a=0[outv]", concat_v, inputs.len()));
+    }
 
     filter_parts.join(";")
 }
 
-/// Normalize and concatenate files for streaming
-///
-/// This is the slow path for incompatible files - transcodes everything
-/// to a common format before outputting to the stream.
-#[allow(dead_code)]
-pub fn normalize_and_concat(
-    files: &[PathBuf],
-    config: &NormalizeConfig,
-    stream_name: &str,
-    protocol: &str,
-    loop_playback: bool,
-) -> Result<Child, NormalizeError> {
-    if files.is_empty() {
-        return Err(NormalizeError::NoFiles);
+/// Map the graph outputs
+fn build_map_args(with_audio: bool) -> Vec<String> {
+    let mut args = vec!["-map".to_string(), "[outv]".to_string()];
+    if with_audio {
+        args.extend(["-map".to_string(), "[outa]".to_string()]);
     }
+    args
+}
 
-    let ffmpeg = find_ffmpeg()?;
-
-    let mut args = Vec::new();
-
-    // Real-time mode
-    args.push("-re".to_string());
-
-    // Add all input files
-    for path in files {
-        if loop_playback {
-            args.extend(["-stream_loop".to_string(), "-1".to_string()]);
-        }
-        args.extend(["-i".to_string(), path.to_string_lossy().to_string()]);
-    }
-
-    // Filter complex for normalization
-    let filter = build_filter_graph(files.len(), config);
-    args.extend(["-filter_complex".to_string(), filter]);
-
-    // Map outputs
-    args.extend([
-        "-map".to_string(),
-        "[outv]".to_string(),
-        "-map".to_string(),
-        "[outa]".to_string(),
-    ]);
-
-    // Video encoding
+/// Encoder arguments for the normalized output
+fn build_encode_args(config: &NormalizeConfig, with_audio: bool) -> Vec<String> {
     let encoder = match config.target_codec.as_str() {
         "h264" => "libx264",
         "hevc" | "h265" => "libx265",
         _ => "libx264",
     };
-    args.extend([
+
+    let mut args = vec![
         "-c:v".to_string(),
         encoder.to_string(),
         "-preset".to_string(),
         "veryfast".to_string(),
-        "-tune".to_string(),
-        "zerolatency".to_string(),
         "-b:v".to_string(),
         format!("{}k", config.target_bitrate),
         "-maxrate".to_string(),
         format!("{}k", config.target_bitrate),
         "-bufsize".to_string(),
         format!("{}k", config.target_bitrate * 2),
-    ]);
-
-    // Audio encoding
-    args.extend([
-        "-c:a".to_string(),
-        config.target_audio_codec.clone(),
-        "-b:a".to_string(),
-        format!("{}k", config.target_audio_bitrate),
-    ]);
-
-    // Output format and destination
-    match protocol {
-        "rtsp" => {
-            args.extend([
-                "-f".to_string(),
-                "rtsp".to_string(),
-                "-rtsp_transport".to_string(),
-                "tcp".to_string(),
-                format!("rtsp://localhost:8554/{}", stream_name),
-            ]);
-        }
-        "srt" => {
-            args.extend([
-                "-f".to_string(),
-                "mpegts".to_string(),
-                format!(
-                    "srt://localhost:8890?streamid=publish:{}&pkt_size=1316",
-                    stream_name
-                ),
-            ]);
-        }
-        _ => {
-            args.extend([
-                "-f".to_string(),
-                "rtsp".to_string(),
-                "-rtsp_transport".to_string(),
-                "tcp".to_string(),
-                format!("rtsp://localhost:8554/{}", stream_name),
-            ]);
-        }
+    ];
+    if with_audio {
+        args.extend([
+            "-c:a".to_string(),
+            config.target_audio_codec.clone(),
+            "-b:a".to_string(),
+            format!("{}k", config.target_audio_bitrate),
+        ]);
     }
+    args
+}
 
-    let child = Command::new(&ffmpeg)
-        .args(&args)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| NormalizeError::SpawnError(e.to_string()))?;
+/// FFmpeg arguments to normalize and concatenate files into one output file
+pub fn build_normalize_file_args(
+    files: &[NormalizeInput],
+    config: &NormalizeConfig,
+    output: &Path,
+) -> Vec<String> {
+    let mut args = vec!["-y".to_string()];
+    let with_audio = has_any_audio(files);
+
+    for input in files {
+        args.extend(["-i".to_string(), input.path.to_string_lossy().to_string()]);
+    }
 
-    Ok(child)
+    args.extend(["-filter_complex".to_string(), build_filter_graph(files, config)]);
+    args.extend(build_map_args(with_audio));
+    args.extend(build_encode_args(config, with_audio));
+    args.push(output.to_string_lossy().to_string());
+
+    args
 }
 
 /// Normalize a single file to cache
@@ -313,7 +351,7 @@ pub fn normalize_to_file(
             "-vf",
             &filter,
             "-af",
-            &format!("aresample={}", config.target_sample_rate),
+            &build_audio_filter(config),
             "-c:v",
             encoder,
             "-preset",
@@ -375,7 +413,7 @@ pub async fn normalize_to_file_with_progress(
             "-vf",
             &filter,
             "-af",
-            &format!("aresample={}", config.target_sample_rate),
+            &build_audio_filter(config),
             "-c:v",
             encoder,
             "-preset",
@@ -459,6 +497,27 @@ mod tests {
         assert_eq!(config.target_height, 720);
     }
 
+    #[test]
+    fn test_from_preset() {
+        assert_eq!(NormalizeConfig::from_preset("720p").unwrap().target_height, 720);
+        assert_eq!(NormalizeConfig::from_preset("4k").unwrap().target_width, 3840);
+        assert!(NormalizeConfig::from_preset("8k").is_none());
+    }
+
+    #[test]
+    fn test_label() {
+        assert_eq!(NormalizeConfig::preset_720p().label(), "720p");
+        assert_eq!(NormalizeConfig::preset_4k().label(), "4K");
+
+        let custom = NormalizeConfig {
+            target_width: 1280,
+            target_height: 544,
+            loudnorm: Some(LoudnormTarget::default()),
+            ..Default::default()
+        };
+        assert_eq!(custom.label(), "1280x544 + loudnorm");
+    }
+
     #[test]
     fn test_preset_4k() {
         let config = NormalizeConfig::preset_4k();
@@ -466,10 +525,20 @@ mod tests {
         assert_eq!(config.target_height, 2160);
     }
 
+    fn inputs(count: usize) -> Vec<NormalizeInput> {
+        (0..count)
+            .map(|i| NormalizeInput {
+                path: PathBuf::from(format!("/{}.mp4", i)),
+                has_audio: true,
+                duration_secs: Some(10.0),
+            })
+            .collect()
+    }
+
     #[test]
     fn test_build_filter_graph_single() {
         let config = NormalizeConfig::default();
-        let filter = build_filter_graph(1, &config);
+        let filter = build_filter_graph(&inputs(1), &config);
         assert!(filter.contains("scale=1920:1080"));
         assert!(filter.contains("fps=30"));
         assert!(filter.contains("concat=n=1"));
@@ -478,13 +547,84 @@ mod tests {
     #[test]
     fn test_build_filter_graph_multiple() {
         let config = NormalizeConfig::default();
-        let filter = build_filter_graph(3, &config);
+        let filter = build_filter_graph(&inputs(3), &config);
         assert!(filter.contains("[0:v]"));
         assert!(filter.contains("[1:v]"));
         assert!(filter.contains("[2:v]"));
         assert!(filter.contains("concat=n=3"));
     }
 
+    #[test]
+    fn test_loudnorm_off_by_default() {
+        let config = NormalizeConfig::default();
+        assert!(config.loudnorm.is_none());
+        assert!(!build_filter_graph(&inputs(2), &config).contains("loudnorm"));
+        assert_eq!(build_audio_filter(&config), "aresample=48000");
+    }
+
+    #[test]
+    fn test_loudnorm_in_filters() {
+        let config = NormalizeConfig {
+            loudnorm: Some(LoudnormTarget::default()),
+            ..Default::default()
+        };
+
+        let filter = build_filter_graph(&inputs(2), &config);
+        assert!(filter.contains("[0:a]loudnorm=I=-16:LRA=11:TP=-1.5,aresample=48000"));
+        assert!(filter.contains("[1:a]loudnorm=I=-16"));
+        assert_eq!(
+            build_audio_filter(&config),
+            "loudnorm=I=-16:LRA=11:TP=-1.5,aresample=48000"
+        );
+    }
+
+    #[test]
+    fn test_silent_input_gets_silent_track() {
+        let config = NormalizeConfig::default();
+        let mut files = inputs(2);
+        files[1].has_audio = false;
+
+        let filter = build_filter_graph(&files, &config);
+        assert!(filter.contains("[0:a]aresample=48000"));
+        assert!(!filter.contains("[1:a]"));
+        assert!(filter.contains("anullsrc=r=48000:cl=stereo,atrim=duration=10,"));
+        assert!(filter.ends_with("[v0][v1][a0][a1]concat=n=2:v=1:a=1[outv][outa]"));
+
+        files[1].duration_secs = None;
+        let filter = build_filter_graph(&files, &config);
+        assert!(filter.contains("anullsrc=r=48000:cl=stereo,aformat="));
+        assert!(!filter.contains("atrim"));
+    }
+
+    #[test]
+    fn test_all_silent_inputs_are_video_only() {
+        let config = NormalizeConfig::default();
+        let mut files = inputs(2);
+        for file in &mut files {
+            file.has_audio = false;
+        }
+
+        let filter = build_filter_graph(&files, &config);
+        assert!(filter.ends_with("[v0][v1]concat=n=2:v=1:a=0[outv]"));
+        assert!(!filter.contains("anullsrc"));
+
+        let args = build_normalize_file_args(&files, &config, Path::new("/out/merged.mp4"));
+        assert!(!args.contains(&"[outa]".to_string()));
+        assert!(!args.contains(&"-c:a".to_string()));
+    }
+
+    #[test]
+    fn test_build_normalize_file_args() {
+        let config = NormalizeConfig::default();
+        let files = inputs(2);
+        let args = build_normalize_file_args(&files, &config, Path::new("/out/merged.mp4"));
+
+        assert_eq!(args.iter().filter(|a| *a == "-i").count(), 2);
+        assert!(args.contains(&"-filter_complex".to_string()));
+        assert!(args.contains(&"libx264".to_string()));
+        assert_eq!(args.last().unwrap(), "/out/merged.mp4");
+    }
+
     #[test]
     fn test_estimate_transcode_time() {
         let config = NormalizeConfig::default();
@@ -493,11 +633,4 @@ mod tests {
         assert!(estimate > 0.0);
         assert!(estimate < duration); // Should be faster than realtime
     }
-
-    #[test]
-    fn test_empty_files_error() {
-        let config = NormalizeConfig::default();
-        let result = normalize_and_concat(&[], &config, "test", "rtsp", false);
-        assert!(matches!(result, Err(NormalizeError::NoFiles)));
-    }
 }
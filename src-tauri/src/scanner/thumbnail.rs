@@ -0,0 +1,184 @@
+//! Poster thumbnails for scanned media
+//! Extracts a single frame per file into the thumbnail cache
+
+use super::ScannerError;
+use crate::sidecar::find_binary;
+use sqlx::sqlite::SqlitePool;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Give up on files that FFmpeg can't seek quickly
+const THUMBNAIL_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Cached thumbnail location for a media record, keyed by its id so the
+/// file goes away with the record
+pub fn thumbnail_path(thumbs_dir: &Path, media_id: &str) -> PathBuf {
+    thumbs_dir.join(format!("{}.jpg", media_id))
+}
+
+/// Delete a stored thumbnail, e.g. once its record is gone or the file it
+/// shows has changed. Best-effort: a leftover file is only wasted space.
+pub async fn remove_thumbnail(thumbnail_path: Option<&str>) {
+    if let Some(path) = thumbnail_path {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+}
+
+/// Grab the poster frame at 10% of the duration (first frame if unknown)
+fn thumbnail_time(duration_secs: Option<f64>) -> f64 {
+    duration_secs.filter(|d| d.is_finite() && *d > 0.0).map(|d| d * 0.1).unwrap_or(0.0)
+}
+
+/// Make sure a thumbnail exists for `media_path`, extracting it if needed.
+/// Returns None (after logging) when extraction fails; thumbnails are best-effort.
+pub async fn ensure_thumbnail(
+    media_path: &Path,
+    duration_secs: Option<f64>,
+    output: &Path,
+) -> Option<PathBuf> {
+    if output.exists() {
+        return Some(output.to_path_buf());
+    }
+
+    let Some(ffmpeg) = find_binary("ffmpeg") else {
+        eprintln!("[Scanner] FFmpeg not found, skipping thumbnail");
+        return None;
+    };
+
+    match extract_thumbnail(&ffmpeg, media_path, duration_secs, output).await {
+        Ok(()) => Some(output.to_path_buf()),
+        Err(e) => {
+            eprintln!("[Scanner] Thumbnail failed for {}: {}", media_path.display(), e);
+            None
+        }
+    }
+}
+
+async fn extract_thumbnail(
+    ffmpeg: &Path,
+    media_path: &Path,
+    duration_secs: Option<f64>,
+    output: &Path,
+) -> Result<(), ScannerError> {
+    if let Some(parent) = output.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let mut child = Command::new(ffmpeg)
+        .args(["-hide_banner", "-loglevel", "error", "-y"])
+        .args(["-ss", &format!("{:.3}", thumbnail_time(duration_secs))])
+        .arg("-i")
+        .arg(media_path)
+        .args(["-frames:v", "1", "-vf", "scale=320:-2", "-q:v", "4"])
+        .arg(output)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let status = tokio::time::timeout(THUMBNAIL_TIMEOUT, child.wait())
+        .await
+        .map_err(|_| ScannerError::Probe("thumbnail extraction timed out".to_string()))??;
+
+    if !status.success() || !output.exists() {
+        let _ = tokio::fs::remove_file(output).await;
+        return Err(ScannerError::Probe(format!("FFmpeg exited with {}", status)));
+    }
+    Ok(())
+}
+
+/// Thumbnail for a media file, extracting it on demand for files scanned
+/// before thumbnails existed. None if the file is unknown or extraction fails.
+pub async fn get_thumbnail(
+    pool: &SqlitePool,
+    media_id: &str,
+    thumbs_dir: &Path,
+) -> Result<Option<PathBuf>, ScannerError> {
+    let row: Option<(String, Option<f64>, Option<String>)> = sqlx::query_as(
+        "SELECT path, duration_secs, thumbnail_path FROM media_files WHERE id = ?",
+    )
+    .bind(media_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some((media_path, duration_secs, existing)) = row else {
+        return Ok(None);
+    };
+
+    if let Some(existing) = existing.map(PathBuf::from).filter(|p| p.exists()) {
+        return Ok(Some(existing));
+    }
+
+    let output = thumbnail_path(thumbs_dir, media_id);
+    let Some(thumb) = ensure_thumbnail(Path::new(&media_path), duration_secs, &output).await else {
+        return Ok(None);
+    };
+
+    sqlx::query("UPDATE media_files SET thumbnail_path = ? WHERE id = ?")
+        .bind(thumb.to_string_lossy().to_string())
+        .bind(media_id)
+        .execute(pool)
+        .await?;
+    Ok(Some(thumb))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbnail_time() {
+        assert_eq!(thumbnail_time(Some(120.0)), 12.0);
+        assert_eq!(thumbnail_time(None), 0.0);
+        assert_eq!(thumbnail_time(Some(f64::NAN)), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_get_thumbnail_reuses_existing() {
+        let pool = crate::db::test_pool().await;
+        let dir = tempfile::tempdir().unwrap();
+        let thumb = thumbnail_path(dir.path(), "m1");
+        std::fs::write(&thumb, "jpeg").unwrap();
+
+        sqlx::query(
+            "INSERT INTO media_files (id, path, folder, filename, scanned_at, thumbnail_path) \
+             VALUES ('m1', '/media/a.mp4', 'media', 'a.mp4', '2024-01-01', ?)",
+        )
+        .bind(thumb.to_string_lossy().to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let found = get_thumbnail(&pool, "m1", dir.path()).await.unwrap();
+        assert_eq!(found, Some(thumb.clone()));
+        assert_eq!(get_thumbnail(&pool, "missing", dir.path()).await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_thumbnail_path_per_record() {
+        let dir = Path::new("/thumbs");
+        let a = thumbnail_path(dir, "m1");
+        assert_eq!(a, dir.join("m1.jpg"));
+        assert_ne!(a, thumbnail_path(dir, "m2"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_extract_thumbnail_failure_leaves_nothing() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let ffmpeg = dir.path().join("ffmpeg");
+        // Writes a partial file, then fails
+        std::fs::write(&ffmpeg, "#!/bin/sh\nfor last; do :; done; echo x > \"$last\"; exit 1\n")
+            .unwrap();
+        std::fs::set_permissions(&ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let output = dir.path().join("thumbs").join("m1.jpg");
+        let result = extract_thumbnail(&ffmpeg, Path::new("/media/a.mp4"), Some(10.0), &output).await;
+        assert!(result.is_err());
+        assert!(!output.exists());
+    }
+}
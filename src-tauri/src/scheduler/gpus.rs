@@ -0,0 +1,72 @@
+//! Per-GPU NVENC session tracking
+//! Spreads NVENC streams across GPUs by placing each on the least-loaded one
+
+use std::collections::HashMap;
+
+/// NVENC sessions per GPU and which GPU each stream was given
+#[derive(Debug, Default)]
+pub struct GpuSessions {
+    /// Running sessions, indexed by GPU
+    counts: Vec<usize>,
+    assigned: HashMap<String, u32>,
+}
+
+impl GpuSessions {
+    /// Set how many GPUs are available; with none, streams get no GPU assigned
+    pub fn set_gpu_count(&mut self, count: usize) {
+        self.counts.resize(count, 0);
+    }
+
+    /// Assign a stream to the GPU with the fewest sessions (lowest index on ties)
+    pub fn assign(&mut self, stream_id: &str) -> Option<u32> {
+        if let Some(&index) = self.assigned.get(stream_id) {
+            return Some(index);
+        }
+
+        let (index, _) = self
+            .counts
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, count)| **count)?;
+        self.counts[index] += 1;
+        self.assigned.insert(stream_id.to_string(), index as u32);
+        Some(index as u32)
+    }
+
+    /// Free the stream's session, if it had one
+    pub fn release(&mut self, stream_id: &str) {
+        if let Some(index) = self.assigned.remove(stream_id) {
+            if let Some(count) = self.counts.get_mut(index as usize) {
+                *count = count.saturating_sub(1);
+            }
+        }
+    }
+
+    /// GPU the stream was assigned to
+    pub fn gpu_for(&self, stream_id: &str) -> Option<u32> {
+        self.assigned.get(stream_id).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assigns_least_loaded_gpu() {
+        let mut gpus = GpuSessions::default();
+        assert_eq!(gpus.assign("s1"), None);
+
+        gpus.set_gpu_count(2);
+        assert_eq!(gpus.assign("s1"), Some(0));
+        assert_eq!(gpus.assign("s2"), Some(1));
+        assert_eq!(gpus.assign("s3"), Some(0));
+        // Assigning again keeps the existing GPU
+        assert_eq!(gpus.assign("s2"), Some(1));
+
+        gpus.release("s2");
+        gpus.release("s2");
+        assert_eq!(gpus.gpu_for("s2"), None);
+        assert_eq!(gpus.assign("s4"), Some(1));
+    }
+}
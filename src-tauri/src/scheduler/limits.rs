@@ -9,7 +9,14 @@ pub struct Limits {
     pub max_total: usize,
     pub max_cpu_transcode: usize,
     pub max_nvenc_transcode: usize,
+    pub max_qsv_transcode: usize,
+    pub max_videotoolbox: usize,
+    pub max_amd_transcode: usize,
     pub max_bitrate_mbps: u32,
+    /// Bandwidth copy streams may use, within `max_bitrate_mbps`
+    pub max_copy_bitrate_mbps: u32,
+    /// Bandwidth transcoded streams (any encoder) may use, within `max_bitrate_mbps`
+    pub max_transcode_bitrate_mbps: u32,
 }
 
 impl Default for Limits {
@@ -18,7 +25,12 @@ impl Default for Limits {
             max_total: 50,
             max_cpu_transcode: 8,
             max_nvenc_transcode: 6,
+            max_qsv_transcode: 4,
+            max_videotoolbox: 4,
+            max_amd_transcode: 4,
             max_bitrate_mbps: 500,
+            max_copy_bitrate_mbps: 500,
+            max_transcode_bitrate_mbps: 500,
         }
     }
 }
@@ -30,7 +42,13 @@ pub struct CurrentUsage {
     pub copy_running: usize,
     pub cpu_transcoding: usize,
     pub nvenc_transcoding: usize,
+    pub qsv_transcoding: usize,
+    pub videotoolbox_transcoding: usize,
+    /// VAAPI and AMF sessions share the AMD limit
+    pub amd_transcoding: usize,
     pub total_bitrate_mbps: u32,
+    pub copy_bitrate_mbps: u32,
+    pub transcode_bitrate_mbps: u32,
 }
 
 impl CurrentUsage {
@@ -38,10 +56,18 @@ impl CurrentUsage {
     pub fn add_stream(&mut self, mode: &str, bitrate_mbps: u32) {
         self.total_running += 1;
         self.total_bitrate_mbps += bitrate_mbps;
+        if mode == "copy" {
+            self.copy_bitrate_mbps += bitrate_mbps;
+        } else {
+            self.transcode_bitrate_mbps += bitrate_mbps;
+        }
         match mode {
             "copy" => self.copy_running += 1,
             "cpu" => self.cpu_transcoding += 1,
             "nvenc" => self.nvenc_transcoding += 1,
+            "qsv" => self.qsv_transcoding += 1,
+            "videotoolbox" => self.videotoolbox_transcoding += 1,
+            "vaapi" | "amf" => self.amd_transcoding += 1,
             _ => {}
         }
     }
@@ -50,10 +76,20 @@ impl CurrentUsage {
     pub fn remove_stream(&mut self, mode: &str, bitrate_mbps: u32) {
         self.total_running = self.total_running.saturating_sub(1);
         self.total_bitrate_mbps = self.total_bitrate_mbps.saturating_sub(bitrate_mbps);
+        if mode == "copy" {
+            self.copy_bitrate_mbps = self.copy_bitrate_mbps.saturating_sub(bitrate_mbps);
+        } else {
+            self.transcode_bitrate_mbps = self.transcode_bitrate_mbps.saturating_sub(bitrate_mbps);
+        }
         match mode {
             "copy" => self.copy_running = self.copy_running.saturating_sub(1),
             "cpu" => self.cpu_transcoding = self.cpu_transcoding.saturating_sub(1),
             "nvenc" => self.nvenc_transcoding = self.nvenc_transcoding.saturating_sub(1),
+            "qsv" => self.qsv_transcoding = self.qsv_transcoding.saturating_sub(1),
+            "videotoolbox" => {
+                self.videotoolbox_transcoding = self.videotoolbox_transcoding.saturating_sub(1)
+            }
+            "vaapi" | "amf" => self.amd_transcoding = self.amd_transcoding.saturating_sub(1),
             _ => {}
         }
     }
@@ -123,6 +159,41 @@ impl LimitsEnforcer {
                     };
                 }
             }
+            "qsv" => {
+                if self.usage.qsv_transcoding >= self.limits.max_qsv_transcode {
+                    return LimitCheckResult::Queued {
+                        reason: format!(
+                            "QSV transcode limit reached ({}/{})",
+                            self.usage.qsv_transcoding, self.limits.max_qsv_transcode
+                        ),
+                    };
+                }
+            }
+            "videotoolbox" if !cfg!(target_os = "macos") => {
+                return LimitCheckResult::Rejected {
+                    reason: "VideoToolbox is only available on macOS".to_string(),
+                };
+            }
+            "videotoolbox" => {
+                if self.usage.videotoolbox_transcoding >= self.limits.max_videotoolbox {
+                    return LimitCheckResult::Queued {
+                        reason: format!(
+                            "VideoToolbox transcode limit reached ({}/{})",
+                            self.usage.videotoolbox_transcoding, self.limits.max_videotoolbox
+                        ),
+                    };
+                }
+            }
+            "vaapi" | "amf" => {
+                if self.usage.amd_transcoding >= self.limits.max_amd_transcode {
+                    return LimitCheckResult::Queued {
+                        reason: format!(
+                            "AMD transcode limit reached ({}/{})",
+                            self.usage.amd_transcoding, self.limits.max_amd_transcode
+                        ),
+                    };
+                }
+            }
             "copy" => {
                 // Copy streams have no specific limit
             }
@@ -143,9 +214,68 @@ impl LimitsEnforcer {
             };
         }
 
+        // Check the bandwidth reserved for this kind of stream
+        let (kind, used, cap) = if mode == "copy" {
+            ("Copy", self.usage.copy_bitrate_mbps, self.limits.max_copy_bitrate_mbps)
+        } else {
+            (
+                "Transcode",
+                self.usage.transcode_bitrate_mbps,
+                self.limits.max_transcode_bitrate_mbps,
+            )
+        };
+        if used + bitrate_mbps > cap {
+            return LimitCheckResult::Queued {
+                reason: format!(
+                    "{} bandwidth limit would be exceeded ({} + {} > {} Mbps)",
+                    kind, used, bitrate_mbps, cap
+                ),
+            };
+        }
+
         LimitCheckResult::Allowed
     }
 
+    /// Whether the mode's own transcode limit (not the total or bandwidth) is full
+    pub fn at_mode_limit(&self, mode: &str) -> bool {
+        match mode {
+            "cpu" => self.usage.cpu_transcoding >= self.limits.max_cpu_transcode,
+            "nvenc" => self.usage.nvenc_transcoding >= self.limits.max_nvenc_transcode,
+            "qsv" => self.usage.qsv_transcoding >= self.limits.max_qsv_transcode,
+            "videotoolbox" => self.usage.videotoolbox_transcoding >= self.limits.max_videotoolbox,
+            "vaapi" | "amf" => self.usage.amd_transcoding >= self.limits.max_amd_transcode,
+            _ => false,
+        }
+    }
+
+    /// Transcode slots for a mode, or None for modes without their own limit
+    pub fn mode_limit(&self, mode: &str) -> Option<usize> {
+        match mode {
+            "cpu" => Some(self.limits.max_cpu_transcode),
+            "nvenc" => Some(self.limits.max_nvenc_transcode),
+            "qsv" => Some(self.limits.max_qsv_transcode),
+            "videotoolbox" => Some(self.limits.max_videotoolbox),
+            "vaapi" | "amf" => Some(self.limits.max_amd_transcode),
+            _ => None,
+        }
+    }
+
+    /// Check a start as if one running stream of the same mode had stopped
+    pub fn can_start_after_stop(
+        &self,
+        mode: &str,
+        bitrate_mbps: u32,
+        freed_bitrate_mbps: u32,
+    ) -> LimitCheckResult {
+        let mut usage = self.usage.clone();
+        usage.remove_stream(mode, freed_bitrate_mbps);
+        let after = Self {
+            limits: self.limits.clone(),
+            usage,
+        };
+        after.can_start(mode, bitrate_mbps)
+    }
+
     /// Record stream start
     pub fn record_start(&mut self, mode: &str, bitrate_mbps: u32) {
         self.usage.add_stream(mode, bitrate_mbps);
@@ -175,6 +305,12 @@ impl LimitsEnforcer {
             max_cpu_transcode: self.limits.max_cpu_transcode,
             nvenc_transcoding: self.usage.nvenc_transcoding,
             max_nvenc_transcode: self.limits.max_nvenc_transcode,
+            qsv_transcoding: self.usage.qsv_transcoding,
+            max_qsv_transcode: self.limits.max_qsv_transcode,
+            videotoolbox_transcoding: self.usage.videotoolbox_transcoding,
+            max_videotoolbox: self.limits.max_videotoolbox,
+            amd_transcoding: self.usage.amd_transcoding,
+            max_amd_transcode: self.limits.max_amd_transcode,
             total_bitrate_mbps: self.usage.total_bitrate_mbps,
             max_bitrate_mbps: self.limits.max_bitrate_mbps,
         }
@@ -190,6 +326,12 @@ pub struct CapacitySummary {
     pub max_cpu_transcode: usize,
     pub nvenc_transcoding: usize,
     pub max_nvenc_transcode: usize,
+    pub qsv_transcoding: usize,
+    pub max_qsv_transcode: usize,
+    pub videotoolbox_transcoding: usize,
+    pub max_videotoolbox: usize,
+    pub amd_transcoding: usize,
+    pub max_amd_transcode: usize,
     pub total_bitrate_mbps: u32,
     pub max_bitrate_mbps: u32,
 }
@@ -213,7 +355,11 @@ mod tests {
             max_total: 2,
             max_cpu_transcode: 1,
             max_nvenc_transcode: 1,
+            max_qsv_transcode: 1,
+            max_videotoolbox: 1,
+            max_amd_transcode: 1,
             max_bitrate_mbps: 100,
+            ..Limits::default()
         });
 
         enforcer.record_start("copy", 10);
@@ -231,7 +377,11 @@ mod tests {
             max_total: 50,
             max_cpu_transcode: 2,
             max_nvenc_transcode: 6,
+            max_qsv_transcode: 4,
+            max_videotoolbox: 4,
+            max_amd_transcode: 4,
             max_bitrate_mbps: 500,
+            ..Limits::default()
         });
 
         enforcer.record_start("cpu", 10);
@@ -249,4 +399,75 @@ mod tests {
             LimitCheckResult::Allowed
         ));
     }
+
+    #[test]
+    fn test_qsv_transcode_limit() {
+        let mut enforcer = LimitsEnforcer::new(Limits {
+            max_qsv_transcode: 1,
+            ..Limits::default()
+        });
+
+        enforcer.record_start("qsv", 10);
+        assert_eq!(enforcer.usage().qsv_transcoding, 1);
+        assert!(matches!(
+            enforcer.can_start("qsv", 10),
+            LimitCheckResult::Queued { .. }
+        ));
+
+        // QSV usage doesn't consume CPU or NVENC slots
+        assert!(matches!(enforcer.can_start("cpu", 10), LimitCheckResult::Allowed));
+        assert!(matches!(enforcer.can_start("nvenc", 10), LimitCheckResult::Allowed));
+
+        enforcer.record_stop("qsv", 10);
+        assert!(matches!(enforcer.can_start("qsv", 10), LimitCheckResult::Allowed));
+    }
+
+    #[test]
+    fn test_amd_modes_share_limit() {
+        let mut enforcer = LimitsEnforcer::new(Limits {
+            max_amd_transcode: 2,
+            ..Limits::default()
+        });
+
+        enforcer.record_start("vaapi", 10);
+        enforcer.record_start("amf", 10);
+        assert_eq!(enforcer.usage().amd_transcoding, 2);
+        assert!(enforcer.at_mode_limit("vaapi"));
+        assert!(matches!(
+            enforcer.can_start("amf", 10),
+            LimitCheckResult::Queued { .. }
+        ));
+
+        enforcer.record_stop("vaapi", 10);
+        assert!(matches!(enforcer.can_start("amf", 10), LimitCheckResult::Allowed));
+    }
+
+    #[test]
+    fn test_copy_bitrate_cap_queues_before_global_cap() {
+        let mut enforcer = LimitsEnforcer::new(Limits {
+            max_bitrate_mbps: 500,
+            max_copy_bitrate_mbps: 100,
+            max_transcode_bitrate_mbps: 300,
+            ..Limits::default()
+        });
+
+        enforcer.record_start("copy", 60);
+        // 120 Mbps is well under the global 500 but over the copy reservation
+        assert!(matches!(
+            enforcer.can_start("copy", 60),
+            LimitCheckResult::Queued { .. }
+        ));
+        assert!(matches!(enforcer.can_start("nvenc", 60), LimitCheckResult::Allowed));
+
+        enforcer.record_start("nvenc", 250);
+        assert_eq!(enforcer.usage().transcode_bitrate_mbps, 250);
+        assert!(matches!(
+            enforcer.can_start("cpu", 60),
+            LimitCheckResult::Queued { .. }
+        ));
+
+        enforcer.record_stop("copy", 60);
+        assert_eq!(enforcer.usage().copy_bitrate_mbps, 0);
+        assert!(matches!(enforcer.can_start("copy", 60), LimitCheckResult::Allowed));
+    }
 }
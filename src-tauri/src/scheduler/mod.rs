@@ -1,19 +1,22 @@
 //! Stream scheduler module
 //! Coordinates queue, limits, and stream state management
 
+pub mod gpus;
 pub mod limits;
 pub mod queue;
 pub mod state;
 
-use crate::db::schema::AppSettings;
-use chrono::Utc;
+use crate::db::schema::{AppSettings, MediaFile, Profile};
+use crate::stream::command::effective_mode;
+use chrono::{DateTime, Utc};
+use gpus::GpuSessions;
 use limits::{CapacitySummary, LimitCheckResult, Limits, LimitsEnforcer};
 use queue::{QueueManager, QueuedStream};
 use serde::{Deserialize, Serialize};
 use state::{StateEvent, StreamState, StreamStateMachine};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
 
 /// Stream info for the scheduler
 #[derive(Debug, Clone)]
@@ -23,6 +26,51 @@ pub struct StreamInfo {
     pub bitrate_mbps: u32,
     pub priority: u8,
     pub pinned: bool,
+    /// Seconds the stream is assumed to hold its slot, see
+    /// `estimate_run_secs`; None when it loops until stopped
+    pub run_secs: Option<f64>,
+}
+
+/// Capacity a stream takes, re-derived when its source is re-probed
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamSize {
+    pub mode: String,
+    pub bitrate_mbps: u32,
+    pub run_secs: Option<f64>,
+}
+
+/// Bandwidth assumed for a stream when nothing better is known
+pub const DEFAULT_BITRATE_MBPS: u32 = 10;
+
+/// Estimate a stream's output bandwidth for limit accounting.
+/// Copy streams send the source bitrate; transcodes send the profile's
+/// video + audio bitrate. Falls back to `DEFAULT_BITRATE_MBPS`.
+pub fn estimate_bitrate_mbps(profile: &Profile, media: &MediaFile) -> u32 {
+    // Copy profiles transcode when the source isn't copy-compatible
+    let copies = effective_mode(profile, media) == "copy";
+
+    let kbps = if copies {
+        media.bitrate.filter(|b| *b > 0).map(|b| b as u64 / 1000)
+    } else {
+        profile
+            .video_bitrate
+            .filter(|b| *b > 0)
+            .map(|v| v as u64 + profile.audio_bitrate.unwrap_or(0).max(0) as u64)
+    };
+
+    kbps.map(|k| k.div_ceil(1000).max(1) as u32)
+        .unwrap_or(DEFAULT_BITRATE_MBPS)
+}
+
+/// Rough seconds a stream holds its slot, for queue ETAs. Streams publish
+/// in real time (`-re`), so that is the total playback duration of its
+/// files whatever the encoder; files without a duration count as 0. A
+/// looping stream never frees its slot on its own, so it has no estimate.
+pub fn estimate_run_secs(files: &[MediaFile], loops: bool) -> Option<f64> {
+    if loops {
+        return None;
+    }
+    Some(files.iter().map(|media| media.duration_secs.unwrap_or(0.0).max(0.0)).sum())
 }
 
 /// Result of a schedule operation
@@ -33,6 +81,18 @@ pub struct ScheduleResult {
     pub queued: bool,
     pub queue_position: Option<usize>,
     pub message: Option<String>,
+    /// Running stream stopped to make room for this one
+    pub preempted: Option<String>,
+    /// Rough wait in seconds for a queued stream, see `Scheduler::estimate_wait`
+    pub eta_secs: Option<u64>,
+}
+
+/// A queued stream admitted by `Scheduler::try_dequeue_next`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dequeued {
+    pub stream_id: String,
+    /// Running stream stopped to make room for this one
+    pub preempted: Option<String>,
 }
 
 /// Batch operation result
@@ -42,57 +102,175 @@ pub struct BatchResult {
     pub failed: Vec<(String, String)>,
 }
 
+/// A stream holding a scheduler slot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunningStream {
+    pub id: String,
+    pub state: StreamState,
+    pub pid: Option<u32>,
+}
+
+/// Capacity, queue and running streams in one view, for dashboards
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerSnapshot {
+    pub capacity: CapacitySummary,
+    /// In dequeue order
+    pub queued: Vec<QueuedStream>,
+    pub running: Vec<RunningStream>,
+    /// Rough wait in seconds for each queued stream, by id
+    pub eta_secs: HashMap<String, u64>,
+}
+
 /// Main scheduler coordinator
 pub struct Scheduler {
     queue: QueueManager,
     limits: LimitsEnforcer,
     states: HashMap<String, StreamStateMachine>,
     stream_info: HashMap<String, StreamInfo>,
+    /// New sizes for running streams, applied once they give back their slot
+    resized: HashMap<String, StreamSize>,
+    /// When each slot-holding stream was admitted
+    started_at: HashMap<String, DateTime<Utc>>,
+    /// NVENC sessions per GPU
+    gpus: GpuSessions,
+    /// Signalled whenever capacity may have freed up for queued streams
+    slot_freed: Arc<Notify>,
+    /// Whether starts may stop lower-priority streams at a transcode limit
+    allow_preemption: bool,
+}
+
+/// Limits from the app settings. VideoToolbox exists only on macOS, so
+/// elsewhere it has no capacity (its profiles run on the CPU instead).
+fn limits_from_settings(settings: &AppSettings) -> Limits {
+    Limits {
+        max_total: settings.max_total_streams as usize,
+        max_cpu_transcode: settings.max_transcode_cpu as usize,
+        max_nvenc_transcode: settings.max_transcode_nvenc as usize,
+        max_qsv_transcode: settings.max_transcode_qsv as usize,
+        max_videotoolbox: if cfg!(target_os = "macos") {
+            settings.max_transcode_videotoolbox as usize
+        } else {
+            0
+        },
+        max_amd_transcode: settings.max_transcode_amd as usize,
+        max_bitrate_mbps: settings.max_total_bitrate_mbps,
+        max_copy_bitrate_mbps: settings.max_copy_bitrate_mbps,
+        max_transcode_bitrate_mbps: settings.max_transcode_bitrate_mbps,
+    }
 }
 
 impl Scheduler {
     pub fn new(settings: &AppSettings) -> Self {
-        let limits = Limits {
-            max_total: settings.max_total_streams as usize,
-            max_cpu_transcode: settings.max_transcode_cpu as usize,
-            max_nvenc_transcode: settings.max_transcode_nvenc as usize,
-            max_bitrate_mbps: settings.max_total_bitrate_mbps,
-        };
+        let limits = limits_from_settings(settings);
 
         Self {
             queue: QueueManager::new(),
             limits: LimitsEnforcer::new(limits),
             states: HashMap::new(),
             stream_info: HashMap::new(),
+            resized: HashMap::new(),
+            started_at: HashMap::new(),
+            gpus: GpuSessions::default(),
+            slot_freed: Arc::new(Notify::new()),
+            allow_preemption: settings.allow_preemption,
         }
     }
 
+    /// Notifier fired when a slot frees up (stop, error, limits raised)
+    pub fn slot_notifier(&self) -> Arc<Notify> {
+        self.slot_freed.clone()
+    }
+
     /// Update limits from settings
     pub fn update_settings(&mut self, settings: &AppSettings) {
-        let limits = Limits {
-            max_total: settings.max_total_streams as usize,
-            max_cpu_transcode: settings.max_transcode_cpu as usize,
-            max_nvenc_transcode: settings.max_transcode_nvenc as usize,
-            max_bitrate_mbps: settings.max_total_bitrate_mbps,
-        };
+        let limits = limits_from_settings(settings);
         self.limits.update_limits(limits);
+        self.allow_preemption = settings.allow_preemption;
+        self.slot_freed.notify_one();
     }
 
     /// Register stream info (call when stream is created)
     pub fn register_stream(&mut self, info: StreamInfo) {
         let id = info.id.clone();
+        self.resized.remove(&id);
         self.stream_info.insert(id.clone(), info);
         self.states
             .insert(id.clone(), StreamStateMachine::new(id));
     }
 
-    /// Unregister stream (call when stream is deleted)
+    /// Whether a stream has been registered
+    pub fn is_registered(&self, stream_id: &str) -> bool {
+        self.stream_info.contains_key(stream_id)
+    }
+
+    /// Unregister stream (call when stream is deleted), releasing its slot
+    /// if it was still running
     pub fn unregister_stream(&mut self, stream_id: &str) {
+        if self.queue.is_running(stream_id) {
+            self.release(stream_id);
+            self.slot_freed.notify_one();
+        }
         self.stream_info.remove(stream_id);
+        self.resized.remove(stream_id);
         self.states.remove(stream_id);
         self.queue.remove_from_queue(stream_id);
     }
 
+    /// Re-size a registered stream after its source changed, keeping its
+    /// priority, pin and state. A running stream keeps the size it was
+    /// admitted with until its slot is released, so the release matches.
+    pub fn resize_stream(&mut self, stream_id: &str, size: StreamSize) {
+        if !self.stream_info.contains_key(stream_id) {
+            return;
+        }
+        if self.queue.is_running(stream_id) {
+            self.resized.insert(stream_id.to_string(), size);
+        } else {
+            self.apply_size(stream_id, size);
+        }
+    }
+
+    fn apply_size(&mut self, stream_id: &str, size: StreamSize) {
+        if let Some(info) = self.stream_info.get_mut(stream_id) {
+            info.mode = size.mode;
+            info.bitrate_mbps = size.bitrate_mbps;
+            info.run_secs = size.run_secs;
+        }
+    }
+
+    /// Change a stream's priority; a queued stream moves to its new place
+    /// and the dequeue loop is woken to re-check what can start
+    pub fn set_priority(&mut self, stream_id: &str, priority: u8) -> Result<(), String> {
+        let info = self
+            .stream_info
+            .get_mut(stream_id)
+            .ok_or_else(|| "Stream not registered".to_string())?;
+        info.priority = priority;
+        let pinned = info.pinned;
+        self.requeue(stream_id, priority, pinned);
+        Ok(())
+    }
+
+    /// Pin or unpin a stream; pinned streams are dequeued before all others.
+    /// Pinning a queued stream wakes the dequeue loop, which preempts for it
+    /// the same way `request_start` would.
+    pub fn set_pinned(&mut self, stream_id: &str, pinned: bool) -> Result<(), String> {
+        let info = self
+            .stream_info
+            .get_mut(stream_id)
+            .ok_or_else(|| "Stream not registered".to_string())?;
+        info.pinned = pinned;
+        let priority = info.priority;
+        self.requeue(stream_id, priority, pinned);
+        Ok(())
+    }
+
+    fn requeue(&mut self, stream_id: &str, priority: u8, pinned: bool) {
+        if self.queue.update_priority(stream_id, priority, pinned) {
+            self.slot_freed.notify_one();
+        }
+    }
+
     /// Request to start a stream
     pub fn request_start(&mut self, stream_id: &str) -> ScheduleResult {
         let info = match self.stream_info.get(stream_id) {
@@ -104,6 +282,8 @@ impl Scheduler {
                     queued: false,
                     queue_position: None,
                     message: Some("Stream not registered".to_string()),
+                    preempted: None,
+                    eta_secs: None,
                 };
             }
         };
@@ -125,6 +305,8 @@ impl Scheduler {
                     "Cannot start from state: {}",
                     state_machine.state.as_str()
                 )),
+                preempted: None,
+                eta_secs: None,
             };
         }
 
@@ -135,8 +317,7 @@ impl Scheduler {
         match self.limits.can_start(&info.mode, info.bitrate_mbps) {
             LimitCheckResult::Allowed => {
                 // Record usage and mark as starting
-                self.limits.record_start(&info.mode, info.bitrate_mbps);
-                self.queue.mark_running(stream_id);
+                self.occupy(stream_id, &info.mode, info.bitrate_mbps);
 
                 ScheduleResult {
                     stream_id: stream_id.to_string(),
@@ -144,13 +325,32 @@ impl Scheduler {
                     queued: false,
                     queue_position: None,
                     message: None,
+                    preempted: None,
+                    eta_secs: None,
                 }
             }
             LimitCheckResult::Queued { reason } => {
+                if let Some(victim) = self.preemption_victim(&info) {
+                    self.preempt(&victim);
+                    self.occupy(stream_id, &info.mode, info.bitrate_mbps);
+
+                    return ScheduleResult {
+                        stream_id: stream_id.to_string(),
+                        status: "starting".to_string(),
+                        queued: false,
+                        queue_position: None,
+                        message: Some(format!("Preempted stream {}", victim)),
+                        preempted: Some(victim),
+                        eta_secs: None,
+                    };
+                }
+
                 // Queue the stream
-                let _ = state_machine.apply(StateEvent::EnqueuedForLimits {
-                    reason: reason.clone(),
-                });
+                if let Some(sm) = self.states.get_mut(stream_id) {
+                    let _ = sm.apply(StateEvent::EnqueuedForLimits {
+                        reason: reason.clone(),
+                    });
+                }
 
                 self.queue.enqueue(QueuedStream {
                     stream_id: stream_id.to_string(),
@@ -168,12 +368,16 @@ impl Scheduler {
                     queued: true,
                     queue_position: Some(position),
                     message: Some(reason),
+                    preempted: None,
+                    eta_secs: self.estimate_wait(stream_id),
                 }
             }
             LimitCheckResult::Rejected { reason } => {
-                let _ = state_machine.apply(StateEvent::ErrorOccurred {
-                    message: reason.clone(),
-                });
+                if let Some(sm) = self.states.get_mut(stream_id) {
+                    let _ = sm.apply(StateEvent::ErrorOccurred {
+                        message: reason.clone(),
+                    });
+                }
 
                 ScheduleResult {
                     stream_id: stream_id.to_string(),
@@ -181,11 +385,100 @@ impl Scheduler {
                     queued: false,
                     queue_position: None,
                     message: Some(reason),
+                    preempted: None,
+                    eta_secs: None,
                 }
             }
         }
     }
 
+    /// Request starts for several streams in one pass. Repeated ids count
+    /// once, and streams are admitted in the order the queue would start
+    /// them (pinned, then higher priority, then as given), so the free slots
+    /// go to the highest-ranked. Results follow that order.
+    pub fn request_start_batch(&mut self, stream_ids: &[String]) -> Vec<ScheduleResult> {
+        let mut seen = HashSet::new();
+        let mut ordered: Vec<&str> = stream_ids
+            .iter()
+            .map(String::as_str)
+            .filter(|id| seen.insert(*id))
+            .collect();
+        ordered.sort_by_key(|id| {
+            let rank = self.stream_info.get(*id).map(|i| (i.pinned, i.priority));
+            std::cmp::Reverse(rank.unwrap_or((false, 0)))
+        });
+
+        ordered.into_iter().map(|id| self.request_start(id)).collect()
+    }
+
+    /// Running stream to stop so `info` can start, if preemption applies:
+    /// the start is blocked only by its mode's limit, and there is an unpinned
+    /// stream in that mode ranked below it (any unpinned one, for pinned starts).
+    /// The lowest-priority candidate is chosen.
+    fn preemption_victim(&self, info: &StreamInfo) -> Option<String> {
+        if !self.allow_preemption || !self.limits.at_mode_limit(&info.mode) {
+            return None;
+        }
+
+        let victim = self
+            .queue
+            .running_ids()
+            .into_iter()
+            .filter_map(|id| self.stream_info.get(&id))
+            .filter(|v| v.id != info.id && v.mode == info.mode && !v.pinned)
+            .filter(|v| info.pinned || v.priority < info.priority)
+            .min_by(|a, b| a.priority.cmp(&b.priority).then_with(|| a.id.cmp(&b.id)))?;
+
+        match self
+            .limits
+            .can_start_after_stop(&info.mode, info.bitrate_mbps, victim.bitrate_mbps)
+        {
+            LimitCheckResult::Allowed => Some(victim.id.clone()),
+            _ => None,
+        }
+    }
+
+    /// Release a running stream's slot for a preempting start
+    fn preempt(&mut self, stream_id: &str) {
+        self.release(stream_id);
+        if let Some(sm) = self.states.get_mut(stream_id) {
+            let _ = sm.apply(StateEvent::StopRequested);
+        }
+    }
+
+    /// Take a slot for a stream, placing NVENC streams on a GPU
+    fn occupy(&mut self, stream_id: &str, mode: &str, bitrate_mbps: u32) {
+        self.limits.record_start(mode, bitrate_mbps);
+        if mode == "nvenc" {
+            self.gpus.assign(stream_id);
+        }
+        self.queue.mark_running(stream_id);
+        self.started_at.insert(stream_id.to_string(), Utc::now());
+    }
+
+    /// Give back a stream's slot and GPU session
+    fn release(&mut self, stream_id: &str) {
+        if let Some(info) = self.stream_info.get(stream_id) {
+            self.limits.record_stop(&info.mode, info.bitrate_mbps);
+        }
+        if let Some(size) = self.resized.remove(stream_id) {
+            self.apply_size(stream_id, size);
+        }
+        self.gpus.release(stream_id);
+        self.queue.mark_stopped(stream_id);
+        self.started_at.remove(stream_id);
+    }
+
+    /// Set how many NVIDIA GPUs NVENC streams can be spread over
+    pub fn set_nvenc_gpu_count(&mut self, count: usize) {
+        self.gpus.set_gpu_count(count);
+    }
+
+    /// GPU an admitted NVENC stream should encode on
+    pub fn assigned_gpu(&self, stream_id: &str) -> Option<u32> {
+        self.gpus.gpu_for(stream_id)
+    }
+
     /// Called when FFmpeg process starts
     pub fn on_process_started(&mut self, stream_id: &str, pid: u32) {
         if let Some(sm) = self.states.get_mut(stream_id) {
@@ -195,28 +488,24 @@ impl Scheduler {
 
     /// Called when stream stops
     pub fn on_stream_stopped(&mut self, stream_id: &str) {
-        if let Some(info) = self.stream_info.get(stream_id) {
-            self.limits.record_stop(&info.mode, info.bitrate_mbps);
-        }
-        self.queue.mark_stopped(stream_id);
+        self.release(stream_id);
 
         if let Some(sm) = self.states.get_mut(stream_id) {
             let _ = sm.apply(StateEvent::ProcessStopped);
         }
+        self.slot_freed.notify_one();
     }
 
     /// Called when stream errors
     pub fn on_stream_error(&mut self, stream_id: &str, message: &str) {
-        if let Some(info) = self.stream_info.get(stream_id) {
-            self.limits.record_stop(&info.mode, info.bitrate_mbps);
-        }
-        self.queue.mark_stopped(stream_id);
+        self.release(stream_id);
 
         if let Some(sm) = self.states.get_mut(stream_id) {
             let _ = sm.apply(StateEvent::ErrorOccurred {
                 message: message.to_string(),
             });
         }
+        self.slot_freed.notify_one();
     }
 
     /// Request to stop a stream
@@ -227,41 +516,118 @@ impl Scheduler {
         if let Some(sm) = self.states.get_mut(stream_id) {
             if sm.state.can_stop() {
                 let _ = sm.apply(StateEvent::StopRequested);
-                if let Some(info) = self.stream_info.get(stream_id) {
-                    self.limits.record_stop(&info.mode, info.bitrate_mbps);
-                }
-                self.queue.mark_stopped(stream_id);
+                self.release(stream_id);
+                self.slot_freed.notify_one();
                 return true;
             }
         }
         false
     }
 
-    /// Try to start next queued stream if slot available
-    pub fn try_dequeue_next(&mut self) -> Option<String> {
-        if let Some(queued) = self.queue.peek() {
-            let mode = queued.mode.clone();
-            let stream_id = queued.stream_id.clone();
-            let bitrate = self
-                .stream_info
-                .get(&stream_id)
-                .map(|i| i.bitrate_mbps)
-                .unwrap_or(0);
-
-            if let LimitCheckResult::Allowed = self.limits.can_start(&mode, bitrate) {
-                // Can start this one
-                self.queue.dequeue();
-                self.limits.record_start(&mode, bitrate);
-                self.queue.mark_running(&stream_id);
-
-                if let Some(sm) = self.states.get_mut(&stream_id) {
-                    let _ = sm.apply(StateEvent::SlotAvailable);
-                }
+    /// Take a queued stream out of the queue without starting it.
+    /// Limits are untouched since a queued stream never held a slot.
+    pub fn cancel_queued(&mut self, stream_id: &str) -> Result<(), String> {
+        let queued = self.get_state(stream_id) == Some(StreamState::Queued);
+        if !queued || !self.queue.is_queued(stream_id) {
+            return Err(format!("Stream {} is not queued", stream_id));
+        }
+
+        self.queue.remove_from_queue(stream_id);
+        if let Some(sm) = self.states.get_mut(stream_id) {
+            sm.apply(StateEvent::QueueCancelled)?;
+        }
+        Ok(())
+    }
+
+    /// Try to start the next queued stream if a slot is available, or if
+    /// it may preempt a running stream under the same rules as `request_start`
+    pub fn try_dequeue_next(&mut self) -> Option<Dequeued> {
+        let queued = self.queue.peek()?;
+        let mode = queued.mode.clone();
+        let stream_id = queued.stream_id.clone();
+        let bitrate = self
+            .stream_info
+            .get(&stream_id)
+            .map(|i| i.bitrate_mbps)
+            .unwrap_or(0);
 
-                return Some(stream_id);
+        let preempted = match self.limits.can_start(&mode, bitrate) {
+            LimitCheckResult::Allowed => None,
+            LimitCheckResult::Queued { .. } => {
+                let victim = self
+                    .stream_info
+                    .get(&stream_id)
+                    .and_then(|info| self.preemption_victim(info))?;
+                self.preempt(&victim);
+                Some(victim)
             }
+            _ => return None,
+        };
+
+        self.queue.dequeue();
+        self.occupy(&stream_id, &mode, bitrate);
+
+        if let Some(sm) = self.states.get_mut(&stream_id) {
+            let _ = sm.apply(StateEvent::SlotAvailable);
+        }
+
+        Some(Dequeued { stream_id, preempted })
+    }
+
+    /// Dequeue every stream that fits in the currently free capacity
+    pub fn drain_ready(&mut self) -> Vec<Dequeued> {
+        let mut ready = Vec::new();
+        while let Some(next) = self.try_dequeue_next() {
+            ready.push(next);
         }
-        None
+        ready
+    }
+
+    /// Rough seconds until a queued stream starts, or None if it isn't
+    /// queued or can't be estimated.
+    ///
+    /// Streams publish in real time, so each holds its slot for its
+    /// `run_secs` — the playback duration of its files — and running ones
+    /// for what remains of that. The slots the stream competes for (its
+    /// mode's transcode slots when that limit is full, otherwise all slots)
+    /// are assumed to work through the running streams and the ones queued
+    /// ahead of it evenly. When any of those loops, its slot never frees on
+    /// its own and there is no estimate. Bandwidth limits and priority
+    /// changes after the estimate are not accounted for.
+    pub fn estimate_wait(&self, stream_id: &str) -> Option<u64> {
+        let queued = self.queue.queued_streams();
+        let position = queued.iter().position(|q| q.stream_id == stream_id)?;
+        let mode = queued[position].mode.as_str();
+
+        let by_mode = self.limits.at_mode_limit(mode);
+        let slots = if by_mode {
+            self.limits.mode_limit(mode).unwrap_or(1)
+        } else {
+            self.limits.limits().max_total
+        }
+        .max(1);
+        let competes = |id: &str| {
+            !by_mode || self.stream_info.get(id).is_some_and(|i| i.mode == mode)
+        };
+        let run_secs = |id: &str| self.stream_info.get(id).map_or(Some(0.0), |i| i.run_secs);
+
+        let now = Utc::now();
+        let running: f64 = self
+            .started_at
+            .iter()
+            .filter(|(id, _)| competes(id))
+            .map(|(id, started)| {
+                let elapsed = (now - *started).num_milliseconds() as f64 / 1000.0;
+                run_secs(id).map(|secs| (secs - elapsed).max(0.0))
+            })
+            .sum::<Option<f64>>()?;
+        let ahead: f64 = queued[..position]
+            .iter()
+            .filter(|q| competes(&q.stream_id))
+            .map(|q| run_secs(&q.stream_id))
+            .sum::<Option<f64>>()?;
+
+        Some(((running + ahead) / slots as f64).ceil() as u64)
     }
 
     /// Get stream state
@@ -283,6 +649,36 @@ impl Scheduler {
     pub fn running_ids(&self) -> Vec<String> {
         self.queue.running_ids()
     }
+
+    /// Capacity, queue and running streams, taken together
+    pub fn snapshot(&self) -> SchedulerSnapshot {
+        let mut running: Vec<RunningStream> = self
+            .running_ids()
+            .into_iter()
+            .map(|id| {
+                let sm = self.states.get(&id);
+                RunningStream {
+                    state: sm.map(|sm| sm.state).unwrap_or(StreamState::Running),
+                    pid: sm.and_then(|sm| sm.pid),
+                    id,
+                }
+            })
+            .collect();
+        running.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let queued = self.queue_info();
+        let eta_secs = queued
+            .iter()
+            .filter_map(|q| Some((q.stream_id.clone(), self.estimate_wait(&q.stream_id)?)))
+            .collect();
+
+        SchedulerSnapshot {
+            capacity: self.capacity_summary(),
+            queued,
+            running,
+            eta_secs,
+        }
+    }
 }
 
 /// Thread-safe scheduler
@@ -291,3 +687,510 @@ pub type SharedScheduler = Arc<Mutex<Scheduler>>;
 pub fn create_shared_scheduler(settings: &AppSettings) -> SharedScheduler {
     Arc::new(Mutex::new(Scheduler::new(settings)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings_with_max_total(max_total_streams: u32) -> AppSettings {
+        AppSettings {
+            max_total_streams,
+            ..Default::default()
+        }
+    }
+
+    fn ready_ids(scheduler: &mut Scheduler) -> Vec<String> {
+        scheduler.drain_ready().into_iter().map(|d| d.stream_id).collect()
+    }
+
+    fn copy_stream(id: &str) -> StreamInfo {
+        StreamInfo {
+            id: id.to_string(),
+            mode: "copy".to_string(),
+            bitrate_mbps: 10,
+            priority: 50,
+            pinned: false,
+            run_secs: Some(0.0),
+        }
+    }
+
+    #[test]
+    fn test_estimate_bitrate() {
+        let mut profile = Profile {
+            id: "p".into(),
+            name: "p".into(),
+            protocol: "rtsp".into(),
+            mode: "cpu".into(),
+            video_bitrate: Some(4000),
+            audio_bitrate: Some(128),
+            resolution: None,
+            gop_size: 30,
+            wan_optimized: 0,
+            codec: "h264".into(),
+            record: 0,
+            record_dir: None,
+            srt_latency_ms: None,
+            srt_overhead_percent: None,
+            overlay_text: None,
+            overlay_position: None,
+            overlay_font_size: None,
+            tonemap: 0,
+        };
+        let mut media: MediaFile = serde_json::from_value(serde_json::json!({
+            "id": "m", "path": "/a.mp4", "folder": "f", "filename": "a.mp4",
+            "has_b_frames": 0, "compatibility": "copy", "scanned_at": "now",
+            "bitrate": 25_000_000
+        }))
+        .unwrap();
+
+        // Transcode: profile video + audio, rounded up
+        assert_eq!(estimate_bitrate_mbps(&profile, &media), 5);
+
+        // Copy: source bitrate
+        profile.mode = "copy".into();
+        profile.video_bitrate = None;
+        assert_eq!(estimate_bitrate_mbps(&profile, &media), 25);
+
+        // A copy profile with a bitrate override is upgraded to a transcode
+        profile.video_bitrate = Some(4000);
+        assert_eq!(estimate_bitrate_mbps(&profile, &media), 5);
+
+        // Copy profile on an incompatible source transcodes with no bitrate set
+        media.compatibility = "transcode".into();
+        profile.video_bitrate = None;
+        assert_eq!(estimate_bitrate_mbps(&profile, &media), DEFAULT_BITRATE_MBPS);
+    }
+
+    #[test]
+    fn test_queued_stream_dequeued_when_slot_frees() {
+        let mut scheduler = Scheduler::new(&settings_with_max_total(2));
+        for id in ["s1", "s2", "s3"] {
+            scheduler.register_stream(copy_stream(id));
+        }
+
+        assert!(!scheduler.request_start("s1").queued);
+        scheduler.on_process_started("s1", 1);
+        assert!(!scheduler.request_start("s2").queued);
+        scheduler.on_process_started("s2", 2);
+        assert!(scheduler.request_start("s3").queued);
+
+        // Nothing fits while both slots are taken
+        assert!(scheduler.drain_ready().is_empty());
+
+        scheduler.on_stream_stopped("s1");
+        assert_eq!(ready_ids(&mut scheduler), vec!["s3".to_string()]);
+        assert_eq!(scheduler.get_state("s3"), Some(StreamState::Starting));
+        assert_eq!(scheduler.capacity_summary().total_streams, 2);
+    }
+
+    #[test]
+    fn test_drain_ready_fills_multiple_slots() {
+        let mut scheduler = Scheduler::new(&settings_with_max_total(1));
+        for id in ["s1", "s2", "s3"] {
+            scheduler.register_stream(copy_stream(id));
+        }
+
+        scheduler.request_start("s1");
+        scheduler.on_process_started("s1", 1);
+        assert!(scheduler.request_start("s2").queued);
+        assert!(scheduler.request_start("s3").queued);
+
+        scheduler.update_settings(&settings_with_max_total(3));
+        assert_eq!(scheduler.drain_ready().len(), 2);
+    }
+
+    #[test]
+    fn test_bumped_stream_starts_next() {
+        let mut scheduler = Scheduler::new(&settings_with_max_total(1));
+        for id in ["s1", "s2", "s3"] {
+            scheduler.register_stream(copy_stream(id));
+        }
+        scheduler.set_priority("s3", 10).unwrap();
+
+        scheduler.request_start("s1");
+        scheduler.on_process_started("s1", 1);
+        assert!(scheduler.request_start("s2").queued);
+        assert!(scheduler.request_start("s3").queued);
+
+        // s3 was lowest; bump it above s2
+        scheduler.set_priority("s3", 200).unwrap();
+        assert!(scheduler.set_priority("missing", 1).is_err());
+
+        scheduler.on_stream_stopped("s1");
+        assert_eq!(ready_ids(&mut scheduler), vec!["s3".to_string()]);
+    }
+
+    #[test]
+    fn test_pinned_stream_starts_next() {
+        let mut scheduler = Scheduler::new(&settings_with_max_total(1));
+        for id in ["s1", "s2", "s3"] {
+            scheduler.register_stream(copy_stream(id));
+        }
+        scheduler.request_start("s1");
+        scheduler.on_process_started("s1", 1);
+        scheduler.request_start("s2");
+        scheduler.request_start("s3");
+
+        scheduler.set_pinned("s3", true).unwrap();
+        scheduler.on_stream_stopped("s1");
+        assert_eq!(ready_ids(&mut scheduler), vec!["s3".to_string()]);
+    }
+
+    #[test]
+    fn test_resize_waits_for_running_stream() {
+        let mut scheduler = Scheduler::new(&settings_with_max_total(4));
+        for id in ["s1", "s2"] {
+            scheduler.register_stream(copy_stream(id));
+        }
+        scheduler.request_start("s1");
+        scheduler.on_process_started("s1", 1);
+
+        let cpu = StreamSize {
+            mode: "cpu".to_string(),
+            bitrate_mbps: 5,
+            run_secs: None,
+        };
+        scheduler.resize_stream("s1", cpu.clone());
+        scheduler.resize_stream("s2", cpu);
+        assert_eq!(scheduler.stream_info["s2"].mode, "cpu");
+
+        // s1 still holds the copy slot it was admitted with
+        assert_eq!(scheduler.stream_info["s1"].mode, "copy");
+        scheduler.on_stream_stopped("s1");
+        let capacity = scheduler.capacity_summary();
+        assert_eq!((capacity.total_streams, capacity.total_bitrate_mbps), (0, 0));
+        assert_eq!(scheduler.stream_info["s1"].mode, "cpu");
+        assert_eq!(scheduler.stream_info["s1"].bitrate_mbps, 5);
+    }
+
+    #[test]
+    fn test_videotoolbox_capacity_only_on_macos() {
+        let settings = AppSettings {
+            max_transcode_videotoolbox: 4,
+            ..Default::default()
+        };
+        let scheduler = Scheduler::new(&settings);
+        let expected = if cfg!(target_os = "macos") { 4 } else { 0 };
+        assert_eq!(scheduler.capacity_summary().max_videotoolbox, expected);
+    }
+
+    #[test]
+    fn test_cancel_queued() {
+        let mut scheduler = Scheduler::new(&settings_with_max_total(1));
+        for id in ["s1", "s2"] {
+            scheduler.register_stream(copy_stream(id));
+        }
+        scheduler.request_start("s1");
+        scheduler.on_process_started("s1", 1);
+        assert!(scheduler.request_start("s2").queued);
+
+        scheduler.cancel_queued("s2").unwrap();
+        assert_eq!(scheduler.get_state("s2"), Some(StreamState::Stopped));
+        assert!(scheduler.queue_info().is_empty());
+
+        // The running stream and its slot are untouched
+        assert_eq!(scheduler.get_state("s1"), Some(StreamState::Running));
+        assert_eq!(scheduler.capacity_summary().total_streams, 1);
+
+        assert!(scheduler.cancel_queued("s1").is_err());
+        assert!(scheduler.cancel_queued("s2").is_err());
+    }
+
+    #[test]
+    fn test_restart_applies_new_bitrate() {
+        let mut scheduler = Scheduler::new(&settings_with_max_total(5));
+        scheduler.register_stream(copy_stream("s1"));
+        scheduler.request_start("s1");
+        scheduler.on_process_started("s1", 1);
+        assert_eq!(scheduler.capacity_summary().total_bitrate_mbps, 10);
+
+        // Stop, re-size from the edited profile, start again under the same id
+        scheduler.on_stream_stopped("s1");
+        scheduler.register_stream(StreamInfo {
+            bitrate_mbps: 4,
+            ..copy_stream("s1")
+        });
+        assert_eq!(scheduler.request_start("s1").status, "starting");
+        scheduler.on_process_started("s1", 2);
+
+        let capacity = scheduler.capacity_summary();
+        assert_eq!((capacity.total_streams, capacity.total_bitrate_mbps), (1, 4));
+        scheduler.on_stream_stopped("s1");
+        assert_eq!(scheduler.capacity_summary().total_bitrate_mbps, 0);
+    }
+
+    #[test]
+    fn test_batch_start_counts_each_stream_once() {
+        let mut scheduler = Scheduler::new(&settings_with_max_total(10));
+        let mut ids: Vec<String> = (0..30).map(|i| format!("s{}", i)).collect();
+        for id in &ids {
+            scheduler.register_stream(copy_stream(id));
+        }
+        scheduler.set_priority("s29", 200).unwrap();
+        // Repeats in the batch must not take a second slot
+        ids.extend(["s0".to_string(), "s29".to_string()]);
+
+        let results = scheduler.request_start_batch(&ids);
+        assert_eq!(results.len(), 30);
+        assert_eq!(results[0].stream_id, "s29");
+        assert_eq!(results.iter().filter(|r| r.status == "starting").count(), 10);
+        assert_eq!(results.iter().filter(|r| r.queued).count(), 20);
+
+        let capacity = scheduler.capacity_summary();
+        assert_eq!(capacity.total_streams, 10);
+        assert_eq!(capacity.total_bitrate_mbps, 100);
+        assert_eq!(scheduler.queue_info().len(), 20);
+
+        // Starting again is refused rather than counted twice
+        assert_eq!(scheduler.request_start_batch(&ids[..5]).iter().filter(|r| r.queued).count(), 0);
+        assert_eq!(scheduler.capacity_summary().total_streams, 10);
+    }
+
+    #[test]
+    fn test_queue_eta_shrinks_as_streams_start() {
+        let mut scheduler = Scheduler::new(&settings_with_max_total(1));
+        for id in ["s1", "s2", "s3"] {
+            scheduler.register_stream(StreamInfo {
+                run_secs: Some(600.0),
+                ..copy_stream(id)
+            });
+        }
+
+        scheduler.request_start("s1");
+        scheduler.on_process_started("s1", 1);
+        assert_eq!(scheduler.request_start("s2").eta_secs, Some(600));
+        scheduler.request_start("s3");
+
+        // s1's remainder plus s2's full run, through one slot
+        let before = scheduler.estimate_wait("s3").unwrap();
+        assert!(before > 600 && before <= 1200);
+        assert_eq!(scheduler.snapshot().eta_secs.get("s3"), Some(&before));
+
+        scheduler.on_stream_stopped("s1");
+        assert_eq!(ready_ids(&mut scheduler), vec!["s2".to_string()]);
+        let after = scheduler.estimate_wait("s3").unwrap();
+        assert!(after > 0 && after < before);
+
+        assert_eq!(scheduler.estimate_wait("s2"), None);
+    }
+
+    #[test]
+    fn test_queue_eta_unknown_behind_looping_stream() {
+        let scheduler_with = |running: Option<f64>, ahead: Option<f64>| {
+            let mut scheduler = Scheduler::new(&settings_with_max_total(1));
+            for (id, run_secs) in [("running", running), ("ahead", ahead), ("last", Some(600.0))] {
+                scheduler.register_stream(StreamInfo {
+                    run_secs,
+                    ..copy_stream(id)
+                });
+            }
+            scheduler.request_start("running");
+            scheduler.on_process_started("running", 1);
+            scheduler.request_start("ahead");
+            scheduler.request_start("last");
+            scheduler
+        };
+
+        assert!(scheduler_with(Some(600.0), Some(600.0)).estimate_wait("last").is_some());
+        // A looping stream never frees its slot on its own
+        assert_eq!(scheduler_with(None, Some(600.0)).estimate_wait("ahead"), None);
+        assert_eq!(scheduler_with(None, Some(600.0)).estimate_wait("last"), None);
+        assert_eq!(scheduler_with(Some(600.0), None).estimate_wait("last"), None);
+        assert!(scheduler_with(Some(600.0), None).estimate_wait("ahead").is_some());
+    }
+
+    #[test]
+    fn test_estimate_run_secs_is_playback_time() {
+        let media = |secs: Option<f64>| -> MediaFile {
+            serde_json::from_value(serde_json::json!({
+                "id": "m", "path": "/a.mp4", "folder": "f", "filename": "a.mp4",
+                "has_b_frames": 0, "compatibility": "copy", "scanned_at": "now",
+                "duration_secs": secs
+            }))
+            .unwrap()
+        };
+        let files = [media(Some(600.0)), media(Some(300.0)), media(None)];
+        assert_eq!(estimate_run_secs(&files, false), Some(900.0));
+        assert_eq!(estimate_run_secs(&files, true), None);
+    }
+
+    fn nvenc_stream(id: &str, priority: u8, pinned: bool) -> StreamInfo {
+        StreamInfo {
+            id: id.to_string(),
+            mode: "nvenc".to_string(),
+            bitrate_mbps: 5,
+            priority,
+            pinned,
+            run_secs: Some(0.0),
+        }
+    }
+
+    fn nvenc_settings(allow_preemption: bool) -> AppSettings {
+        AppSettings {
+            max_transcode_nvenc: 2,
+            allow_preemption,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pinned_start_preempts_lowest_priority() {
+        let mut scheduler = Scheduler::new(&nvenc_settings(true));
+        scheduler.register_stream(nvenc_stream("low", 10, false));
+        scheduler.register_stream(nvenc_stream("mid", 50, false));
+        scheduler.register_stream(nvenc_stream("critical", 1, true));
+        for (pid, id) in ["low", "mid"].into_iter().enumerate() {
+            scheduler.request_start(id);
+            scheduler.on_process_started(id, pid as u32);
+        }
+
+        let result = scheduler.request_start("critical");
+        assert!(!result.queued);
+        assert_eq!(result.preempted.as_deref(), Some("low"));
+        assert_eq!(scheduler.get_state("low"), Some(StreamState::Stopped));
+        assert_eq!(scheduler.get_state("critical"), Some(StreamState::Starting));
+        assert_eq!(scheduler.capacity_summary().nvenc_transcoding, 2);
+    }
+
+    #[test]
+    fn test_pinning_queued_stream_preempts_on_dequeue() {
+        let mut scheduler = Scheduler::new(&nvenc_settings(true));
+        for (pid, id) in ["a", "b"].into_iter().enumerate() {
+            scheduler.register_stream(nvenc_stream(id, 10 * (pid as u8 + 1), false));
+            scheduler.request_start(id);
+            scheduler.on_process_started(id, pid as u32);
+        }
+        scheduler.register_stream(nvenc_stream("c", 1, false));
+        assert!(scheduler.request_start("c").queued);
+        assert!(scheduler.drain_ready().is_empty());
+
+        scheduler.set_pinned("c", true).unwrap();
+        assert_eq!(
+            scheduler.drain_ready(),
+            vec![Dequeued {
+                stream_id: "c".to_string(),
+                preempted: Some("a".to_string()),
+            }]
+        );
+        assert_eq!(scheduler.get_state("a"), Some(StreamState::Stopped));
+        assert_eq!(scheduler.get_state("c"), Some(StreamState::Starting));
+        assert_eq!(scheduler.capacity_summary().nvenc_transcoding, 2);
+    }
+
+    #[test]
+    fn test_preemption_disabled_or_not_outranking() {
+        let mut scheduler = Scheduler::new(&nvenc_settings(false));
+        for id in ["a", "b"] {
+            scheduler.register_stream(nvenc_stream(id, 10, false));
+            scheduler.request_start(id);
+        }
+        scheduler.register_stream(nvenc_stream("c", 1, true));
+        assert!(scheduler.request_start("c").queued);
+
+        // Enabled, but an unpinned start at equal priority doesn't outrank
+        let mut scheduler = Scheduler::new(&nvenc_settings(true));
+        for id in ["a", "b", "c"] {
+            scheduler.register_stream(nvenc_stream(id, 10, false));
+        }
+        scheduler.request_start("a");
+        scheduler.request_start("b");
+        let result = scheduler.request_start("c");
+        assert!(result.queued);
+        assert_eq!(result.preempted, None);
+    }
+
+    #[test]
+    fn test_no_preemption_when_total_limit_blocks() {
+        let settings = AppSettings {
+            max_total_streams: 2,
+            ..nvenc_settings(true)
+        };
+        let mut scheduler = Scheduler::new(&settings);
+        scheduler.register_stream(copy_stream("copy"));
+        scheduler.register_stream(nvenc_stream("low", 10, false));
+        scheduler.register_stream(nvenc_stream("critical", 1, true));
+        scheduler.request_start("copy");
+        scheduler.request_start("low");
+
+        // The NVENC limit isn't what's full, so nothing is stopped
+        assert!(scheduler.request_start("critical").queued);
+        assert_eq!(scheduler.get_state("low"), Some(StreamState::Starting));
+    }
+
+    #[test]
+    fn test_unregister_running_frees_capacity() {
+        let mut scheduler = Scheduler::new(&settings_with_max_total(1));
+        for id in ["s1", "s2"] {
+            scheduler.register_stream(copy_stream(id));
+        }
+        scheduler.request_start("s1");
+        scheduler.on_process_started("s1", 1);
+        assert!(scheduler.request_start("s2").queued);
+
+        scheduler.unregister_stream("s1");
+        assert_eq!(scheduler.capacity_summary().total_streams, 0);
+        assert!(scheduler.running_ids().is_empty());
+        assert_eq!(ready_ids(&mut scheduler), vec!["s2".to_string()]);
+    }
+
+    #[test]
+    fn test_nvenc_streams_spread_across_gpus() {
+        let mut scheduler = Scheduler::new(&nvenc_settings(false));
+        scheduler.set_nvenc_gpu_count(2);
+        for (pid, id) in ["a", "b"].into_iter().enumerate() {
+            scheduler.register_stream(nvenc_stream(id, 10, false));
+            scheduler.request_start(id);
+            scheduler.on_process_started(id, pid as u32);
+        }
+        assert_eq!(scheduler.assigned_gpu("a"), Some(0));
+        assert_eq!(scheduler.assigned_gpu("b"), Some(1));
+
+        // A freed GPU is reused by the next start
+        scheduler.on_stream_stopped("a");
+        assert_eq!(scheduler.assigned_gpu("a"), None);
+        scheduler.request_start("a");
+        assert_eq!(scheduler.assigned_gpu("a"), Some(0));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut scheduler = Scheduler::new(&settings_with_max_total(2));
+        for id in ["s1", "s2", "s3"] {
+            scheduler.register_stream(copy_stream(id));
+        }
+        scheduler.request_start("s2");
+        scheduler.on_process_started("s2", 42);
+        scheduler.request_start("s1");
+        scheduler.request_start("s3");
+
+        let snapshot = scheduler.snapshot();
+        assert_eq!(snapshot.capacity.total_streams, 2);
+        assert_eq!(
+            snapshot.queued.iter().map(|q| q.stream_id.as_str()).collect::<Vec<_>>(),
+            vec!["s3"]
+        );
+        let running: Vec<_> = snapshot
+            .running
+            .iter()
+            .map(|r| (r.id.as_str(), r.state, r.pid))
+            .collect();
+        assert_eq!(
+            running,
+            vec![("s1", StreamState::Starting, None), ("s2", StreamState::Running, Some(42))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stop_signals_slot_freed() {
+        let mut scheduler = Scheduler::new(&settings_with_max_total(2));
+        scheduler.register_stream(copy_stream("s1"));
+        scheduler.request_start("s1");
+        scheduler.on_process_started("s1", 1);
+
+        let notify = scheduler.slot_notifier();
+        scheduler.on_stream_stopped("s1");
+        tokio::time::timeout(std::time::Duration::from_secs(1), notify.notified())
+            .await
+            .expect("stop should signal a free slot");
+    }
+}
@@ -4,7 +4,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 /// A stream waiting in the queue
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,40 +16,50 @@ pub struct QueuedStream {
     pub queued_at: DateTime<Utc>,
 }
 
-impl PartialEq for QueuedStream {
-    fn eq(&self, other: &Self) -> bool {
-        self.stream_id == other.stream_id
-    }
+/// Sort key for a queued stream: pinned first, then priority (higher
+/// first), then FIFO (earlier first), with an insertion counter breaking
+/// exact timestamp ties
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct QueueKey {
+    pinned: bool,
+    priority: u8,
+    queued_at: DateTime<Utc>,
+    seq: u64,
 }
 
-impl Eq for QueuedStream {}
-
-impl Ord for QueuedStream {
+impl Ord for QueueKey {
     fn cmp(&self, other: &Self) -> Ordering {
-        // Pinned first, then priority (higher first), then FIFO (earlier first)
-        self.pinned
-            .cmp(&other.pinned)
-            .then_with(|| self.priority.cmp(&other.priority))
-            .then_with(|| other.queued_at.cmp(&self.queued_at))
+        other
+            .pinned
+            .cmp(&self.pinned)
+            .then_with(|| other.priority.cmp(&self.priority))
+            .then_with(|| self.queued_at.cmp(&other.queued_at))
+            .then_with(|| self.seq.cmp(&other.seq))
     }
 }
 
-impl PartialOrd for QueuedStream {
+impl PartialOrd for QueueKey {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-/// Queue manager for pending streams
+/// Queue manager for pending streams.
+/// Queued streams are kept sorted in a `BTreeMap` with a by-id index, so
+/// enqueue, dequeue, removal and reprioritizing are all O(log n).
 pub struct QueueManager {
-    queue: BinaryHeap<QueuedStream>,
+    queue: BTreeMap<QueueKey, QueuedStream>,
+    index: HashMap<String, QueueKey>,
+    next_seq: u64,
     running: HashSet<String>,
 }
 
 impl QueueManager {
     pub fn new() -> Self {
         Self {
-            queue: BinaryHeap::new(),
+            queue: BTreeMap::new(),
+            index: HashMap::new(),
+            next_seq: 0,
             running: HashSet::new(),
         }
     }
@@ -57,21 +67,34 @@ impl QueueManager {
     /// Add stream to queue
     pub fn enqueue(&mut self, stream: QueuedStream) {
         // Don't add if already running or queued
-        if !self.running.contains(&stream.stream_id)
-            && !self.queue.iter().any(|s| s.stream_id == stream.stream_id)
-        {
-            self.queue.push(stream);
+        if self.running.contains(&stream.stream_id) || self.index.contains_key(&stream.stream_id) {
+            return;
         }
+        self.insert(stream);
+    }
+
+    fn insert(&mut self, stream: QueuedStream) {
+        let key = QueueKey {
+            pinned: stream.pinned,
+            priority: stream.priority,
+            queued_at: stream.queued_at,
+            seq: self.next_seq,
+        };
+        self.next_seq += 1;
+        self.index.insert(stream.stream_id.clone(), key.clone());
+        self.queue.insert(key, stream);
     }
 
     /// Get next stream if limits allow
     pub fn peek(&self) -> Option<&QueuedStream> {
-        self.queue.peek()
+        self.queue.values().next()
     }
 
     /// Remove and return next stream
     pub fn dequeue(&mut self) -> Option<QueuedStream> {
-        self.queue.pop()
+        let (_, stream) = self.queue.pop_first()?;
+        self.index.remove(&stream.stream_id);
+        Some(stream)
     }
 
     /// Mark stream as running
@@ -84,16 +107,30 @@ impl QueueManager {
         self.running.remove(stream_id);
     }
 
-    /// Remove stream from queue (cancelled)
-    pub fn remove_from_queue(&mut self, stream_id: &str) {
-        let items: Vec<_> = self.queue.drain().collect();
-        for item in items {
-            if item.stream_id != stream_id {
-                self.queue.push(item);
+    /// Change a queued stream's priority and pin, moving it to its new place.
+    /// Returns false if the stream isn't queued.
+    pub fn update_priority(&mut self, stream_id: &str, priority: u8, pinned: bool) -> bool {
+        match self.take(stream_id) {
+            Some(mut stream) => {
+                stream.priority = priority;
+                stream.pinned = pinned;
+                self.insert(stream);
+                true
             }
+            None => false,
         }
     }
 
+    /// Remove stream from queue (cancelled)
+    pub fn remove_from_queue(&mut self, stream_id: &str) {
+        self.take(stream_id);
+    }
+
+    fn take(&mut self, stream_id: &str) -> Option<QueuedStream> {
+        let key = self.index.remove(stream_id)?;
+        self.queue.remove(&key)
+    }
+
     /// Get queue length
     pub fn queue_len(&self) -> usize {
         self.queue.len()
@@ -109,14 +146,14 @@ impl QueueManager {
         self.running.iter().cloned().collect()
     }
 
-    /// Get all queued streams (for UI display)
+    /// Get all queued streams in start order (for UI display)
     pub fn queued_streams(&self) -> Vec<QueuedStream> {
-        self.queue.iter().cloned().collect()
+        self.queue.values().cloned().collect()
     }
 
     /// Check if stream is queued
     pub fn is_queued(&self, stream_id: &str) -> bool {
-        self.queue.iter().any(|s| s.stream_id == stream_id)
+        self.index.contains_key(stream_id)
     }
 
     /// Check if stream is running
@@ -199,4 +236,70 @@ mod tests {
         assert_eq!(queue.dequeue().unwrap().stream_id, "first");
         assert_eq!(queue.dequeue().unwrap().stream_id, "second");
     }
+
+    #[test]
+    fn test_update_priority_reorders() {
+        let mut queue = QueueManager::new();
+        let now = Utc::now();
+        for (id, priority) in [("a", 50), ("b", 10)] {
+            queue.enqueue(QueuedStream {
+                stream_id: id.to_string(),
+                priority,
+                pinned: false,
+                mode: "copy".to_string(),
+                queued_at: now,
+            });
+        }
+
+        assert!(queue.update_priority("b", 90, false));
+        assert!(!queue.update_priority("missing", 90, false));
+        assert_eq!(queue.peek().unwrap().stream_id, "b");
+
+        assert!(queue.update_priority("a", 1, true));
+        assert_eq!(queue.dequeue().unwrap().stream_id, "a");
+    }
+
+    #[test]
+    fn test_same_timestamp_keeps_insertion_order() {
+        let mut queue = QueueManager::new();
+        let now = Utc::now();
+        for id in ["a", "b", "c"] {
+            queue.enqueue(QueuedStream {
+                stream_id: id.to_string(),
+                priority: 50,
+                pinned: false,
+                mode: "copy".to_string(),
+                queued_at: now,
+            });
+        }
+        queue.remove_from_queue("b");
+        assert!(!queue.is_queued("b"));
+        let order: Vec<_> = queue.queued_streams().into_iter().map(|s| s.stream_id).collect();
+        assert_eq!(order, vec!["a", "c"]);
+    }
+
+    #[test]
+    fn test_removal_scales() {
+        // Rebuilding the queue on every removal made this quadratic
+        // (~10^10 steps); it should take well under a second
+        const N: usize = 100_000;
+        let mut queue = QueueManager::new();
+        let now = Utc::now();
+        for i in 0..N {
+            queue.enqueue(QueuedStream {
+                stream_id: format!("s{}", i),
+                priority: (i % 256) as u8,
+                pinned: false,
+                mode: "copy".to_string(),
+                queued_at: now,
+            });
+        }
+
+        let started = std::time::Instant::now();
+        for i in 0..N {
+            queue.remove_from_queue(&format!("s{}", i));
+        }
+        assert_eq!(queue.queue_len(), 0);
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
 }
@@ -80,6 +80,8 @@ pub enum StateEvent {
     ErrorOccurred { message: String },
     /// User requested stop
     StopRequested,
+    /// Removed from the queue before it ever started
+    QueueCancelled,
 }
 
 /// State machine for a single stream
@@ -127,6 +129,12 @@ impl StreamStateMachine {
                 StreamState::Starting
             }
 
+            // Queued -> Stopped (cancelled before starting)
+            (StreamState::Queued, StateEvent::QueueCancelled) => {
+                self.last_error = None;
+                StreamState::Stopped
+            }
+
             // Running -> Stopped
             (StreamState::Running, StateEvent::ProcessStopped) => {
                 self.pid = None;
@@ -207,6 +215,22 @@ mod tests {
         assert_eq!(sm.pid, None);
     }
 
+    #[test]
+    fn test_cancel_only_from_queued() {
+        let mut sm = StreamStateMachine::new("test".to_string());
+
+        sm.apply(StateEvent::StartRequested).unwrap();
+        assert!(sm.apply(StateEvent::QueueCancelled).is_err());
+
+        sm.apply(StateEvent::EnqueuedForLimits {
+            reason: "CPU limit".to_string(),
+        })
+        .unwrap();
+        sm.apply(StateEvent::QueueCancelled).unwrap();
+        assert_eq!(sm.state, StreamState::Stopped);
+        assert_eq!(sm.last_error, None);
+    }
+
     #[test]
     fn test_error_recovery() {
         let mut sm = StreamStateMachine::new("test".to_string());
@@ -1,11 +1,14 @@
 //! Stream authentication manager
 //! Generates and validates stream credentials
 
+use crate::sidecar::mediamtx::PortConfig;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqlitePool;
+use sqlx::FromRow;
 
 /// Stream authentication credentials
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
 pub struct StreamAuth {
     pub stream_id: String,
     pub username: String,
@@ -13,6 +16,14 @@ pub struct StreamAuth {
     pub srt_passphrase: Option<String>,
 }
 
+/// SRT socket options a profile can set on its publish URL
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SrtTuning {
+    pub latency_ms: Option<u32>,
+    /// Recovery bandwidth overhead over the stream bitrate
+    pub overhead_percent: Option<u32>,
+}
+
 /// Generate random alphanumeric string
 fn random_string(len: usize) -> String {
     const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
@@ -42,6 +53,41 @@ pub fn generate_credentials(stream_id: &str) -> StreamAuth {
     }
 }
 
+/// Stored credentials for a stream, generating and persisting them on first use
+/// so FFmpeg, MediaMTX and readers all agree on the same values
+pub async fn get_or_create_credentials(
+    pool: &SqlitePool,
+    stream_id: &str,
+) -> Result<StreamAuth, sqlx::Error> {
+    if let Some(auth) = load_credentials(pool, stream_id).await? {
+        return Ok(auth);
+    }
+
+    let auth = generate_credentials(stream_id);
+    // A concurrent caller may have stored credentials first; theirs win
+    sqlx::query(
+        "INSERT OR IGNORE INTO stream_auth (stream_id, username, password, srt_passphrase) VALUES (?, ?, ?, ?)",
+    )
+    .bind(&auth.stream_id)
+    .bind(&auth.username)
+    .bind(&auth.password)
+    .bind(&auth.srt_passphrase)
+    .execute(pool)
+    .await?;
+
+    load_credentials(pool, stream_id)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)
+}
+
+/// Stored credentials for a stream, without creating any
+pub async fn load_credentials(pool: &SqlitePool, stream_id: &str) -> Result<Option<StreamAuth>, sqlx::Error> {
+    sqlx::query_as("SELECT stream_id, username, password, srt_passphrase FROM stream_auth WHERE stream_id = ?")
+        .bind(stream_id)
+        .fetch_optional(pool)
+        .await
+}
+
 /// Build RTSP URL with embedded credentials
 pub fn build_rtsp_url(
     host: &str,
@@ -65,12 +111,21 @@ pub fn build_srt_url(
     stream_name: &str,
     mode: &str, // "publish" or "read"
     auth: Option<&StreamAuth>,
+    tuning: SrtTuning,
 ) -> String {
     let mut url = format!(
         "srt://{}:{}?streamid={}:{}&pkt_size=1316",
         host, port, mode, stream_name
     );
 
+    // FFmpeg takes the latency in microseconds
+    if let Some(latency_ms) = tuning.latency_ms {
+        url.push_str(&format!("&latency={}", latency_ms as u64 * 1000));
+    }
+    if let Some(overhead) = tuning.overhead_percent {
+        url.push_str(&format!("&oheadbw={}", overhead));
+    }
+
     if let Some(a) = auth {
         if let Some(ref passphrase) = a.srt_passphrase {
             url.push_str(&format!("&passphrase={}&pbkeylen=32", passphrase));
@@ -97,19 +152,43 @@ pub fn build_rtmp_url(
     }
 }
 
+/// Build HLS playlist URL with optional basic-auth credentials
+pub fn build_hls_url(host: &str, port: u16, stream_name: &str, auth: Option<&StreamAuth>) -> String {
+    match auth {
+        Some(a) => format!(
+            "http://{}:{}@{}:{}/{}/index.m3u8",
+            a.username, a.password, host, port, stream_name
+        ),
+        None => format!("http://{}:{}/{}/index.m3u8", host, port, stream_name),
+    }
+}
+
+/// Build WebRTC (WHEP) endpoint URL with optional basic-auth credentials
+pub fn build_whep_url(host: &str, port: u16, stream_name: &str, auth: Option<&StreamAuth>) -> String {
+    match auth {
+        Some(a) => format!(
+            "http://{}:{}@{}:{}/{}/whep",
+            a.username, a.password, host, port, stream_name
+        ),
+        None => format!("http://{}:{}/{}/whep", host, port, stream_name),
+    }
+}
+
 /// Build publisher URL (for FFmpeg to push to MediaMTX)
 pub fn build_publish_url(
     protocol: &str,
     stream_name: &str,
     auth: Option<&StreamAuth>,
     wan_mode: bool,
+    srt: SrtTuning,
+    ports: &PortConfig,
 ) -> String {
     let host = if wan_mode { "0.0.0.0" } else { "localhost" };
 
     match protocol {
-        "srt" => build_srt_url(host, 8890, stream_name, "publish", auth),
-        "rtmp" => build_rtmp_url(host, 1935, "live", stream_name, auth),
-        _ => build_rtsp_url(host, 8554, stream_name, auth),
+        "srt" => build_srt_url(host, ports.srt, stream_name, "publish", auth, srt),
+        "rtmp" => build_rtmp_url(host, ports.rtmp, "live", stream_name, auth),
+        _ => build_rtsp_url(host, ports.rtsp, stream_name, auth),
     }
 }
 
@@ -119,11 +198,14 @@ pub fn build_reader_url(
     stream_name: &str,
     auth: Option<&StreamAuth>,
     host: &str,
+    ports: &PortConfig,
 ) -> String {
     match protocol {
-        "srt" => build_srt_url(host, 8890, stream_name, "read", auth),
-        "rtmp" => build_rtmp_url(host, 1935, "live", stream_name, auth),
-        _ => build_rtsp_url(host, 8554, stream_name, auth),
+        "srt" => build_srt_url(host, ports.srt, stream_name, "read", auth, SrtTuning::default()),
+        "rtmp" => build_rtmp_url(host, ports.rtmp, "live", stream_name, auth),
+        "hls" => build_hls_url(host, ports.hls, stream_name, auth),
+        "webrtc" => build_whep_url(host, ports.webrtc, stream_name, auth),
+        _ => build_rtsp_url(host, ports.rtsp, stream_name, auth),
     }
 }
 
@@ -140,6 +222,26 @@ mod tests {
         assert_eq!(creds.srt_passphrase.unwrap().len(), 24);
     }
 
+    #[tokio::test]
+    async fn test_credentials_persisted() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query("INSERT INTO streams (id, name) VALUES ('stream-1', 'cam1')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let first = get_or_create_credentials(&pool, "stream-1").await.unwrap();
+        let second = get_or_create_credentials(&pool, "stream-1").await.unwrap();
+        assert_eq!(first, second);
+
+        // Deleting the stream drops its credentials
+        sqlx::query("DELETE FROM streams WHERE id = 'stream-1'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(load_credentials(&pool, "stream-1").await.unwrap().is_none());
+    }
+
     #[test]
     fn test_build_rtsp_url() {
         let auth = StreamAuth {
@@ -165,21 +267,60 @@ mod tests {
             srt_passphrase: Some("mysecretpassphrase123".into()),
         };
 
-        let url = build_srt_url("192.168.1.1", 8890, "stream1", "read", Some(&auth));
+        let url = build_srt_url("192.168.1.1", 8890, "stream1", "read", Some(&auth), SrtTuning::default());
         assert!(url.contains("passphrase=mysecretpassphrase123"));
         assert!(url.contains("pbkeylen=32"));
         assert!(url.contains("streamid=read:stream1"));
+        assert!(!url.contains("latency="));
+    }
+
+    #[test]
+    fn test_build_srt_url_with_tuning() {
+        let tuning = SrtTuning {
+            latency_ms: Some(1200),
+            overhead_percent: Some(30),
+        };
+        let url = build_publish_url("srt", "stream1", None, true, tuning, &PortConfig::default());
+        assert_eq!(
+            url,
+            "srt://0.0.0.0:8890?streamid=publish:stream1&pkt_size=1316&latency=1200000&oheadbw=30"
+        );
     }
 
     #[test]
     fn test_build_publish_url() {
-        let url = build_publish_url("rtsp", "stream1", None, false);
+        let url = build_publish_url("rtsp", "stream1", None, false, SrtTuning::default(), &PortConfig::default());
         assert!(url.contains("localhost"));
 
-        let url_wan = build_publish_url("srt", "stream1", None, true);
+        let url_wan = build_publish_url("srt", "stream1", None, true, SrtTuning::default(), &PortConfig::default());
         assert!(url_wan.contains("0.0.0.0"));
 
-        let url_rtmp = build_publish_url("rtmp", "stream1", None, false);
+        let url_rtmp = build_publish_url("rtmp", "stream1", None, false, SrtTuning::default(), &PortConfig::default());
         assert!(url_rtmp.contains("rtmp://localhost:1935/live/stream1"));
     }
+
+    #[test]
+    fn test_build_hls_reader_url() {
+        let url = build_reader_url("hls", "stream1", None, "10.0.0.5", &PortConfig::default());
+        assert_eq!(url, "http://10.0.0.5:8888/stream1/index.m3u8");
+    }
+
+    #[test]
+    fn test_build_webrtc_reader_url() {
+        let url = build_reader_url("webrtc", "stream1", None, "10.0.0.5", &PortConfig::default());
+        assert_eq!(url, "http://10.0.0.5:8889/stream1/whep");
+    }
+
+    #[test]
+    fn test_urls_follow_configured_ports() {
+        let ports = PortConfig {
+            rtsp: 9554,
+            srt: 9890,
+            ..Default::default()
+        };
+        let url = build_publish_url("rtsp", "stream1", None, false, SrtTuning::default(), &ports);
+        assert_eq!(url, "rtsp://localhost:9554/stream1");
+        let url = build_reader_url("srt", "stream1", None, "10.0.0.5", &ports);
+        assert!(url.starts_with("srt://10.0.0.5:9890?"));
+    }
 }
@@ -3,4 +3,4 @@
 
 pub mod auth;
 
-pub use auth::{generate_credentials, StreamAuth};
+pub use auth::{get_or_create_credentials, load_credentials, StreamAuth};
@@ -2,17 +2,158 @@
 //! Handles starting, stopping, and health checking the MediaMTX server
 
 use super::SidecarError;
+use crate::db::schema::{AppSettings, Profile};
 use crate::security::auth::StreamAuth;
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::net::{TcpListener, UdpSocket};
 use std::path::PathBuf;
 use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Path changes are local calls; anything slower means the API is gone
+const API_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long `start` waits for the API to answer before giving up
+const READY_TIMEOUT: Duration = Duration::from_secs(10);
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Server SRT latency for WAN paths whose profile doesn't set one
+pub const WAN_SRT_LATENCY_MS: u32 = 2000;
+
+/// Lines of MediaMTX output kept for error reports
+const OUTPUT_TAIL_LINES: usize = 50;
+
+type OutputTail = Arc<Mutex<VecDeque<String>>>;
+
+/// Crash restarts back off from this delay, doubling each attempt
+const RESTART_BASE_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTARTS: u32 = 5;
+
+/// A server that stays up this long after a restart earns a fresh budget
+const RESTART_RESET_AFTER: Duration = Duration::from_secs(60);
+
+/// Ports MediaMTX listens on, written into the generated config
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PortConfig {
+    pub rtsp: u16,
+    /// UDP pair RTSP readers get media (RTP) and reports (RTCP) on
+    pub rtp: u16,
+    pub rtcp: u16,
+    pub rtmp: u16,
+    pub srt: u16,
+    pub hls: u16,
+    pub webrtc: u16,
+    /// UDP port WebRTC media flows through
+    pub webrtc_udp: u16,
+    /// Control API, bound to localhost only
+    pub api: u16,
+}
+
+impl PortConfig {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            rtsp: settings.rtsp_port,
+            rtp: settings.rtp_port,
+            rtcp: settings.rtcp_port,
+            rtmp: settings.rtmp_port,
+            srt: settings.srt_port,
+            hls: settings.hls_port,
+            webrtc: settings.webrtc_port,
+            webrtc_udp: settings.webrtc_udp_port,
+            api: settings.api_port,
+        }
+    }
+
+    /// Every port must be set and belong to one listener only
+    pub fn validate(&self) -> Result<(), SidecarError> {
+        let ports = [
+            ("RTSP", self.rtsp),
+            ("RTP", self.rtp),
+            ("RTCP", self.rtcp),
+            ("RTMP", self.rtmp),
+            ("SRT", self.srt),
+            ("HLS", self.hls),
+            ("WebRTC", self.webrtc),
+            ("WebRTC UDP", self.webrtc_udp),
+            ("API", self.api),
+        ];
+        for (i, (label, port)) in ports.iter().enumerate() {
+            if *port == 0 {
+                return Err(SidecarError::ConfigError(format!("{} port can't be 0", label)));
+            }
+            if let Some((other, _)) = ports[..i].iter().find(|(_, p)| p == port) {
+                return Err(SidecarError::ConfigError(format!(
+                    "{} and {} both use port {}",
+                    other, label, port
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for PortConfig {
+    fn default() -> Self {
+        Self {
+            rtsp: 8554,
+            rtp: 8000,
+            rtcp: 8001,
+            rtmp: 1935,
+            srt: 8890,
+            hls: 8888,
+            webrtc: 8889,
+            webrtc_udp: 8189,
+            api: 9997,
+        }
+    }
+}
 
 /// Stream configuration for MediaMTX
+#[derive(Debug, Clone)]
 pub struct StreamConfig {
     pub name: String,
     pub auth: Option<StreamAuth>,
 }
 
+/// Browser-facing outputs MediaMTX can serve next to RTSP/RTMP/SRT
+#[derive(Debug, Clone, Default)]
+pub struct DeliveryOptions {
+    /// Serve HLS (on :8888 by default)
+    pub enable_hls: bool,
+    /// Serve WebRTC (WHEP, on :8889 by default)
+    pub enable_webrtc: bool,
+    /// STUN/TURN servers handed to WebRTC readers
+    pub webrtc_ice_servers: Vec<IceServer>,
+}
+
+/// A STUN or TURN server; TURN servers usually need credentials
+#[derive(Debug, Clone, PartialEq)]
+pub struct IceServer {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Parse ICE servers from the settings form: one per line (or comma
+/// separated), as `url` or `url username password`
+pub fn parse_ice_servers(spec: &str) -> Vec<IceServer> {
+    spec.split([',', '\n'])
+        .filter_map(|entry| {
+            let mut parts = entry.split_whitespace();
+            let url = parts.next()?.to_string();
+            Some(IceServer {
+                url,
+                username: parts.next().map(str::to_string),
+                password: parts.next().map(str::to_string),
+            })
+        })
+        .collect()
+}
+
 /// Default MediaMTX configuration template
 const MEDIAMTX_CONFIG_TEMPLATE: &str = r#"
 # MediaMTX configuration for C-Video
@@ -57,15 +198,243 @@ paths:
 /// Manager for MediaMTX server lifecycle
 pub struct MediaMTXManager {
     process: Option<Child>,
-    config_path: Option<PathBuf>,
+    /// Config file owned by this instance, so side-by-side app copies (or a
+    /// crashed run's leftover) never clobber each other
+    config_path: PathBuf,
+    /// Per-stream path settings, keyed by stream name, with the SRT latency
+    /// each one asked for
+    paths: BTreeMap<String, (StreamConfig, Option<u32>)>,
+    delivery: DeliveryOptions,
+    ports: PortConfig,
+    api_base: String,
+    client: reqwest::Client,
+    /// Recent stdout/stderr lines of the running server
+    output: OutputTail,
+    output_readers: Vec<JoinHandle<()>>,
+    /// Started and not deliberately stopped, so an exit is a crash
+    should_run: bool,
+    restart_attempts: u32,
+    last_restart: Option<Instant>,
+}
+
+/// Why a control API call didn't apply
+enum ApiFailure {
+    /// Nothing answered; the config file is the only way in
+    Unreachable(String),
+    /// The server answered but refused the change
+    Rejected(String),
 }
 
 impl MediaMTXManager {
     pub fn new() -> Self {
+        let ports = PortConfig::default();
         Self {
             process: None,
-            config_path: None,
+            config_path: std::env::temp_dir().join(format!(
+                "cvideo-mediamtx-{}-{}.yml",
+                std::process::id(),
+                &uuid::Uuid::new_v4().simple().to_string()[..8]
+            )),
+            paths: BTreeMap::new(),
+            delivery: DeliveryOptions::default(),
+            ports,
+            api_base: format!("http://127.0.0.1:{}", ports.api),
+            client: reqwest::Client::builder()
+                .timeout(API_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            output: Arc::new(Mutex::new(VecDeque::new())),
+            output_readers: Vec::new(),
+            should_run: false,
+            restart_attempts: 0,
+            last_restart: None,
+        }
+    }
+
+    /// Choose the extra outputs written into the config; applies from the
+    /// next (re)start
+    pub fn set_delivery_options(&mut self, delivery: DeliveryOptions) {
+        self.delivery = delivery;
+    }
+
+    /// Choose the listener ports; applies from the next (re)start
+    pub fn set_ports(&mut self, ports: PortConfig) {
+        self.ports = ports;
+        self.api_base = format!("http://127.0.0.1:{}", ports.api);
+    }
+
+    /// Ports the server listens on, or will once started
+    pub fn ports(&self) -> PortConfig {
+        self.ports
+    }
+
+    /// Number of stream paths that are configured on the server
+    pub fn path_count(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Restarts attempted since the server last stayed up
+    pub fn restart_attempts(&self) -> u32 {
+        self.restart_attempts
+    }
+
+    /// Configure a stream's path (e.g. publish credentials) through the
+    /// control API, so other live paths are left untouched. Falls back to
+    /// rewriting the config file when the API can't be reached. A path that
+    /// raises the server's SRT latency has the new value applied live too.
+    pub async fn add_path(
+        &mut self,
+        name: &str,
+        auth: Option<StreamAuth>,
+        srt_latency_ms: Option<u32>,
+    ) -> Result<(), SidecarError> {
+        check_path_name(name)?;
+        let config = StreamConfig {
+            name: name.to_string(),
+            auth,
+        };
+        let body = path_body(&config);
+        let previous_latency = self.srt_latency_ms();
+        self.paths.insert(name.to_string(), (config, srt_latency_ms));
+
+        match self.api_add_path(name, &body).await {
+            Ok(()) => {
+                let latency = self.srt_latency_ms();
+                if let Some(ms) = latency.filter(|_| latency != previous_latency) {
+                    self.api_set_srt_latency(ms).await;
+                }
+                Ok(())
+            }
+            Err(ApiFailure::Unreachable(e)) => {
+                println!("[MediaMTX] API unreachable ({}), rewriting config for {}", e, name);
+                self.write_config()?;
+                Ok(())
+            }
+            Err(ApiFailure::Rejected(e)) => Err(SidecarError::ApiError(e)),
+        }
+    }
+
+    /// Drop a stream's path settings once it stops
+    pub async fn remove_path(&mut self, name: &str) -> Result<(), SidecarError> {
+        if self.paths.remove(name).is_none() {
+            return Ok(());
+        }
+
+        let url = format!("{}/v3/config/paths/delete/{}", self.api_base, name);
+        match self.api_call(self.client.delete(&url)).await {
+            Ok(()) => Ok(()),
+            Err(ApiFailure::Unreachable(e)) => {
+                println!("[MediaMTX] API unreachable ({}), rewriting config without {}", e, name);
+                self.write_config()?;
+                Ok(())
+            }
+            Err(ApiFailure::Rejected(e)) => Err(SidecarError::ApiError(e)),
+        }
+    }
+
+    /// Whether a publisher is live on the path, per the control API. A path
+    /// the server doesn't know has no publisher.
+    pub async fn is_publishing(&self, name: &str) -> Result<bool, SidecarError> {
+        check_path_name(name)?;
+        let url = format!("{}/v3/paths/get/{}", self.api_base, name);
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| SidecarError::ApiError(e.to_string()))?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        if !status.is_success() {
+            return Err(SidecarError::ApiError(status.to_string()));
+        }
+        let path: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| SidecarError::ApiError(e.to_string()))?;
+        Ok(path["ready"].as_bool().unwrap_or(false))
+    }
+
+    /// Names of every path with a live publisher, from one pass over the
+    /// control API's path list
+    pub async fn publishing_paths(&self) -> Result<HashSet<String>, SidecarError> {
+        let mut names = HashSet::new();
+        let mut page = 0;
+        loop {
+            let url = format!("{}/v3/paths/list?itemsPerPage=1000&page={}", self.api_base, page);
+            let response = self
+                .client
+                .get(&url)
+                .send()
+                .await
+                .map_err(|e| SidecarError::ApiError(e.to_string()))?;
+            if !response.status().is_success() {
+                return Err(SidecarError::ApiError(response.status().to_string()));
+            }
+            let list: serde_json::Value = response
+                .json()
+                .await
+                .map_err(|e| SidecarError::ApiError(e.to_string()))?;
+
+            let items = list["items"].as_array().map(Vec::as_slice).unwrap_or_default();
+            names.extend(
+                items
+                    .iter()
+                    .filter(|path| path["ready"].as_bool().unwrap_or(false))
+                    .filter_map(|path| path["name"].as_str().map(str::to_string)),
+            );
+
+            page += 1;
+            if page >= list["pageCount"].as_u64().unwrap_or(0) {
+                return Ok(names);
+            }
+        }
+    }
+
+    /// Change the server-wide SRT latency on the running server. A refusal
+    /// only costs latency, so it is logged rather than failing the stream.
+    async fn api_set_srt_latency(&self, ms: u32) {
+        let url = format!("{}/v3/config/global/patch", self.api_base);
+        let body = serde_json::json!({ "srtLatency": format!("{}ms", ms) });
+        match self.api_call(self.client.patch(&url).json(&body)).await {
+            Ok(()) => println!("[MediaMTX] SRT latency set to {}ms", ms),
+            Err(ApiFailure::Unreachable(e) | ApiFailure::Rejected(e)) => {
+                eprintln!("[MediaMTX] Failed to set SRT latency to {}ms: {}", ms, e);
+            }
+        }
+    }
+
+    /// Create the path, or patch it if a previous run left it configured
+    async fn api_add_path(&self, name: &str, body: &serde_json::Value) -> Result<(), ApiFailure> {
+        let add = format!("{}/v3/config/paths/add/{}", self.api_base, name);
+        match self.api_call(self.client.post(&add).json(body)).await {
+            Err(ApiFailure::Rejected(_)) => {
+                let patch = format!("{}/v3/config/paths/patch/{}", self.api_base, name);
+                self.api_call(self.client.patch(&patch).json(body)).await
+            }
+            result => result,
+        }
+    }
+
+    async fn api_call(&self, request: reqwest::RequestBuilder) -> Result<(), ApiFailure> {
+        let response = request.send().await.map_err(|e| {
+            if e.is_connect() || e.is_timeout() {
+                ApiFailure::Unreachable(e.to_string())
+            } else {
+                ApiFailure::Rejected(e.to_string())
+            }
+        })?;
+
+        let status = response.status();
+        // Deleting a path the server never had is as good as deleting it
+        if status.is_success() || status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(());
         }
+        let text = response.text().await.unwrap_or_default();
+        Err(ApiFailure::Rejected(format!("{}: {}", status, text.trim())))
     }
 
     /// Start the MediaMTX server
@@ -74,9 +443,21 @@ impl MediaMTXManager {
             return Err(SidecarError::AlreadyRunning);
         }
 
+        // Another app instance's server would answer our readiness check
+        // and leave us publishing into its paths
+        if self.api_answers(&format!("{}/v3/config/global/get", self.api_base)).await {
+            return Err(SidecarError::Conflict(format!(
+                "another MediaMTX is already serving {}",
+                self.api_base
+            )));
+        }
+
+        // MediaMTX only reports a clash once it tries to bind, and then
+        // with a log line rather than an error we can show
+        check_ports_available(&self.ports, &self.delivery)?;
+
         // Write config to temp location
         let config_path = self.write_config()?;
-        self.config_path = Some(config_path.clone());
 
         // Get sidecar path - for development, try system mediamtx first
         let mediamtx_path = self.find_mediamtx_binary()?;
@@ -91,28 +472,147 @@ impl MediaMTXManager {
             .spawn()
             .map_err(|e| SidecarError::SpawnError(format!("{}: {:?}", e, mediamtx_path)))?;
 
-        self.process = Some(child);
+        self.attach(child);
 
-        // Wait a bit for startup
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        // A port clash only shows up once MediaMTX tries to bind
+        if let Err(e) = self.wait_ready(READY_TIMEOUT).await {
+            eprintln!("[MediaMTX] {}", e);
+            let _ = self.stop().await;
+            return Err(e);
+        }
 
+        self.should_run = true;
         println!("[MediaMTX] Server started successfully");
         Ok(())
     }
 
+    /// True when the server should be running but isn't. Logs the output
+    /// tail the first time a crash is noticed.
+    pub fn needs_restart(&mut self) -> bool {
+        if !self.should_run {
+            return false;
+        }
+
+        if let Some(process) = self.process.as_mut() {
+            match process.try_wait() {
+                Ok(None) => return false,
+                Ok(Some(status)) => eprintln!("[MediaMTX] Exited unexpectedly with {}", status),
+                Err(e) => eprintln!("[MediaMTX] Lost track of server process: {}", e),
+            }
+            self.process = None;
+            let tail = self.output_tail();
+            if !tail.is_empty() {
+                eprintln!("[MediaMTX] Last output:\n{}", tail);
+            }
+        }
+        true
+    }
+
+    /// Backoff before the next crash restart, or None once the restart
+    /// budget is spent (the server is then left stopped)
+    pub fn next_restart_delay(&mut self) -> Option<Duration> {
+        if self.last_restart.is_some_and(|t| t.elapsed() >= RESTART_RESET_AFTER) {
+            self.restart_attempts = 0;
+        }
+
+        if self.restart_attempts >= MAX_RESTARTS {
+            self.should_run = false;
+            self.restart_attempts = 0;
+            return None;
+        }
+
+        let delay = RESTART_BASE_DELAY * 2u32.pow(self.restart_attempts);
+        self.restart_attempts += 1;
+        self.last_restart = Some(Instant::now());
+        Some(delay)
+    }
+
+    /// Relaunch after a crash. The config is regenerated from the current
+    /// state, so live stream paths come back with it.
+    pub async fn restart(&mut self) -> Result<(), SidecarError> {
+        let result = self.start().await;
+        // A failed attempt leaves the next one to the monitor
+        self.should_run = true;
+        result
+    }
+
+    /// Take ownership of a spawned server, draining its output into the tail
+    /// buffer so the pipes never fill up
+    fn attach(&mut self, mut child: Child) {
+        self.output.lock().unwrap().clear();
+        if let Some(stdout) = child.stdout.take() {
+            self.output_readers.push(capture_output(stdout, self.output.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            self.output_readers.push(capture_output(stderr, self.output.clone()));
+        }
+        self.process = Some(child);
+    }
+
+    /// Poll the control API until it answers, failing early if the server
+    /// exits (e.g. a port is already in use)
+    pub async fn wait_ready(&mut self, timeout: Duration) -> Result<(), SidecarError> {
+        let url = format!("{}/v3/config/global/get", self.api_base);
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let Some(process) = self.process.as_mut() else {
+                return Err(SidecarError::NotRunning);
+            };
+            if let Ok(Some(status)) = process.try_wait() {
+                self.process = None;
+                return Err(SidecarError::NotReady(format!(
+                    "exited with {}\n{}",
+                    status,
+                    self.output_tail()
+                )));
+            }
+
+            if self.api_answers(&url).await {
+                return Ok(());
+            }
+
+            if Instant::now() >= deadline {
+                return Err(SidecarError::NotReady(format!(
+                    "API not answering after {}s\n{}",
+                    timeout.as_secs(),
+                    self.output_tail()
+                )));
+            }
+            tokio::time::sleep(READY_POLL_INTERVAL).await;
+        }
+    }
+
+    async fn api_answers(&self, url: &str) -> bool {
+        matches!(self.client.get(url).send().await, Ok(r) if r.status().is_success())
+    }
+
+    /// Captured output of the last server run, once its pipes have closed
+    fn output_tail(&mut self) -> String {
+        if self.process.is_none() {
+            for reader in self.output_readers.drain(..) {
+                let _ = reader.join();
+            }
+        }
+        let tail = self.output.lock().unwrap();
+        tail.iter().cloned().collect::<Vec<_>>().join("\n")
+    }
+
     /// Stop the MediaMTX server
     pub async fn stop(&mut self) -> Result<(), SidecarError> {
+        self.should_run = false;
         if let Some(mut process) = self.process.take() {
             println!("[MediaMTX] Stopping server...");
             process.kill().map_err(|e| SidecarError::IoError(e))?;
             process.wait().map_err(|e| SidecarError::IoError(e))?;
             println!("[MediaMTX] Server stopped");
         }
+        for reader in self.output_readers.drain(..) {
+            let _ = reader.join();
+        }
 
         // Cleanup config file
-        if let Some(config_path) = self.config_path.take() {
-            let _ = fs::remove_file(config_path);
-        }
+        let _ = fs::remove_file(&self.config_path);
 
         Ok(())
     }
@@ -134,61 +634,43 @@ impl MediaMTXManager {
         }
     }
 
-    /// Write MediaMTX config to temp file
+    /// Write MediaMTX config, including every configured stream path, to this
+    /// instance's config file
     fn write_config(&self) -> Result<PathBuf, SidecarError> {
-        self.write_config_with_streams(&[], false)
+        let streams: Vec<StreamConfig> = self.paths.values().map(|(c, _)| c.clone()).collect();
+        self.write_config_with_streams(&streams, self.srt_latency_ms())
+    }
+
+    /// SRT latency the server should run with. It is one server-wide
+    /// setting, so the most patient path wins.
+    fn srt_latency_ms(&self) -> Option<u32> {
+        self.paths.values().filter_map(|(_, latency)| *latency).max()
     }
 
     /// Write MediaMTX config with stream auth
     pub fn write_config_with_streams(
         &self,
         streams: &[StreamConfig],
-        wan_mode: bool,
+        srt_latency_ms: Option<u32>,
     ) -> Result<PathBuf, SidecarError> {
-        let temp_dir = std::env::temp_dir();
-        let config_path = temp_dir.join("cvideo-mediamtx.yml");
-
-        let config = generate_mediamtx_config(streams, wan_mode);
-        fs::write(&config_path, config).map_err(|e| SidecarError::ConfigError(e.to_string()))?;
+        for stream in streams {
+            check_path_name(&stream.name)?;
+        }
+        let config = generate_mediamtx_config(streams, srt_latency_ms, &self.delivery, &self.ports);
+        fs::write(&self.config_path, config)
+            .map_err(|e| SidecarError::ConfigError(e.to_string()))?;
 
-        Ok(config_path)
+        Ok(self.config_path.clone())
     }
 
-    /// Find MediaMTX binary - check bundled first, then system
+    /// Find MediaMTX binary - check system first, then bundled
     fn find_mediamtx_binary(&self) -> Result<PathBuf, SidecarError> {
-        // For development: try system mediamtx first
-        if let Ok(output) = Command::new("which").arg("mediamtx").output() {
-            if output.status.success() {
-                let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                if !path.is_empty() {
-                    return Ok(PathBuf::from(path));
-                }
-            }
-        }
-
-        // Check common locations
-        let common_paths = [
-            "/opt/homebrew/bin/mediamtx",
-            "/usr/local/bin/mediamtx",
-            "/usr/bin/mediamtx",
-        ];
-
-        for path in common_paths {
-            let p = PathBuf::from(path);
-            if p.exists() {
-                return Ok(p);
-            }
-        }
-
-        // Bundled sidecar path (for production)
-        let sidecar_path = super::get_sidecar_path("mediamtx");
-        if sidecar_path.exists() {
-            return Ok(sidecar_path);
-        }
-
-        Err(SidecarError::SpawnError(
-            "MediaMTX binary not found. Install via: brew install mediamtx".to_string(),
-        ))
+        // For development the system install wins; bundled sidecar is the fallback
+        super::find_binary("mediamtx").ok_or_else(|| {
+            SidecarError::SpawnError(
+                "MediaMTX binary not found. Install via: brew install mediamtx".to_string(),
+            )
+        })
     }
 }
 
@@ -199,15 +681,113 @@ impl Drop for MediaMTXManager {
             let _ = process.kill();
         }
         // Cleanup config
-        if let Some(config_path) = self.config_path.take() {
-            let _ = fs::remove_file(config_path);
+        let _ = fs::remove_file(&self.config_path);
+    }
+}
+
+/// Server SRT latency for a WAN path published with `profile`
+pub fn wan_srt_latency_ms(profile: &Profile) -> u32 {
+    profile.srt_latency_ms.map_or(WAN_SRT_LATENCY_MS, |ms| ms as u32)
+}
+
+/// Fail with a readable error when another program holds one of the ports
+/// the server is about to listen on
+fn check_ports_available(ports: &PortConfig, delivery: &DeliveryOptions) -> Result<(), SidecarError> {
+    let mut listeners = vec![
+        ("RTSP", "0.0.0.0", ports.rtsp, false),
+        ("RTP", "0.0.0.0", ports.rtp, true),
+        ("RTCP", "0.0.0.0", ports.rtcp, true),
+        ("RTMP", "0.0.0.0", ports.rtmp, false),
+        ("SRT", "0.0.0.0", ports.srt, true),
+        ("API", "127.0.0.1", ports.api, false),
+    ];
+    if delivery.enable_hls {
+        listeners.push(("HLS", "0.0.0.0", ports.hls, false));
+    }
+    if delivery.enable_webrtc {
+        listeners.push(("WebRTC", "0.0.0.0", ports.webrtc, false));
+        listeners.push(("WebRTC UDP", "0.0.0.0", ports.webrtc_udp, true));
+    }
+
+    for (label, host, port, udp) in listeners {
+        let bound = if udp {
+            UdpSocket::bind((host, port)).map(drop)
+        } else {
+            TcpListener::bind((host, port)).map(drop)
+        };
+        if matches!(bound, Err(ref e) if e.kind() == std::io::ErrorKind::AddrInUse) {
+            let owner = port_owner(port, udp).map(|o| format!(" by {}", o)).unwrap_or_default();
+            return Err(SidecarError::ConfigError(format!("{} port {} in use{}", label, port, owner)));
         }
     }
+    Ok(())
+}
+
+/// Program holding `port`, as "name (pid N)", where lsof is there to ask
+fn port_owner(port: u16, udp: bool) -> Option<String> {
+    let protocol = if udp { "UDP" } else { "TCP" };
+    let output = Command::new("lsof")
+        .args(["-nP", "-t", &format!("-i{}:{}", protocol, port)])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let pid: u32 = String::from_utf8_lossy(&output.stdout).lines().next()?.trim().parse().ok()?;
+
+    let pid = sysinfo::Pid::from_u32(pid);
+    let mut sys = sysinfo::System::new();
+    sys.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[pid]), true);
+    let name = sys.process(pid)?.name().to_string_lossy().to_string();
+    Some(format!("{} (pid {})", name, pid))
+}
+
+/// Path names go into YAML keys and API URLs unescaped
+fn check_path_name(name: &str) -> Result<(), SidecarError> {
+    crate::stream::validate_name(name).map_err(|e| SidecarError::ConfigError(e.to_string()))
+}
+
+/// Keep the last `OUTPUT_TAIL_LINES` lines read from a server pipe
+fn capture_output<R: Read + Send + 'static>(reader: R, tail: OutputTail) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        for line in BufReader::new(reader).lines().map_while(Result::ok) {
+            let mut tail = tail.lock().unwrap();
+            if tail.len() == OUTPUT_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+    })
+}
+
+/// Path settings in the shape the control API expects
+fn path_body(stream: &StreamConfig) -> serde_json::Value {
+    let mut body = serde_json::json!({ "source": "publisher" });
+    if let Some(ref auth) = stream.auth {
+        body["publishUser"] = auth.username.clone().into();
+        body["publishPass"] = auth.password.clone().into();
+        body["readUser"] = auth.username.clone().into();
+        body["readPass"] = auth.password.clone().into();
+        if let Some(ref passphrase) = auth.srt_passphrase {
+            body["srtPublishPassphrase"] = passphrase.clone().into();
+            body["srtReadPassphrase"] = passphrase.clone().into();
+        }
+    }
+    body
+}
+
+/// A user-supplied value as a double-quoted YAML scalar; JSON string
+/// escaping is valid YAML, so characters like `:` or `#` stay literal
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
 }
 
 /// Generate MediaMTX configuration with optional auth
-pub fn generate_mediamtx_config(streams: &[StreamConfig], wan_mode: bool) -> String {
-    let mut config = String::from(
+pub fn generate_mediamtx_config(
+    streams: &[StreamConfig],
+    srt_latency_ms: Option<u32>,
+    delivery: &DeliveryOptions,
+    ports: &PortConfig,
+) -> String {
+    let mut config = format!(
         r#"# MediaMTX configuration for C-Video
 # Generated automatically - do not edit manually
 
@@ -216,40 +796,90 @@ logDestinations: [stdout]
 
 # API Configuration
 api: yes
-apiAddress: 127.0.0.1:9997
+apiAddress: 127.0.0.1:{}
 metrics: no
 
 # RTSP Server
 rtsp: yes
-rtspAddress: :8554
+rtspAddress: :{}
+rtpAddress: :{}
+rtcpAddress: :{}
 protocols: [tcp, udp]
 rtspTransport: tcp
 
 # RTMP Server
 rtmp: yes
-rtmpAddress: :1935
+rtmpAddress: :{}
 rtmpEncryption: no
 
 # SRT Server
 srt: yes
-srtAddress: :8890
+srtAddress: :{}
 "#,
+        ports.api, ports.rtsp, ports.rtp, ports.rtcp, ports.rtmp, ports.srt
     );
 
     // WAN optimizations
-    if wan_mode {
-        config.push_str("srtLatency: 2000ms\n");
+    if let Some(latency_ms) = srt_latency_ms {
+        config.push_str(&format!("srtLatency: {}ms\n", latency_ms));
     }
 
-    // WebRTC and HLS disabled
-    config.push_str(
-        r#"
+    if delivery.enable_webrtc {
+        config.push_str(&format!(
+            r#"
+# WebRTC
+webrtc: yes
+webrtcAddress: :{}
+webrtcLocalUDPAddress: :{}
+"#,
+            ports.webrtc, ports.webrtc_udp
+        ));
+        if !delivery.webrtc_ice_servers.is_empty() {
+            config.push_str("webrtcICEServers2:\n");
+            for server in &delivery.webrtc_ice_servers {
+                config.push_str(&format!("  - url: {}\n", yaml_string(&server.url)));
+                if let Some(ref username) = server.username {
+                    config.push_str(&format!("    username: {}\n", yaml_string(username)));
+                }
+                if let Some(ref password) = server.password {
+                    config.push_str(&format!("    password: {}\n", yaml_string(password)));
+                }
+            }
+        }
+    } else {
+        config.push_str(
+            r#"
 # WebRTC (disabled)
 webrtc: no
+"#,
+        );
+    }
 
+    if delivery.enable_hls {
+        // Low-latency HLS plays in Safari natively and elsewhere via hls.js
+        config.push_str(&format!(
+            r#"
+# HLS
+hls: yes
+hlsAddress: :{}
+hlsVariant: lowLatency
+hlsSegmentCount: 7
+hlsSegmentDuration: 1s
+hlsPartDuration: 200ms
+"#,
+            ports.hls
+        ));
+    } else {
+        config.push_str(
+            r#"
 # HLS (disabled)
 hls: no
+"#,
+        );
+    }
 
+    config.push_str(
+        r#"
 # Paths configuration
 paths:
 "#,
@@ -274,8 +904,20 @@ paths:
                 // Read credentials can be different, but for now use same
                 config.push_str(&format!("    readUser: {}\n", auth.username));
                 config.push_str(&format!("    readPass: {}\n", auth.password));
+                // SRT encrypts instead of using user/pass, so both ends need the passphrase
+                if let Some(ref passphrase) = auth.srt_passphrase {
+                    config.push_str(&format!("    srtPublishPassphrase: {}\n", passphrase));
+                    config.push_str(&format!("    srtReadPassphrase: {}\n", passphrase));
+                }
             }
         }
+
+        // Streams without their own path entry stay open
+        config.push_str(
+            r#"  all_others:
+    source: publisher
+"#,
+        );
     }
 
     config
@@ -287,7 +929,12 @@ mod tests {
 
     #[test]
     fn test_generate_config_no_streams() {
-        let config = generate_mediamtx_config(&[], false);
+        let config = generate_mediamtx_config(
+            &[],
+            None,
+            &DeliveryOptions::default(),
+            &PortConfig::default(),
+        );
         assert!(config.contains("rtsp: yes"));
         assert!(config.contains("rtmp: yes"));
         assert!(config.contains("srt: yes"));
@@ -306,21 +953,156 @@ mod tests {
             }),
         }];
 
-        let config = generate_mediamtx_config(&streams, false);
+        let config = generate_mediamtx_config(
+            &streams,
+            None,
+            &DeliveryOptions::default(),
+            &PortConfig::default(),
+        );
         assert!(config.contains("stream1:"));
         assert!(config.contains("publishUser: user1"));
         assert!(config.contains("publishPass: pass1"));
+        assert!(config.contains("all_others:"));
+    }
+
+    #[test]
+    fn test_generate_config_hls() {
+        let config = generate_mediamtx_config(
+            &[],
+            None,
+            &DeliveryOptions::default(),
+            &PortConfig::default(),
+        );
+        assert!(config.contains("hls: no"));
+
+        let delivery = DeliveryOptions {
+            enable_hls: true,
+            ..Default::default()
+        };
+        let config = generate_mediamtx_config(
+            &[],
+            None,
+            &delivery,
+            &PortConfig::default(),
+        );
+        assert!(config.contains("hls: yes"));
+        assert!(config.contains("hlsAddress: :8888"));
+        assert!(config.contains("hlsSegmentDuration: 1s"));
+    }
+
+    #[test]
+    fn test_generate_config_webrtc() {
+        let config = generate_mediamtx_config(
+            &[],
+            None,
+            &DeliveryOptions::default(),
+            &PortConfig::default(),
+        );
+        assert!(config.contains("webrtc: no"));
+
+        let delivery = DeliveryOptions {
+            enable_webrtc: true,
+            webrtc_ice_servers: parse_ice_servers(
+                "stun:stun.l.google.com:19302\nturn:turn.example.com:3478 user secret",
+            ),
+            ..Default::default()
+        };
+        let config = generate_mediamtx_config(
+            &[],
+            None,
+            &delivery,
+            &PortConfig::default(),
+        );
+        assert!(config.contains("webrtc: yes"));
+        assert!(config.contains("webrtcAddress: :8889"));
+        assert!(config.contains("webrtcLocalUDPAddress: :8189"));
+        assert!(config.contains(
+            "webrtcICEServers2:\n  - url: \"stun:stun.l.google.com:19302\"\n  - url: \"turn:turn.example.com:3478\"\n    username: \"user\"\n    password: \"secret\"\n"
+        ));
+
+        // Passwords with YAML syntax in them stay one literal string
+        let delivery = DeliveryOptions {
+            enable_webrtc: true,
+            webrtc_ice_servers: parse_ice_servers("turn:t:3478 user #se\"cret:x"),
+            ..Default::default()
+        };
+        let config = generate_mediamtx_config(&[], None, &delivery, &PortConfig::default());
+        assert!(config.contains(r##"    password: "#se\"cret:x""##));
+    }
+
+    #[test]
+    fn test_parse_ice_servers() {
+        assert!(parse_ice_servers("").is_empty());
+        let servers = parse_ice_servers(" stun:a:3478 , turn:b:3478 u p\n");
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[0].url, "stun:a:3478");
+        assert_eq!(servers[0].username, None);
+        assert_eq!(servers[1].password.as_deref(), Some("p"));
     }
 
     #[test]
     fn test_generate_config_wan_mode() {
-        let config = generate_mediamtx_config(&[], true);
+        let config = generate_mediamtx_config(
+            &[],
+            Some(WAN_SRT_LATENCY_MS),
+            &DeliveryOptions::default(),
+            &PortConfig::default(),
+        );
         assert!(config.contains("srtLatency: 2000ms"));
     }
 
+    #[test]
+    fn test_generate_config_custom_ports() {
+        let ports = PortConfig {
+            rtsp: 9554,
+            api: 9998,
+            ..Default::default()
+        };
+        let config = generate_mediamtx_config(&[], None, &DeliveryOptions::default(), &ports);
+        assert!(config.contains("rtspAddress: :9554"));
+        assert!(config.contains("rtpAddress: :8000"));
+        assert!(config.contains("rtcpAddress: :8001"));
+        assert!(config.contains("apiAddress: 127.0.0.1:9998"));
+
+        let mut manager = MediaMTXManager::new();
+        manager.set_ports(ports);
+        assert_eq!(manager.api_base, "http://127.0.0.1:9998");
+    }
+
+    #[test]
+    fn test_port_config_validation() {
+        assert!(PortConfig::default().validate().is_ok());
+        let clash = PortConfig {
+            srt: 8554,
+            ..Default::default()
+        };
+        assert_eq!(
+            clash.validate().unwrap_err().to_string(),
+            "Config error: RTSP and SRT both use port 8554"
+        );
+        let unset = PortConfig {
+            hls: 0,
+            ..Default::default()
+        };
+        assert!(unset.validate().is_err());
+        let rtp_clash = PortConfig {
+            webrtc_udp: 8001,
+            ..Default::default()
+        };
+        assert_eq!(
+            rtp_clash.validate().unwrap_err().to_string(),
+            "Config error: RTCP and WebRTC UDP both use port 8001"
+        );
+    }
+
     #[test]
     fn test_rtmp_config_enabled() {
-        let config = generate_mediamtx_config(&[], false);
+        let config = generate_mediamtx_config(
+            &[],
+            None,
+            &DeliveryOptions::default(),
+            &PortConfig::default(),
+        );
 
         // Verify RTMP server is enabled
         assert!(config.contains("rtmp: yes"));
@@ -336,4 +1118,362 @@ mod tests {
         let srt_pos = config.find("srt:").unwrap();
         assert!(rmp_pos < srt_pos);
     }
+
+    /// Minimal stand-in for the MediaMTX API: answers each request with the
+    /// next status and records "METHOD /path body"
+    async fn fake_api(statuses: Vec<u16>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        fake_api_with_bodies(statuses.into_iter().map(|status| (status, "")).collect()).await
+    }
+
+    /// `fake_api` that answers each request with a status and body
+    async fn fake_api_with_bodies(
+        responses: Vec<(u16, &'static str)>,
+    ) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let mut seen = Vec::new();
+            for (status, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 1024];
+                let (head_end, length) = loop {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&buf).to_string();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text[..end]
+                            .lines()
+                            .find_map(|l| {
+                                let l = l.to_lowercase();
+                                l.strip_prefix("content-length:")?.trim().parse::<usize>().ok()
+                            })
+                            .unwrap_or(0);
+                        break (end + 4, length);
+                    }
+                };
+                while buf.len() < head_end + length {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+
+                let text = String::from_utf8_lossy(&buf).to_string();
+                let request_line = text.lines().next().unwrap_or_default();
+                let mut parts = request_line.split(' ');
+                seen.push(format!(
+                    "{} {} {}",
+                    parts.next().unwrap_or_default(),
+                    parts.next().unwrap_or_default(),
+                    &text[head_end..]
+                ));
+
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            seen
+        });
+        (base, handle)
+    }
+
+    fn test_auth() -> StreamAuth {
+        StreamAuth {
+            stream_id: "s1".into(),
+            username: "user1".into(),
+            password: "pass1".into(),
+            srt_passphrase: Some("srt-passphrase-123".into()),
+        }
+    }
+
+    /// Port nothing is listening on
+    fn closed_api_base() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        format!("http://{}", addr)
+    }
+
+    #[cfg(unix)]
+    fn spawn_sh(script: &str) -> Child {
+        Command::new("sh")
+            .args(["-c", script])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_wait_ready_reports_exit_output() {
+        let mut manager = MediaMTXManager::new();
+        manager.api_base = closed_api_base();
+        manager.attach(spawn_sh(
+            "echo 'ERR listen tcp :8554: bind: address already in use'; exit 1",
+        ));
+
+        let err = manager.wait_ready(Duration::from_secs(5)).await.unwrap_err();
+        assert!(matches!(err, SidecarError::NotReady(_)));
+        assert!(err.to_string().contains("address already in use"));
+        assert!(!manager.is_running());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_wait_ready_when_api_answers() {
+        let (base, handle) = fake_api(vec![200]).await;
+        let mut manager = MediaMTXManager::new();
+        manager.api_base = base;
+        manager.attach(spawn_sh("exec sleep 30"));
+
+        manager.wait_ready(Duration::from_secs(5)).await.unwrap();
+        assert!(handle.await.unwrap()[0].starts_with("GET /v3/config/global/get"));
+        manager.stop().await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_crash_detection() {
+        let mut manager = MediaMTXManager::new();
+        manager.attach(spawn_sh("echo 'panic: boom'; exit 3"));
+        // Not started through `start`, so an exit is expected
+        assert!(!manager.needs_restart());
+
+        manager.should_run = true;
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !manager.needs_restart() {
+            assert!(Instant::now() < deadline);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(manager.process.is_none());
+        assert!(manager.output_tail().contains("panic: boom"));
+    }
+
+    #[test]
+    fn test_restart_backoff() {
+        let mut manager = MediaMTXManager::new();
+        manager.should_run = true;
+
+        let delays: Vec<u64> = std::iter::from_fn(|| manager.next_restart_delay())
+            .map(|d| d.as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16]);
+        // Out of budget: stay down until started again
+        assert!(!manager.needs_restart());
+    }
+
+    #[tokio::test]
+    async fn test_config_path_per_instance() {
+        let mut first = MediaMTXManager::new();
+        let second = MediaMTXManager::new();
+        assert_ne!(first.config_path, second.config_path);
+        assert!(first
+            .config_path
+            .to_string_lossy()
+            .contains(&std::process::id().to_string()));
+
+        let path = first.write_config().unwrap();
+        assert!(path.exists());
+        first.stop().await.unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_start_refuses_when_another_server_answers() {
+        let (base, handle) = fake_api(vec![200]).await;
+        let mut manager = MediaMTXManager::new();
+        manager.api_base = base;
+
+        let err = manager.start().await.unwrap_err();
+        assert!(matches!(err, SidecarError::Conflict(_)));
+        assert!(manager.process.is_none());
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_start_reports_port_in_use() {
+        let taken = std::net::TcpListener::bind("0.0.0.0:0").unwrap();
+        let port = taken.local_addr().unwrap().port();
+        let mut manager = MediaMTXManager::new();
+        manager.api_base = closed_api_base();
+        manager.ports.rtsp = port;
+
+        let err = manager.start().await.unwrap_err();
+        assert!(matches!(err, SidecarError::ConfigError(_)));
+        assert!(err.to_string().contains(&format!("RTSP port {} in use", port)));
+        assert!(manager.process.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_path_via_api() {
+        let (base, handle) = fake_api(vec![200, 200]).await;
+        let mut manager = MediaMTXManager::new();
+        manager.api_base = base;
+
+        manager.add_path("cam1", Some(test_auth()), None).await.unwrap();
+        manager.remove_path("cam1").await.unwrap();
+        // Unknown paths never reach the API
+        manager.remove_path("cam1").await.unwrap();
+
+        let seen = handle.await.unwrap();
+        assert!(seen[0].starts_with("POST /v3/config/paths/add/cam1 "));
+        let body: serde_json::Value =
+            serde_json::from_str(seen[0].splitn(3, ' ').nth(2).unwrap()).unwrap();
+        assert_eq!(body["source"], "publisher");
+        assert_eq!(body["publishUser"], "user1");
+        assert_eq!(body["readPass"], "pass1");
+        assert_eq!(body["srtReadPassphrase"], "srt-passphrase-123");
+        assert!(seen[1].starts_with("DELETE /v3/config/paths/delete/cam1"));
+    }
+
+    #[tokio::test]
+    async fn test_srt_latency_applied_to_live_server() {
+        let (base, handle) = fake_api(vec![200, 200, 200, 200, 200]).await;
+        let mut manager = MediaMTXManager::new();
+        manager.api_base = base;
+
+        manager.add_path("cam1", Some(test_auth()), Some(1200)).await.unwrap();
+        // A lower latency doesn't change the server-wide value
+        manager.add_path("cam2", Some(test_auth()), Some(800)).await.unwrap();
+        manager.add_path("cam3", Some(test_auth()), Some(WAN_SRT_LATENCY_MS)).await.unwrap();
+
+        let seen = handle.await.unwrap();
+        let requests: Vec<&str> = seen.iter().map(|r| r.splitn(3, ' ').take(2).last().unwrap()).collect();
+        assert_eq!(
+            requests,
+            [
+                "/v3/config/paths/add/cam1",
+                "/v3/config/global/patch",
+                "/v3/config/paths/add/cam2",
+                "/v3/config/paths/add/cam3",
+                "/v3/config/global/patch",
+            ]
+        );
+        assert!(seen[1].starts_with("PATCH "));
+        let body: serde_json::Value =
+            serde_json::from_str(seen[1].splitn(3, ' ').nth(2).unwrap()).unwrap();
+        assert_eq!(body["srtLatency"], "1200ms");
+        assert!(seen[4].ends_with(r#"{"srtLatency":"2000ms"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_is_publishing() {
+        let (base, handle) = fake_api_with_bodies(vec![
+            (200, r#"{"name":"cam1","ready":true,"source":{"type":"rtspSession"}}"#),
+            (200, r#"{"name":"cam1","ready":false,"source":null}"#),
+            (404, ""),
+        ])
+        .await;
+        let mut manager = MediaMTXManager::new();
+        manager.api_base = base;
+
+        assert!(manager.is_publishing("cam1").await.unwrap());
+        assert!(!manager.is_publishing("cam1").await.unwrap());
+        assert!(!manager.is_publishing("cam1").await.unwrap());
+        assert!(handle.await.unwrap()[0].starts_with("GET /v3/paths/get/cam1"));
+
+        manager.api_base = closed_api_base();
+        assert!(manager.is_publishing("cam1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_publishing_paths() {
+        let (base, handle) = fake_api_with_bodies(vec![
+            (
+                200,
+                r#"{"pageCount":2,"items":[{"name":"cam1","ready":true},{"name":"cam2","ready":false}]}"#,
+            ),
+            (200, r#"{"pageCount":2,"items":[{"name":"cam3","ready":true}]}"#),
+        ])
+        .await;
+        let mut manager = MediaMTXManager::new();
+        manager.api_base = base;
+
+        let names = manager.publishing_paths().await.unwrap();
+        assert_eq!(names, HashSet::from(["cam1".to_string(), "cam3".to_string()]));
+        let seen = handle.await.unwrap();
+        assert!(seen[0].starts_with("GET /v3/paths/list?itemsPerPage=1000&page=0"));
+        assert!(seen[1].starts_with("GET /v3/paths/list?itemsPerPage=1000&page=1"));
+
+        manager.api_base = closed_api_base();
+        assert!(manager.publishing_paths().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_add_existing_path_patches() {
+        let (base, handle) = fake_api(vec![400, 200]).await;
+        let mut manager = MediaMTXManager::new();
+        manager.api_base = base;
+
+        manager.add_path("cam1", Some(test_auth()), None).await.unwrap();
+
+        let seen = handle.await.unwrap();
+        assert!(seen[0].starts_with("POST /v3/config/paths/add/cam1"));
+        assert!(seen[1].starts_with("PATCH /v3/config/paths/patch/cam1"));
+    }
+
+    #[tokio::test]
+    async fn test_add_path_falls_back_when_api_unreachable() {
+        let mut manager = MediaMTXManager::new();
+        manager.api_base = closed_api_base();
+
+        manager.add_path("cam1", Some(test_auth()), None).await.unwrap();
+        assert!(manager.paths.contains_key("cam1"));
+        manager.remove_path("cam1").await.unwrap();
+        assert!(manager.paths.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_profile_srt_latency_reaches_config() {
+        let mut manager = MediaMTXManager::new();
+        manager.api_base = closed_api_base();
+        let mut profile = Profile {
+            id: "wan".into(),
+            name: "WAN".into(),
+            protocol: "srt".into(),
+            mode: "cpu".into(),
+            video_bitrate: Some(4000),
+            audio_bitrate: Some(128),
+            resolution: None,
+            gop_size: 60,
+            wan_optimized: 1,
+            codec: "h264".into(),
+            record: 0,
+            record_dir: None,
+            srt_latency_ms: Some(1200),
+            srt_overhead_percent: None,
+            overlay_text: None,
+            overlay_position: None,
+            overlay_font_size: None,
+            tonemap: 0,
+        };
+
+        manager
+            .add_path("cam1", Some(test_auth()), Some(wan_srt_latency_ms(&profile)))
+            .await
+            .unwrap();
+        let config = fs::read_to_string(&manager.config_path).unwrap();
+        assert!(config.contains("srtLatency: 1200ms"));
+
+        profile.srt_latency_ms = None;
+        assert_eq!(wan_srt_latency_ms(&profile), WAN_SRT_LATENCY_MS);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_path_names_rejected() {
+        let mut manager = MediaMTXManager::new();
+        manager.api_base = closed_api_base();
+
+        let err = manager.add_path("my stream/1", None, None).await.unwrap_err();
+        assert!(matches!(err, SidecarError::ConfigError(_)));
+        assert!(manager.paths.is_empty());
+
+        let streams = [StreamConfig { name: "a: b".into(), auth: None }];
+        assert!(manager.write_config_with_streams(&streams, None).is_err());
+    }
 }
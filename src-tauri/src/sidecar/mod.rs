@@ -1,15 +1,111 @@
 //! Sidecar management module for external binaries
 //! Handles FFmpeg, FFprobe, and MediaMTX processes
 
-pub mod ffmpeg;
 pub mod mediamtx;
 
+use std::ffi::OsStr;
 use std::path::PathBuf;
+use std::process::Command;
 
 /// Get the path to sidecar binaries based on current platform
 pub fn get_sidecar_path(name: &str) -> PathBuf {
     let target = get_target_triple();
-    PathBuf::from(format!("binaries/{}-{}", name, target))
+    PathBuf::from(format!(
+        "binaries/{}-{}{}",
+        name,
+        target,
+        std::env::consts::EXE_SUFFIX
+    ))
+}
+
+/// Find an external binary: system PATH first, then common install
+/// locations, then the bundled sidecar
+pub fn find_binary(name: &str) -> Option<PathBuf> {
+    find_binary_with_path(name, None)
+}
+
+/// Same as `find_binary`, optionally searching a specific PATH value
+fn find_binary_with_path(name: &str, path_var: Option<&OsStr>) -> Option<PathBuf> {
+    if let Some(path) = find_on_path(name, path_var) {
+        return Some(path);
+    }
+
+    if let Some(path) = common_install_paths(name).into_iter().find(|p| p.exists()) {
+        return Some(path);
+    }
+
+    let sidecar_path = get_sidecar_path(name);
+    if sidecar_path.exists() {
+        return Some(sidecar_path);
+    }
+
+    None
+}
+
+/// Resolve a binary via `where` (Windows) or `which` (elsewhere)
+fn find_on_path(name: &str, path_var: Option<&OsStr>) -> Option<PathBuf> {
+    let lookup = if cfg!(windows) { "where" } else { "which" };
+
+    let mut cmd = match path_var {
+        Some(path_var) => {
+            // The child's PATH is also used to resolve the program itself,
+            // so locate the lookup tool against our own PATH first
+            let tool = std::env::var_os("PATH")
+                .map(|p| std::env::split_paths(&p).collect::<Vec<_>>())
+                .unwrap_or_default()
+                .into_iter()
+                .map(|dir| dir.join(format!("{}{}", lookup, std::env::consts::EXE_SUFFIX)))
+                .find(|p| p.is_file())
+                .unwrap_or_else(|| PathBuf::from(lookup));
+
+            let mut cmd = Command::new(tool);
+            cmd.env("PATH", path_var);
+            cmd
+        }
+        None => Command::new(lookup),
+    };
+    cmd.arg(name);
+
+    let output = cmd.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // `where` lists every match, one per line - take the first
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| l.trim())
+        .find(|l| !l.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Common install locations for a binary on the current platform
+fn common_install_paths(name: &str) -> Vec<PathBuf> {
+    if cfg!(windows) {
+        let exe = format!("{}.exe", name);
+        let mut paths = Vec::new();
+
+        if let Some(program_files) = std::env::var_os("ProgramFiles") {
+            let base = PathBuf::from(program_files);
+            paths.push(base.join(name).join("bin").join(&exe));
+            paths.push(base.join(name).join(&exe));
+        }
+
+        if let Some(local_app_data) = std::env::var_os("LOCALAPPDATA") {
+            let base = PathBuf::from(local_app_data);
+            paths.push(base.join("Programs").join(name).join("bin").join(&exe));
+            paths.push(base.join("Programs").join(name).join(&exe));
+            // winget installs expose shims here
+            paths.push(base.join("Microsoft").join("WinGet").join("Links").join(&exe));
+        }
+
+        paths
+    } else {
+        ["/opt/homebrew/bin", "/usr/local/bin", "/usr/bin"]
+            .iter()
+            .map(|dir| PathBuf::from(dir).join(name))
+            .collect()
+    }
 }
 
 /// Get current platform target triple for sidecar naming
@@ -52,4 +148,60 @@ pub enum SidecarError {
 
     #[error("Config error: {0}")]
     ConfigError(String),
+
+    #[error("Conflicting server: {0}")]
+    Conflict(String),
+
+    #[error("Server not ready: {0}")]
+    NotReady(String),
+
+    #[error("MediaMTX API error: {0}")]
+    ApiError(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create an executable stub named like the real binary
+    fn stub_binary(dir: &std::path::Path, name: &str) -> PathBuf {
+        let path = dir.join(format!("{}{}", name, std::env::consts::EXE_SUFFIX));
+        std::fs::write(&path, "").unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        path
+    }
+
+    #[test]
+    fn test_find_binary_on_stubbed_path() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for name in ["ffmpeg", "ffprobe", "mediamtx"] {
+            let expected = stub_binary(dir.path(), name);
+            let found = find_on_path(name, Some(dir.path().as_os_str())).unwrap();
+            assert_eq!(
+                found.canonicalize().unwrap(),
+                expected.canonicalize().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_binary_missing_from_path() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(find_on_path("cvideo-no-such-binary", Some(dir.path().as_os_str())).is_none());
+    }
+
+    #[test]
+    fn test_sidecar_path_suffix() {
+        let path = get_sidecar_path("ffmpeg");
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("ffmpeg-"));
+        assert!(name.ends_with(std::env::consts::EXE_SUFFIX));
+    }
 }
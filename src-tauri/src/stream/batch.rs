@@ -0,0 +1,144 @@
+//! Operations over many streams at once
+//! Callers lock the supervisor and scheduler once and pass them in, so a
+//! batch doesn't contend with itself item by item
+
+use super::supervisor::Supervisor;
+use crate::scheduler::Scheduler;
+use std::collections::{HashMap, HashSet};
+
+/// Live status of one stream: running if its process is up, else the
+/// scheduler's view, else stopped
+pub fn stream_status(supervisor: &mut Supervisor, scheduler: &Scheduler, id: &str) -> String {
+    if supervisor.is_running(id) {
+        return "running".to_string();
+    }
+    scheduler
+        .get_state(id)
+        .map(|state| state.as_str().to_string())
+        .unwrap_or_else(|| "stopped".to_string())
+}
+
+/// Refine "running" with what MediaMTX reports: "publishing" once it has
+/// the stream's publisher, "connecting" until then. Stays "running" when
+/// MediaMTX couldn't be asked.
+pub fn publish_status(status: String, publishing: Option<bool>) -> String {
+    match (status.as_str(), publishing) {
+        ("running", Some(true)) => "publishing".to_string(),
+        ("running", Some(false)) => "connecting".to_string(),
+        _ => status,
+    }
+}
+
+/// `publish_status` for many streams at once, given each stream's name by
+/// id and the paths MediaMTX has publishers on (None when it couldn't be
+/// asked)
+pub fn publish_statuses(
+    statuses: HashMap<String, String>,
+    names: &HashMap<String, String>,
+    publishing: Option<&HashSet<String>>,
+) -> HashMap<String, String> {
+    statuses
+        .into_iter()
+        .map(|(id, status)| {
+            let live = names
+                .get(&id)
+                .and_then(|name| publishing.map(|paths| paths.contains(name)));
+            (id, publish_status(status, live))
+        })
+        .collect()
+}
+
+/// Live status of each stream, by id
+pub fn statuses(
+    supervisor: &mut Supervisor,
+    scheduler: &Scheduler,
+    ids: &[String],
+) -> HashMap<String, String> {
+    ids.iter()
+        .map(|id| (id.clone(), stream_status(supervisor, scheduler, id)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::AppSettings;
+    use crate::scheduler::StreamInfo;
+    use crate::stream::supervisor::create_shared_supervisor;
+
+    #[test]
+    fn test_publish_status() {
+        assert_eq!(publish_status("running".into(), Some(true)), "publishing");
+        assert_eq!(publish_status("running".into(), Some(false)), "connecting");
+        assert_eq!(publish_status("running".into(), None), "running");
+        assert_eq!(publish_status("queued".into(), Some(false)), "queued");
+    }
+
+    #[test]
+    fn test_publish_statuses() {
+        let statuses = HashMap::from([
+            ("a".to_string(), "running".to_string()),
+            ("b".to_string(), "running".to_string()),
+            ("c".to_string(), "queued".to_string()),
+            ("gone".to_string(), "running".to_string()),
+        ]);
+        let names = HashMap::from([
+            ("a".to_string(), "cam-a".to_string()),
+            ("b".to_string(), "cam-b".to_string()),
+            ("c".to_string(), "cam-c".to_string()),
+        ]);
+        let publishing = HashSet::from(["cam-a".to_string()]);
+
+        let live = publish_statuses(statuses.clone(), &names, Some(&publishing));
+        assert_eq!(live["a"], "publishing");
+        assert_eq!(live["b"], "connecting");
+        assert_eq!(live["c"], "queued");
+        assert_eq!(live["gone"], "running");
+
+        let live = publish_statuses(statuses, &names, None);
+        assert_eq!(live["a"], "running");
+    }
+
+    #[tokio::test]
+    async fn test_batch_statuses_under_one_lock() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query(
+            "INSERT INTO media_files (id, path, folder, filename, scanned_at)
+            VALUES ('m1', '/test/m1.mp4', 'test', 'm1.mp4', '2024-01-01')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let supervisor = create_shared_supervisor();
+        let scheduler = crate::scheduler::create_shared_scheduler(&AppSettings::default());
+        let mut ids = Vec::new();
+        for i in 0..20 {
+            let stream = crate::stream::create_stream(&pool, "m1", &format!("cam{}", i), "lan-copy")
+                .await
+                .unwrap();
+            scheduler.lock().await.register_stream(StreamInfo {
+                id: stream.id.clone(),
+                mode: "copy".to_string(),
+                bitrate_mbps: 1,
+                priority: 50,
+                pinned: false,
+                run_secs: Some(0.0),
+            });
+            ids.push(stream.id);
+        }
+        scheduler.lock().await.request_start(&ids[0]);
+
+        // One acquisition of each lock covers the whole batch
+        let mut supervisor = supervisor.lock().await;
+        let mut scheduler = scheduler.lock().await;
+        let statuses = statuses(&mut supervisor, &scheduler, &ids);
+        assert_eq!(statuses.len(), 20);
+        assert_eq!(statuses[&ids[0]], "starting");
+        assert_eq!(statuses[&ids[1]], "pending");
+
+        scheduler.unregister_stream(&ids[0]);
+        assert_eq!(scheduler.capacity_summary().total_streams, 0);
+        assert_eq!(stream_status(&mut supervisor, &scheduler, &ids[0]), "stopped");
+    }
+}
@@ -0,0 +1,129 @@
+//! Stream event history
+//! Persists lifecycle events so flapping streams can be diagnosed later
+
+use super::supervisor::StreamEvent;
+use super::StreamError;
+use crate::db::schema::StreamEventRecord;
+use sqlx::sqlite::SqlitePool;
+
+/// Events kept per stream; older ones are pruned as new ones arrive
+pub const EVENTS_KEPT_PER_STREAM: i64 = 1000;
+
+/// Append an event to a stream's history
+pub async fn record_event(
+    pool: &SqlitePool,
+    stream_id: &str,
+    event_type: &str,
+    message: Option<&str>,
+) -> Result<(), StreamError> {
+    sqlx::query("INSERT INTO stream_events (stream_id, event_type, message) VALUES (?, ?, ?)")
+        .bind(stream_id)
+        .bind(event_type)
+        .bind(message)
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM stream_events
+        WHERE stream_id = ? AND id <= (
+            SELECT id FROM stream_events WHERE stream_id = ?
+            ORDER BY id DESC LIMIT 1 OFFSET ?
+        )
+        "#,
+    )
+    .bind(stream_id)
+    .bind(stream_id)
+    .bind(EVENTS_KEPT_PER_STREAM)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Append a supervisor event. Progress updates are not kept, and exits are
+/// left to whoever confirms the process ended on its own.
+pub async fn record_stream_event(pool: &SqlitePool, event: &StreamEvent) -> Result<(), StreamError> {
+    match event {
+        StreamEvent::Started { stream_id } => record_event(pool, stream_id, "started", None).await,
+        StreamEvent::Stopped { stream_id } => record_event(pool, stream_id, "stopped", None).await,
+        StreamEvent::Error { stream_id, message } => {
+            record_event(pool, stream_id, "error", Some(message)).await
+        }
+        StreamEvent::Preempted { stream_id, by } => {
+            let message = format!("Preempted by {}", by);
+            record_event(pool, stream_id, "preempted", Some(&message)).await
+        }
+        StreamEvent::Progress(_) | StreamEvent::Exited { .. } => Ok(()),
+    }
+}
+
+/// A stream's most recent events, newest first
+pub async fn get_events(
+    pool: &SqlitePool,
+    stream_id: &str,
+    limit: i64,
+) -> Result<Vec<StreamEventRecord>, StreamError> {
+    let events = sqlx::query_as(
+        "SELECT * FROM stream_events WHERE stream_id = ? ORDER BY id DESC LIMIT ?",
+    )
+    .bind(stream_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn pool_with_stream() -> SqlitePool {
+        let pool = crate::db::test_pool().await;
+        sqlx::query("INSERT INTO streams (id, name) VALUES ('s1', 'cam')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_error_then_restart_logged() {
+        let pool = pool_with_stream().await;
+        let events = [
+            StreamEvent::Error {
+                stream_id: "s1".into(),
+                message: "Connection refused".into(),
+            },
+            StreamEvent::Progress(crate::stream::supervisor::StreamProgress {
+                stream_id: "s1".into(),
+                frame: 1,
+                fps: 25.0,
+                bitrate: String::new(),
+                time: String::new(),
+                speed: String::new(),
+                percent: None,
+            }),
+            StreamEvent::Started { stream_id: "s1".into() },
+        ];
+        for event in &events {
+            record_stream_event(&pool, event).await.unwrap();
+        }
+
+        let history = get_events(&pool, "s1", 10).await.unwrap();
+        let types: Vec<&str> = history.iter().map(|e| e.event_type.as_str()).collect();
+        assert_eq!(types, ["started", "error"]);
+        assert_eq!(history[1].message.as_deref(), Some("Connection refused"));
+    }
+
+    #[tokio::test]
+    async fn test_history_is_pruned() {
+        let pool = pool_with_stream().await;
+        for i in 0..EVENTS_KEPT_PER_STREAM + 5 {
+            record_event(&pool, "s1", "error", Some(&i.to_string())).await.unwrap();
+        }
+
+        let history = get_events(&pool, "s1", EVENTS_KEPT_PER_STREAM * 2).await.unwrap();
+        assert_eq!(history.len() as i64, EVENTS_KEPT_PER_STREAM);
+        assert_eq!(history.last().unwrap().message.as_deref(), Some("5"));
+    }
+}
@@ -1,11 +1,20 @@
 //! Stream management module
 //! Handles stream lifecycle, command generation, and process supervision
 
+pub mod batch;
 pub mod command;
+pub mod events;
+pub mod outputs;
+pub mod playlist;
+pub mod recording;
+pub mod schedule;
+pub mod snapshot;
 pub mod supervisor;
+pub mod tags;
 
 use crate::db::schema::{Profile, Stream};
-use sqlx::sqlite::SqlitePool;
+use crate::scanner::metadata::{parse_audio_tracks, parse_subtitle_tracks};
+use sqlx::sqlite::{SqliteConnection, SqlitePool};
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -17,8 +26,111 @@ pub enum StreamError {
     NotFound(String),
     #[error("Stream already exists: {0}")]
     AlreadyExists(String),
+    #[error("Stream already running")]
+    AlreadyRunning,
     #[error("Process error: {0}")]
     Process(String),
+    #[error("FFmpeg not found. Install via: brew install ffmpeg")]
+    FfmpegNotFound,
+    #[error("Stream is not publishing: {0}")]
+    NotPublishing(String),
+    #[error("Invalid profile: {0}")]
+    InvalidProfile(String),
+    #[error("Profile is in use by {0} stream(s)")]
+    ProfileInUse(i64),
+    #[error("Invalid output: {0}")]
+    InvalidOutput(String),
+    #[error("Invalid tag: {0}")]
+    InvalidTag(String),
+    #[error("Invalid stream name: {0}")]
+    InvalidName(String),
+    #[error("Invalid schedule: {0}")]
+    InvalidSchedule(String),
+    #[error("Invalid playlist: {0}")]
+    InvalidPlaylist(String),
+    #[error("Invalid audio track: {0}")]
+    InvalidAudioTrack(String),
+    #[error("Invalid subtitle track: {0}")]
+    InvalidSubtitles(String),
+    #[error("Invalid extra arguments: {0}")]
+    InvalidExtraArgs(String),
+}
+
+/// Protocols a profile or output can publish with. HLS and WebRTC are only
+/// served to readers, from whatever is published.
+pub const PROFILE_PROTOCOLS: &[&str] = &["rtsp", "srt", "rtmp"];
+
+/// Encoding modes a profile can use
+pub const PROFILE_MODES: &[&str] = &["copy", "cpu", "nvenc", "qsv", "videotoolbox", "vaapi", "amf"];
+
+/// Target codecs for hardware transcode modes
+pub const PROFILE_CODECS: &[&str] = &["h264", "hevc", "av1"];
+
+/// Accepted video bitrate range in kbps
+pub const VIDEO_BITRATE_RANGE: std::ops::RangeInclusive<i32> = 100..=100_000;
+
+/// Accepted audio bitrate range in kbps
+pub const AUDIO_BITRATE_RANGE: std::ops::RangeInclusive<i32> = 32..=512;
+
+/// Accepted GOP sizes in frames
+pub const GOP_SIZE_RANGE: std::ops::RangeInclusive<i32> = 1..=600;
+
+/// Accepted SRT latency range in milliseconds
+pub const SRT_LATENCY_RANGE_MS: std::ops::RangeInclusive<i32> = 20..=8000;
+
+/// Accepted SRT overhead bandwidth range in percent (libsrt's own bounds)
+pub const SRT_OVERHEAD_RANGE: std::ops::RangeInclusive<i32> = 5..=100;
+
+/// Corners a text overlay can be drawn in
+pub const OVERLAY_POSITIONS: &[&str] = &["top-left", "top-right", "bottom-left", "bottom-right"];
+
+/// Accepted overlay font sizes, in pixels
+pub const OVERLAY_FONT_SIZE_RANGE: std::ops::RangeInclusive<i32> = 8..=200;
+
+/// Maximum stream name length; names become MediaMTX paths and URLs
+pub const MAX_NAME_LEN: usize = 64;
+
+/// Check that a name is safe to use as a MediaMTX path: 1-64 letters,
+/// digits, '-' or '_'
+pub fn validate_name(name: &str) -> Result<(), StreamError> {
+    if name.is_empty() {
+        return Err(StreamError::InvalidName("name is required".into()));
+    }
+    if name.len() > MAX_NAME_LEN {
+        return Err(StreamError::InvalidName(format!(
+            "'{}' is longer than {} characters",
+            name, MAX_NAME_LEN
+        )));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(StreamError::InvalidName(format!(
+            "'{}' may only contain letters, digits, '-' and '_'",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Validate a name and make sure no stream or output already uses it,
+/// since both share the MediaMTX path namespace
+pub async fn ensure_name_available(pool: &SqlitePool, name: &str) -> Result<(), StreamError> {
+    ensure_name_available_on(&mut *pool.acquire().await?, name).await
+}
+
+async fn ensure_name_available_on(conn: &mut SqliteConnection, name: &str) -> Result<(), StreamError> {
+    validate_name(name)?;
+
+    let (taken,): (i64,) = sqlx::query_as(
+        "SELECT (SELECT COUNT(*) FROM streams WHERE name = ?) + (SELECT COUNT(*) FROM stream_outputs WHERE name = ?)",
+    )
+    .bind(name)
+    .bind(name)
+    .fetch_one(conn)
+    .await?;
+    if taken > 0 {
+        return Err(StreamError::AlreadyExists(name.to_string()));
+    }
+    Ok(())
 }
 
 /// Create a new stream record
@@ -28,10 +140,22 @@ pub async fn create_stream(
     name: &str,
     profile_id: &str,
 ) -> Result<Stream, StreamError> {
+    insert_stream(&mut *pool.acquire().await?, media_file_id, name, profile_id).await
+}
+
+/// `create_stream` on one connection, so it can be part of a transaction
+pub(crate) async fn insert_stream(
+    conn: &mut SqliteConnection,
+    media_file_id: &str,
+    name: &str,
+    profile_id: &str,
+) -> Result<Stream, StreamError> {
+    ensure_name_available_on(&mut *conn, name).await?;
+
     // Get profile
     let profile: Profile = sqlx::query_as("SELECT * FROM profiles WHERE id = ?")
         .bind(profile_id)
-        .fetch_optional(pool)
+        .fetch_optional(&mut *conn)
         .await?
         .ok_or_else(|| StreamError::NotFound(format!("Profile: {}", profile_id)))?;
 
@@ -46,6 +170,13 @@ pub async fn create_stream(
         pid: None,
         started_at: None,
         error_message: None,
+        scheduled_start: None,
+        scheduled_stop: None,
+        loop_playback: 1,
+        audio_track_index: 0,
+        burn_subtitles: None,
+        extra_args: None,
+        tags: Vec::new(),
     };
 
     sqlx::query(
@@ -61,7 +192,7 @@ pub async fn create_stream(
     .bind(&stream.protocol)
     .bind(&stream.mode)
     .bind(&stream.status)
-    .execute(pool)
+    .execute(conn)
     .await?;
 
     Ok(stream)
@@ -69,30 +200,50 @@ pub async fn create_stream(
 
 /// Get stream by ID
 pub async fn get_stream(pool: &SqlitePool, id: &str) -> Result<Option<Stream>, StreamError> {
-    let stream: Option<Stream> = sqlx::query_as("SELECT * FROM streams WHERE id = ?")
+    let mut stream: Option<Stream> = sqlx::query_as("SELECT * FROM streams WHERE id = ?")
         .bind(id)
         .fetch_optional(pool)
         .await?;
+    if let Some(ref mut stream) = stream {
+        tags::attach_tags(pool, std::slice::from_mut(stream)).await?;
+    }
     Ok(stream)
 }
 
 /// Get stream by name
 pub async fn get_stream_by_name(pool: &SqlitePool, name: &str) -> Result<Option<Stream>, StreamError> {
-    let stream: Option<Stream> = sqlx::query_as("SELECT * FROM streams WHERE name = ?")
+    let mut stream: Option<Stream> = sqlx::query_as("SELECT * FROM streams WHERE name = ?")
         .bind(name)
         .fetch_optional(pool)
         .await?;
+    if let Some(ref mut stream) = stream {
+        tags::attach_tags(pool, std::slice::from_mut(stream)).await?;
+    }
     Ok(stream)
 }
 
 /// Get all streams
 pub async fn get_all_streams(pool: &SqlitePool) -> Result<Vec<Stream>, StreamError> {
-    let streams: Vec<Stream> = sqlx::query_as("SELECT * FROM streams ORDER BY name")
+    let mut streams: Vec<Stream> = sqlx::query_as("SELECT * FROM streams ORDER BY name")
         .fetch_all(pool)
         .await?;
+    tags::attach_tags(pool, &mut streams).await?;
     Ok(streams)
 }
 
+/// Ids of the streams that play a media file, directly or in a playlist
+pub async fn streams_using_media(pool: &SqlitePool, media_id: &str) -> Result<Vec<String>, StreamError> {
+    let ids: Vec<(String,)> = sqlx::query_as(
+        "SELECT id FROM streams WHERE media_file_id = ? \
+         UNION SELECT stream_id FROM stream_playlist_items WHERE media_file_id = ?",
+    )
+    .bind(media_id)
+    .bind(media_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(ids.into_iter().map(|(id,)| id).collect())
+}
+
 /// Update stream status
 pub async fn update_stream_status(
     pool: &SqlitePool,
@@ -101,21 +252,170 @@ pub async fn update_stream_status(
     pid: Option<i32>,
     error_message: Option<&str>,
 ) -> Result<(), StreamError> {
-    sqlx::query(
+    // Many streams change state at once; a locked database is retried
+    crate::db::retry_busy(|| async {
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            r#"
+            UPDATE streams
+            SET status = ?, pid = ?, error_message = ?,
+                started_at = CASE WHEN ? = 'running' THEN datetime('now') ELSE started_at END
+            WHERE id = ?
+            "#,
+        )
+        .bind(status)
+        .bind(pid)
+        .bind(error_message)
+        .bind(status)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await
+    })
+    .await?;
+    Ok(())
+}
+
+/// Mark streams the previous run left active as stopped. Nothing supervises
+/// them after a restart, so `kill_orphan` is given each stored pid to end a
+/// still-running FFmpeg. Returns how many streams were reset.
+pub async fn reconcile_stale_streams(
+    pool: &SqlitePool,
+    kill_orphan: impl Fn(u32) -> bool,
+) -> Result<usize, StreamError> {
+    let stale: Vec<(String, String, Option<i32>)> = sqlx::query_as(
+        "SELECT id, name, pid FROM streams WHERE status IN ('starting', 'running', 'queued', 'stopping')",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for (id, name, pid) in &stale {
+        if let Some(pid) = pid.and_then(|pid| u32::try_from(pid).ok()) {
+            if kill_orphan(pid) {
+                println!("[Stream] Killed orphaned FFmpeg {} of {}", pid, name);
+            }
+        }
+        update_stream_status(pool, id, "stopped", None, None).await?;
+        events::record_event(pool, id, "stopped", Some("app restarted while the stream was active")).await?;
+    }
+    Ok(stale.len())
+}
+
+/// Choose whether a stream loops its file or plays it once; applies from the next start
+pub async fn set_loop_playback(pool: &SqlitePool, id: &str, enabled: bool) -> Result<(), StreamError> {
+    let result = sqlx::query("UPDATE streams SET loop_playback = ? WHERE id = ?")
+        .bind(enabled as i32)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(StreamError::NotFound(id.to_string()));
+    }
+    Ok(())
+}
+
+/// Choose which of the source's audio tracks a stream publishes; applies from the next start.
+/// Checked against the probed tracks when the file was scanned with them.
+pub async fn set_audio_track(pool: &SqlitePool, id: &str, index: i32) -> Result<(), StreamError> {
+    let row: Option<(Option<String>,)> = sqlx::query_as(
+        "SELECT m.audio_tracks FROM streams s JOIN media_files m ON m.id = s.media_file_id WHERE s.id = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+    let Some((tracks,)) = row else {
+        return Err(StreamError::NotFound(id.to_string()));
+    };
+
+    let tracks = parse_audio_tracks(tracks.as_deref());
+    if index < 0 || (!tracks.is_empty() && index as usize >= tracks.len()) {
+        return Err(StreamError::InvalidAudioTrack(format!(
+            "track {} not found, the source has {}",
+            index,
+            tracks.len()
+        )));
+    }
+
+    sqlx::query("UPDATE streams SET audio_track_index = ? WHERE id = ?")
+        .bind(index)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Choose a subtitle track to render into the video, or `None` to stop;
+/// applies from the next start. Needs a transcode profile, since copied
+/// video can't be filtered.
+pub async fn set_burn_subtitles(
+    pool: &SqlitePool,
+    id: &str,
+    index: Option<i32>,
+) -> Result<(), StreamError> {
+    let row: Option<(Option<String>, String)> = sqlx::query_as(
         r#"
-        UPDATE streams
-        SET status = ?, pid = ?, error_message = ?,
-            started_at = CASE WHEN ? = 'running' THEN datetime('now') ELSE started_at END
-        WHERE id = ?
+        SELECT m.subtitle_tracks, p.mode FROM streams s
+        JOIN media_files m ON m.id = s.media_file_id
+        JOIN profiles p ON p.id = s.profile_id
+        WHERE s.id = ?
         "#,
     )
-    .bind(status)
-    .bind(pid)
-    .bind(error_message)
-    .bind(status)
     .bind(id)
-    .execute(pool)
+    .fetch_optional(pool)
     .await?;
+    let Some((tracks, mode)) = row else {
+        return Err(StreamError::NotFound(id.to_string()));
+    };
+
+    if let Some(index) = index {
+        if mode == "copy" {
+            return Err(StreamError::InvalidSubtitles(
+                "burning in subtitles needs a transcode profile".to_string(),
+            ));
+        }
+        let tracks = parse_subtitle_tracks(tracks.as_deref());
+        if index < 0 || (!tracks.is_empty() && index as usize >= tracks.len()) {
+            return Err(StreamError::InvalidSubtitles(format!(
+                "track {} not found, the source has {}",
+                index,
+                tracks.len()
+            )));
+        }
+        if let Some(track) = tracks.get(index as usize).filter(|t| !t.is_text()) {
+            return Err(StreamError::InvalidSubtitles(format!(
+                "track {} is {}, only text subtitles can be burned in",
+                index,
+                track.codec.as_deref().unwrap_or("unknown")
+            )));
+        }
+    }
+
+    sqlx::query("UPDATE streams SET burn_subtitles = ? WHERE id = ?")
+        .bind(index)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Replace a stream's extra FFmpeg flags, applied from the next start.
+/// An empty list clears them.
+pub async fn set_extra_args(pool: &SqlitePool, id: &str, args: &[String]) -> Result<(), StreamError> {
+    command::validate_extra_args(args).map_err(StreamError::InvalidExtraArgs)?;
+    let json = if args.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(args).map_err(|e| StreamError::InvalidExtraArgs(e.to_string()))?)
+    };
+
+    let result = sqlx::query("UPDATE streams SET extra_args = ? WHERE id = ?")
+        .bind(json)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(StreamError::NotFound(id.to_string()));
+    }
     Ok(())
 }
 
@@ -135,3 +435,522 @@ pub async fn get_all_profiles(pool: &SqlitePool) -> Result<Vec<Profile>, StreamE
         .await?;
     Ok(profiles)
 }
+
+/// Check a profile's fields before it is stored
+pub fn validate_profile(profile: &Profile) -> Result<(), StreamError> {
+    let invalid = |msg: String| Err(StreamError::InvalidProfile(msg));
+
+    if profile.name.trim().is_empty() {
+        return invalid("name is required".into());
+    }
+    if !PROFILE_PROTOCOLS.contains(&profile.protocol.as_str()) {
+        return invalid(format!("unknown protocol '{}'", profile.protocol));
+    }
+    if !PROFILE_MODES.contains(&profile.mode.as_str()) {
+        return invalid(format!("unknown mode '{}'", profile.mode));
+    }
+    if !PROFILE_CODECS.contains(&profile.codec.as_str()) {
+        return invalid(format!("unknown codec '{}'", profile.codec));
+    }
+    if profile.mode == "videotoolbox" && !cfg!(target_os = "macos") {
+        return invalid("VideoToolbox is only available on macOS".into());
+    }
+    if profile.codec == "av1" && profile.mode != "nvenc" {
+        return invalid("AV1 is only available with NVENC".into());
+    }
+    if let Some(kbps) = profile.video_bitrate {
+        if !VIDEO_BITRATE_RANGE.contains(&kbps) {
+            return invalid(format!(
+                "video bitrate must be {}-{} kbps",
+                VIDEO_BITRATE_RANGE.start(),
+                VIDEO_BITRATE_RANGE.end()
+            ));
+        }
+    }
+    if let Some(kbps) = profile.audio_bitrate {
+        if !AUDIO_BITRATE_RANGE.contains(&kbps) {
+            return invalid(format!(
+                "audio bitrate must be {}-{} kbps",
+                AUDIO_BITRATE_RANGE.start(),
+                AUDIO_BITRATE_RANGE.end()
+            ));
+        }
+    }
+    if !GOP_SIZE_RANGE.contains(&profile.gop_size) {
+        return invalid(format!(
+            "GOP size must be {}-{} frames",
+            GOP_SIZE_RANGE.start(),
+            GOP_SIZE_RANGE.end()
+        ));
+    }
+    if let Some(ms) = profile.srt_latency_ms {
+        if !SRT_LATENCY_RANGE_MS.contains(&ms) {
+            return invalid(format!(
+                "SRT latency must be {}-{} ms",
+                SRT_LATENCY_RANGE_MS.start(),
+                SRT_LATENCY_RANGE_MS.end()
+            ));
+        }
+    }
+    if let Some(percent) = profile.srt_overhead_percent {
+        if !SRT_OVERHEAD_RANGE.contains(&percent) {
+            return invalid(format!(
+                "SRT overhead must be {}-{}%",
+                SRT_OVERHEAD_RANGE.start(),
+                SRT_OVERHEAD_RANGE.end()
+            ));
+        }
+    }
+    if let Some(ref text) = profile.overlay_text {
+        if text.trim().is_empty() {
+            return invalid("overlay text is empty".into());
+        }
+        // Copy mode never decodes the video, so there is nothing to draw on
+        if profile.mode == "copy" {
+            return invalid("text overlay needs a transcode mode".into());
+        }
+    }
+    if profile.tonemap == 1 && profile.mode == "copy" {
+        return invalid("tone-mapping needs a transcode mode".into());
+    }
+    if let Some(ref position) = profile.overlay_position {
+        if !OVERLAY_POSITIONS.contains(&position.as_str()) {
+            return invalid(format!("unknown overlay position '{}'", position));
+        }
+    }
+    if let Some(size) = profile.overlay_font_size {
+        if !OVERLAY_FONT_SIZE_RANGE.contains(&size) {
+            return invalid(format!(
+                "overlay font size must be {}-{} px",
+                OVERLAY_FONT_SIZE_RANGE.start(),
+                OVERLAY_FONT_SIZE_RANGE.end()
+            ));
+        }
+    }
+    if let Some(ref resolution) = profile.resolution {
+        let valid = resolution
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.parse::<u32>().ok()?, h.parse::<u32>().ok()?)))
+            .is_some_and(|(w, h)| w > 0 && h > 0);
+        if !valid {
+            return invalid(format!("resolution '{}' is not WIDTHxHEIGHT", resolution));
+        }
+    }
+    Ok(())
+}
+
+/// Create a profile under a new ID
+pub async fn create_profile(pool: &SqlitePool, mut profile: Profile) -> Result<Profile, StreamError> {
+    validate_profile(&profile)?;
+    profile.id = Uuid::new_v4().to_string();
+
+    sqlx::query(
+        r#"
+        INSERT INTO profiles (id, name, protocol, mode, video_bitrate, audio_bitrate, resolution, gop_size, wan_optimized, codec, record, record_dir, srt_latency_ms, srt_overhead_percent, overlay_text, overlay_position, overlay_font_size, tonemap)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&profile.id)
+    .bind(&profile.name)
+    .bind(&profile.protocol)
+    .bind(&profile.mode)
+    .bind(profile.video_bitrate)
+    .bind(profile.audio_bitrate)
+    .bind(&profile.resolution)
+    .bind(profile.gop_size)
+    .bind(profile.wan_optimized)
+    .bind(&profile.codec)
+    .bind(profile.record)
+    .bind(&profile.record_dir)
+    .bind(profile.srt_latency_ms)
+    .bind(profile.srt_overhead_percent)
+    .bind(&profile.overlay_text)
+    .bind(&profile.overlay_position)
+    .bind(profile.overlay_font_size)
+    .bind(profile.tonemap)
+    .execute(pool)
+    .await?;
+
+    Ok(profile)
+}
+
+/// Replace an existing profile's fields
+pub async fn update_profile(pool: &SqlitePool, profile: Profile) -> Result<Profile, StreamError> {
+    validate_profile(&profile)?;
+
+    let result = sqlx::query(
+        r#"
+        UPDATE profiles
+        SET name = ?, protocol = ?, mode = ?, video_bitrate = ?, audio_bitrate = ?, resolution = ?,
+            gop_size = ?, wan_optimized = ?, codec = ?, record = ?, record_dir = ?,
+            srt_latency_ms = ?, srt_overhead_percent = ?, overlay_text = ?,
+            overlay_position = ?, overlay_font_size = ?, tonemap = ?
+        WHERE id = ?
+        "#,
+    )
+    .bind(&profile.name)
+    .bind(&profile.protocol)
+    .bind(&profile.mode)
+    .bind(profile.video_bitrate)
+    .bind(profile.audio_bitrate)
+    .bind(&profile.resolution)
+    .bind(profile.gop_size)
+    .bind(profile.wan_optimized)
+    .bind(&profile.codec)
+    .bind(profile.record)
+    .bind(&profile.record_dir)
+    .bind(profile.srt_latency_ms)
+    .bind(profile.srt_overhead_percent)
+    .bind(&profile.overlay_text)
+    .bind(&profile.overlay_position)
+    .bind(profile.overlay_font_size)
+    .bind(profile.tonemap)
+    .bind(&profile.id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(StreamError::NotFound(format!("Profile: {}", profile.id)));
+    }
+    Ok(profile)
+}
+
+/// Delete a profile no stream refers to
+pub async fn delete_profile(pool: &SqlitePool, id: &str) -> Result<(), StreamError> {
+    let (in_use,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM streams WHERE profile_id = ?")
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+    if in_use > 0 {
+        return Err(StreamError::ProfileInUse(in_use));
+    }
+
+    let result = sqlx::query("DELETE FROM profiles WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(StreamError::NotFound(format!("Profile: {}", id)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom_profile() -> Profile {
+        Profile {
+            id: String::new(),
+            name: "Custom".into(),
+            protocol: "srt".into(),
+            mode: "nvenc".into(),
+            video_bitrate: Some(5000),
+            audio_bitrate: Some(128),
+            resolution: Some("1920x1080".into()),
+            gop_size: 60,
+            wan_optimized: 1,
+            codec: "hevc".into(),
+            record: 0,
+            record_dir: None,
+            srt_latency_ms: None,
+            srt_overhead_percent: None,
+            overlay_text: None,
+            overlay_position: None,
+            overlay_font_size: None,
+            tonemap: 0,
+        }
+    }
+
+    #[test]
+    fn test_validate_profile() {
+        assert!(validate_profile(&custom_profile()).is_ok());
+
+        let cases: [fn(&mut Profile); 15] = [
+            |p| p.name = " ".into(),
+            |p| p.protocol = "udp".into(),
+            |p| p.protocol = "hls".into(),
+            |p| p.protocol = "webrtc".into(),
+            |p| p.mode = "turbo".into(),
+            |p| p.video_bitrate = Some(10),
+            |p| p.gop_size = 0,
+            |p| p.resolution = Some("1080p".into()),
+            |p| p.srt_latency_ms = Some(10),
+            |p| p.srt_overhead_percent = Some(150),
+            |p| p.overlay_position = Some("center".into()),
+            |p| p.overlay_font_size = Some(2),
+            |p| {
+                p.tonemap = 1;
+                p.mode = "copy".into();
+            },
+            |p| {
+                p.overlay_text = Some("%{localtime}".into());
+                p.mode = "copy".into();
+            },
+            |p| {
+                p.codec = "av1".into();
+                p.mode = "cpu".into();
+            },
+        ];
+        for mutate in cases {
+            let mut profile = custom_profile();
+            mutate(&mut profile);
+            assert!(matches!(validate_profile(&profile), Err(StreamError::InvalidProfile(_))));
+        }
+
+        let mut videotoolbox = custom_profile();
+        videotoolbox.mode = "videotoolbox".into();
+        assert_eq!(validate_profile(&videotoolbox).is_ok(), cfg!(target_os = "macos"));
+    }
+
+    #[tokio::test]
+    async fn test_profile_round_trip() {
+        let pool = crate::db::test_pool().await;
+
+        let mut profile = create_profile(&pool, custom_profile()).await.unwrap();
+        assert!(!profile.id.is_empty());
+
+        profile.video_bitrate = Some(3000);
+        profile.srt_latency_ms = Some(1200);
+        update_profile(&pool, profile.clone()).await.unwrap();
+        let stored: Profile = sqlx::query_as("SELECT * FROM profiles WHERE id = ?")
+            .bind(&profile.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored.video_bitrate, Some(3000));
+        assert_eq!(stored.srt_latency_ms, Some(1200));
+        assert_eq!(stored.codec, "hevc");
+
+        delete_profile(&pool, &profile.id).await.unwrap();
+        assert!(matches!(
+            delete_profile(&pool, &profile.id).await,
+            Err(StreamError::NotFound(_))
+        ));
+        assert!(matches!(
+            update_profile(&pool, profile).await,
+            Err(StreamError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_delete_referenced_profile_fails() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query("INSERT INTO media_files (id, path, folder, filename, scanned_at) VALUES ('m1', '/test/m1.mp4', 'test', 'm1.mp4', '2024-01-01')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        create_stream(&pool, "m1", "cam", "lan-copy").await.unwrap();
+
+        assert!(matches!(
+            delete_profile(&pool, "lan-copy").await,
+            Err(StreamError::ProfileInUse(1))
+        ));
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("cam_01-main").is_ok());
+        for name in ["", "my stream/1", "cam:1", "caméra", &"a".repeat(MAX_NAME_LEN + 1)] {
+            assert!(matches!(validate_name(name), Err(StreamError::InvalidName(_))), "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_stream_checks_name() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query("INSERT INTO media_files (id, path, folder, filename, scanned_at) VALUES ('m1', '/test/m1.mp4', 'test', 'm1.mp4', '2024-01-01')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let err = create_stream(&pool, "m1", "my stream/1", "lan-copy").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid stream name: 'my stream/1' may only contain letters, digits, '-' and '_'"
+        );
+
+        create_stream(&pool, "m1", "cam", "lan-copy").await.unwrap();
+        assert!(matches!(
+            create_stream(&pool, "m1", "cam", "lan-copy").await,
+            Err(StreamError::AlreadyExists(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_loop_playback_defaults_on() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query("INSERT INTO media_files (id, path, folder, filename, scanned_at) VALUES ('m1', '/test/m1.mp4', 'test', 'm1.mp4', '2024-01-01')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let stream = create_stream(&pool, "m1", "cam", "lan-copy").await.unwrap();
+        assert_eq!(get_stream(&pool, &stream.id).await.unwrap().unwrap().loop_playback, 1);
+
+        set_loop_playback(&pool, &stream.id, false).await.unwrap();
+        assert_eq!(get_stream(&pool, &stream.id).await.unwrap().unwrap().loop_playback, 0);
+        assert!(matches!(
+            set_loop_playback(&pool, "missing", true).await,
+            Err(StreamError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_set_audio_track() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query(
+            r#"INSERT INTO media_files (id, path, folder, filename, scanned_at, audio_tracks)
+            VALUES ('m1', '/test/m1.mkv', 'test', 'm1.mkv', '2024-01-01', '[{"index":0,"language":"eng","codec":"aac","channels":2},{"index":1,"language":"fra","codec":"ac3","channels":6}]')"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let stream = create_stream(&pool, "m1", "cam", "lan-copy").await.unwrap();
+        assert_eq!(stream.audio_track_index, 0);
+
+        set_audio_track(&pool, &stream.id, 1).await.unwrap();
+        assert_eq!(get_stream(&pool, &stream.id).await.unwrap().unwrap().audio_track_index, 1);
+        assert!(matches!(
+            set_audio_track(&pool, &stream.id, 2).await,
+            Err(StreamError::InvalidAudioTrack(_))
+        ));
+        assert!(matches!(
+            set_audio_track(&pool, "missing", 0).await,
+            Err(StreamError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_set_burn_subtitles() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query(
+            r#"INSERT INTO media_files (id, path, folder, filename, scanned_at, subtitle_tracks)
+            VALUES ('m1', '/test/m1.mkv', 'test', 'm1.mkv', '2024-01-01',
+                '[{"index":0,"language":"eng","codec":"subrip","path":null},{"index":1,"language":"eng","codec":"hdmv_pgs_subtitle","path":null}]')"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let stream = create_stream(&pool, "m1", "cam", "lan-copy").await.unwrap();
+        assert!(matches!(
+            set_burn_subtitles(&pool, &stream.id, Some(0)).await,
+            Err(StreamError::InvalidSubtitles(_))
+        ));
+
+        sqlx::query("UPDATE streams SET profile_id = 'lan-high' WHERE id = ?")
+            .bind(&stream.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        set_burn_subtitles(&pool, &stream.id, Some(0)).await.unwrap();
+        assert_eq!(get_stream(&pool, &stream.id).await.unwrap().unwrap().burn_subtitles, Some(0));
+        // Bitmap subtitles can't go through the subtitles filter
+        assert!(matches!(
+            set_burn_subtitles(&pool, &stream.id, Some(1)).await,
+            Err(StreamError::InvalidSubtitles(_))
+        ));
+        assert!(matches!(
+            set_burn_subtitles(&pool, &stream.id, Some(2)).await,
+            Err(StreamError::InvalidSubtitles(_))
+        ));
+
+        set_burn_subtitles(&pool, &stream.id, None).await.unwrap();
+        assert_eq!(get_stream(&pool, &stream.id).await.unwrap().unwrap().burn_subtitles, None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_status_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = crate::db::init_pool(&dir.path().join("stress.db")).await.unwrap();
+        sqlx::query(
+            "INSERT INTO media_files (id, path, folder, filename, scanned_at)
+            VALUES ('m1', '/test/m1.mp4', 'test', 'm1.mp4', '2024-01-01')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let mut ids = Vec::new();
+        for i in 0..20 {
+            ids.push(create_stream(&pool, "m1", &format!("cam{}", i), "lan-copy").await.unwrap().id);
+        }
+
+        let tasks: Vec<_> = (0..200)
+            .map(|i| {
+                let pool = pool.clone();
+                let id = ids[i % ids.len()].clone();
+                let status = if i % 2 == 0 { "running" } else { "stopped" };
+                tokio::spawn(async move {
+                    update_stream_status(&pool, &id, status, Some(i as i32), None).await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_extra_args() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query(
+            "INSERT INTO media_files (id, path, folder, filename, scanned_at)
+            VALUES ('m1', '/test/m1.mp4', 'test', 'm1.mp4', '2024-01-01')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let stream = create_stream(&pool, "m1", "cam", "lan-copy").await.unwrap();
+
+        let args = vec!["-threads".to_string(), "2".to_string()];
+        set_extra_args(&pool, &stream.id, &args).await.unwrap();
+        let saved = get_stream(&pool, &stream.id).await.unwrap().unwrap();
+        assert_eq!(command::parse_extra_args(saved.extra_args.as_deref()), args);
+
+        assert!(matches!(
+            set_extra_args(&pool, &stream.id, &["-f".to_string(), "mp4".to_string()]).await,
+            Err(StreamError::InvalidExtraArgs(_))
+        ));
+        assert!(matches!(
+            set_extra_args(&pool, "missing", &args).await,
+            Err(StreamError::NotFound(_))
+        ));
+
+        set_extra_args(&pool, &stream.id, &[]).await.unwrap();
+        assert_eq!(get_stream(&pool, &stream.id).await.unwrap().unwrap().extra_args, None);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_stale_streams() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query("INSERT INTO media_files (id, path, folder, filename, scanned_at) VALUES ('m1', '/test/m1.mp4', 'test', 'm1.mp4', '2024-01-01')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let alive = create_stream(&pool, "m1", "alive", "lan-copy").await.unwrap();
+        let dead = create_stream(&pool, "m1", "dead", "lan-copy").await.unwrap();
+        let idle = create_stream(&pool, "m1", "idle", "lan-copy").await.unwrap();
+        update_stream_status(&pool, &alive.id, "running", Some(100), None).await.unwrap();
+        update_stream_status(&pool, &dead.id, "running", Some(200), None).await.unwrap();
+
+        let killed = std::sync::Mutex::new(Vec::new());
+        let reset = reconcile_stale_streams(&pool, |pid| {
+            killed.lock().unwrap().push(pid);
+            pid == 100
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(reset, 2);
+        let mut killed = killed.into_inner().unwrap();
+        killed.sort();
+        assert_eq!(killed, [100, 200]);
+        for stream in [&alive, &dead, &idle] {
+            let stream = get_stream(&pool, &stream.id).await.unwrap().unwrap();
+            assert_eq!(stream.status, "stopped");
+            assert_eq!(stream.pid, None);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Additional stream outputs (fan-out)
+//! One encode published to several MediaMTX paths at once
+
+use super::{StreamError, PROFILE_PROTOCOLS};
+use crate::db::schema::StreamOutput;
+use sqlx::sqlite::SqlitePool;
+use uuid::Uuid;
+
+/// Add an output to a stream; it is used from the stream's next start
+pub async fn add_output(
+    pool: &SqlitePool,
+    stream_id: &str,
+    protocol: &str,
+    name: &str,
+    wan_optimized: bool,
+) -> Result<StreamOutput, StreamError> {
+    if !PROFILE_PROTOCOLS.contains(&protocol) {
+        return Err(StreamError::InvalidOutput(format!("unknown protocol '{}'", protocol)));
+    }
+    if super::get_stream(pool, stream_id).await?.is_none() {
+        return Err(StreamError::NotFound(stream_id.to_string()));
+    }
+    super::ensure_name_available(pool, name).await?;
+
+    let id = Uuid::new_v4().to_string();
+    sqlx::query(
+        "INSERT INTO stream_outputs (id, stream_id, protocol, name, wan_optimized) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(&id)
+    .bind(stream_id)
+    .bind(protocol)
+    .bind(name)
+    .bind(wan_optimized as i32)
+    .execute(pool)
+    .await?;
+
+    let output = sqlx::query_as("SELECT * FROM stream_outputs WHERE id = ?")
+        .bind(&id)
+        .fetch_one(pool)
+        .await?;
+    Ok(output)
+}
+
+/// Outputs of a stream, oldest first
+pub async fn get_outputs(pool: &SqlitePool, stream_id: &str) -> Result<Vec<StreamOutput>, StreamError> {
+    let outputs = sqlx::query_as(
+        "SELECT * FROM stream_outputs WHERE stream_id = ? ORDER BY created_at, name",
+    )
+    .bind(stream_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(outputs)
+}
+
+/// The output publishing to MediaMTX path `name`, if any
+pub async fn get_output_by_name(pool: &SqlitePool, name: &str) -> Result<Option<StreamOutput>, StreamError> {
+    let output = sqlx::query_as("SELECT * FROM stream_outputs WHERE name = ?")
+        .bind(name)
+        .fetch_optional(pool)
+        .await?;
+    Ok(output)
+}
+
+/// Remove an output; a running stream keeps publishing to it until restarted
+pub async fn remove_output(pool: &SqlitePool, id: &str) -> Result<(), StreamError> {
+    let result = sqlx::query("DELETE FROM stream_outputs WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(StreamError::NotFound(format!("Output: {}", id)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn pool_with_stream() -> SqlitePool {
+        let pool = crate::db::test_pool().await;
+        sqlx::query("INSERT INTO streams (id, name) VALUES ('s1', 'cam')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_output() {
+        let pool = pool_with_stream().await;
+        let output = add_output(&pool, "s1", "srt", "cam-wan", true).await.unwrap();
+        assert_eq!(output.wan_optimized, 1);
+        assert_eq!(get_outputs(&pool, "s1").await.unwrap(), vec![output.clone()]);
+        assert_eq!(get_output_by_name(&pool, &output.name).await.unwrap(), Some(output.clone()));
+
+        // Read-only protocols can't be published to
+        assert!(matches!(
+            add_output(&pool, "s1", "hls", "cam-hls", false).await,
+            Err(StreamError::InvalidOutput(_))
+        ));
+
+        remove_output(&pool, &output.id).await.unwrap();
+        assert!(get_outputs(&pool, "s1").await.unwrap().is_empty());
+        assert!(matches!(
+            remove_output(&pool, &output.id).await,
+            Err(StreamError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_output_names_are_unique() {
+        let pool = pool_with_stream().await;
+        add_output(&pool, "s1", "rtsp", "cam-2", false).await.unwrap();
+
+        for name in ["cam", "cam-2"] {
+            assert!(matches!(
+                add_output(&pool, "s1", "rtsp", name, false).await,
+                Err(StreamError::AlreadyExists(_))
+            ));
+        }
+        assert!(matches!(
+            add_output(&pool, "s1", "ftp", "cam-3", false).await,
+            Err(StreamError::InvalidOutput(_))
+        ));
+        assert!(matches!(
+            add_output(&pool, "s1", "rtsp", "cam 3", false).await,
+            Err(StreamError::InvalidName(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_outputs_deleted_with_stream() {
+        let pool = pool_with_stream().await;
+        add_output(&pool, "s1", "srt", "cam-wan", true).await.unwrap();
+        crate::stream::delete_stream(&pool, "s1").await.unwrap();
+        assert!(get_outputs(&pool, "s1").await.unwrap().is_empty());
+    }
+}
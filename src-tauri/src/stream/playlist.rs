@@ -0,0 +1,379 @@
+//! Playlist streams
+//! Play several files back to back as one live stream without merging them
+//! first. Files are joined by the concat demuxer; mixed ones are first
+//! normalized to one format through the cache.
+
+use super::command::{build_fanout_args, effective_mode, OutputTarget, PlaybackOptions};
+use super::StreamError;
+use crate::db::schema::{MediaFile, Profile, Stream};
+use crate::merge::concat::create_concat_list;
+use crate::merge::normalize::NormalizeConfig;
+use crate::merge::{check_merge_compatibility, MergeStrategy};
+use crate::scanner::metadata::is_url;
+use sqlx::sqlite::SqlitePool;
+use std::path::{Path, PathBuf};
+
+/// Create a stream that plays `media_file_ids` in order
+pub async fn create_playlist_stream(
+    pool: &SqlitePool,
+    name: &str,
+    media_file_ids: &[String],
+    profile_id: &str,
+) -> Result<Stream, StreamError> {
+    if media_file_ids.len() < 2 {
+        return Err(StreamError::InvalidPlaylist(
+            "a playlist needs at least two files".to_string(),
+        ));
+    }
+    for id in media_file_ids {
+        let path: Option<(String,)> = sqlx::query_as("SELECT path FROM media_files WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+        match path {
+            None => return Err(StreamError::NotFound(format!("Media file: {}", id))),
+            Some((path,)) if is_url(&path) => {
+                return Err(StreamError::InvalidPlaylist(format!(
+                    "live URL sources can't be played in a playlist: {}",
+                    id
+                )));
+            }
+            Some(_) => {}
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+    let stream = super::insert_stream(&mut tx, &media_file_ids[0], name, profile_id).await?;
+    for (position, media_file_id) in media_file_ids.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO stream_playlist_items (stream_id, position, media_file_id) VALUES (?, ?, ?)",
+        )
+        .bind(&stream.id)
+        .bind(position as i64)
+        .bind(media_file_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(stream)
+}
+
+/// Files of a playlist stream in play order; empty for single-file streams
+pub async fn get_playlist(pool: &SqlitePool, stream_id: &str) -> Result<Vec<MediaFile>, StreamError> {
+    let files: Vec<MediaFile> = sqlx::query_as(
+        r#"
+        SELECT m.* FROM stream_playlist_items p
+        JOIN media_files m ON m.id = p.media_file_id
+        WHERE p.stream_id = ?
+        ORDER BY p.position
+        "#,
+    )
+    .bind(stream_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(files)
+}
+
+/// Mode the playlist really runs in, as `effective_mode` decides it for
+/// the format of the files it plays
+pub fn playlist_mode<'a>(profile: &'a Profile, files: &[MediaFile]) -> &'a str {
+    match playlist_media(profile, files) {
+        Some(media) => effective_mode(profile, &media),
+        None => &profile.mode,
+    }
+}
+
+/// The format a playlist's encode starts from: its first file, or for mixed
+/// playlists the format `normalize_config` gives every file. Audio is there
+/// if any file has it.
+fn playlist_media(profile: &Profile, files: &[MediaFile]) -> Option<MediaFile> {
+    let mut media = files.first()?.clone();
+    if needs_normalize(files) {
+        let config = normalize_config(profile);
+        media.video_codec = Some(config.target_codec);
+        media.width = Some(config.target_width as i32);
+        media.height = Some(config.target_height as i32);
+        media.fps = Some(config.target_fps);
+        media.profile = None;
+        media.level = None;
+        // Normalizing decodes upright and keeps 8-bit SDR
+        media.rotation = None;
+        media.pix_fmt = None;
+        media.hdr = 0;
+        media.compatibility = "copy".to_string();
+    }
+    if media.audio_codec.is_none() {
+        media.audio_codec = files.iter().find_map(|f| f.audio_codec.clone());
+    }
+    media.subtitle_tracks = None;
+    Some(media)
+}
+
+/// Whether the files differ too much to be joined as they are, so each has
+/// to be normalized to `normalize_config` first
+pub fn needs_normalize(files: &[MediaFile]) -> bool {
+    check_merge_compatibility(files) == MergeStrategy::TranscodeNormalize
+}
+
+/// Write the concat list a playlist plays from. `sources` are the files
+/// themselves, or their normalized copies when the playlist
+/// `needs_normalize`. The list has to outlive FFmpeg, so it is kept next to
+/// the app data rather than in a temp file.
+pub fn write_playlist_list(sources: &[PathBuf], list_path: &Path) -> Result<(), StreamError> {
+    if sources.is_empty() {
+        return Err(StreamError::InvalidPlaylist("playlist is empty".to_string()));
+    }
+    let list = create_concat_list(sources).map_err(|e| StreamError::Process(e.to_string()))?;
+    std::fs::copy(list.path(), list_path).map_err(|e| StreamError::Process(e.to_string()))?;
+    Ok(())
+}
+
+/// FFmpeg arguments that play `files` in order to every output, read from
+/// the concat list at `list_path`. The encode follows the profile's mode
+/// like any other stream's; `playback` loops the whole list.
+pub fn build_playlist_args(
+    files: &[MediaFile],
+    list_path: &Path,
+    outputs: &[OutputTarget],
+    profile: &Profile,
+    gpu: Option<u32>,
+    playback: PlaybackOptions<'_>,
+) -> Result<Vec<String>, StreamError> {
+    let Some(media) = playlist_media(profile, files) else {
+        return Err(StreamError::InvalidPlaylist("playlist is empty".to_string()));
+    };
+
+    let playback = PlaybackOptions {
+        audio_track: 0,
+        burn_subtitles: None,
+        concat_list: Some(list_path),
+        ..playback
+    };
+    Ok(build_fanout_args(&media, profile, outputs, None, gpu, playback))
+}
+
+/// Common format for mixed playlists, taken from the profile where it sets one
+pub fn normalize_config(profile: &Profile) -> NormalizeConfig {
+    let mut config = NormalizeConfig::default();
+    if let Some((w, h)) = profile
+        .resolution
+        .as_deref()
+        .and_then(|r| r.split_once('x'))
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+    {
+        config.target_width = w;
+        config.target_height = h;
+    }
+    if let Some(kbps) = profile.video_bitrate {
+        config.target_bitrate = kbps as u32;
+    }
+    if let Some(kbps) = profile.audio_bitrate {
+        config.target_audio_bitrate = kbps as u32;
+    }
+    if profile.codec == "hevc" {
+        config.target_codec = "hevc".into();
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sidecar::mediamtx::PortConfig;
+
+    async fn pool_with_media(paths: &[(&str, &str)]) -> SqlitePool {
+        let pool = crate::db::test_pool().await;
+        for (id, path) in paths {
+            sqlx::query(
+                r#"
+                INSERT INTO media_files (id, path, folder, filename, video_codec, audio_codec, width, height, fps, compatibility, scanned_at)
+                VALUES (?, ?, 'test', ?, 'h264', 'aac', 1920, 1080, 30.0, 'copy', '2024-01-01')
+                "#,
+            )
+            .bind(id)
+            .bind(path)
+            .bind(path)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        pool
+    }
+
+    async fn lan_copy(pool: &SqlitePool) -> Profile {
+        sqlx::query_as("SELECT * FROM profiles WHERE id = 'lan-copy'")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_playlist_plays_files_in_order() {
+        let pool = pool_with_media(&[("m1", "/clips/a.mp4"), ("m2", "/clips/b.mp4")]).await;
+        let ids = vec!["m2".to_string(), "m1".to_string()];
+        let stream = create_playlist_stream(&pool, "show", &ids, "lan-copy").await.unwrap();
+
+        let files = get_playlist(&pool, &stream.id).await.unwrap();
+        let order: Vec<&str> = files.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(order, ["m2", "m1"]);
+        let profile = lan_copy(&pool).await;
+        assert_eq!(playlist_mode(&profile, &files), "copy");
+
+        let dir = tempfile::tempdir().unwrap();
+        let list_path = dir.path().join("show.txt");
+        let output = OutputTarget {
+            protocol: "rtsp",
+            name: "show",
+            auth: None,
+            wan_mode: false,
+            ports: PortConfig::default(),
+        };
+        assert!(!needs_normalize(&files));
+        let sources: Vec<PathBuf> = files.iter().map(|f| PathBuf::from(&f.path)).collect();
+        write_playlist_list(&sources, &list_path).unwrap();
+        let args = build_playlist_args(
+            &files,
+            &list_path,
+            &[output],
+            &profile,
+            None,
+            PlaybackOptions::default(),
+        )
+        .unwrap();
+
+        let list = std::fs::read_to_string(&list_path).unwrap();
+        assert_eq!(list, "file '/clips/b.mp4'\nfile '/clips/a.mp4'\n");
+        assert!(args.windows(2).any(|w| w[0] == "-f" && w[1] == "concat"));
+        assert!(args.contains(&"-stream_loop".to_string()));
+        assert_eq!(args.last().unwrap(), "rtsp://localhost:8554/show");
+    }
+
+    #[tokio::test]
+    async fn test_mixed_playlist_plays_normalized_copies() {
+        let pool = pool_with_media(&[("m1", "/clips/a.mp4"), ("m2", "/clips/b.mp4")]).await;
+        sqlx::query("UPDATE media_files SET width = 1280, height = 720 WHERE id = 'm2'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let ids = vec!["m1".to_string(), "m2".to_string()];
+        let stream = create_playlist_stream(&pool, "show", &ids, "lan-copy").await.unwrap();
+        let files = get_playlist(&pool, &stream.id).await.unwrap();
+        assert!(needs_normalize(&files));
+        // Normalized copies share one format, so a copy profile still copies
+        let profile = lan_copy(&pool).await;
+        assert_eq!(playlist_mode(&profile, &files), "copy");
+
+        let dir = tempfile::tempdir().unwrap();
+        let list_path = dir.path().join("show.txt");
+        let sources = vec![PathBuf::from("/cache/aaaa.ts"), PathBuf::from("/cache/bbbb.ts")];
+        let output = OutputTarget {
+            protocol: "rtsp",
+            name: "show",
+            auth: None,
+            wan_mode: false,
+            ports: PortConfig::default(),
+        };
+        write_playlist_list(&sources, &list_path).unwrap();
+        let args = build_playlist_args(
+            &files,
+            &list_path,
+            &[output],
+            &profile,
+            None,
+            PlaybackOptions::default(),
+        )
+        .unwrap();
+
+        let list = std::fs::read_to_string(&list_path).unwrap();
+        assert_eq!(list, "file '/cache/aaaa.ts'\nfile '/cache/bbbb.ts'\n");
+        assert!(args.contains(&"-stream_loop".to_string()));
+        assert_eq!(args.last().unwrap(), "rtsp://localhost:8554/show");
+    }
+
+    #[tokio::test]
+    async fn test_playlist_follows_profile_and_outputs() {
+        let pool = pool_with_media(&[("m1", "/clips/a.mp4"), ("m2", "/clips/b.mp4")]).await;
+        let ids = vec!["m1".to_string(), "m2".to_string()];
+        let stream = create_playlist_stream(&pool, "show", &ids, "lan-copy").await.unwrap();
+        let files = get_playlist(&pool, &stream.id).await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let list_path = dir.path().join("show.txt");
+        let mut profile = lan_copy(&pool).await;
+        profile.mode = "vaapi".into();
+        let outputs = [
+            OutputTarget {
+                protocol: "rtsp",
+                name: "show",
+                auth: None,
+                wan_mode: false,
+                ports: PortConfig::default(),
+            },
+            OutputTarget {
+                protocol: "srt",
+                name: "show-srt",
+                auth: None,
+                wan_mode: false,
+                ports: PortConfig::default(),
+            },
+        ];
+        let playback = PlaybackOptions {
+            loop_input: false,
+            ..Default::default()
+        };
+        let args = build_playlist_args(&files, &list_path, &outputs, &profile, None, playback).unwrap();
+        // Building the command writes nothing; the list is written at launch
+        assert!(!list_path.exists());
+
+        assert!(args.windows(2).any(|w| w[0] == "-vaapi_device"));
+        assert!(args.windows(2).any(|w| w[0] == "-c:v" && w[1] == "h264_vaapi"));
+        assert!(!args.contains(&"-stream_loop".to_string()));
+        let tee = args.iter().position(|a| a == "tee").unwrap();
+        assert_eq!(args[tee - 1], "-f");
+        assert!(args[tee + 1].contains("/show|"));
+        assert!(args[tee + 1].contains("streamid=publish:show-srt"));
+    }
+
+    #[tokio::test]
+    async fn test_create_playlist_validation() {
+        let pool = pool_with_media(&[("m1", "/clips/a.mp4"), ("cam", "rtsp://10.0.0.5/live")]).await;
+
+        let one = vec!["m1".to_string()];
+        assert!(matches!(
+            create_playlist_stream(&pool, "show", &one, "lan-copy").await,
+            Err(StreamError::InvalidPlaylist(_))
+        ));
+        let missing = vec!["m1".to_string(), "nope".to_string()];
+        assert!(matches!(
+            create_playlist_stream(&pool, "show", &missing, "lan-copy").await,
+            Err(StreamError::NotFound(_))
+        ));
+        let live = vec!["m1".to_string(), "cam".to_string()];
+        assert!(matches!(
+            create_playlist_stream(&pool, "show", &live, "lan-copy").await,
+            Err(StreamError::InvalidPlaylist(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_playlist_leaves_no_stream() {
+        let pool = pool_with_media(&[("m1", "/clips/a.mp4"), ("m2", "/clips/b.mp4")]).await;
+        // Fail the second item, after the stream row is already written
+        let ids = vec!["m1".to_string(), "m2".to_string()];
+        sqlx::query(
+            "CREATE TRIGGER fail_items BEFORE INSERT ON stream_playlist_items WHEN NEW.position = 1 \
+             BEGIN SELECT RAISE(ABORT, 'boom'); END",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert!(create_playlist_stream(&pool, "show", &ids, "lan-copy").await.is_err());
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM streams")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+}
@@ -0,0 +1,105 @@
+//! Local recordings of broadcast streams
+//! Tracks the MP4 files written alongside live output
+
+use super::StreamError;
+use crate::db::schema::Recording;
+use chrono::Local;
+use sqlx::sqlite::SqlitePool;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Timestamped MP4 path for a new recording of `stream_name`
+pub fn recording_path(dir: &Path, stream_name: &str) -> PathBuf {
+    dir.join(format!(
+        "{}-{}.mp4",
+        stream_name,
+        Local::now().format("%Y%m%d-%H%M%S")
+    ))
+}
+
+/// Record that a stream started writing to `path`
+pub async fn start_recording(
+    pool: &SqlitePool,
+    stream_id: &str,
+    path: &Path,
+) -> Result<Recording, StreamError> {
+    let id = Uuid::new_v4().to_string();
+    sqlx::query("INSERT INTO recordings (id, stream_id, path) VALUES (?, ?, ?)")
+        .bind(&id)
+        .bind(stream_id)
+        .bind(path.to_string_lossy().to_string())
+        .execute(pool)
+        .await?;
+
+    let recording = sqlx::query_as("SELECT * FROM recordings WHERE id = ?")
+        .bind(&id)
+        .fetch_one(pool)
+        .await?;
+    Ok(recording)
+}
+
+/// Close any open recordings of a stream, filling in end time and duration
+pub async fn finish_recordings(pool: &SqlitePool, stream_id: &str) -> Result<u64, StreamError> {
+    let result = sqlx::query(
+        r#"
+        UPDATE recordings
+        SET ended_at = datetime('now'),
+            duration_secs = (julianday('now') - julianday(started_at)) * 86400.0
+        WHERE stream_id = ? AND ended_at IS NULL
+        "#,
+    )
+    .bind(stream_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// List recordings, newest first, optionally for a single stream
+pub async fn get_recordings(
+    pool: &SqlitePool,
+    stream_id: Option<&str>,
+) -> Result<Vec<Recording>, StreamError> {
+    let recordings = match stream_id {
+        Some(id) => {
+            sqlx::query_as("SELECT * FROM recordings WHERE stream_id = ? ORDER BY started_at DESC")
+                .bind(id)
+                .fetch_all(pool)
+                .await?
+        }
+        None => {
+            sqlx::query_as("SELECT * FROM recordings ORDER BY started_at DESC")
+                .fetch_all(pool)
+                .await?
+        }
+    };
+    Ok(recordings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_recording_lifecycle() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query("INSERT INTO streams (id, name) VALUES ('s1', 'cam1')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let path = recording_path(Path::new("/rec"), "cam1");
+        assert!(path.file_name().unwrap().to_string_lossy().starts_with("cam1-"));
+
+        let recording = start_recording(&pool, "s1", &path).await.unwrap();
+        assert!(recording.ended_at.is_none());
+
+        assert_eq!(finish_recordings(&pool, "s1").await.unwrap(), 1);
+        // Already closed recordings are left alone
+        assert_eq!(finish_recordings(&pool, "s1").await.unwrap(), 0);
+
+        let recordings = get_recordings(&pool, Some("s1")).await.unwrap();
+        assert_eq!(recordings.len(), 1);
+        assert!(recordings[0].ended_at.is_some());
+        assert!(recordings[0].duration_secs.unwrap() >= 0.0);
+    }
+}
@@ -0,0 +1,230 @@
+//! Scheduled stream start/stop
+//! Streams can be given a time to go live and a time to stop; the app's
+//! schedule loop picks them up with `take_due_actions`
+
+use super::StreamError;
+use crate::db::schema::Stream;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::sqlite::SqlitePool;
+
+/// What a stream's schedule asks for now
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleAction {
+    Start,
+    Stop,
+}
+
+/// A scheduled action whose time has come
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DueAction {
+    pub stream_id: String,
+    pub action: ScheduleAction,
+}
+
+/// Parse an RFC 3339 timestamp from the frontend
+pub fn parse_time(value: &str) -> Result<DateTime<Utc>, StreamError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| StreamError::InvalidSchedule(format!("{}: {}", value, e)))
+}
+
+/// Set or clear a stream's scheduled start and stop times
+pub async fn set_schedule(
+    pool: &SqlitePool,
+    stream_id: &str,
+    start: Option<DateTime<Utc>>,
+    stop: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<(), StreamError> {
+    if let (Some(start), Some(stop)) = (start, stop) {
+        if stop <= start {
+            return Err(StreamError::InvalidSchedule(
+                "stop time must be after start time".to_string(),
+            ));
+        }
+    }
+    if stop.is_some_and(|stop| stop <= now) {
+        return Err(StreamError::InvalidSchedule("stop time is in the past".to_string()));
+    }
+
+    let result = sqlx::query("UPDATE streams SET scheduled_start = ?, scheduled_stop = ? WHERE id = ?")
+        .bind(start.map(|t| t.to_rfc3339()))
+        .bind(stop.map(|t| t.to_rfc3339()))
+        .bind(stream_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(StreamError::NotFound(stream_id.to_string()));
+    }
+    Ok(())
+}
+
+/// Collect the starts and stops due at `now` and clear them from their streams.
+///
+/// A start whose stop time has also passed (the app was offline through the
+/// whole window) is dropped rather than started late. Starts are skipped for
+/// streams that are already running or queued, and stops for streams that
+/// aren't.
+pub async fn take_due_actions(
+    pool: &SqlitePool,
+    now: DateTime<Utc>,
+) -> Result<Vec<DueAction>, StreamError> {
+    let streams: Vec<Stream> = sqlx::query_as(
+        "SELECT * FROM streams WHERE scheduled_start IS NOT NULL OR scheduled_stop IS NOT NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut due = Vec::new();
+    for stream in streams {
+        let start = stream.scheduled_start.as_deref().and_then(|t| parse_time(t).ok());
+        let stop = stream.scheduled_stop.as_deref().and_then(|t| parse_time(t).ok());
+        let active = matches!(stream.status.as_str(), "running" | "starting" | "queued");
+
+        if start.is_some_and(|start| start <= now) {
+            if stop.is_some_and(|stop| stop <= now) {
+                println!("[Schedule] Skipping start of {}: its window already ended", stream.name);
+                clear(pool, &stream.id, "scheduled_start = NULL, scheduled_stop = NULL").await?;
+                super::events::record_event(
+                    pool,
+                    &stream.id,
+                    "schedule_missed",
+                    Some("Scheduled window ended while the app was offline"),
+                )
+                .await?;
+                continue;
+            }
+            clear(pool, &stream.id, "scheduled_start = NULL").await?;
+            if !active {
+                due.push(DueAction {
+                    stream_id: stream.id,
+                    action: ScheduleAction::Start,
+                });
+            }
+        } else if stop.is_some_and(|stop| stop <= now) {
+            clear(pool, &stream.id, "scheduled_stop = NULL").await?;
+            if active {
+                due.push(DueAction {
+                    stream_id: stream.id,
+                    action: ScheduleAction::Stop,
+                });
+            }
+        }
+    }
+
+    Ok(due)
+}
+
+async fn clear(pool: &SqlitePool, stream_id: &str, assignments: &str) -> Result<(), StreamError> {
+    sqlx::query(&format!("UPDATE streams SET {} WHERE id = ?", assignments))
+        .bind(stream_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    async fn pool_with_stream(status: &str) -> SqlitePool {
+        let pool = crate::db::test_pool().await;
+        sqlx::query("INSERT INTO streams (id, name, status) VALUES ('s1', 'cam', ?)")
+            .bind(status)
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+
+    async fn schedule_of(pool: &SqlitePool) -> (Option<String>, Option<String>) {
+        sqlx::query_as("SELECT scheduled_start, scheduled_stop FROM streams WHERE id = 's1'")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_start_then_stop_when_due() {
+        let pool = pool_with_stream("stopped").await;
+        let now = Utc::now();
+        let start = now + Duration::seconds(10);
+        let stop = now + Duration::minutes(30);
+        set_schedule(&pool, "s1", Some(start), Some(stop), now).await.unwrap();
+
+        assert!(take_due_actions(&pool, now).await.unwrap().is_empty());
+
+        let due = take_due_actions(&pool, start).await.unwrap();
+        assert_eq!(due, vec![DueAction { stream_id: "s1".into(), action: ScheduleAction::Start }]);
+        // Consumed, so the next tick doesn't start it again
+        assert!(take_due_actions(&pool, start).await.unwrap().is_empty());
+
+        sqlx::query("UPDATE streams SET status = 'running' WHERE id = 's1'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let due = take_due_actions(&pool, stop + Duration::seconds(1)).await.unwrap();
+        assert_eq!(due, vec![DueAction { stream_id: "s1".into(), action: ScheduleAction::Stop }]);
+        assert_eq!(schedule_of(&pool).await, (None, None));
+    }
+
+    #[tokio::test]
+    async fn test_window_missed_while_offline_is_not_started() {
+        let pool = pool_with_stream("stopped").await;
+        let now = Utc::now();
+        let start = now + Duration::minutes(1);
+        let stop = now + Duration::minutes(2);
+        set_schedule(&pool, "s1", Some(start), Some(stop), now).await.unwrap();
+
+        // First tick after the app comes back is past the whole window
+        let due = take_due_actions(&pool, now + Duration::hours(1)).await.unwrap();
+        assert!(due.is_empty());
+        assert_eq!(schedule_of(&pool).await, (None, None));
+
+        let events = super::super::events::get_events(&pool, "s1", 10).await.unwrap();
+        assert_eq!(events[0].event_type, "schedule_missed");
+    }
+
+    #[tokio::test]
+    async fn test_late_start_inside_window_still_starts() {
+        let pool = pool_with_stream("stopped").await;
+        let now = Utc::now();
+        let start = now + Duration::minutes(1);
+        set_schedule(&pool, "s1", Some(start), Some(now + Duration::hours(1)), now)
+            .await
+            .unwrap();
+
+        let due = take_due_actions(&pool, now + Duration::minutes(10)).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].action, ScheduleAction::Start);
+    }
+
+    #[tokio::test]
+    async fn test_set_schedule_validation() {
+        let pool = pool_with_stream("stopped").await;
+        let now = Utc::now();
+        let later = now + Duration::minutes(5);
+
+        assert!(matches!(
+            set_schedule(&pool, "s1", Some(later), Some(now + Duration::minutes(1)), now).await,
+            Err(StreamError::InvalidSchedule(_))
+        ));
+        assert!(matches!(
+            set_schedule(&pool, "s1", None, Some(now - Duration::minutes(1)), now).await,
+            Err(StreamError::InvalidSchedule(_))
+        ));
+        assert!(matches!(
+            set_schedule(&pool, "missing", Some(later), None, now).await,
+            Err(StreamError::NotFound(_))
+        ));
+        assert!(parse_time("tomorrow").is_err());
+
+        // Clearing both removes the schedule
+        set_schedule(&pool, "s1", Some(later), None, now).await.unwrap();
+        set_schedule(&pool, "s1", None, None, now).await.unwrap();
+        assert_eq!(schedule_of(&pool).await, (None, None));
+    }
+}
@@ -0,0 +1,143 @@
+//! Snapshot capture for live streams
+//! Grabs a single JPEG frame from a stream's MediaMTX output
+
+use super::command::get_stream_url;
+use super::StreamError;
+use crate::security::auth::StreamAuth;
+use crate::sidecar::find_binary;
+use crate::sidecar::mediamtx::PortConfig;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// How long to wait for a frame before giving up on the stream
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Capture a JPEG of a running stream into `out_dir/<stream_name>.jpg`.
+/// Repeated calls overwrite the previous snapshot. `auth` reads paths that
+/// require credentials, as WAN ones do.
+pub async fn capture_snapshot(
+    stream_name: &str,
+    out_dir: &Path,
+    auth: Option<&StreamAuth>,
+    ports: &PortConfig,
+) -> Result<PathBuf, StreamError> {
+    let ffmpeg = find_binary("ffmpeg")
+        .ok_or_else(|| StreamError::Process("FFmpeg not found".to_string()))?;
+    let url = get_stream_url("rtsp", stream_name, auth, ports);
+    capture_snapshot_with(&ffmpeg, &url, stream_name, out_dir, SNAPSHOT_TIMEOUT).await
+}
+
+async fn capture_snapshot_with(
+    ffmpeg: &Path,
+    url: &str,
+    stream_name: &str,
+    out_dir: &Path,
+    wait: Duration,
+) -> Result<PathBuf, StreamError> {
+    tokio::fs::create_dir_all(out_dir)
+        .await
+        .map_err(|e| StreamError::Process(format!("Failed to create snapshot dir: {}", e)))?;
+
+    // Write beside the target and rename, so pollers never see half a JPEG
+    let output = out_dir.join(format!("{}.jpg", stream_name));
+    let partial = out_dir.join(format!("{}.partial.jpg", stream_name));
+
+    let child = Command::new(ffmpeg)
+        .args(["-hide_banner", "-loglevel", "error", "-y"])
+        .args(["-rtsp_transport", "tcp", "-i", url])
+        .args(["-frames:v", "1", "-q:v", "3"])
+        .arg(&partial)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| StreamError::Process(format!("Failed to spawn FFmpeg: {}", e)))?;
+
+    let result = match tokio::time::timeout(wait, child.wait_with_output()).await {
+        Ok(result) => result.map_err(|e| StreamError::Process(e.to_string()))?,
+        Err(_) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(StreamError::NotPublishing(format!(
+                "{} (no frame within {}s)",
+                stream_name,
+                wait.as_secs()
+            )));
+        }
+    };
+
+    if !result.status.success() {
+        let _ = tokio::fs::remove_file(&partial).await;
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        // MediaMTX answers 404 for paths nobody is publishing to
+        if stderr.contains("404") || stderr.contains("Not Found") {
+            return Err(StreamError::NotPublishing(stream_name.to_string()));
+        }
+        return Err(StreamError::Process(format!(
+            "Snapshot failed: {}",
+            stderr.lines().last().unwrap_or("unknown error")
+        )));
+    }
+
+    tokio::fs::rename(&partial, &output)
+        .await
+        .map_err(|e| StreamError::Process(format!("Failed to save snapshot: {}", e)))?;
+    Ok(output)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Fake ffmpeg running `body`; the output path is its last argument
+    fn fake_ffmpeg(dir: &Path, body: &str) -> PathBuf {
+        let path = dir.join("ffmpeg");
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let ffmpeg = fake_ffmpeg(dir.path(), r#"for last; do :; done; echo jpeg > "$last""#);
+        let out = dir.path().join("snaps");
+
+        let path = capture_snapshot_with(&ffmpeg, "rtsp://x/cam", "cam", &out, SNAPSHOT_TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(path, out.join("cam.jpg"));
+        assert!(path.exists());
+        assert!(!out.join("cam.partial.jpg").exists());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_not_publishing() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("snaps");
+
+        let ffmpeg = fake_ffmpeg(
+            dir.path(),
+            "echo 'method DESCRIBE failed: 404 Not Found' >&2; exit 1",
+        );
+        let err = capture_snapshot_with(&ffmpeg, "rtsp://x/cam", "cam", &out, SNAPSHOT_TIMEOUT)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StreamError::NotPublishing(_)));
+
+        // A stream that never delivers a frame times out the same way
+        let ffmpeg = fake_ffmpeg(dir.path(), "exec sleep 30");
+        let err = capture_snapshot_with(
+            &ffmpeg,
+            "rtsp://x/cam",
+            "cam",
+            &out,
+            Duration::from_millis(200),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, StreamError::NotPublishing(_)));
+    }
+}
@@ -1,15 +1,29 @@
 //! Process supervisor for FFmpeg streams
 //! Manages process lifecycle and progress parsing
 
-use regex::Regex;
+use crate::diagnostics::logs::spawn_stream_log;
+use super::StreamError;
+use crate::sidecar::find_binary;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
-use std::process::{Child, Command, Stdio};
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
 use std::sync::Arc;
+use std::time::Duration;
+use sysinfo::{Pid, ProcessesToUpdate, System};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
 use tokio::sync::{mpsc, Mutex};
 
+/// How long FFmpeg gets to flush its muxer after SIGTERM before being killed
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long `wait_reaped` gives a process to go away after its output closes
+const REAP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often `wait_reaped` checks on the process, without holding the lock
+const REAP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 /// Stream progress event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamProgress {
@@ -19,6 +33,9 @@ pub struct StreamProgress {
     pub bitrate: String,
     pub time: String,
     pub speed: String,
+    /// Playback position as a share of the source duration, when known.
+    /// Looped sources restart from 0 on every pass; others stop at 100.
+    pub percent: Option<f32>,
 }
 
 /// Stream event types
@@ -29,112 +46,247 @@ pub enum StreamEvent {
     Progress(StreamProgress),
     Stopped { stream_id: String },
     Error { stream_id: String, message: String },
+    /// Stopped by the scheduler to make room for `by`
+    Preempted { stream_id: String, by: String },
+    /// FFmpeg closed its output, whether stopped or on its own.
+    /// `generation` identifies the process, see `Supervisor::reap_exited`.
+    Exited { stream_id: String, generation: u64, last_error: Option<String> },
+}
+
+/// A stream's FFmpeg process and the generation it was spawned as
+struct Supervised {
+    child: Child,
+    generation: u64,
+}
+
+/// How a process ended, kept until the exit is reaped
+struct ExitRecord {
+    generation: u64,
+    status: std::io::Result<ExitStatus>,
+}
+
+/// Outcome of `Supervisor::reap_exited`
+#[derive(Debug)]
+pub enum Reap {
+    /// The process ended on its own with this status
+    Exited(std::io::Result<ExitStatus>),
+    /// Its output closed, but the process hasn't gone yet
+    Running,
+    /// Stopped through the supervisor or replaced by a newer process
+    Gone,
 }
 
 /// Process supervisor
 pub struct Supervisor {
-    processes: HashMap<String, Child>,
-    event_tx: Option<mpsc::Sender<StreamEvent>>,
+    processes: HashMap<String, Supervised>,
+    /// Processes that ended on their own and haven't been reaped yet
+    exited: HashMap<String, ExitRecord>,
+    next_generation: u64,
+    event_tx: Option<mpsc::UnboundedSender<StreamEvent>>,
+    /// Where each stream's FFmpeg stderr is kept, if anywhere
+    log_dir: Option<PathBuf>,
 }
 
 impl Supervisor {
     pub fn new() -> Self {
         Self {
             processes: HashMap::new(),
+            exited: HashMap::new(),
+            next_generation: 0,
             event_tx: None,
+            log_dir: None,
         }
     }
 
-    /// Set event channel for progress updates
-    pub fn set_event_channel(&mut self, tx: mpsc::Sender<StreamEvent>) {
+    /// Keep each stream's full FFmpeg stderr in `dir/stream-<id>.log`
+    pub fn set_log_dir(&mut self, dir: PathBuf) {
+        self.log_dir = Some(dir);
+    }
+
+    /// Set event channel for progress updates. It is unbounded because
+    /// events are sent while the supervisor lock is held.
+    pub fn set_event_channel(&mut self, tx: mpsc::UnboundedSender<StreamEvent>) {
         self.event_tx = Some(tx);
     }
 
-    /// Start a stream with given FFmpeg arguments
+    /// Start a stream with given FFmpeg arguments. `duration_secs` is the
+    /// source length, used to report progress as a percentage.
     pub async fn start_stream(
         &mut self,
         stream_id: &str,
         args: Vec<String>,
-    ) -> Result<u32, String> {
+        duration_secs: Option<f64>,
+    ) -> Result<u32, StreamError> {
         if self.processes.contains_key(stream_id) {
-            return Err("Stream already running".to_string());
+            return Err(StreamError::AlreadyRunning);
         }
 
-        let ffmpeg_path = find_ffmpeg()?;
+        let ffmpeg_path = find_binary("ffmpeg").ok_or(StreamError::FfmpegNotFound)?;
+        self.spawn_process(stream_id, &ffmpeg_path, args, duration_secs)
+            .await
+            .map_err(StreamError::Process)
+    }
 
-        let mut child = Command::new(&ffmpeg_path)
+    /// Spawn a supervised process, reading progress from stdout and errors from stderr
+    async fn spawn_process(
+        &mut self,
+        stream_id: &str,
+        program: &Path,
+        args: Vec<String>,
+        duration_secs: Option<f64>,
+    ) -> Result<u32, String> {
+        let mut child = Command::new(program)
             .args(&args)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
             .map_err(|e| format!("Failed to spawn FFmpeg: {}", e))?;
 
-        let pid = child.id();
+        let pid = child.id().unwrap_or(0);
+        self.next_generation += 1;
+        let generation = self.next_generation;
+
+        let log = self.log_dir.as_ref().map(|dir| {
+            let log = spawn_stream_log(dir, stream_id);
+            let _ = log.send(format!("=== {} {}", program.display(), args.join(" ")));
+            log
+        });
 
         // Spawn progress parser task
+        if let Some(stdout) = child.stdout.take() {
+            let tx = self.event_tx.clone();
+            let parser = ProgressParser::new(stream_id, duration_secs, is_looped(&args));
+            tokio::spawn(async move {
+                parse_ffmpeg_progress(stdout, parser, tx).await;
+            });
+        }
+
+        // Spawn error capture task
         if let Some(stderr) = child.stderr.take() {
             let tx = self.event_tx.clone();
             let id = stream_id.to_string();
             tokio::spawn(async move {
-                parse_ffmpeg_progress(stderr, &id, tx).await;
+                capture_ffmpeg_errors(stderr, &id, generation, tx, log).await;
             });
         }
 
-        self.processes.insert(stream_id.to_string(), child);
+        // An unreaped exit of an earlier process is superseded by this one
+        self.exited.remove(stream_id);
+        self.processes.insert(stream_id.to_string(), Supervised { child, generation });
 
         // Send started event
         if let Some(tx) = &self.event_tx {
             let _ = tx.send(StreamEvent::Started {
                 stream_id: stream_id.to_string(),
-            }).await;
+            });
         }
 
         Ok(pid)
     }
 
-    /// Stop a stream
-    pub fn stop_stream(&mut self, stream_id: &str) -> Result<(), String> {
-        if let Some(mut child) = self.processes.remove(stream_id) {
-            child.kill().map_err(|e| format!("Failed to kill process: {}", e))?;
-            child.wait().map_err(|e| format!("Failed to wait: {}", e))?;
-
-            // Send stopped event
-            if let Some(tx) = &self.event_tx {
-                let tx = tx.clone();
-                let id = stream_id.to_string();
-                tokio::spawn(async move {
-                    let _ = tx.send(StreamEvent::Stopped { stream_id: id }).await;
-                });
+    /// Stop a stream, giving FFmpeg up to `STOP_TIMEOUT` to exit cleanly.
+    /// A process that already ended but wasn't reaped counts as stopped.
+    pub async fn stop_stream(&mut self, stream_id: &str) -> Result<(), String> {
+        if let Some(Supervised { mut child, .. }) = self.processes.remove(stream_id) {
+            if request_terminate(&mut child).is_ok() {
+                if let Ok(status) = tokio::time::timeout(STOP_TIMEOUT, child.wait()).await {
+                    status.map_err(|e| format!("Failed to wait: {}", e))?;
+                } else {
+                    println!("[Supervisor] Stream {} ignored SIGTERM, killing", stream_id);
+                    child.kill().await.map_err(|e| format!("Failed to kill process: {}", e))?;
+                }
+            } else {
+                child.kill().await.map_err(|e| format!("Failed to kill process: {}", e))?;
             }
 
+            self.send_stopped(stream_id);
+            Ok(())
+        } else if self.exited.remove(stream_id).is_some() {
+            self.send_stopped(stream_id);
             Ok(())
         } else {
             Err("Stream not running".to_string())
         }
     }
 
+    fn send_stopped(&self, stream_id: &str) {
+        if let Some(tx) = &self.event_tx {
+            let _ = tx.send(StreamEvent::Stopped {
+                stream_id: stream_id.to_string(),
+            });
+        }
+    }
+
     /// Stop all streams
-    pub fn stop_all(&mut self) {
+    pub async fn stop_all(&mut self) {
         let ids: Vec<String> = self.processes.keys().cloned().collect();
         for id in ids {
-            let _ = self.stop_stream(&id);
+            let _ = self.stop_stream(&id).await;
+        }
+    }
+
+    /// Stop a stream the scheduler preempted in favour of `by`
+    pub async fn preempt_stream(&mut self, stream_id: &str, by: &str) -> Result<(), String> {
+        self.stop_stream(stream_id).await?;
+
+        if let Some(tx) = &self.event_tx {
+            let _ = tx.send(StreamEvent::Preempted {
+                stream_id: stream_id.to_string(),
+                by: by.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Reap the process `generation` of a stream if it ended on its own.
+    /// Never waits, so the supervisor lock isn't held across an exit; see
+    /// `wait_reaped`.
+    pub fn reap_exited(&mut self, stream_id: &str, generation: u64) -> Reap {
+        self.collect_exit(stream_id);
+
+        if self.exited.get(stream_id).is_some_and(|e| e.generation == generation) {
+            let record = self.exited.remove(stream_id).expect("checked above");
+            return Reap::Exited(record.status);
+        }
+        match self.processes.get(stream_id) {
+            Some(process) if process.generation == generation => Reap::Running,
+            _ => Reap::Gone,
         }
     }
 
+    /// Move a stream's process to `exited` once it has ended
+    fn collect_exit(&mut self, stream_id: &str) {
+        let Some(process) = self.processes.get_mut(stream_id) else {
+            return;
+        };
+        let status = match process.child.try_wait() {
+            Ok(Some(status)) => Ok(status),
+            Ok(None) => return,
+            Err(e) => Err(e),
+        };
+        let generation = process.generation;
+        self.processes.remove(stream_id);
+        self.exited.insert(stream_id.to_string(), ExitRecord { generation, status });
+    }
+
     /// Check if stream is running
     pub fn is_running(&mut self, stream_id: &str) -> bool {
-        if let Some(child) = self.processes.get_mut(stream_id) {
-            match child.try_wait() {
-                Ok(Some(_)) => {
-                    self.processes.remove(stream_id);
-                    false
-                }
-                Ok(None) => true,
-                Err(_) => false,
-            }
-        } else {
-            false
-        }
+        self.collect_exit(stream_id);
+        self.processes.contains_key(stream_id)
+    }
+
+    /// Whether the supervisor still has the stream, running or ended but
+    /// not yet reaped
+    pub fn has_stream(&self, stream_id: &str) -> bool {
+        self.processes.contains_key(stream_id) || self.exited.contains_key(stream_id)
+    }
+
+    /// Process id of each running stream (reaped processes are skipped)
+    pub fn stream_pids(&self) -> Vec<(String, u32)> {
+        self.processes
+            .iter()
+            .filter_map(|(id, process)| Some((id.clone(), process.child.id()?)))
+            .collect()
     }
 
     /// Get running stream IDs
@@ -145,83 +297,562 @@ impl Supervisor {
 
 impl Drop for Supervisor {
     fn drop(&mut self) {
-        self.stop_all();
+        // Can't await here - just make sure nothing outlives us
+        for (_, mut process) in self.processes.drain() {
+            let _ = process.child.start_kill();
+        }
     }
 }
 
-/// Parse FFmpeg progress from stderr
-async fn parse_ffmpeg_progress<R: std::io::Read>(
-    stderr: R,
+/// Wait for the process `generation` of a stream to be reaped after its
+/// output closed, taking the lock only to check. None when it was stopped
+/// through the supervisor, replaced, or didn't go within `REAP_TIMEOUT`.
+pub async fn wait_reaped(
+    supervisor: &Mutex<Supervisor>,
     stream_id: &str,
-    tx: Option<mpsc::Sender<StreamEvent>>,
+    generation: u64,
+) -> Option<std::io::Result<ExitStatus>> {
+    let deadline = tokio::time::Instant::now() + REAP_TIMEOUT;
+    loop {
+        match supervisor.lock().await.reap_exited(stream_id, generation) {
+            Reap::Exited(status) => return Some(status),
+            Reap::Gone => return None,
+            Reap::Running => {}
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return None;
+        }
+        tokio::time::sleep(REAP_POLL_INTERVAL).await;
+    }
+}
+
+/// Ask a process to exit gracefully (SIGTERM on Unix)
+#[cfg(unix)]
+fn request_terminate(child: &mut Child) -> std::io::Result<()> {
+    let Some(pid) = child.id() else {
+        return Ok(()); // Already exited
+    };
+
+    // SAFETY: kill(2) with a pid we spawned and have not yet reaped
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// Windows has no SIGTERM equivalent for console-less children
+#[cfg(not(unix))]
+fn request_terminate(child: &mut Child) -> std::io::Result<()> {
+    child.start_kill()
+}
+
+/// Kill an FFmpeg left behind by a previous run of the app. It can't be
+/// supervised again, so it would only hold its MediaMTX path. Returns false
+/// when `pid` is gone or now belongs to some other program.
+pub fn kill_orphaned_ffmpeg(pid: u32) -> bool {
+    let pid = Pid::from_u32(pid);
+    let mut sys = System::new();
+    sys.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+    match sys.process(pid) {
+        Some(process) if process.name().to_string_lossy().to_lowercase().contains("ffmpeg") => {
+            process.kill()
+        }
+        _ => false,
+    }
+}
+
+/// Accumulates FFmpeg `-progress` key=value blocks into progress events
+struct ProgressParser {
+    stream_id: String,
+    duration_secs: Option<f64>,
+    looped: bool,
+    frame: u64,
+    fps: f32,
+    bitrate: String,
+    out_time_us: u64,
+    speed: String,
+}
+
+impl ProgressParser {
+    fn new(stream_id: &str, duration_secs: Option<f64>, looped: bool) -> Self {
+        Self {
+            stream_id: stream_id.to_string(),
+            duration_secs: duration_secs.filter(|d| *d > 0.0),
+            looped,
+            frame: 0,
+            fps: 0.0,
+            bitrate: String::new(),
+            out_time_us: 0,
+            speed: String::new(),
+        }
+    }
+
+    /// Take one line; returns an event when it closes a block (`progress=...`).
+    /// Values FFmpeg reports as N/A keep their previous reading.
+    fn feed(&mut self, line: &str) -> Option<StreamProgress> {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+        match key {
+            "frame" => self.frame = value.parse().unwrap_or(self.frame),
+            "fps" => self.fps = value.parse().unwrap_or(self.fps),
+            "bitrate" if value != "N/A" => self.bitrate = value.to_string(),
+            "out_time_us" => self.out_time_us = value.parse().unwrap_or(self.out_time_us),
+            "speed" if value != "N/A" => self.speed = value.to_string(),
+            "progress" => return Some(self.snapshot()),
+            _ => {}
+        }
+        None
+    }
+
+    fn snapshot(&self) -> StreamProgress {
+        let secs = self.out_time_us as f64 / 1_000_000.0;
+        StreamProgress {
+            stream_id: self.stream_id.clone(),
+            frame: self.frame,
+            fps: self.fps,
+            bitrate: self.bitrate.clone(),
+            time: format_out_time(self.out_time_us),
+            speed: self.speed.clone(),
+            percent: self.duration_secs.map(|d| {
+                let position = if self.looped { secs % d } else { secs.min(d) };
+                (position / d * 100.0) as f32
+            }),
+        }
+    }
+}
+
+/// Whether the args loop their input (`-stream_loop` with a non-zero count)
+fn is_looped(args: &[String]) -> bool {
+    args.windows(2)
+        .any(|pair| pair[0] == "-stream_loop" && pair[1] != "0")
+}
+
+/// Why an exited FFmpeg failed, or None when it finished cleanly. A clean
+/// exit (status 0) wins over error lines FFmpeg logged along the way, except
+/// for a live source: a camera never ends, so a clean exit means it hung up.
+pub fn exit_failure(
+    status: &std::io::Result<ExitStatus>,
+    last_error: Option<String>,
+    live_source: bool,
+) -> Option<String> {
+    match status {
+        Ok(status) if status.success() && live_source => {
+            Some(last_error.unwrap_or_else(|| "Live source ended the stream".to_string()))
+        }
+        Ok(status) if status.success() => None,
+        Ok(status) => Some(last_error.unwrap_or_else(|| format!("FFmpeg exited with {}", status))),
+        Err(e) => Some(last_error.unwrap_or_else(|| format!("Failed to wait for FFmpeg: {}", e))),
+    }
+}
+
+/// Format microseconds as FFmpeg's HH:MM:SS.cc
+fn format_out_time(us: u64) -> String {
+    let centis = us / 10_000;
+    format!(
+        "{:02}:{:02}:{:02}.{:02}",
+        centis / 360_000,
+        centis / 6_000 % 60,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+/// Parse FFmpeg `-progress pipe:1` output into progress events
+async fn parse_ffmpeg_progress<R: AsyncRead + Unpin>(
+    stdout: R,
+    mut parser: ProgressParser,
+    tx: Option<mpsc::UnboundedSender<StreamEvent>>,
 ) {
-    let reader = BufReader::new(stderr);
-    let progress_regex = Regex::new(
-        r"frame=\s*(\d+)\s+fps=\s*([\d.]+)\s+.*?bitrate=\s*([\d.]+[kM]?bits/s).*?time=(\S+).*?speed=\s*([\d.]+x)"
-    ).ok();
-
-    for line in reader.lines().flatten() {
-        // Parse progress line
-        if let Some(ref regex) = progress_regex {
-            if let Some(caps) = regex.captures(&line) {
-                let progress = StreamProgress {
-                    stream_id: stream_id.to_string(),
-                    frame: caps.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0),
-                    fps: caps.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0.0),
-                    bitrate: caps.get(3).map(|m| m.as_str().to_string()).unwrap_or_default(),
-                    time: caps.get(4).map(|m| m.as_str().to_string()).unwrap_or_default(),
-                    speed: caps.get(5).map(|m| m.as_str().to_string()).unwrap_or_default(),
-                };
-
-                if let Some(ref tx) = tx {
-                    let _ = tx.send(StreamEvent::Progress(progress)).await;
-                }
+    let mut lines = BufReader::new(stdout).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if let Some(progress) = parser.feed(&line) {
+            if let Some(ref tx) = tx {
+                let _ = tx.send(StreamEvent::Progress(progress));
             }
         }
+    }
+}
+
+/// Report FFmpeg errors from stderr, copying every line to `log`
+async fn capture_ffmpeg_errors<R: AsyncRead + Unpin>(
+    stderr: R,
+    stream_id: &str,
+    generation: u64,
+    tx: Option<mpsc::UnboundedSender<StreamEvent>>,
+    log: Option<mpsc::UnboundedSender<String>>,
+) {
+    let mut lines = BufReader::new(stderr).lines();
+    let mut last_error = None;
+    while let Ok(Some(line)) = lines.next_line().await {
+        if let Some(ref log) = log {
+            let _ = log.send(line.clone());
+        }
 
-        // Check for errors
         if line.contains("Error") || line.contains("error") {
             if let Some(ref tx) = tx {
                 let _ = tx.send(StreamEvent::Error {
                     stream_id: stream_id.to_string(),
                     message: line.clone(),
-                }).await;
+                });
             }
+            last_error = Some(line);
         }
     }
+
+    if let Some(ref tx) = tx {
+        let _ = tx.send(StreamEvent::Exited {
+            stream_id: stream_id.to_string(),
+            generation,
+            last_error,
+        });
+    }
+}
+
+/// Thread-safe supervisor wrapper
+pub type SharedSupervisor = Arc<Mutex<Supervisor>>;
+
+pub fn create_shared_supervisor() -> SharedSupervisor {
+    Arc::new(Mutex::new(Supervisor::new()))
 }
 
-/// Find FFmpeg binary
-fn find_ffmpeg() -> Result<String, String> {
-    // Check system PATH first
-    if let Ok(output) = Command::new("which").arg("ffmpeg").output() {
-        if output.status.success() {
-            let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !path.is_empty() {
-                return Ok(path);
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::diagnostics::logs::stream_log_path;
+
+    #[tokio::test]
+    async fn test_stop_stream_terminates_process() {
+        let mut supervisor = Supervisor::new();
+        supervisor
+            .spawn_process("s1", Path::new("sleep"), vec!["30".into()], None)
+            .await
+            .unwrap();
+        assert!(supervisor.is_running("s1"));
+
+        let started = std::time::Instant::now();
+        supervisor.stop_stream("s1").await.unwrap();
+        assert!(started.elapsed() < STOP_TIMEOUT);
+        assert!(!supervisor.is_running("s1"));
+    }
+
+    #[tokio::test]
+    async fn test_stop_stream_lets_process_finalize() {
+        // Stands in for FFmpeg writing the muxer trailer on SIGTERM
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("finalized");
+        let script = format!(
+            "trap 'echo ok > {}; exit 0' TERM; while true; do sleep 0.1; done",
+            marker.display()
+        );
+        let mut supervisor = Supervisor::new();
+        supervisor
+            .spawn_process("s1", Path::new("sh"), vec!["-c".into(), script], None)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        supervisor.stop_stream("s1").await.unwrap();
+        assert_eq!(std::fs::read_to_string(&marker).unwrap().trim(), "ok");
+    }
+
+    #[tokio::test]
+    async fn test_preempt_stream_emits_event() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut supervisor = Supervisor::new();
+        supervisor.set_event_channel(tx);
+        supervisor
+            .spawn_process("low", Path::new("sleep"), vec!["30".into()], None)
+            .await
+            .unwrap();
+
+        supervisor.preempt_stream("low", "critical").await.unwrap();
+        assert!(!supervisor.is_running("low"));
+
+        let mut preempted = None;
+        while let Ok(Some(event)) = tokio::time::timeout(Duration::from_secs(1), rx.recv()).await {
+            if let StreamEvent::Preempted { stream_id, by } = event {
+                preempted = Some((stream_id, by));
+                break;
             }
         }
+        assert_eq!(preempted, Some(("low".to_string(), "critical".to_string())));
     }
 
-    // Common paths
-    let paths = [
-        "/opt/homebrew/bin/ffmpeg",
-        "/usr/local/bin/ffmpeg",
-        "/usr/bin/ffmpeg",
-    ];
+    #[tokio::test]
+    async fn test_stderr_events_from_many_streams_on_one_thread() {
+        // The default test runtime has a single worker thread, so a stderr
+        // reader that blocked it would stall every other stream's events
+        const STREAMS: usize = 20;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut supervisor = Supervisor::new();
+        supervisor.set_event_channel(tx);
+
+        for i in 0..STREAMS {
+            supervisor
+                .spawn_process(
+                    &format!("s{}", i),
+                    Path::new("sh"),
+                    vec!["-c".into(), "echo 'Error opening output' >&2; sleep 30".into()],
+                    None,
+                )
+                .await
+                .unwrap();
+        }
 
-    for path in paths {
-        if Path::new(path).exists() {
-            return Ok(path.to_string());
+        let mut reported = std::collections::HashSet::new();
+        while reported.len() < STREAMS {
+            match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+                Ok(Some(StreamEvent::Error { stream_id, .. })) => {
+                    reported.insert(stream_id);
+                }
+                Ok(Some(_)) => {}
+                _ => panic!("only {} of {} streams reported", reported.len(), STREAMS),
+            }
+        }
+
+        for i in 0..STREAMS {
+            supervisor.stop_stream(&format!("s{}", i)).await.unwrap();
         }
     }
 
-    Err("FFmpeg not found. Install via: brew install ffmpeg".to_string())
-}
+    #[tokio::test]
+    async fn test_stderr_written_to_stream_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut supervisor = Supervisor::new();
+        supervisor.set_log_dir(dir.path().to_path_buf());
+        supervisor
+            .spawn_process(
+                "s1",
+                Path::new("sh"),
+                vec!["-c".into(), "echo 'Input #0, mov' >&2; echo 'Conversion failed!' >&2".into()],
+                None,
+            )
+            .await
+            .unwrap();
 
-/// Thread-safe supervisor wrapper
-pub type SharedSupervisor = Arc<Mutex<Supervisor>>;
+        let path = stream_log_path(dir.path(), "s1");
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        loop {
+            let log = std::fs::read_to_string(&path).unwrap_or_default();
+            if log.contains("Conversion failed!") {
+                assert!(log.contains("=== sh -c"));
+                assert!(log.contains("Input #0, mov"));
+                break;
+            }
+            assert!(std::time::Instant::now() < deadline);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
 
-pub fn create_shared_supervisor() -> SharedSupervisor {
-    Arc::new(Mutex::new(Supervisor::new()))
+    #[test]
+    fn test_progress_parser_emits_per_block() {
+        let mut parser = ProgressParser::new("s1", Some(60.0), true);
+        let block = [
+            "frame=1500",
+            "fps=25.00",
+            "bitrate=2048.3kbits/s",
+            "out_time_us=90000000",
+            "out_time=00:01:30.000000",
+            "speed=1.00x",
+        ];
+        for line in block {
+            assert!(parser.feed(line).is_none());
+        }
+
+        let progress = parser.feed("progress=continue").unwrap();
+        assert_eq!(progress.frame, 1500);
+        assert_eq!(progress.fps, 25.0);
+        assert_eq!(progress.bitrate, "2048.3kbits/s");
+        assert_eq!(progress.time, "00:01:30.00");
+        assert_eq!(progress.speed, "1.00x");
+        // Second pass over a 60s file, half way through
+        assert_eq!(progress.percent, Some(50.0));
+
+        // N/A keeps the last reading
+        parser.feed("bitrate=N/A");
+        assert_eq!(parser.feed("progress=end").unwrap().bitrate, "2048.3kbits/s");
+    }
+
+    #[test]
+    fn test_progress_parser_without_duration() {
+        let mut parser = ProgressParser::new("s1", None, false);
+        parser.feed("out_time_us=1000000");
+        assert_eq!(parser.feed("progress=continue").unwrap().percent, None);
+    }
+
+    #[test]
+    fn test_progress_percent_for_finite_source() {
+        let mut parser = ProgressParser::new("s1", Some(40.0), false);
+        let mut percents = Vec::new();
+        for us in [0u64, 10_000_000, 40_000_000, 41_000_000] {
+            parser.feed(&format!("out_time_us={}", us));
+            percents.push(parser.feed("progress=continue").unwrap().percent.unwrap());
+        }
+        // Overshoot from the last packet's duration is capped
+        assert_eq!(percents, [0.0, 25.0, 100.0, 100.0]);
+    }
+
+    #[test]
+    fn test_is_looped() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(is_looped(&args(&["-stream_loop", "-1", "-i", "a.mp4"])));
+        assert!(!is_looped(&args(&["-stream_loop", "0", "-i", "a.mp4"])));
+        assert!(!is_looped(&args(&["-i", "a.mp4"])));
+    }
+
+    #[tokio::test]
+    async fn test_progress_read_from_stdout() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut supervisor = Supervisor::new();
+        supervisor.set_event_channel(tx);
+        supervisor
+            .spawn_process(
+                "s1",
+                Path::new("sh"),
+                vec!["-c".into(), "printf 'frame=10\\nout_time_us=2500000\\nprogress=continue\\n'".into()],
+                Some(10.0),
+            )
+            .await
+            .unwrap();
+
+        let mut progress = None;
+        while let Ok(Some(event)) = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+            if let StreamEvent::Progress(p) = event {
+                progress = Some(p);
+                break;
+            }
+        }
+        let progress = progress.unwrap();
+        assert_eq!(progress.frame, 10);
+        assert_eq!(progress.percent, Some(25.0));
+    }
+
+    #[tokio::test]
+    async fn test_reap_only_unexpected_exits() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut supervisor = Supervisor::new();
+        supervisor.set_event_channel(tx);
+        supervisor
+            .spawn_process(
+                "crash",
+                Path::new("sh"),
+                vec!["-c".into(), "echo 'Error writing trailer' >&2; exit 1".into()],
+                None,
+            )
+            .await
+            .unwrap();
+        supervisor
+            .spawn_process("stopped", Path::new("sleep"), vec!["30".into()], None)
+            .await
+            .unwrap();
+        supervisor.stop_stream("stopped").await.unwrap();
+
+        let exits = exit_events(&mut rx, 2).await;
+        assert_eq!(exits["crash"].1.as_deref(), Some("Error writing trailer"));
+        assert_eq!(exits["stopped"].1, None);
+
+        let supervisor = Mutex::new(supervisor);
+        let crashed = wait_reaped(&supervisor, "crash", exits["crash"].0).await.unwrap().unwrap();
+        assert_eq!(crashed.code(), Some(1));
+        assert!(wait_reaped(&supervisor, "stopped", exits["stopped"].0).await.is_none());
+        assert!(supervisor.lock().await.running_streams().is_empty());
+    }
+
+    /// Generation and last error of the next `count` exits, by stream
+    async fn exit_events(
+        rx: &mut mpsc::UnboundedReceiver<StreamEvent>,
+        count: usize,
+    ) -> HashMap<String, (u64, Option<String>)> {
+        let mut exits = HashMap::new();
+        while exits.len() < count {
+            match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+                Ok(Some(StreamEvent::Exited { stream_id, generation, last_error })) => {
+                    exits.insert(stream_id, (generation, last_error));
+                }
+                Ok(Some(_)) => {}
+                _ => panic!("missing exit events: {:?}", exits),
+            }
+        }
+        exits
+    }
+
+    #[tokio::test]
+    async fn test_clean_exit_is_not_a_failure() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut supervisor = Supervisor::new();
+        supervisor.set_event_channel(tx);
+        supervisor
+            .spawn_process("once", Path::new("sh"), vec!["-c".into(), "exit 0".into()], None)
+            .await
+            .unwrap();
+        supervisor
+            .spawn_process("crash", Path::new("sh"), vec!["-c".into(), "exit 1".into()], None)
+            .await
+            .unwrap();
+        let exits = exit_events(&mut rx, 2).await;
+        let supervisor = Mutex::new(supervisor);
+
+        let once = wait_reaped(&supervisor, "once", exits["once"].0).await.unwrap();
+        // Non-fatal errors logged during playback don't turn a clean end into a failure
+        assert_eq!(exit_failure(&once, Some("Error while decoding".into()), false), None);
+        // A camera that hangs up is a failure, not the end of the stream
+        assert!(exit_failure(&once, None, true).is_some());
+
+        let crash = wait_reaped(&supervisor, "crash", exits["crash"].0).await.unwrap();
+        assert!(exit_failure(&crash, None, false).unwrap().contains("exit status: 1"));
+    }
+
+    #[tokio::test]
+    async fn test_exit_seen_by_status_poll_is_still_reaped() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut supervisor = Supervisor::new();
+        supervisor.set_event_channel(tx);
+        supervisor
+            .spawn_process("s1", Path::new("sh"), vec!["-c".into(), "exit 3".into()], None)
+            .await
+            .unwrap();
+        let generation = exit_events(&mut rx, 1).await["s1"].0;
+
+        // A status poll notices the exit before the event loop gets to it
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while supervisor.is_running("s1") {
+            assert!(std::time::Instant::now() < deadline);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(supervisor.has_stream("s1"));
+
+        let supervisor = Mutex::new(supervisor);
+        let status = wait_reaped(&supervisor, "s1", generation).await.unwrap().unwrap();
+        assert_eq!(status.code(), Some(3));
+        assert!(!supervisor.lock().await.has_stream("s1"));
+    }
+
+    #[tokio::test]
+    async fn test_stale_exit_after_restart_is_ignored() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut supervisor = Supervisor::new();
+        supervisor.set_event_channel(tx);
+        supervisor
+            .spawn_process("s1", Path::new("sleep"), vec!["30".into()], None)
+            .await
+            .unwrap();
+        supervisor.stop_stream("s1").await.unwrap();
+        let old = exit_events(&mut rx, 1).await["s1"].0;
+        supervisor
+            .spawn_process("s1", Path::new("sleep"), vec!["30".into()], None)
+            .await
+            .unwrap();
+
+        // Answered at once, without waiting on the new process
+        assert!(matches!(supervisor.reap_exited("s1", old), Reap::Gone));
+        assert!(supervisor.is_running("s1"));
+        supervisor.stop_stream("s1").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stop_unknown_stream() {
+        let mut supervisor = Supervisor::new();
+        assert!(supervisor.stop_stream("missing").await.is_err());
+    }
 }
@@ -0,0 +1,129 @@
+//! Stream tags
+//! Free-form labels for grouping streams by event, client and so on
+
+use super::StreamError;
+use crate::db::schema::Stream;
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+
+/// Tag a stream; tagging twice is a no-op
+pub async fn add_tag(pool: &SqlitePool, stream_id: &str, tag: &str) -> Result<(), StreamError> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err(StreamError::InvalidTag("tag must not be empty".to_string()));
+    }
+    if super::get_stream(pool, stream_id).await?.is_none() {
+        return Err(StreamError::NotFound(stream_id.to_string()));
+    }
+
+    sqlx::query("INSERT OR IGNORE INTO stream_tags (stream_id, tag) VALUES (?, ?)")
+        .bind(stream_id)
+        .bind(tag)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Remove a tag from a stream
+pub async fn remove_tag(pool: &SqlitePool, stream_id: &str, tag: &str) -> Result<(), StreamError> {
+    sqlx::query("DELETE FROM stream_tags WHERE stream_id = ? AND tag = ?")
+        .bind(stream_id)
+        .bind(tag.trim())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Streams carrying `tag`, by name
+pub async fn get_streams_by_tag(pool: &SqlitePool, tag: &str) -> Result<Vec<Stream>, StreamError> {
+    let mut streams: Vec<Stream> = sqlx::query_as(
+        r#"
+        SELECT s.* FROM streams s
+        JOIN stream_tags t ON t.stream_id = s.id
+        WHERE t.tag = ?
+        ORDER BY s.name
+        "#,
+    )
+    .bind(tag.trim())
+    .fetch_all(pool)
+    .await?;
+    attach_tags(pool, &mut streams).await?;
+    Ok(streams)
+}
+
+/// Stream ids bound per tag query, well under SQLite's variable limit
+const TAG_QUERY_BATCH: usize = 500;
+
+/// Fill in each stream's tags, sorted
+pub async fn attach_tags(pool: &SqlitePool, streams: &mut [Stream]) -> Result<(), StreamError> {
+    if streams.is_empty() {
+        return Ok(());
+    }
+
+    // Only the listed streams' tags, a bounded batch of ids per query
+    let mut by_stream: HashMap<String, Vec<String>> = HashMap::new();
+    for chunk in streams.chunks(TAG_QUERY_BATCH) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let sql = format!(
+            "SELECT stream_id, tag FROM stream_tags WHERE stream_id IN ({}) ORDER BY tag",
+            placeholders
+        );
+        let mut query = sqlx::query_as::<_, (String, String)>(&sql);
+        for stream in chunk {
+            query = query.bind(&stream.id);
+        }
+        for (stream_id, tag) in query.fetch_all(pool).await? {
+            by_stream.entry(stream_id).or_default().push(tag);
+        }
+    }
+
+    for stream in streams {
+        stream.tags = by_stream.remove(&stream.id).unwrap_or_default();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tag_round_trip() {
+        let pool = crate::db::test_pool().await;
+        for (id, name) in [("s1", "cam-a"), ("s2", "cam-b")] {
+            sqlx::query("INSERT INTO streams (id, name) VALUES (?, ?)")
+                .bind(id)
+                .bind(name)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        add_tag(&pool, "s1", "wedding").await.unwrap();
+        add_tag(&pool, "s1", " client-x ").await.unwrap();
+        add_tag(&pool, "s1", "wedding").await.unwrap();
+        add_tag(&pool, "s2", "wedding").await.unwrap();
+
+        let all = crate::stream::get_all_streams(&pool).await.unwrap();
+        assert_eq!(all[0].tags, ["client-x", "wedding"]);
+        assert_eq!(all[1].tags, ["wedding"]);
+
+        let tagged = get_streams_by_tag(&pool, "client-x").await.unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].id, "s1");
+
+        remove_tag(&pool, "s1", "wedding").await.unwrap();
+        let names: Vec<String> = get_streams_by_tag(&pool, "wedding")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, ["cam-b"]);
+
+        assert!(matches!(
+            add_tag(&pool, "missing", "x").await,
+            Err(StreamError::NotFound(_))
+        ));
+    }
+}
@@ -4,6 +4,13 @@
 use serde::{Deserialize, Serialize};
 use std::process::Command;
 
+/// Core `nvidia-smi --query-gpu` fields, in the order `apply_gpu_query` expects
+const GPU_QUERY_FIELDS: &str =
+    "name,utilization.gpu,memory.used,memory.total,encoder.stats.averageFps";
+
+/// Thermal fields; older drivers reject them, which fails the whole query
+const GPU_THERMAL_FIELDS: &str = "temperature.gpu,power.draw";
+
 /// GPU metrics snapshot
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GpuMetrics {
@@ -15,6 +22,8 @@ pub struct GpuMetrics {
     pub encoder_utilization_percent: Option<f32>,
     pub nvenc_sessions_active: u32,
     pub nvenc_sessions_max: u32,
+    pub temperature_c: Option<f32>,
+    pub power_watts: Option<f32>,
 }
 
 impl Default for GpuMetrics {
@@ -28,11 +37,15 @@ impl Default for GpuMetrics {
             encoder_utilization_percent: None,
             nvenc_sessions_active: 0,
             nvenc_sessions_max: 6, // Conservative default for consumer GPUs
+            temperature_c: None,
+            power_watts: None,
         }
     }
 }
 
-/// GPU telemetry collector
+/// GPU telemetry collector. Collecting runs nvidia-smi and blocks, so
+/// async callers run it on a clone in `spawn_blocking`.
+#[derive(Debug, Clone)]
 pub struct GpuTelemetry {
     nvidia_smi_available: bool,
     detected_max_sessions: Option<u32>,
@@ -58,55 +71,50 @@ impl GpuTelemetry {
     }
 
     /// Collect GPU metrics
-    pub fn collect(&mut self) -> GpuMetrics {
+    pub fn collect(&self) -> GpuMetrics {
         if !self.nvidia_smi_available {
             return GpuMetrics::default();
         }
 
         let mut metrics = GpuMetrics {
             available: true,
-            nvenc_sessions_max: self.detected_max_sessions.unwrap_or(6),
+            nvenc_sessions_max: crate::gpu::nvenc::cached_max_sessions()
+                .or(self.detected_max_sessions)
+                .unwrap_or(6),
             ..Default::default()
         };
 
-        // Query GPU info
+        // Query GPU info, retrying without thermal fields for older drivers
+        let line = query_gpu(&format!("{},{}", GPU_QUERY_FIELDS, GPU_THERMAL_FIELDS))
+            .or_else(|| query_gpu(GPU_QUERY_FIELDS));
+        if let Some(line) = line {
+            apply_gpu_query(&mut metrics, &line);
+        }
+
+        // Count active NVENC sessions by checking encoding processes
+        metrics.nvenc_sessions_active = self.count_nvenc_sessions();
+
+        metrics
+    }
+
+    /// Count active NVENC encoding sessions
+    fn count_nvenc_sessions(&self) -> u32 {
+        // Ask the driver for its live encoder session count
         if let Ok(output) = Command::new("nvidia-smi")
             .args([
-                "--query-gpu=name,utilization.gpu,memory.used,memory.total,encoder.stats.averageFps",
+                "--query-gpu=encoder.stats.sessionCount",
                 "--format=csv,noheader,nounits",
             ])
             .output()
         {
             if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let line = stdout.trim();
-                let parts: Vec<&str> = line.split(',').map(|s| s.trim()).collect();
-
-                if parts.len() >= 4 {
-                    metrics.name = Some(parts[0].to_string());
-                    metrics.utilization_percent = parts[1].parse().ok();
-                    metrics.memory_used_mb = parts[2].parse().ok();
-                    metrics.memory_total_mb = parts[3].parse().ok();
-
-                    // Encoder FPS can indicate activity
-                    if parts.len() > 4 {
-                        if let Ok(fps) = parts[4].parse::<f32>() {
-                            metrics.encoder_utilization_percent = Some(fps.min(100.0));
-                        }
-                    }
+                if let Some(count) = parse_session_count(&String::from_utf8_lossy(&output.stdout)) {
+                    return count;
                 }
             }
         }
 
-        // Count active NVENC sessions by checking encoding processes
-        metrics.nvenc_sessions_active = self.count_nvenc_sessions();
-
-        metrics
-    }
-
-    /// Count active NVENC encoding sessions
-    fn count_nvenc_sessions(&self) -> u32 {
-        // Query encoder sessions from nvidia-smi
+        // Older drivers: estimate from per-process encoder activity
         if let Ok(output) = Command::new("nvidia-smi")
             .args(["pmon", "-c", "1", "-s", "e"])
             .output()
@@ -141,8 +149,12 @@ impl GpuTelemetry {
         0
     }
 
-    /// Detect maximum NVENC sessions (run once at startup)
+    /// Detect maximum NVENC sessions; the name-based guess is made once
     pub fn detect_max_sessions(&mut self) -> u32 {
+        // Prefer the ceiling measured by the latest NVENC session probe
+        if let Some(max) = crate::gpu::nvenc::cached_max_sessions() {
+            return max;
+        }
         if let Some(max) = self.detected_max_sessions {
             return max;
         }
@@ -185,6 +197,54 @@ impl GpuTelemetry {
     }
 }
 
+/// Run an `nvidia-smi --query-gpu` and return the first GPU's line
+fn query_gpu(fields: &str) -> Option<String> {
+    let output = Command::new("nvidia-smi")
+        .arg(format!("--query-gpu={}", fields))
+        .arg("--format=csv,noheader,nounits")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().next().map(|l| l.trim().to_string())
+}
+
+/// Fill metrics from a CSV line of `GPU_QUERY_FIELDS` (plus optionally
+/// `GPU_THERMAL_FIELDS`). Values like "[N/A]" are left as None.
+fn apply_gpu_query(metrics: &mut GpuMetrics, line: &str) {
+    let parts: Vec<&str> = line.split(',').map(|s| s.trim()).collect();
+    if parts.len() < 4 {
+        return;
+    }
+
+    metrics.name = Some(parts[0].to_string());
+    metrics.utilization_percent = parts[1].parse().ok();
+    metrics.memory_used_mb = parts[2].parse().ok();
+    metrics.memory_total_mb = parts[3].parse().ok();
+
+    // Encoder FPS can indicate activity
+    if let Some(fps) = parts.get(4).and_then(|s| s.parse::<f32>().ok()) {
+        metrics.encoder_utilization_percent = Some(fps.min(100.0));
+    }
+
+    metrics.temperature_c = parts.get(5).and_then(|s| s.parse().ok());
+    metrics.power_watts = parts.get(6).and_then(|s| s.parse().ok());
+}
+
+/// Sum per-GPU encoder session counts (one line per GPU)
+/// Returns None if any line isn't a number, e.g. "[N/A]" on unsupported drivers
+fn parse_session_count(stdout: &str) -> Option<u32> {
+    let mut total = 0u32;
+    let mut any = false;
+    for line in stdout.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        total += line.parse::<u32>().ok()?;
+        any = true;
+    }
+    any.then_some(total)
+}
+
 impl Default for GpuTelemetry {
     fn default() -> Self {
         Self::new()
@@ -208,4 +268,34 @@ mod tests {
         assert!(!metrics.available);
         assert_eq!(metrics.nvenc_sessions_max, 6);
     }
+
+    #[test]
+    fn test_apply_gpu_query() {
+        let mut metrics = GpuMetrics::default();
+        apply_gpu_query(&mut metrics, "NVIDIA GeForce RTX 3080, 12, 1024, 10240, 0, 64, 115.42");
+        assert_eq!(metrics.name.as_deref(), Some("NVIDIA GeForce RTX 3080"));
+        assert_eq!(metrics.memory_total_mb, Some(10240));
+        assert_eq!(metrics.temperature_c, Some(64.0));
+        assert_eq!(metrics.power_watts, Some(115.42));
+
+        // Unsupported sensors and the core-only fallback query leave them unset
+        let mut metrics = GpuMetrics::default();
+        apply_gpu_query(&mut metrics, "Tesla T4, 5, 100, 15360, 0, 41, [N/A]");
+        assert_eq!(metrics.temperature_c, Some(41.0));
+        assert_eq!(metrics.power_watts, None);
+
+        let mut metrics = GpuMetrics::default();
+        apply_gpu_query(&mut metrics, "Tesla T4, 5, 100, 15360, 0");
+        assert_eq!(metrics.utilization_percent, Some(5.0));
+        assert_eq!(metrics.temperature_c, None);
+    }
+
+    #[test]
+    fn test_parse_session_count() {
+        assert_eq!(parse_session_count("3\n"), Some(3));
+        // Sessions across multiple GPUs are summed
+        assert_eq!(parse_session_count("2\n1\n"), Some(3));
+        assert_eq!(parse_session_count("[N/A]\n"), None);
+        assert_eq!(parse_session_count(""), None);
+    }
 }
@@ -0,0 +1,109 @@
+//! Telemetry history
+//! Keeps recent samples in memory so the UI can draw trend lines
+
+use super::TelemetryMetrics;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Default number of samples kept (5 minutes at one sample per second)
+pub const DEFAULT_HISTORY_SIZE: usize = 300;
+
+/// A collected snapshot and when it was taken
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetrySample {
+    /// Unix timestamp in milliseconds
+    pub timestamp: i64,
+    pub metrics: TelemetryMetrics,
+}
+
+/// Fixed-size ring buffer of telemetry samples, oldest first
+pub struct TelemetryHistory {
+    samples: VecDeque<TelemetrySample>,
+    capacity: usize,
+}
+
+impl TelemetryHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Record a sample, dropping the oldest once full
+    pub fn push(&mut self, metrics: TelemetryMetrics) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(TelemetrySample {
+            timestamp: Utc::now().timestamp_millis(),
+            metrics,
+        });
+    }
+
+    /// Change how many samples are kept, discarding the oldest if shrinking
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.samples.len() > capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    /// All retained samples, oldest first
+    pub fn samples(&self) -> Vec<TelemetrySample> {
+        self.samples.iter().cloned().collect()
+    }
+}
+
+impl Default for TelemetryHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::gpu::GpuMetrics;
+    use crate::telemetry::system::SystemMetrics;
+
+    fn metrics(cpu_percent: f32) -> TelemetryMetrics {
+        TelemetryMetrics {
+            system: SystemMetrics {
+                cpu_percent,
+                cpu_count: 4,
+                memory_used_mb: 1024,
+                memory_total_mb: 4096,
+                memory_percent: 25.0,
+                disk_read_bytes_per_sec: 0,
+                disk_write_bytes_per_sec: 0,
+                network_rx_bytes_per_sec: 0,
+                network_tx_bytes_per_sec: 0,
+            },
+            gpu: GpuMetrics::default(),
+        }
+    }
+
+    #[test]
+    fn test_ring_buffer_keeps_newest() {
+        let mut history = TelemetryHistory::new(3);
+        for cpu in [10.0, 20.0, 30.0, 40.0] {
+            history.push(metrics(cpu));
+        }
+
+        let cpu: Vec<f32> = history.samples().iter().map(|s| s.metrics.system.cpu_percent).collect();
+        assert_eq!(cpu, vec![20.0, 30.0, 40.0]);
+
+        history.set_capacity(1);
+        assert_eq!(history.samples().len(), 1);
+        assert_eq!(history.samples()[0].metrics.system.cpu_percent, 40.0);
+
+        history.set_capacity(0);
+        history.push(metrics(50.0));
+        assert!(history.samples().is_empty());
+    }
+}
@@ -1,12 +1,17 @@
 //! Telemetry module for system and GPU monitoring
 
 pub mod gpu;
+pub mod history;
+pub mod prometheus;
 pub mod system;
 
+use crate::db::schema::AppSettings;
 use gpu::{GpuMetrics, GpuTelemetry};
+use history::{TelemetryHistory, TelemetrySample};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use system::{SystemMetrics, SystemTelemetry};
+use std::time::Duration;
+use system::{ProcessStats, SystemMetrics, SystemTelemetry};
 use tokio::sync::Mutex;
 
 /// Combined telemetry metrics
@@ -16,10 +21,24 @@ pub struct TelemetryMetrics {
     pub gpu: GpuMetrics,
 }
 
+/// Resource usage of one running stream's FFmpeg process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamStats {
+    pub stream_id: String,
+    #[serde(flatten)]
+    pub process: ProcessStats,
+}
+
+/// How often the background sampler records metrics, so the history is
+/// evenly spaced however often the UI asks
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Telemetry collector combining system and GPU metrics
 pub struct TelemetryCollector {
     system: SystemTelemetry,
     gpu: GpuTelemetry,
+    history: TelemetryHistory,
+    latest: Option<TelemetryMetrics>,
 }
 
 impl TelemetryCollector {
@@ -27,17 +46,69 @@ impl TelemetryCollector {
         Self {
             system: SystemTelemetry::new(),
             gpu: GpuTelemetry::new(),
+            history: TelemetryHistory::default(),
+            latest: None,
         }
     }
 
-    /// Collect all metrics
-    pub fn collect(&mut self) -> TelemetryMetrics {
-        TelemetryMetrics {
+    /// The GPU collector, for running its blocking queries off the lock
+    pub fn gpu(&self) -> GpuTelemetry {
+        self.gpu.clone()
+    }
+
+    /// Collect system metrics and record them in the history with `gpu`,
+    /// as collected from `gpu()`; called every `SAMPLE_INTERVAL` by the
+    /// background sampler
+    pub fn record(&mut self, gpu: GpuMetrics) -> TelemetryMetrics {
+        let metrics = TelemetryMetrics {
             system: self.system.collect(),
-            gpu: self.gpu.collect(),
+            gpu,
+        };
+        self.history.push(metrics.clone());
+        self.latest = Some(metrics.clone());
+        metrics
+    }
+
+    /// The latest sample. Before the sampler's first run there is none, so
+    /// system metrics are read now and the GPU is reported unavailable.
+    pub fn collect(&mut self) -> TelemetryMetrics {
+        match self.latest {
+            Some(ref metrics) => metrics.clone(),
+            None => TelemetryMetrics {
+                system: self.system.collect(),
+                gpu: GpuMetrics::default(),
+            },
         }
     }
 
+    /// Recent samples, oldest first
+    pub fn history(&self) -> Vec<TelemetrySample> {
+        self.history.samples()
+    }
+
+    /// Apply the history length from settings
+    pub fn update_settings(&mut self, settings: &AppSettings) {
+        self.history.set_capacity(settings.telemetry_history_size as usize);
+    }
+
+    /// Per-stream CPU and memory for `(stream_id, pid)` pairs; streams whose
+    /// process has already exited are omitted
+    pub fn stream_stats(&mut self, streams: &[(String, u32)]) -> Vec<StreamStats> {
+        let pids: Vec<u32> = streams.iter().map(|(_, pid)| *pid).collect();
+        let stats = self.system.per_process_stats(&pids);
+
+        streams
+            .iter()
+            .filter_map(|(stream_id, pid)| {
+                let process = stats.iter().find(|s| s.pid == *pid)?.clone();
+                Some(StreamStats {
+                    stream_id: stream_id.clone(),
+                    process,
+                })
+            })
+            .collect()
+    }
+
     /// Get system metrics only
     pub fn system_metrics(&mut self) -> SystemMetrics {
         self.system.collect()
@@ -68,6 +139,35 @@ impl Default for TelemetryCollector {
 /// Thread-safe telemetry collector
 pub type SharedTelemetry = Arc<Mutex<TelemetryCollector>>;
 
-pub fn create_shared_telemetry() -> SharedTelemetry {
-    Arc::new(Mutex::new(TelemetryCollector::new()))
+pub fn create_shared_telemetry(settings: &AppSettings) -> SharedTelemetry {
+    let mut collector = TelemetryCollector::new();
+    collector.update_settings(settings);
+    Arc::new(Mutex::new(collector))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_reads_latest_sample() {
+        let mut collector = TelemetryCollector::new();
+        assert!(!collector.collect().gpu.available);
+        assert!(collector.history().is_empty());
+
+        let gpu = GpuMetrics {
+            available: true,
+            ..Default::default()
+        };
+        collector.record(gpu);
+        assert_eq!(collector.history().len(), 1);
+
+        // Reading doesn't add samples; only the sampler does
+        assert!(collector.collect().gpu.available);
+        collector.collect();
+        assert_eq!(collector.history().len(), 1);
+
+        collector.record(collector.gpu().collect());
+        assert_eq!(collector.history().len(), 2);
+    }
 }
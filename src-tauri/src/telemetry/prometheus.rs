@@ -0,0 +1,383 @@
+//! Prometheus metrics export
+//! Formats telemetry as text exposition and serves it on `/metrics`
+
+use super::{StreamStats, TelemetryMetrics};
+use crate::scheduler::limits::CapacitySummary;
+use std::fmt::Write;
+use std::future::Future;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Content type of the text exposition format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Requests with larger headers than this are not from a scraper
+const MAX_REQUEST_BYTES: usize = 8192;
+
+/// A client that hasn't sent its headers by now is dropped, so idle
+/// connections can't pile up
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Render metrics in Prometheus text exposition format
+pub fn render(
+    metrics: &TelemetryMetrics,
+    capacity: &CapacitySummary,
+    streams: &[StreamStats],
+) -> String {
+    let mut out = String::new();
+
+    gauge(
+        &mut out,
+        "cvideo_streams_running",
+        "Streams currently running",
+        &[("", capacity.total_streams as f64)],
+    );
+    gauge(
+        &mut out,
+        "cvideo_streams_max",
+        "Configured stream limit",
+        &[("", capacity.max_streams as f64)],
+    );
+    gauge(
+        &mut out,
+        "cvideo_transcodes_active",
+        "Running transcodes by encoder",
+        &[
+            ("encoder=\"cpu\"", capacity.cpu_transcoding as f64),
+            ("encoder=\"nvenc\"", capacity.nvenc_transcoding as f64),
+            ("encoder=\"qsv\"", capacity.qsv_transcoding as f64),
+            (
+                "encoder=\"videotoolbox\"",
+                capacity.videotoolbox_transcoding as f64,
+            ),
+            ("encoder=\"amd\"", capacity.amd_transcoding as f64),
+        ],
+    );
+    gauge(
+        &mut out,
+        "cvideo_transcodes_max",
+        "Configured transcode limit by encoder",
+        &[
+            ("encoder=\"cpu\"", capacity.max_cpu_transcode as f64),
+            ("encoder=\"nvenc\"", capacity.max_nvenc_transcode as f64),
+            ("encoder=\"qsv\"", capacity.max_qsv_transcode as f64),
+            ("encoder=\"videotoolbox\"", capacity.max_videotoolbox as f64),
+            ("encoder=\"amd\"", capacity.max_amd_transcode as f64),
+        ],
+    );
+    gauge(
+        &mut out,
+        "cvideo_bitrate_mbps",
+        "Estimated bitrate of running streams",
+        &[("", capacity.total_bitrate_mbps as f64)],
+    );
+    gauge(
+        &mut out,
+        "cvideo_bitrate_max_mbps",
+        "Configured bitrate limit",
+        &[("", capacity.max_bitrate_mbps as f64)],
+    );
+
+    let system = &metrics.system;
+    gauge(
+        &mut out,
+        "cvideo_cpu_percent",
+        "System CPU usage",
+        &[("", system.cpu_percent as f64)],
+    );
+    gauge(
+        &mut out,
+        "cvideo_memory_used_bytes",
+        "System memory in use",
+        &[("", mb_to_bytes(system.memory_used_mb))],
+    );
+    gauge(
+        &mut out,
+        "cvideo_memory_total_bytes",
+        "System memory installed",
+        &[("", mb_to_bytes(system.memory_total_mb))],
+    );
+    gauge(
+        &mut out,
+        "cvideo_disk_read_bytes_per_second",
+        "Disk read throughput",
+        &[("", system.disk_read_bytes_per_sec as f64)],
+    );
+    gauge(
+        &mut out,
+        "cvideo_disk_write_bytes_per_second",
+        "Disk write throughput",
+        &[("", system.disk_write_bytes_per_sec as f64)],
+    );
+    gauge(
+        &mut out,
+        "cvideo_network_rx_bytes_per_second",
+        "Network receive throughput",
+        &[("", system.network_rx_bytes_per_sec as f64)],
+    );
+    gauge(
+        &mut out,
+        "cvideo_network_tx_bytes_per_second",
+        "Network transmit throughput",
+        &[("", system.network_tx_bytes_per_sec as f64)],
+    );
+
+    let gpu = &metrics.gpu;
+    gauge(
+        &mut out,
+        "cvideo_gpu_available",
+        "Whether an NVIDIA GPU was found",
+        &[("", if gpu.available { 1.0 } else { 0.0 })],
+    );
+    gauge(
+        &mut out,
+        "cvideo_nvenc_sessions_active",
+        "Active NVENC sessions",
+        &[("", gpu.nvenc_sessions_active as f64)],
+    );
+    gauge(
+        &mut out,
+        "cvideo_nvenc_sessions_max",
+        "NVENC session limit",
+        &[("", gpu.nvenc_sessions_max as f64)],
+    );
+    // Sensors the GPU doesn't report are left out rather than exported as 0
+    if let Some(value) = gpu.utilization_percent {
+        gauge(
+            &mut out,
+            "cvideo_gpu_utilization_percent",
+            "GPU utilization",
+            &[("", value as f64)],
+        );
+    }
+    if let Some(value) = gpu.temperature_c {
+        gauge(
+            &mut out,
+            "cvideo_gpu_temperature_celsius",
+            "GPU temperature",
+            &[("", value as f64)],
+        );
+    }
+    if let Some(value) = gpu.power_watts {
+        gauge(
+            &mut out,
+            "cvideo_gpu_power_watts",
+            "GPU power draw",
+            &[("", value as f64)],
+        );
+    }
+
+    let labels: Vec<String> = streams
+        .iter()
+        .map(|s| format!("stream_id=\"{}\"", escape_label(&s.stream_id)))
+        .collect();
+    let cpu: Vec<(&str, f64)> = labels
+        .iter()
+        .zip(streams)
+        .map(|(l, s)| (l.as_str(), s.process.cpu_percent as f64))
+        .collect();
+    let memory: Vec<(&str, f64)> = labels
+        .iter()
+        .zip(streams)
+        .map(|(l, s)| (l.as_str(), mb_to_bytes(s.process.memory_mb)))
+        .collect();
+    gauge(
+        &mut out,
+        "cvideo_stream_cpu_percent",
+        "CPU usage of a stream's FFmpeg process",
+        &cpu,
+    );
+    gauge(
+        &mut out,
+        "cvideo_stream_memory_bytes",
+        "Memory of a stream's FFmpeg process",
+        &memory,
+    );
+
+    out
+}
+
+/// Append one gauge family; each sample is (label set without braces, value)
+fn gauge(out: &mut String, name: &str, help: &str, samples: &[(&str, f64)]) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    for (labels, value) in samples {
+        if labels.is_empty() {
+            let _ = writeln!(out, "{} {}", name, value);
+        } else {
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+        }
+    }
+}
+
+fn mb_to_bytes(mb: u64) -> f64 {
+    (mb * 1024 * 1024) as f64
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Serve `/metrics` on `listener`, calling `render` for every scrape
+pub async fn serve<F, Fut>(listener: TcpListener, render: F)
+where
+    F: Fn() -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = String> + Send,
+{
+    loop {
+        let (socket, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("[Metrics] Accept failed: {}", e);
+                continue;
+            }
+        };
+
+        let render = render.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(socket, render).await {
+                eprintln!("[Metrics] Request failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_connection<F, Fut>(mut socket: TcpStream, render: F) -> std::io::Result<()>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = String>,
+{
+    // Only the request line matters; read until the headers end
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    let read_headers = async {
+        while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < MAX_REQUEST_BYTES {
+            let n = socket.read(&mut chunk).await?;
+            if n == 0 {
+                break;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+        Ok::<_, std::io::Error>(())
+    };
+    tokio::time::timeout(REQUEST_TIMEOUT, read_headers)
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "request timed out"))??;
+
+    let request = String::from_utf8_lossy(&buf);
+    let mut parts = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+
+    let (status, body) = if method == "GET" && path.split('?').next() == Some("/metrics") {
+        ("200 OK", render().await)
+    } else {
+        ("404 Not Found", "Not Found\n".to_string())
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        CONTENT_TYPE,
+        body.len(),
+        body
+    );
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::gpu::GpuMetrics;
+    use crate::telemetry::system::{ProcessStats, SystemMetrics};
+
+    fn sample() -> (TelemetryMetrics, CapacitySummary, Vec<StreamStats>) {
+        let metrics = TelemetryMetrics {
+            system: SystemMetrics {
+                cpu_percent: 12.5,
+                cpu_count: 8,
+                memory_used_mb: 2,
+                memory_total_mb: 8,
+                memory_percent: 25.0,
+                disk_read_bytes_per_sec: 0,
+                disk_write_bytes_per_sec: 0,
+                network_rx_bytes_per_sec: 0,
+                network_tx_bytes_per_sec: 1500,
+            },
+            gpu: GpuMetrics::default(),
+        };
+        let capacity = CapacitySummary {
+            total_streams: 3,
+            max_streams: 50,
+            cpu_transcoding: 1,
+            max_cpu_transcode: 8,
+            nvenc_transcoding: 0,
+            max_nvenc_transcode: 6,
+            qsv_transcoding: 0,
+            max_qsv_transcode: 4,
+            videotoolbox_transcoding: 0,
+            max_videotoolbox: 4,
+            amd_transcoding: 0,
+            max_amd_transcode: 4,
+            total_bitrate_mbps: 30,
+            max_bitrate_mbps: 500,
+        };
+        let streams = vec![StreamStats {
+            stream_id: "s1".into(),
+            process: ProcessStats {
+                pid: 42,
+                cpu_percent: 150.0,
+                memory_mb: 64,
+            },
+        }];
+        (metrics, capacity, streams)
+    }
+
+    #[test]
+    fn test_render() {
+        let (metrics, capacity, streams) = sample();
+        let text = render(&metrics, &capacity, &streams);
+
+        assert!(text.contains("# TYPE cvideo_streams_running gauge\ncvideo_streams_running 3\n"));
+        assert!(text.contains("cvideo_transcodes_active{encoder=\"cpu\"} 1\n"));
+        assert!(text.contains("cvideo_network_tx_bytes_per_second 1500\n"));
+        assert!(text.contains("cvideo_stream_cpu_percent{stream_id=\"s1\"} 150\n"));
+        assert!(text.contains("cvideo_stream_memory_bytes{stream_id=\"s1\"} 67108864\n"));
+        // No GPU, so no GPU sensor series
+        assert!(!text.contains("cvideo_gpu_temperature_celsius"));
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("a\"b\\c"), "a\\\"b\\\\c");
+    }
+
+    #[tokio::test]
+    async fn test_serve_metrics() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, || async {
+            "cvideo_streams_running 1\n".to_string()
+        }));
+
+        let response = reqwest::get(format!("{}/metrics", base)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain"));
+        assert_eq!(response.text().await.unwrap(), "cvideo_streams_running 1\n");
+
+        let response = reqwest::get(format!("{}/other", base)).await.unwrap();
+        assert_eq!(response.status(), 404);
+    }
+}
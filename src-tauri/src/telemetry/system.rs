@@ -2,7 +2,8 @@
 //! Monitors CPU, memory, and process metrics
 
 use serde::{Deserialize, Serialize};
-use sysinfo::System;
+use std::time::Instant;
+use sysinfo::{Networks, Pid, ProcessRefreshKind, ProcessesToUpdate, System};
 
 /// System metrics snapshot
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,18 +13,91 @@ pub struct SystemMetrics {
     pub memory_used_mb: u64,
     pub memory_total_mb: u64,
     pub memory_percent: f32,
+    pub disk_read_bytes_per_sec: u64,
+    pub disk_write_bytes_per_sec: u64,
+    pub network_rx_bytes_per_sec: u64,
+    pub network_tx_bytes_per_sec: u64,
+}
+
+/// Cumulative I/O byte counters at a point in time
+#[derive(Debug, Clone, Copy)]
+struct IoCounters {
+    at: Instant,
+    disk_read: u64,
+    disk_written: u64,
+    net_rx: u64,
+    net_tx: u64,
+}
+
+/// Per-second rates between two counter samples, as
+/// (disk read, disk write, network rx, network tx)
+fn io_rates(prev: &IoCounters, cur: &IoCounters) -> (u64, u64, u64, u64) {
+    let secs = cur.at.duration_since(prev.at).as_secs_f64();
+    if secs <= 0.0 {
+        return (0, 0, 0, 0);
+    }
+    // Counters can go backwards when processes exit or interfaces vanish
+    let rate = |prev: u64, cur: u64| (cur.saturating_sub(prev) as f64 / secs) as u64;
+    (
+        rate(prev.disk_read, cur.disk_read),
+        rate(prev.disk_written, cur.disk_written),
+        rate(prev.net_rx, cur.net_rx),
+        rate(prev.net_tx, cur.net_tx),
+    )
+}
+
+/// Resource usage of a single process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessStats {
+    pub pid: u32,
+    /// Percent of one core, so a busy multi-threaded encoder can exceed 100
+    pub cpu_percent: f32,
+    pub memory_mb: u64,
 }
 
 /// System telemetry collector
 pub struct SystemTelemetry {
     sys: System,
+    networks: Networks,
+    last_io: Option<IoCounters>,
 }
 
 impl SystemTelemetry {
     pub fn new() -> Self {
         let mut sys = System::new_all();
         sys.refresh_all();
-        Self { sys }
+        Self {
+            sys,
+            networks: Networks::new_with_refreshed_list(),
+            last_io: None,
+        }
+    }
+
+    /// Current cumulative disk and network byte counters. sysinfo only
+    /// tracks disk I/O per process, so disk totals are summed across them.
+    fn io_counters(&mut self) -> IoCounters {
+        self.sys.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::new().with_disk_usage(),
+        );
+        let (disk_read, disk_written) = self.sys.processes().values().fold((0u64, 0u64), |acc, p| {
+            let usage = p.disk_usage();
+            (acc.0 + usage.total_read_bytes, acc.1 + usage.total_written_bytes)
+        });
+
+        self.networks.refresh_list();
+        let (net_rx, net_tx) = self.networks.list().values().fold((0u64, 0u64), |acc, n| {
+            (acc.0 + n.total_received(), acc.1 + n.total_transmitted())
+        });
+
+        IoCounters {
+            at: Instant::now(),
+            disk_read,
+            disk_written,
+            net_rx,
+            net_tx,
+        }
     }
 
     /// Refresh and collect system metrics
@@ -44,15 +118,46 @@ impl SystemTelemetry {
             0.0
         };
 
+        // Rates need a previous sample; the first collect reports zero
+        let io = self.io_counters();
+        let (disk_read, disk_write, net_rx, net_tx) = self
+            .last_io
+            .replace(io)
+            .map(|prev| io_rates(&prev, &io))
+            .unwrap_or_default();
+
         SystemMetrics {
             cpu_percent,
             cpu_count,
             memory_used_mb,
             memory_total_mb,
             memory_percent,
+            disk_read_bytes_per_sec: disk_read,
+            disk_write_bytes_per_sec: disk_write,
+            network_rx_bytes_per_sec: net_rx,
+            network_tx_bytes_per_sec: net_tx,
         }
     }
 
+    /// CPU and memory of the given processes. Pids that have exited are
+    /// left out. CPU is measured since the previous call, so the first
+    /// reading for a process is 0.
+    pub fn per_process_stats(&mut self, pids: &[u32]) -> Vec<ProcessStats> {
+        let pids: Vec<Pid> = pids.iter().map(|p| Pid::from_u32(*p)).collect();
+        self.sys.refresh_processes(ProcessesToUpdate::Some(&pids), true);
+
+        pids.iter()
+            .filter_map(|pid| {
+                let process = self.sys.process(*pid)?;
+                Some(ProcessStats {
+                    pid: pid.as_u32(),
+                    cpu_percent: process.cpu_usage(),
+                    memory_mb: process.memory() / 1024 / 1024,
+                })
+            })
+            .collect()
+    }
+
     /// Get number of FFmpeg processes running
     pub fn count_ffmpeg_processes(&mut self) -> usize {
         self.sys.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
@@ -86,4 +191,37 @@ mod tests {
         assert!(metrics.memory_total_mb > 0);
         assert!(metrics.memory_percent >= 0.0 && metrics.memory_percent <= 100.0);
     }
+
+    #[test]
+    fn test_io_rates() {
+        let start = Instant::now();
+        let prev = IoCounters {
+            at: start,
+            disk_read: 1_000,
+            disk_written: 5_000,
+            net_rx: 0,
+            net_tx: 10_000,
+        };
+        let cur = IoCounters {
+            at: start + std::time::Duration::from_secs(2),
+            disk_read: 3_000,
+            disk_written: 4_000, // a writer exited
+            net_rx: 2_000_000,
+            net_tx: 30_000,
+        };
+
+        assert_eq!(io_rates(&prev, &cur), (1_000, 0, 1_000_000, 10_000));
+        assert_eq!(io_rates(&prev, &prev), (0, 0, 0, 0));
+    }
+
+    #[test]
+    fn test_per_process_stats_skips_exited() {
+        let mut telemetry = SystemTelemetry::new();
+        let own = std::process::id();
+        let stats = telemetry.per_process_stats(&[own, u32::MAX]);
+
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].pid, own);
+        assert!(stats[0].memory_mb > 0);
+    }
 }
@@ -211,6 +211,33 @@ export function CapacityDashboard() {
             color="#8b5cf6"
           />
         )}
+        {capacity.max_qsv_transcode > 0 && (
+          <ProgressBar
+            label="QSV Transcode"
+            value={capacity.qsv_transcoding}
+            max={capacity.max_qsv_transcode}
+            icon={<ZapIcon className="w-4 h-4 text-sky-500" />}
+            color="#0ea5e9"
+          />
+        )}
+        {capacity.max_videotoolbox > 0 && (
+          <ProgressBar
+            label="VideoToolbox Transcode"
+            value={capacity.videotoolbox_transcoding}
+            max={capacity.max_videotoolbox}
+            icon={<ZapIcon className="w-4 h-4 text-pink-500" />}
+            color="#ec4899"
+          />
+        )}
+        {capacity.max_amd_transcode > 0 && (
+          <ProgressBar
+            label="AMD Transcode"
+            value={capacity.amd_transcoding}
+            max={capacity.max_amd_transcode}
+            icon={<ZapIcon className="w-4 h-4 text-red-500" />}
+            color="#ef4444"
+          />
+        )}
         <ProgressBar
           label="Bandwidth"
           value={capacity.total_bitrate_mbps}
@@ -2,6 +2,7 @@
 
 import { useState } from "react";
 import type { MediaFile, Profile } from "../types";
+import { errorMessage } from "../lib/utils";
 
 interface CreateStreamModalProps {
   mediaFile: MediaFile;
@@ -40,7 +41,7 @@ export function CreateStreamModal({
       await onCreate(name, profileId);
       onClose();
     } catch (err) {
-      setError(String(err));
+      setError(errorMessage(err));
     } finally {
       setLoading(false);
     }
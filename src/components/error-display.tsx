@@ -33,7 +33,7 @@ const ERROR_MAP: Record<string, Omit<ErrorInfo, "code">> = {
   SRT_CONNECTION_FAILED: {
     message: "Failed to connect SRT stream",
     suggestion:
-      "Check if port 8890 is open and not blocked by firewall. Ensure the network path is accessible.",
+      "Check if the SRT port (8890 by default) is open and not blocked by firewall. Ensure the network path is accessible.",
   },
   MEDIAMTX_CRASH: {
     message: "Media server stopped unexpectedly",
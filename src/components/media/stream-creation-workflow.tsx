@@ -5,6 +5,7 @@ import { ArrowRight, CheckCircle, AlertCircle, Clock, Settings, Film, Radio, Wif
 import { Card } from "../ui/card";
 import { Button } from "../ui/button";
 import type { MediaFile, Profile } from "../../types";
+import { errorMessage } from "../../lib/utils";
 
 interface StreamCreationWorkflowProps {
   selectedFiles: MediaFile[];
@@ -110,7 +111,7 @@ export function StreamCreationWorkflow({
     try {
       await onCreate(streamName, selectedProfile.id);
     } catch (err) {
-      setError(String(err));
+      setError(errorMessage(err));
       setIsCreating(false);
     }
   };
@@ -1,12 +1,14 @@
 // Merge preview component - shows summary before creating merge job
 
 import type { MediaFile, Profile, MergeCheckResult } from "../types";
+import { DEFAULT_PORTS, readerUrl, type ServerPorts } from "../lib/stream-url";
 
 interface MergePreviewProps {
   files: MediaFile[];
   checkResult: MergeCheckResult | null;
   streamName: string;
   profile?: Profile;
+  ports?: ServerPorts;
 }
 
 export function MergePreview({
@@ -14,7 +16,10 @@ export function MergePreview({
   checkResult,
   streamName,
   profile,
+  ports = DEFAULT_PORTS,
 }: MergePreviewProps) {
+  const url = readerUrl(profile?.protocol ?? "rtsp", streamName, ports);
+
   const formatDuration = (secs: number): string => {
     const hours = Math.floor(secs / 3600);
     const mins = Math.floor((secs % 3600) / 60);
@@ -183,23 +188,11 @@ export function MergePreview({
         <div className="output-preview">
           <div className="output-item">
             <span className="label">URL (Local)</span>
-            <code className="url">
-              {profile?.protocol === "srt"
-                ? `srt://localhost:8890?streamid=read:${streamName}`
-                : profile?.protocol === "rtmp"
-                ? `rtmp://localhost:1935/live/${streamName}`
-                : `rtsp://localhost:8554/${streamName}`}
-            </code>
+            <code className="url">{url}</code>
           </div>
           <div className="output-item">
             <span className="label">Playback Command</span>
-            <code className="command">
-              {profile?.protocol === "srt"
-                ? `ffplay "srt://localhost:8890?streamid=read:${streamName}"`
-                : profile?.protocol === "rtmp"
-                ? `ffplay "rtmp://localhost:1935/live/${streamName}"`
-                : `ffplay "rtsp://localhost:8554/${streamName}"`}
-            </code>
+            <code className="command">{`ffplay "${url}"`}</code>
           </div>
         </div>
       </div>
@@ -1,13 +1,17 @@
 // Stream card component - displays individual stream status with glassmorphism design
 
 import { useState } from "react";
-import type { Stream } from "../types";
+import type { LiveStreamStatus, Stream } from "../types";
 import { cn } from "@/lib/utils";
+import { DEFAULT_PORTS, readerUrl, type ServerPorts } from "@/lib/stream-url";
 import { PlayIcon, SquareStopIcon, TrashIcon, LinkIcon, ClockIcon, AlertTriangleIcon, CheckCircleIcon } from "lucide-react";
 import { Typography, StreamUrl } from "./ui/typography";
 
 interface StreamCardProps {
   stream: Stream;
+  // From batch_get_status; falls back to the stored status
+  liveStatus?: LiveStreamStatus;
+  ports?: ServerPorts;
   selected?: boolean;
   selectMode?: boolean;
   onSelect?: () => void;
@@ -38,6 +42,20 @@ function StreamStatusBadge({ status }: { status: string }) {
       border: "border-amber-500/20",
       label: "QUEUED"
     },
+    connecting: {
+      color: "text-blue-400",
+      bg: "bg-blue-500/10",
+      border: "border-blue-500/20",
+      label: "CONNECTING",
+      pulse: true
+    },
+    publishing: {
+      color: "text-green-400",
+      bg: "bg-green-500/10",
+      border: "border-green-500/20",
+      label: "LIVE",
+      pulse: true
+    },
     running: {
       color: "text-green-400",
       bg: "bg-green-500/10",
@@ -56,25 +74,16 @@ function StreamStatusBadge({ status }: { status: string }) {
   const config = statusConfig[status] || statusConfig.stopped;
 
   return (
-    <Typography.StatusText status={status === "running" ? "online" : status === "starting" ? "starting" : status === "error" ? "error" : status === "queued" ? "warning" : "offline"}>
+    <Typography.StatusText status={status === "running" || status === "publishing" ? "online" : status === "starting" || status === "connecting" ? "starting" : status === "error" ? "error" : status === "queued" ? "warning" : "offline"}>
       <span className="text-xs font-medium uppercase tracking-wider">{config.label}</span>
     </Typography.StatusText>
   );
 }
 
-// Get stream URL for display
-function getStreamUrl(stream: Stream): string {
-  if (stream.protocol === "rtsp") {
-    return `rtsp://localhost:8554/${stream.name}`;
-  }
-  if (stream.protocol === "rtmp") {
-    return `rtmp://localhost:1935/live/${stream.name}`;
-  }
-  return `srt://localhost:8890?streamid=read:${stream.name}`;
-}
-
 export function StreamCard({
   stream,
+  liveStatus,
+  ports = DEFAULT_PORTS,
   selected = false,
   selectMode = false,
   onSelect,
@@ -82,10 +91,11 @@ export function StreamCard({
   onStop,
   onDelete,
 }: StreamCardProps) {
-  const isRunning = stream.status === "running";
-  const isStarting = stream.status === "starting";
-  const isQueued = stream.status === "queued";
-  const url = getStreamUrl(stream);
+  const status = liveStatus ?? stream.status;
+  const isRunning = status === "running" || status === "publishing" || status === "connecting";
+  const isStarting = status === "starting";
+  const isQueued = status === "queued";
+  const url = readerUrl(stream.protocol, stream.name, ports);
 
   const handleClick = () => {
     if (selectMode && onSelect) {
@@ -134,7 +144,7 @@ export function StreamCard({
               {stream.name}
             </Typography.Heading>
             <div className="flex items-center gap-2">
-              <StreamStatusBadge status={stream.status} />
+              <StreamStatusBadge status={status} />
               <Typography.Text variant="caption" className="font-mono-display uppercase">
                 {stream.protocol} • {stream.mode}
               </Typography.Text>
@@ -1,22 +1,41 @@
 // API hooks for Tauri commands
 
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { useState, useCallback } from "react";
 import type {
   MediaFile,
+  RescanResult,
   Stream,
   Profile,
+  Recording,
+  StreamOutput,
+  CommandPreview,
+  StreamEventRecord,
   AppSettings,
+  ConfigImportResult,
   CapacitySummary,
+  SchedulerSnapshot,
+  HealthReport,
   TelemetryMetrics,
+  TelemetrySample,
+  StreamStats,
   BatchResult,
+  LiveStreamStatus,
   NvencCapability,
+  QsvCapability,
+  VideoToolboxCapability,
+  AmdCapability,
   StreamAuth,
   MergeCheckResult,
   MergeJob,
   CacheStats,
   CacheCleanupResult,
   NormalizePreset,
+  PrewarmPresetResult,
+  PrewarmProgress,
+  CacheVerifyReport,
+  CacheEntryInfo,
 } from "../types";
 
 // MediaMTX hooks
@@ -57,10 +76,13 @@ export function useScanner() {
   const [files, setFiles] = useState<MediaFile[]>([]);
   const [loading, setLoading] = useState(false);
 
-  const scan = useCallback(async (folderPath: string) => {
+  const scan = useCallback(async (folderPath: string, recursive = false) => {
     setLoading(true);
     try {
-      const scanned = await invoke<MediaFile[]>("scan_folder", { folderPath });
+      const scanned = await invoke<MediaFile[]>("scan_folder", {
+        folderPath,
+        recursive,
+      });
       setFiles((prev) => [...prev, ...scanned]);
       return scanned;
     } finally {
@@ -78,12 +100,42 @@ export function useScanner() {
     }
   }, []);
 
+  const rescan = useCallback(async (folderPath: string, recursive = false) => {
+    setLoading(true);
+    try {
+      const result = await invoke<RescanResult>("rescan_folder", {
+        folderPath,
+        recursive,
+      });
+      setFiles(await invoke<MediaFile[]>("get_media_files"));
+      return result;
+    } finally {
+      setLoading(false);
+    }
+  }, []);
+
+  const addUrl = useCallback(async (url: string) => {
+    const file = await invoke<MediaFile>("add_url_source", { url });
+    setFiles((prev) => (prev.some((f) => f.id === file.id) ? prev : [...prev, file]));
+    return file;
+  }, []);
+
+  const rescanFile = useCallback(async (mediaId: string) => {
+    const file = await invoke<MediaFile>("rescan_file", { mediaId });
+    setFiles((prev) => prev.map((f) => (f.id === file.id ? file : f)));
+    return file;
+  }, []);
+
   const remove = useCallback(async (id: string) => {
     await invoke("delete_media_file", { id });
     setFiles((prev) => prev.filter((f) => f.id !== id));
   }, []);
 
-  return { files, loading, scan, loadAll, remove };
+  const getThumbnail = useCallback(async (mediaId: string) => {
+    return await invoke<string | null>("get_thumbnail", { mediaId });
+  }, []);
+
+  return { files, loading, scan, rescan, rescanFile, addUrl, loadAll, remove, getThumbnail };
 }
 
 // Stream hooks
@@ -114,6 +166,23 @@ export function useStreams() {
     []
   );
 
+  const createPlaylist = useCallback(
+    async (mediaFileIds: string[], name: string, profileId: string) => {
+      const stream = await invoke<Stream>("create_playlist_stream", {
+        mediaFileIds,
+        name,
+        profileId,
+      });
+      setStreams((prev) => [...prev, stream]);
+      return stream;
+    },
+    []
+  );
+
+  const getPlaylist = useCallback(async (id: string) => {
+    return await invoke<MediaFile[]>("get_stream_playlist", { id });
+  }, []);
+
   const createBatch = useCallback(
     async (mediaFileIds: string[], name: string, profileId: string) => {
       const result = await invoke<BatchResult>("create_batch_streams", {
@@ -143,12 +212,157 @@ export function useStreams() {
     );
   }, []);
 
+  const restart = useCallback(async (id: string) => {
+    const url = await invoke<string>("restart_stream", { id });
+    setStreams((prev) =>
+      prev.map((s) => (s.id === id ? { ...s, status: "running" as const } : s))
+    );
+    return url;
+  }, []);
+
   const remove = useCallback(async (id: string) => {
     await invoke("delete_stream", { id });
     setStreams((prev) => prev.filter((s) => s.id !== id));
   }, []);
 
-  return { streams, loading, loadAll, create, createBatch, start, stop, remove };
+  const listRecordings = useCallback(async (streamId?: string) => {
+    return await invoke<Recording[]>("get_recordings", { streamId: streamId ?? null });
+  }, []);
+
+  const captureSnapshot = useCallback(async (streamName: string) => {
+    return await invoke<string>("capture_snapshot", { streamName });
+  }, []);
+
+  const getLog = useCallback(async (id: string, lines?: number) => {
+    return await invoke<string>("get_stream_log", { id, lines: lines ?? null });
+  }, []);
+
+  const getEvents = useCallback(async (streamId: string, limit?: number) => {
+    return await invoke<StreamEventRecord[]>("get_stream_events", {
+      streamId,
+      limit: limit ?? null,
+    });
+  }, []);
+
+  const listOutputs = useCallback(async (streamId: string) => {
+    return await invoke<StreamOutput[]>("get_stream_outputs", { streamId });
+  }, []);
+
+  const addOutput = useCallback(
+    async (streamId: string, protocol: string, name: string, wanOptimized: boolean) => {
+      return await invoke<StreamOutput>("add_stream_output", {
+        streamId,
+        protocol,
+        name,
+        wanOptimized,
+      });
+    },
+    []
+  );
+
+  const removeOutput = useCallback(async (id: string) => {
+    await invoke("remove_stream_output", { id });
+  }, []);
+
+  const getUrls = useCallback(async (id: string) => {
+    return await invoke<string[]>("get_stream_urls", { id });
+  }, []);
+
+  const previewCommand = useCallback(async (id: string) => {
+    return await invoke<CommandPreview>("preview_stream_command", { id });
+  }, []);
+
+  const addTag = useCallback(async (streamId: string, tag: string) => {
+    await invoke("add_stream_tag", { streamId, tag });
+    const trimmed = tag.trim();
+    setStreams((prev) =>
+      prev.map((s) =>
+        s.id === streamId && !s.tags.includes(trimmed)
+          ? { ...s, tags: [...s.tags, trimmed].sort() }
+          : s
+      )
+    );
+  }, []);
+
+  const removeTag = useCallback(async (streamId: string, tag: string) => {
+    await invoke("remove_stream_tag", { streamId, tag });
+    setStreams((prev) =>
+      prev.map((s) =>
+        s.id === streamId ? { ...s, tags: s.tags.filter((t) => t !== tag.trim()) } : s
+      )
+    );
+  }, []);
+
+  const listByTag = useCallback(async (tag: string) => {
+    return await invoke<Stream[]>("get_streams_by_tag", { tag });
+  }, []);
+
+  const schedule = useCallback(
+    async (id: string, start: string | null, stop: string | null) => {
+      const updated = await invoke<Stream>("schedule_stream", { id, start, stop });
+      setStreams((prev) => prev.map((s) => (s.id === id ? updated : s)));
+      return updated;
+    },
+    []
+  );
+
+  const setLoop = useCallback(async (id: string, enabled: boolean) => {
+    await invoke("set_stream_loop", { id, enabled });
+    setStreams((prev) =>
+      prev.map((s) => (s.id === id ? { ...s, loop_playback: enabled ? 1 : 0 } : s))
+    );
+  }, []);
+
+  const setAudioTrack = useCallback(async (id: string, index: number) => {
+    await invoke("set_stream_audio_track", { id, index });
+    setStreams((prev) =>
+      prev.map((s) => (s.id === id ? { ...s, audio_track_index: index } : s))
+    );
+  }, []);
+
+  const setSubtitles = useCallback(async (id: string, index: number | null) => {
+    await invoke("set_stream_subtitles", { id, index });
+    setStreams((prev) =>
+      prev.map((s) => (s.id === id ? { ...s, burn_subtitles: index } : s))
+    );
+  }, []);
+
+  const setExtraArgs = useCallback(async (id: string, args: string[]) => {
+    await invoke("set_stream_extra_args", { id, args });
+    const extra_args = args.length > 0 ? JSON.stringify(args) : null;
+    setStreams((prev) => prev.map((s) => (s.id === id ? { ...s, extra_args } : s)));
+  }, []);
+
+  return {
+    streams,
+    loading,
+    loadAll,
+    create,
+    createPlaylist,
+    getPlaylist,
+    createBatch,
+    start,
+    stop,
+    restart,
+    remove,
+    listRecordings,
+    captureSnapshot,
+    getLog,
+    getEvents,
+    listOutputs,
+    addOutput,
+    removeOutput,
+    getUrls,
+    previewCommand,
+    addTag,
+    removeTag,
+    listByTag,
+    schedule,
+    setLoop,
+    setAudioTrack,
+    setSubtitles,
+    setExtraArgs,
+  };
 }
 
 // Profile hooks
@@ -166,7 +380,24 @@ export function useProfiles() {
     }
   }, []);
 
-  return { profiles, loading, loadAll };
+  const create = useCallback(async (profile: Omit<Profile, "id">) => {
+    const created = await invoke<Profile>("create_profile", { profile: { ...profile, id: "" } });
+    setProfiles((prev) => [...prev, created].sort((a, b) => a.name.localeCompare(b.name)));
+    return created;
+  }, []);
+
+  const update = useCallback(async (profile: Profile) => {
+    const updated = await invoke<Profile>("update_profile", { profile });
+    setProfiles((prev) => prev.map((p) => (p.id === updated.id ? updated : p)));
+    return updated;
+  }, []);
+
+  const remove = useCallback(async (id: string) => {
+    await invoke("delete_profile", { id });
+    setProfiles((prev) => prev.filter((p) => p.id !== id));
+  }, []);
+
+  return { profiles, loading, loadAll, create, update, remove };
 }
 
 // Settings hooks
@@ -195,7 +426,20 @@ export function useSettings() {
     }
   }, []);
 
-  return { settings, loading, load, save };
+  const exportConfig = useCallback(async (path: string) => {
+    await invoke("export_config", { path });
+  }, []);
+
+  const importConfig = useCallback(
+    async (path: string) => {
+      const result = await invoke<ConfigImportResult>("import_config", { path });
+      await load();
+      return result;
+    },
+    [load]
+  );
+
+  return { settings, loading, load, save, exportConfig, importConfig };
 }
 
 // Telemetry hooks
@@ -213,7 +457,21 @@ export function useTelemetry() {
     return { metrics: m, capacity: c };
   }, []);
 
-  return { metrics, capacity, refresh };
+  const history = useCallback(async () => {
+    return await invoke<TelemetrySample[]>("get_telemetry_history");
+  }, []);
+
+  const streamStats = useCallback(async () => {
+    return await invoke<StreamStats[]>("get_stream_stats");
+  }, []);
+
+  const schedulerSnapshot = useCallback(async () => {
+    const snapshot = await invoke<SchedulerSnapshot>("get_scheduler_snapshot");
+    setCapacity(snapshot.capacity);
+    return snapshot;
+  }, []);
+
+  return { metrics, capacity, refresh, history, streamStats, schedulerSnapshot };
 }
 
 // Batch operations hooks
@@ -238,7 +496,37 @@ export function useBatchOperations() {
     }
   }, []);
 
-  return { loading, batchStart, batchStop };
+  const batchDelete = useCallback(async (streamIds: string[]) => {
+    setLoading(true);
+    try {
+      return await invoke<BatchResult>("batch_delete_streams", { streamIds });
+    } finally {
+      setLoading(false);
+    }
+  }, []);
+
+  const batchStatus = useCallback(async (streamIds: string[]) => {
+    return await invoke<Record<string, LiveStreamStatus>>("batch_get_status", { streamIds });
+  }, []);
+
+  return { loading, batchStart, batchStop, batchDelete, batchStatus };
+}
+
+// Queue hooks
+export function useQueue() {
+  const reprioritize = useCallback(async (id: string, priority: number) => {
+    await invoke("reprioritize_stream", { id, priority });
+  }, []);
+
+  const pin = useCallback(async (id: string, pinned: boolean) => {
+    await invoke("pin_stream", { id, pinned });
+  }, []);
+
+  const cancelQueued = useCallback(async (id: string) => {
+    await invoke("cancel_queued_stream", { id });
+  }, []);
+
+  return { reprioritize, pin, cancelQueued };
 }
 
 // NVENC detection hooks
@@ -260,6 +548,63 @@ export function useNvenc() {
   return { capability, loading, detect };
 }
 
+// QSV detection hooks
+export function useQsv() {
+  const [capability, setCapability] = useState<QsvCapability | null>(null);
+  const [loading, setLoading] = useState(false);
+
+  const detect = useCallback(async () => {
+    setLoading(true);
+    try {
+      const cap = await invoke<QsvCapability>("detect_qsv");
+      setCapability(cap);
+      return cap;
+    } finally {
+      setLoading(false);
+    }
+  }, []);
+
+  return { capability, loading, detect };
+}
+
+// VideoToolbox detection hooks
+export function useVideoToolbox() {
+  const [capability, setCapability] = useState<VideoToolboxCapability | null>(null);
+  const [loading, setLoading] = useState(false);
+
+  const detect = useCallback(async () => {
+    setLoading(true);
+    try {
+      const cap = await invoke<VideoToolboxCapability>("detect_videotoolbox");
+      setCapability(cap);
+      return cap;
+    } finally {
+      setLoading(false);
+    }
+  }, []);
+
+  return { capability, loading, detect };
+}
+
+// AMD detection hooks
+export function useAmd() {
+  const [capability, setCapability] = useState<AmdCapability | null>(null);
+  const [loading, setLoading] = useState(false);
+
+  const detect = useCallback(async () => {
+    setLoading(true);
+    try {
+      const cap = await invoke<AmdCapability>("detect_amd");
+      setCapability(cap);
+      return cap;
+    } finally {
+      setLoading(false);
+    }
+  }, []);
+
+  return { capability, loading, detect };
+}
+
 // Stream URL generation hooks
 export function useStreamUrl() {
   const [loading, setLoading] = useState(false);
@@ -328,6 +673,20 @@ export function useMerge() {
     []
   );
 
+  const startJob = useCallback(async (jobId: string) => {
+    await invoke("start_merge_job", { jobId });
+    setJobs((prev) =>
+      prev.map((j) => (j.id === jobId ? { ...j, status: "running" } : j))
+    );
+  }, []);
+
+  const cancelJob = useCallback(async (jobId: string) => {
+    await invoke("cancel_merge_job", { jobId });
+    setJobs((prev) =>
+      prev.map((j) => (j.id === jobId ? { ...j, status: "cancelled" } : j))
+    );
+  }, []);
+
   const getJob = useCallback(async (jobId: string) => {
     return await invoke<MergeJob | null>("get_merge_job", { jobId });
   }, []);
@@ -353,6 +712,8 @@ export function useMerge() {
     loading,
     checkFiles,
     createJob,
+    startJob,
+    cancelJob,
     getJob,
     loadJobs,
     deleteJob,
@@ -397,7 +758,72 @@ export function useCache() {
     }
   }, [loadStats]);
 
-  return { stats, loading, loadStats, clearOld, clearAll };
+  // Runs in the background; resolves once every preset has reported
+  const prewarm = useCallback(
+    async (
+      mediaFileId: string,
+      presetIds: string[],
+      onProgress?: (progress: PrewarmProgress) => void
+    ) => {
+      if (presetIds.length === 0) return [];
+      const results: PrewarmPresetResult[] = [];
+      let finish: () => void = () => {};
+      const done = new Promise<void>((resolve) => {
+        finish = resolve;
+      });
+      const unlisten = await listen<PrewarmProgress>("prewarm-progress", (event) => {
+        if (event.payload.media_file_id !== mediaFileId) return;
+        results.push(event.payload.result);
+        onProgress?.(event.payload);
+        if (event.payload.completed === event.payload.total) finish();
+      });
+      try {
+        await invoke("prewarm_cache", { mediaFileId, presetIds });
+        await done;
+      } finally {
+        unlisten();
+      }
+      await loadStats();
+      return results;
+    },
+    [loadStats]
+  );
+
+  const verify = useCallback(async () => {
+    setLoading(true);
+    try {
+      const report = await invoke<CacheVerifyReport>("verify_cache");
+      await loadStats();
+      return report;
+    } finally {
+      setLoading(false);
+    }
+  }, [loadStats]);
+
+  const listEntries = useCallback(async () => {
+    return await invoke<CacheEntryInfo[]>("get_cache_entries");
+  }, []);
+
+  const deleteEntry = useCallback(
+    async (id: string) => {
+      const result = await invoke<CacheCleanupResult>("delete_cache_entry", { id });
+      await loadStats();
+      return result;
+    },
+    [loadStats]
+  );
+
+  return {
+    stats,
+    loading,
+    loadStats,
+    clearOld,
+    clearAll,
+    prewarm,
+    verify,
+    listEntries,
+    deleteEntry,
+  };
 }
 
 // Normalize presets hook
@@ -447,18 +873,27 @@ export function useOnboarding() {
 export function useDiagnostics() {
   const [loading, setLoading] = useState(false);
 
-  const exportDiagnostics = useCallback(async (outputPath: string) => {
+  const exportDiagnostics = useCallback(async (outputPath: string, includeDatabase = false) => {
     setLoading(true);
     try {
       return await invoke<{ path: string; size_bytes: number; files_included: number }>(
         "export_diagnostics_zip",
-        { outputPath }
+        { outputPath, includeDatabase }
       );
     } finally {
       setLoading(false);
     }
   }, []);
 
+  const backupDatabase = useCallback(async (path: string) => {
+    setLoading(true);
+    try {
+      await invoke("backup_database", { path });
+    } finally {
+      setLoading(false);
+    }
+  }, []);
+
   const getSystemInfo = useCallback(async () => {
     return await invoke<{
       os_name: string;
@@ -469,5 +904,9 @@ export function useDiagnostics() {
     }>("get_system_info");
   }, []);
 
-  return { loading, exportDiagnostics, getSystemInfo };
+  const getHealth = useCallback(async () => {
+    return await invoke<HealthReport>("get_health");
+  }, []);
+
+  return { loading, exportDiagnostics, backupDatabase, getSystemInfo, getHealth };
 }
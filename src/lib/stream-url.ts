@@ -0,0 +1,36 @@
+// Reader URLs for streams served by MediaMTX
+
+import type { AppSettings, Profile } from "../types";
+
+export type ServerPorts = Pick<AppSettings, "rtsp_port" | "rtmp_port" | "srt_port">;
+
+// MediaMTX's ports when settings haven't been loaded
+export const DEFAULT_PORTS: ServerPorts = {
+  rtsp_port: 8554,
+  rtmp_port: 1935,
+  srt_port: 8890,
+};
+
+// Port a reader connects to for `protocol`
+export function protocolPort(protocol: Profile["protocol"], ports: ServerPorts): number {
+  if (protocol === "srt") return ports.srt_port;
+  if (protocol === "rtmp") return ports.rtmp_port;
+  return ports.rtsp_port;
+}
+
+// URL a player opens to read stream `name`
+export function readerUrl(
+  protocol: Profile["protocol"],
+  name: string,
+  ports: ServerPorts,
+  host = "localhost"
+): string {
+  const port = protocolPort(protocol, ports);
+  if (protocol === "srt") {
+    return `srt://${host}:${port}?streamid=read:${name}`;
+  }
+  if (protocol === "rtmp") {
+    return `rtmp://${host}:${port}/live/${name}`;
+  }
+  return `rtsp://${host}:${port}/${name}`;
+}
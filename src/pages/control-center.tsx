@@ -3,7 +3,10 @@
 import { useState, useEffect } from "react";
 import { StreamCard } from "../components/stream-card";
 import { CapacityDashboard } from "../components/capacity-dashboard";
-import { useMediaMTX, useStreams, useBatchOperations } from "../hooks/use-api";
+import { useMediaMTX, useStreams, useBatchOperations, useSettings } from "../hooks/use-api";
+import { errorMessage } from "../lib/utils";
+import { DEFAULT_PORTS, protocolPort, readerUrl } from "../lib/stream-url";
+import type { LiveStreamStatus } from "../types";
 import {
   PlayIcon,
   SquareStopIcon,
@@ -24,25 +27,40 @@ interface ControlCenterPageProps {
 export function ControlCenterPage({ onNavigate }: ControlCenterPageProps) {
   const { status: mtxStatus, loading: mtxLoading, start: startMtx, stop: stopMtx, refresh: refreshMtx } = useMediaMTX();
   const { streams, loading: streamsLoading, loadAll, start, stop, remove } = useStreams();
-  const { loading: batchLoading, batchStart, batchStop } = useBatchOperations();
+  const { loading: batchLoading, batchStart, batchStop, batchStatus } = useBatchOperations();
+  const { settings, load: loadSettings } = useSettings();
+  const ports = settings ?? DEFAULT_PORTS;
 
   const [error, setError] = useState<string | null>(null);
   const [selectedIds, setSelectedIds] = useState<Set<string>>(new Set());
   const [selectMode, setSelectMode] = useState(false);
   const [copiedEndpoint, setCopiedEndpoint] = useState<string | null>(null);
+  const [liveStatus, setLiveStatus] = useState<Record<string, LiveStreamStatus>>({});
 
   // Load data on mount
   useEffect(() => {
     refreshMtx();
     loadAll();
-  }, [refreshMtx, loadAll]);
+    loadSettings();
+  }, [refreshMtx, loadAll, loadSettings]);
+
+  // Poll live status, so a started stream shows as connecting until
+  // MediaMTX sees it publishing
+  useEffect(() => {
+    if (streams.length === 0) return;
+    const ids = streams.map((s) => s.id);
+    const poll = () => batchStatus(ids).then(setLiveStatus).catch(() => {});
+    poll();
+    const interval = setInterval(poll, 2000);
+    return () => clearInterval(interval);
+  }, [streams, batchStatus]);
 
   const handleStartMtx = async () => {
     try {
       setError(null);
       await startMtx();
     } catch (err) {
-      setError(`Failed to start MediaMTX: ${err}`);
+      setError(`Failed to start MediaMTX: ${errorMessage(err)}`);
     }
   };
 
@@ -51,7 +69,7 @@ export function ControlCenterPage({ onNavigate }: ControlCenterPageProps) {
       setError(null);
       await stopMtx();
     } catch (err) {
-      setError(`Failed to stop MediaMTX: ${err}`);
+      setError(`Failed to stop MediaMTX: ${errorMessage(err)}`);
     }
   };
 
@@ -64,7 +82,7 @@ export function ControlCenterPage({ onNavigate }: ControlCenterPageProps) {
       await start(id);
       await loadAll();
     } catch (err) {
-      setError(`Failed to start stream: ${err}`);
+      setError(`Failed to start stream: ${errorMessage(err)}`);
     }
   };
 
@@ -74,7 +92,7 @@ export function ControlCenterPage({ onNavigate }: ControlCenterPageProps) {
       await stop(id);
       await loadAll();
     } catch (err) {
-      setError(`Failed to stop stream: ${err}`);
+      setError(`Failed to stop stream: ${errorMessage(err)}`);
     }
   };
 
@@ -84,7 +102,7 @@ export function ControlCenterPage({ onNavigate }: ControlCenterPageProps) {
       setError(null);
       await remove(id);
     } catch (err) {
-      setError(`Failed to delete stream: ${err}`);
+      setError(`Failed to delete stream: ${errorMessage(err)}`);
     }
   };
 
@@ -122,7 +140,7 @@ export function ControlCenterPage({ onNavigate }: ControlCenterPageProps) {
       setSelectedIds(new Set());
       setSelectMode(false);
     } catch (err) {
-      setError(`Batch start failed: ${err}`);
+      setError(`Batch start failed: ${errorMessage(err)}`);
     }
   };
 
@@ -138,7 +156,7 @@ export function ControlCenterPage({ onNavigate }: ControlCenterPageProps) {
       setSelectedIds(new Set());
       setSelectMode(false);
     } catch (err) {
-      setError(`Batch stop failed: ${err}`);
+      setError(`Batch stop failed: ${errorMessage(err)}`);
     }
   };
 
@@ -398,6 +416,8 @@ export function ControlCenterPage({ onNavigate }: ControlCenterPageProps) {
                 <div key={stream.id} className="glass-surface rounded-xl p-4 border-0 hover:scale-[1.02] transition-all duration-300">
                   <StreamCard
                     stream={stream}
+                    liveStatus={liveStatus[stream.id]}
+                    ports={ports}
                     selected={selectMode && selectedIds.has(stream.id)}
                     selectMode={selectMode}
                     onSelect={() => toggleSelect(stream.id)}
@@ -419,26 +439,16 @@ export function ControlCenterPage({ onNavigate }: ControlCenterPageProps) {
           </h2>
 
           <div className="grid grid-cols-1 lg:grid-cols-3 gap-4">
-            {[
-              {
-                id: 'rtsp',
-                label: 'RTSP Base',
-                url: 'rtsp://localhost:8554/<stream-name>',
-                port: '8554'
-              },
-              {
-                id: 'srt',
-                label: 'SRT Base',
-                url: 'srt://localhost:8890?streamid=read:<stream-name>',
-                port: '8890'
-              },
-              {
-                id: 'rtmp',
-                label: 'RTMP Base',
-                url: 'rtmp://localhost:1935/live/<stream-name>',
-                port: '1935'
-              }
-            ].map((endpoint) => (
+            {([
+              { id: 'rtsp', label: 'RTSP Base' },
+              { id: 'srt', label: 'SRT Base' },
+              { id: 'rtmp', label: 'RTMP Base' }
+            ] as const).map(({ id, label }) => ({
+              id,
+              label,
+              url: readerUrl(id, '<stream-name>', ports),
+              port: protocolPort(id, ports)
+            })).map((endpoint) => (
               <div key={endpoint.id} className="glass-surface rounded-xl p-4 border-0 hover:scale-[1.02] transition-all duration-300">
                 <div className="flex items-center justify-between mb-2">
                   <span className="text-sm font-medium text-muted-foreground">{endpoint.label}</span>
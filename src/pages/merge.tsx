@@ -1,9 +1,10 @@
 // Merge page - wizard for merging multiple files into a single stream
 
 import { useState, useEffect } from "react";
-import { useScanner, useProfiles, useMerge } from "../hooks/use-api";
+import { useScanner, useProfiles, useMerge, useSettings } from "../hooks/use-api";
 import { MergePreview } from "../components/merge-preview";
 import type { MediaFile } from "../types";
+import { errorMessage } from "../lib/utils";
 
 interface MergePageProps {
   onNavigate: (page: string) => void;
@@ -15,6 +16,7 @@ export function MergePage({ onNavigate }: MergePageProps) {
   const { files, loadAll: loadFiles } = useScanner();
   const { profiles, loadAll: loadProfiles } = useProfiles();
   const { checkResult, loading, checkFiles, createJob } = useMerge();
+  const { settings, load: loadSettings } = useSettings();
 
   const [step, setStep] = useState<MergeStep>("select");
   const [selectedFiles, setSelectedFiles] = useState<string[]>([]);
@@ -27,12 +29,13 @@ export function MergePage({ onNavigate }: MergePageProps) {
   useEffect(() => {
     loadFiles();
     loadProfiles();
-  }, [loadFiles, loadProfiles]);
+    loadSettings();
+  }, [loadFiles, loadProfiles, loadSettings]);
 
   // Auto-check compatibility when selection changes
   useEffect(() => {
     if (selectedFiles.length >= 2) {
-      checkFiles(selectedFiles).catch((e) => setError(String(e)));
+      checkFiles(selectedFiles).catch((e) => setError(errorMessage(e)));
     }
   }, [selectedFiles, checkFiles]);
 
@@ -99,7 +102,7 @@ export function MergePage({ onNavigate }: MergePageProps) {
       setCreatedJobId(job.id);
       setStep("complete");
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     }
   };
 
@@ -443,6 +446,7 @@ export function MergePage({ onNavigate }: MergePageProps) {
             checkResult={checkResult}
             streamName={streamName}
             profile={profiles.find((p) => p.id === selectedProfile)}
+            ports={settings ?? undefined}
           />
         )}
 
@@ -3,11 +3,25 @@
 import { useState, useEffect } from "react";
 import { useSettings, useTelemetry, useNvenc } from "../hooks/use-api";
 import type { AppSettings } from "../types";
+import { errorMessage } from "../lib/utils";
 
 interface SettingsPageProps {
   onNavigate: (page: string) => void;
 }
 
+// MediaMTX listener ports shown in the settings form
+const SERVER_PORTS: { key: keyof AppSettings; label: string }[] = [
+  { key: "rtsp_port", label: "RTSP" },
+  { key: "rtmp_port", label: "RTMP" },
+  { key: "srt_port", label: "SRT" },
+  { key: "hls_port", label: "HLS" },
+  { key: "webrtc_port", label: "WebRTC" },
+  { key: "rtp_port", label: "RTP (UDP)" },
+  { key: "rtcp_port", label: "RTCP (UDP)" },
+  { key: "webrtc_udp_port", label: "WebRTC (UDP)" },
+  { key: "api_port", label: "API" },
+];
+
 export function SettingsPage({ onNavigate }: SettingsPageProps) {
   const { settings, loading, load, save } = useSettings();
   const { metrics, refresh } = useTelemetry();
@@ -20,7 +34,35 @@ export function SettingsPage({ onNavigate }: SettingsPageProps) {
     max_total_streams: 50,
     max_transcode_cpu: 8,
     max_transcode_nvenc: 6,
+    max_transcode_qsv: 4,
+    max_transcode_videotoolbox: 4,
+    max_transcode_amd: 4,
     max_total_bitrate_mbps: 500,
+    max_copy_bitrate_mbps: 500,
+    max_transcode_bitrate_mbps: 500,
+    cache_maintenance_interval_mins: 60,
+    cache_max_size_gb: 50,
+    cache_max_age_days: 30,
+    cache_eviction_policy: "lru",
+    scan_depth: 2,
+    probe_timeout_secs: 15,
+    enable_hls: false,
+    enable_webrtc: false,
+    webrtc_ice_servers: "",
+    telemetry_history_size: 300,
+    metrics_port: 0,
+    metrics_bind_address: "127.0.0.1",
+    db_max_connections: 5,
+    allow_preemption: false,
+    rtsp_port: 8554,
+    rtmp_port: 1935,
+    srt_port: 8890,
+    hls_port: 8888,
+    webrtc_port: 8889,
+    rtp_port: 8000,
+    rtcp_port: 8001,
+    webrtc_udp_port: 8189,
+    api_port: 9997,
   });
   const [dirty, setDirty] = useState(false);
   const [error, setError] = useState<string | null>(null);
@@ -41,7 +83,7 @@ export function SettingsPage({ onNavigate }: SettingsPageProps) {
     }
   }, [settings]);
 
-  const handleChange = (field: keyof AppSettings, value: number) => {
+  const handleChange = (field: keyof AppSettings, value: number | boolean | string) => {
     setForm((prev) => ({ ...prev, [field]: value }));
     setDirty(true);
     setSaved(false);
@@ -61,10 +103,71 @@ export function SettingsPage({ onNavigate }: SettingsPageProps) {
       setError("Max NVENC sessions must be between 0 and 24");
       return;
     }
+    if (form.max_transcode_qsv < 0 || form.max_transcode_qsv > 24) {
+      setError("Max QSV sessions must be between 0 and 24");
+      return;
+    }
+    if (form.max_transcode_videotoolbox < 0 || form.max_transcode_videotoolbox > 24) {
+      setError("Max VideoToolbox sessions must be between 0 and 24");
+      return;
+    }
+    if (form.max_transcode_amd < 0 || form.max_transcode_amd > 24) {
+      setError("Max AMD sessions must be between 0 and 24");
+      return;
+    }
     if (form.max_total_bitrate_mbps < 10 || form.max_total_bitrate_mbps > 10000) {
       setError("Max bitrate must be between 10 and 10000 Mbps");
       return;
     }
+    if (form.max_copy_bitrate_mbps < 10 || form.max_copy_bitrate_mbps > 10000) {
+      setError("Copy bitrate cap must be between 10 and 10000 Mbps");
+      return;
+    }
+    if (form.max_transcode_bitrate_mbps < 10 || form.max_transcode_bitrate_mbps > 10000) {
+      setError("Transcode bitrate cap must be between 10 and 10000 Mbps");
+      return;
+    }
+    if (form.cache_maintenance_interval_mins < 0 || form.cache_maintenance_interval_mins > 10080) {
+      setError("Cache cleanup interval must be between 0 and 10080 minutes");
+      return;
+    }
+    if (form.cache_max_size_gb < 1 || form.cache_max_size_gb > 10000) {
+      setError("Cache size limit must be between 1 and 10000 GB");
+      return;
+    }
+    if (form.cache_max_age_days < 1 || form.cache_max_age_days > 3650) {
+      setError("Cache max age must be between 1 and 3650 days");
+      return;
+    }
+    if (form.scan_depth < 0 || form.scan_depth > 32) {
+      setError("Scan depth must be between 0 and 32");
+      return;
+    }
+    if (form.probe_timeout_secs < 1 || form.probe_timeout_secs > 600) {
+      setError("Probe timeout must be between 1 and 600 seconds");
+      return;
+    }
+    if (form.telemetry_history_size < 0 || form.telemetry_history_size > 3600) {
+      setError("Telemetry history must be between 0 and 3600 samples");
+      return;
+    }
+    if (form.metrics_port !== 0 && (form.metrics_port < 1024 || form.metrics_port > 65535)) {
+      setError("Metrics port must be 0 (disabled) or between 1024 and 65535");
+      return;
+    }
+    if (form.db_max_connections < 1 || form.db_max_connections > 64) {
+      setError("Database connections must be between 1 and 64");
+      return;
+    }
+    const ports = SERVER_PORTS.map(({ key }) => form[key] as number);
+    if (ports.some((port) => port < 1 || port > 65535)) {
+      setError("MediaMTX ports must be between 1 and 65535");
+      return;
+    }
+    if (new Set(ports).size !== ports.length) {
+      setError("Each MediaMTX port must be different");
+      return;
+    }
 
     try {
       setError(null);
@@ -73,7 +176,7 @@ export function SettingsPage({ onNavigate }: SettingsPageProps) {
       setSaved(true);
       setTimeout(() => setSaved(false), 2000);
     } catch (err) {
-      setError(`Failed to save: ${err}`);
+      setError(`Failed to save: ${errorMessage(err)}`);
     }
   };
 
@@ -206,6 +309,60 @@ export function SettingsPage({ onNavigate }: SettingsPageProps) {
               </p>
             </div>
 
+            <div className="space-y-2">
+              <label htmlFor="max_transcode_qsv" className="text-sm font-medium text-gray-300">
+                Max QSV Sessions
+              </label>
+              <input
+                id="max_transcode_qsv"
+                type="number"
+                min="0"
+                max="24"
+                value={form.max_transcode_qsv}
+                onChange={(e) => handleChange("max_transcode_qsv", Number(e.target.value))}
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              />
+              <p className="text-xs text-gray-500">
+                Maximum Intel Quick Sync encoding sessions (0-24)
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label htmlFor="max_transcode_videotoolbox" className="text-sm font-medium text-gray-300">
+                Max VideoToolbox Sessions
+              </label>
+              <input
+                id="max_transcode_videotoolbox"
+                type="number"
+                min="0"
+                max="24"
+                value={form.max_transcode_videotoolbox}
+                onChange={(e) => handleChange("max_transcode_videotoolbox", Number(e.target.value))}
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              />
+              <p className="text-xs text-gray-500">
+                Maximum Apple VideoToolbox encoding sessions on macOS (0-24)
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label htmlFor="max_transcode_amd" className="text-sm font-medium text-gray-300">
+                Max AMD Sessions
+              </label>
+              <input
+                id="max_transcode_amd"
+                type="number"
+                min="0"
+                max="24"
+                value={form.max_transcode_amd}
+                onChange={(e) => handleChange("max_transcode_amd", Number(e.target.value))}
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              />
+              <p className="text-xs text-gray-500">
+                Maximum VAAPI (Linux) or AMF (Windows) encoding sessions (0-24)
+              </p>
+            </div>
+
             <div className="space-y-2">
               <label htmlFor="max_total_bitrate_mbps" className="text-sm font-medium text-gray-300">
                 Max Total Bitrate (Mbps)
@@ -223,6 +380,317 @@ export function SettingsPage({ onNavigate }: SettingsPageProps) {
                 Maximum combined bandwidth for all streams (10-10000 Mbps)
               </p>
             </div>
+
+            <div className="space-y-2">
+              <label htmlFor="max_copy_bitrate_mbps" className="text-sm font-medium text-gray-300">
+                Copy Bitrate Cap (Mbps)
+              </label>
+              <input
+                id="max_copy_bitrate_mbps"
+                type="number"
+                min="10"
+                max="10000"
+                value={form.max_copy_bitrate_mbps}
+                onChange={(e) => handleChange("max_copy_bitrate_mbps", Number(e.target.value))}
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              />
+              <p className="text-xs text-gray-500">
+                Bandwidth copy streams may use, within the total (10-10000 Mbps)
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label htmlFor="max_transcode_bitrate_mbps" className="text-sm font-medium text-gray-300">
+                Transcode Bitrate Cap (Mbps)
+              </label>
+              <input
+                id="max_transcode_bitrate_mbps"
+                type="number"
+                min="10"
+                max="10000"
+                value={form.max_transcode_bitrate_mbps}
+                onChange={(e) => handleChange("max_transcode_bitrate_mbps", Number(e.target.value))}
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              />
+              <p className="text-xs text-gray-500">
+                Bandwidth transcoded streams may use, within the total (10-10000 Mbps)
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label className="flex items-center gap-3 cursor-pointer group">
+                <input
+                  type="checkbox"
+                  checked={form.allow_preemption}
+                  onChange={(e) => handleChange("allow_preemption", e.target.checked)}
+                  className="w-5 h-5 text-blue-500 bg-white/10 border-white/20 rounded focus:ring-2 focus:ring-blue-500/50 focus:ring-offset-0"
+                />
+                <span className="text-white font-medium group-hover:text-blue-400 transition-colors">
+                  Allow Preemption
+                </span>
+              </label>
+              <p className="text-xs text-gray-500 ml-8">
+                When a transcode limit is full, a pinned or higher-priority start stops the lowest-priority stream using the same encoder
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label htmlFor="cache_maintenance_interval_mins" className="text-sm font-medium text-gray-300">
+                Cache Cleanup Interval (minutes)
+              </label>
+              <input
+                id="cache_maintenance_interval_mins"
+                type="number"
+                min="0"
+                max="10080"
+                value={form.cache_maintenance_interval_mins}
+                onChange={(e) => handleChange("cache_maintenance_interval_mins", Number(e.target.value))}
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              />
+              <p className="text-xs text-gray-500">
+                How often old and over-limit cache files are removed (0 disables)
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label htmlFor="cache_max_size_gb" className="text-sm font-medium text-gray-300">
+                Cache Size Limit (GB)
+              </label>
+              <input
+                id="cache_max_size_gb"
+                type="number"
+                min="1"
+                max="10000"
+                value={form.cache_max_size_gb}
+                onChange={(e) => handleChange("cache_max_size_gb", Number(e.target.value))}
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              />
+              <p className="text-xs text-gray-500">
+                Cleanup trims the normalize cache back under this size
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label htmlFor="cache_max_age_days" className="text-sm font-medium text-gray-300">
+                Cache Max Age (days)
+              </label>
+              <input
+                id="cache_max_age_days"
+                type="number"
+                min="1"
+                max="3650"
+                value={form.cache_max_age_days}
+                onChange={(e) => handleChange("cache_max_age_days", Number(e.target.value))}
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              />
+              <p className="text-xs text-gray-500">
+                Cache files older than this are removed by cleanup
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label htmlFor="cache_eviction_policy" className="text-sm font-medium text-gray-300">
+                Cache Eviction Order
+              </label>
+              <select
+                id="cache_eviction_policy"
+                value={form.cache_eviction_policy}
+                onChange={(e) => handleChange("cache_eviction_policy", e.target.value)}
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              >
+                <option value="lru">Least recently used</option>
+                <option value="largest_first">Largest first</option>
+                <option value="least_valuable">Least valuable (size × use)</option>
+              </select>
+              <p className="text-xs text-gray-500">
+                Which cache files go first when the cache is over its size limit
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label htmlFor="scan_depth" className="text-sm font-medium text-gray-300">
+                Scan Depth
+              </label>
+              <input
+                id="scan_depth"
+                type="number"
+                min="0"
+                max="32"
+                value={form.scan_depth}
+                onChange={(e) => handleChange("scan_depth", Number(e.target.value))}
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              />
+              <p className="text-xs text-gray-500">
+                Subfolder levels a scan walks unless it asks for its own depth
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label htmlFor="probe_timeout_secs" className="text-sm font-medium text-gray-300">
+                Probe Timeout (seconds)
+              </label>
+              <input
+                id="probe_timeout_secs"
+                type="number"
+                min="1"
+                max="600"
+                value={form.probe_timeout_secs}
+                onChange={(e) => handleChange("probe_timeout_secs", Number(e.target.value))}
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              />
+              <p className="text-xs text-gray-500">
+                How long reading one file's metadata may take before it's skipped
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label htmlFor="telemetry_history_size" className="text-sm font-medium text-gray-300">
+                Telemetry History (samples)
+              </label>
+              <input
+                id="telemetry_history_size"
+                type="number"
+                min="0"
+                max="3600"
+                value={form.telemetry_history_size}
+                onChange={(e) => handleChange("telemetry_history_size", Number(e.target.value))}
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              />
+              <p className="text-xs text-gray-500">
+                Samples kept for trend charts; one per second (0-3600)
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label htmlFor="metrics_port" className="text-sm font-medium text-gray-300">
+                Prometheus Metrics Port
+              </label>
+              <input
+                id="metrics_port"
+                type="number"
+                min="0"
+                max="65535"
+                value={form.metrics_port}
+                onChange={(e) => handleChange("metrics_port", Number(e.target.value))}
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              />
+              <p className="text-xs text-gray-500">
+                Serves /metrics for scraping (0 disables; applies after restarting the app)
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label htmlFor="metrics_bind_address" className="text-sm font-medium text-gray-300">
+                Metrics Bind Address
+              </label>
+              <input
+                id="metrics_bind_address"
+                type="text"
+                value={form.metrics_bind_address}
+                onChange={(e) => handleChange("metrics_bind_address", e.target.value.trim())}
+                placeholder="127.0.0.1"
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              />
+              <p className="text-xs text-gray-500">
+                Localhost by default. Use 0.0.0.0 or a LAN address to let other machines scrape it; /metrics has no authentication
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label htmlFor="db_max_connections" className="text-sm font-medium text-gray-300">
+                Database Connections
+              </label>
+              <input
+                id="db_max_connections"
+                type="number"
+                min="1"
+                max="64"
+                value={form.db_max_connections}
+                onChange={(e) => handleChange("db_max_connections", Number(e.target.value))}
+                className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+              />
+              <p className="text-xs text-gray-500">
+                SQLite connection pool size (1-64; applies after restarting the app)
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label className="flex items-center gap-3 cursor-pointer group">
+                <input
+                  type="checkbox"
+                  checked={form.enable_hls}
+                  onChange={(e) => handleChange("enable_hls", e.target.checked)}
+                  className="w-5 h-5 text-blue-500 bg-white/10 border-white/20 rounded focus:ring-2 focus:ring-blue-500/50 focus:ring-offset-0"
+                />
+                <span className="text-white font-medium group-hover:text-blue-400 transition-colors">
+                  Enable HLS Output
+                </span>
+              </label>
+              <p className="text-xs text-gray-500 ml-8">
+                Serve browser-playable HLS on port {form.hls_port} (applies when MediaMTX next starts)
+              </p>
+            </div>
+
+            <div className="space-y-2">
+              <label className="flex items-center gap-3 cursor-pointer group">
+                <input
+                  type="checkbox"
+                  checked={form.enable_webrtc}
+                  onChange={(e) => handleChange("enable_webrtc", e.target.checked)}
+                  className="w-5 h-5 text-blue-500 bg-white/10 border-white/20 rounded focus:ring-2 focus:ring-blue-500/50 focus:ring-offset-0"
+                />
+                <span className="text-white font-medium group-hover:text-blue-400 transition-colors">
+                  Enable WebRTC Output
+                </span>
+              </label>
+              <p className="text-xs text-gray-500 ml-8">
+                Serve low-latency WHEP playback on port {form.webrtc_port} (applies when MediaMTX next starts)
+              </p>
+            </div>
+
+            {form.enable_webrtc && (
+              <div className="space-y-2">
+                <label htmlFor="webrtc_ice_servers" className="text-sm font-medium text-gray-300">
+                  WebRTC ICE Servers
+                </label>
+                <textarea
+                  id="webrtc_ice_servers"
+                  rows={3}
+                  value={form.webrtc_ice_servers}
+                  onChange={(e) => handleChange("webrtc_ice_servers", e.target.value)}
+                  placeholder="stun:stun.l.google.com:19302"
+                  className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+                />
+                <p className="text-xs text-gray-500">
+                  One per line as "url" or "url username password"; leave empty for LAN-only playback
+                </p>
+              </div>
+            )}
+
+            <div className="space-y-2">
+              <span className="text-sm font-medium text-gray-300">MediaMTX Ports</span>
+              <div className="grid grid-cols-3 gap-3">
+                {SERVER_PORTS.map(({ key, label }) => (
+                  <div key={key} className="space-y-1">
+                    <label htmlFor={key} className="text-xs text-gray-400">
+                      {label}
+                    </label>
+                    <input
+                      id={key}
+                      type="number"
+                      min="1"
+                      max="65535"
+                      value={form[key] as number}
+                      onChange={(e) => handleChange(key, Number(e.target.value))}
+                      className="w-full px-4 py-3 bg-white/5 border border-white/10 rounded-lg text-white placeholder-gray-500 focus:border-blue-500/50 focus:outline-none focus:ring-2 focus:ring-blue-500/20 transition-all"
+                    />
+                  </div>
+                ))}
+              </div>
+              <p className="text-xs text-gray-500">
+                Change these to avoid a conflict or run a second instance (applies when MediaMTX next starts)
+              </p>
+            </div>
           </div>
 
           <div className="flex items-center justify-end gap-4 pt-6 border-t border-white/10 mt-6">
@@ -382,6 +850,14 @@ export function SettingsPage({ onNavigate }: SettingsPageProps) {
                           {nvencCap.hevc_nvenc ? "Supported" : "Not supported"}
                         </p>
                       </div>
+                      <div className="p-4 bg-white/5 rounded-lg border border-white/10">
+                        <p className="text-gray-400 text-sm mb-1">AV1 NVENC</p>
+                        <p className={`font-medium ${
+                          nvencCap.av1_nvenc ? "text-emerald-300" : "text-red-300"
+                        }`}>
+                          {nvencCap.av1_nvenc ? "Supported" : "Not supported"}
+                        </p>
+                      </div>
                     </div>
                   </>
                 )}
@@ -14,13 +14,62 @@ export interface MediaFile {
   height: number | null;
   duration_secs: number | null;
   bitrate: number | null;
+  fps: number | null;
+  audio_sample_rate: number | null;
+  audio_channels: number | null;
   size_bytes: number | null;
+  modified_at: number | null;
+  thumbnail_path: string | null;
+  rotation: 90 | 180 | 270 | null;
+  color_transfer: string | null;
+  color_primaries: string | null;
+  color_space: string | null;
+  pix_fmt: string | null;
+  hdr: number;
+  audio_tracks: string | null;
+  subtitle_tracks: string | null;
   compatibility: "copy" | "transcode" | "unsupported";
   scanned_at: string;
   created_at?: string;
   thumbnail_url?: string;
 }
 
+// One audio stream of a media file
+export interface AudioTrack {
+  index: number;
+  language: string | null;
+  codec: string | null;
+  channels: number | null;
+}
+
+// One subtitle track; sidecar .srt files carry their path
+export interface SubtitleTrack {
+  index: number;
+  language: string | null;
+  codec: string | null;
+  path: string | null;
+}
+
+// Incremental rescan result
+export interface RescanResult {
+  added: number;
+  updated: number;
+  removed: number;
+}
+
+// Scan progress events
+export interface ScanProgress {
+  scanned: number;
+  total_estimated: number;
+  current_path: string;
+}
+
+export interface ScanComplete {
+  folder_path: string;
+  added: number;
+  error: string | null;
+}
+
 // View modes for media library
 export type ViewMode = "grid" | "list" | "tree";
 
@@ -33,23 +82,103 @@ export interface Stream {
   name: string;
   profile_id: string | null;
   protocol: "rtsp" | "srt" | "rtmp";
-  mode: "copy" | "cpu" | "nvenc";
+  mode: "copy" | "cpu" | "nvenc" | "qsv" | "videotoolbox" | "vaapi" | "amf";
   status: "stopped" | "starting" | "queued" | "running" | "error";
   pid: number | null;
   started_at: string | null;
   error_message: string | null;
+  scheduled_start: string | null;
+  scheduled_stop: string | null;
+  loop_playback: number;
+  audio_track_index: number;
+  burn_subtitles: number | null;
+  // Extra FFmpeg flags as a JSON array of strings
+  extra_args: string | null;
+  tags: string[];
+}
+
+// Live status from get_stream_status and batch_get_status; a running FFmpeg is "connecting"
+// until MediaMTX sees it publishing
+export type LiveStreamStatus =
+  | "publishing"
+  | "connecting"
+  | "running"
+  | "pending"
+  | "queued"
+  | "starting"
+  | "stopped"
+  | "error";
+
+// Error payload every Tauri command rejects with
+export type AppErrorCode =
+  | "not_found"
+  | "invalid_input"
+  | "limit_reached"
+  | "ffmpeg_missing"
+  | "conflict"
+  | "unavailable"
+  | "database"
+  | "internal";
+
+export interface AppError {
+  code: AppErrorCode;
+  message: string;
 }
 
 export interface Profile {
   id: string;
   name: string;
   protocol: "rtsp" | "srt" | "rtmp";
-  mode: "copy" | "cpu" | "nvenc";
+  mode: "copy" | "cpu" | "nvenc" | "qsv" | "videotoolbox" | "vaapi" | "amf";
   video_bitrate: number | null;
   audio_bitrate: number | null;
   resolution: string | null;
   gop_size: number;
   wan_optimized: number;
+  codec: "h264" | "hevc" | "av1";
+  record: number;
+  record_dir: string | null;
+  srt_latency_ms: number | null;
+  srt_overhead_percent: number | null;
+  overlay_text: string | null;
+  overlay_position: "top-left" | "top-right" | "bottom-left" | "bottom-right" | null;
+  overlay_font_size: number | null;
+  tonemap: number;
+}
+
+// Extra destination fed by the same encode as its stream
+export interface StreamOutput {
+  id: string;
+  stream_id: string;
+  protocol: Stream["protocol"];
+  name: string;
+  wan_optimized: number;
+  created_at: string;
+}
+
+// FFmpeg command a stream would run
+export interface CommandPreview {
+  args: string[];
+  // Shell-quoted, with credentials masked
+  command: string;
+}
+
+// Stream lifecycle history entry
+export interface StreamEventRecord {
+  id: number;
+  stream_id: string;
+  event_type: string;
+  message: string | null;
+  created_at: string;
+}
+
+export interface Recording {
+  id: string;
+  stream_id: string | null;
+  path: string;
+  started_at: string;
+  ended_at: string | null;
+  duration_secs: number | null;
 }
 
 export interface StreamProgress {
@@ -59,8 +188,18 @@ export interface StreamProgress {
   bitrate: string;
   time: string;
   speed: string;
+  percent: number | null;
 }
 
+// Payload of the "stream-event" event
+export type StreamEvent =
+  | { type: "Started"; stream_id: string }
+  | ({ type: "Progress" } & StreamProgress)
+  | { type: "Stopped"; stream_id: string }
+  | { type: "Error"; stream_id: string; message: string }
+  | { type: "Preempted"; stream_id: string; by: string }
+  | { type: "Exited"; stream_id: string; generation: number; last_error: string | null };
+
 // Grouped media files by folder
 export interface MediaGroup {
   folder: string;
@@ -72,7 +211,52 @@ export interface AppSettings {
   max_total_streams: number;
   max_transcode_cpu: number;
   max_transcode_nvenc: number;
+  max_transcode_qsv: number;
+  max_transcode_videotoolbox: number;
+  max_transcode_amd: number;
   max_total_bitrate_mbps: number;
+  max_copy_bitrate_mbps: number;
+  max_transcode_bitrate_mbps: number;
+  cache_maintenance_interval_mins: number;
+  cache_max_size_gb: number;
+  cache_max_age_days: number;
+  cache_eviction_policy: "lru" | "largest_first" | "least_valuable";
+  scan_depth: number;
+  probe_timeout_secs: number;
+  enable_hls: boolean;
+  enable_webrtc: boolean;
+  webrtc_ice_servers: string;
+  telemetry_history_size: number;
+  metrics_port: number;
+  metrics_bind_address: string;
+  db_max_connections: number;
+  allow_preemption: boolean;
+  rtsp_port: number;
+  rtmp_port: number;
+  srt_port: number;
+  hls_port: number;
+  webrtc_port: number;
+  rtp_port: number;
+  rtcp_port: number;
+  webrtc_udp_port: number;
+  api_port: number;
+}
+
+// Result of restoring a config backup
+export interface ConfigImportResult {
+  profiles: number;
+  settings: number;
+  streams: number;
+  streams_missing_media: number;
+  // Streams skipped because their name can't be used here
+  rejected_streams: RejectedStream[];
+}
+
+// A stream a config import skipped, and why
+export interface RejectedStream {
+  id: string;
+  name: string;
+  reason: string;
 }
 
 // Capacity summary
@@ -83,22 +267,71 @@ export interface CapacitySummary {
   max_cpu_transcode: number;
   nvenc_transcoding: number;
   max_nvenc_transcode: number;
+  qsv_transcoding: number;
+  max_qsv_transcode: number;
+  videotoolbox_transcoding: number;
+  max_videotoolbox: number;
+  amd_transcoding: number;
+  max_amd_transcode: number;
   total_bitrate_mbps: number;
   max_bitrate_mbps: number;
 }
 
+// Stream waiting for a scheduler slot
+export interface QueuedStream {
+  stream_id: string;
+  priority: number;
+  pinned: boolean;
+  mode: string;
+  queued_at: string;
+}
+
+// Stream holding a scheduler slot
+export interface RunningStream {
+  id: string;
+  state: "pending" | "queued" | "starting" | "running" | "stopped" | "error";
+  pid: number | null;
+}
+
+// Full scheduler view for dashboard refreshes
+export interface SchedulerSnapshot {
+  capacity: CapacitySummary;
+  queued: QueuedStream[];
+  running: RunningStream[];
+  // Rough wait in seconds per queued stream id
+  eta_secs: Record<string, number>;
+}
+
 // Telemetry metrics
 export interface TelemetryMetrics {
   system: SystemMetrics;
   gpu: GpuMetrics;
 }
 
+// Telemetry snapshot with its capture time (unix ms)
+export interface TelemetrySample {
+  timestamp: number;
+  metrics: TelemetryMetrics;
+}
+
+// CPU and memory of one running stream's FFmpeg process
+export interface StreamStats {
+  stream_id: string;
+  pid: number;
+  cpu_percent: number;
+  memory_mb: number;
+}
+
 export interface SystemMetrics {
   cpu_percent: number;
   cpu_count: number;
   memory_used_mb: number;
   memory_total_mb: number;
   memory_percent: number;
+  disk_read_bytes_per_sec: number;
+  disk_write_bytes_per_sec: number;
+  network_rx_bytes_per_sec: number;
+  network_tx_bytes_per_sec: number;
 }
 
 export interface GpuMetrics {
@@ -110,6 +343,8 @@ export interface GpuMetrics {
   encoder_utilization_percent: number | null;
   nvenc_sessions_active: number;
   nvenc_sessions_max: number;
+  temperature_c: number | null;
+  power_watts: number | null;
 }
 
 // Batch result
@@ -118,13 +353,44 @@ export interface BatchResult {
   failed: [string, string][];
 }
 
+// NVIDIA GPU reported by nvidia-smi
+export interface GpuInfo {
+  index: number;
+  name: string;
+}
+
 // NVENC capability
 export interface NvencCapability {
   available: boolean;
   max_sessions: number;
   h264_nvenc: boolean;
   hevc_nvenc: boolean;
+  av1_nvenc: boolean;
   gpu_name: string | null;
+  gpus: GpuInfo[];
+}
+
+// Intel QSV capability
+export interface QsvCapability {
+  available: boolean;
+  h264_qsv: boolean;
+  hevc_qsv: boolean;
+}
+
+// Apple VideoToolbox capability
+export interface VideoToolboxCapability {
+  available: boolean;
+  h264_videotoolbox: boolean;
+  hevc_videotoolbox: boolean;
+}
+
+// AMD capability (VAAPI on Linux, AMF on Windows)
+export interface AmdCapability {
+  available: boolean;
+  h264_vaapi: boolean;
+  hevc_vaapi: boolean;
+  h264_amf: boolean;
+  hevc_amf: boolean;
 }
 
 // Stream authentication
@@ -136,9 +402,23 @@ export interface StreamAuth {
 }
 
 // Merge types
+export interface CompatIssue {
+  field:
+    | "video_codec"
+    | "audio_codec"
+    | "resolution"
+    | "fps"
+    | "sample_rate"
+    | "channels";
+  file_index: number;
+  expected: string;
+  actual: string;
+}
+
 export interface MergeCheckResult {
   strategy: "empty" | "concat_copy" | "transcode_normalize";
   issues: string[];
+  detailed_issues: CompatIssue[];
   total_duration_secs: number;
   file_count: number;
 }
@@ -148,11 +428,19 @@ export interface MergeJob {
   stream_id: string | null;
   strategy: string;
   file_ids: string;
-  status: "pending" | "running" | "completed" | "failed";
+  status: "pending" | "running" | "completed" | "failed" | "cancelled";
   progress: number | null;
   error_message: string | null;
   created_at: string;
   completed_at: string | null;
+  output_path: string | null;
+}
+
+export interface MergeProgress {
+  job_id: string;
+  status: MergeJob["status"];
+  progress: number;
+  error: string | null;
 }
 
 // Cache types
@@ -162,6 +450,9 @@ export interface CacheStats {
   max_size_bytes: number;
   usage_percent: number;
   warning: boolean;
+  hit_count: number;
+  miss_count: number;
+  hit_ratio: number;
 }
 
 export interface CacheCleanupResult {
@@ -169,6 +460,43 @@ export interface CacheCleanupResult {
   files_removed: number;
 }
 
+export interface CacheEntryInfo {
+  id: string;
+  source_file_id: string;
+  filename: string | null;
+  preset_label: string;
+  cache_path: string;
+  size_bytes: number;
+  created_at: string;
+  last_accessed: string;
+  access_count: number;
+  in_use: boolean;
+}
+
+export interface CacheVerifyReport {
+  checked: number;
+  valid: number;
+  removed: number;
+  skipped: number;
+  stale: number;
+  freed_bytes: number;
+}
+
+export interface PrewarmPresetResult {
+  preset_id: string;
+  cache_key: string;
+  status: "cached" | "already_cached" | "failed";
+  error: string | null;
+}
+
+// Payload of the "prewarm-progress" event, one per finished preset
+export interface PrewarmProgress {
+  media_file_id: string;
+  completed: number;
+  total: number;
+  result: PrewarmPresetResult;
+}
+
 export interface NormalizePreset {
   id: string;
   name: string;
@@ -186,6 +514,19 @@ export interface SystemInfo {
   app_version: string;
 }
 
+// Subsystem status for status pages
+export interface HealthReport {
+  mediamtx_running: boolean;
+  running_streams: number;
+  max_streams: number;
+  queued_streams: number;
+  gpu_available: boolean;
+  // null when cache stats couldn't be read
+  cache_warning: boolean | null;
+  database_ok: boolean;
+  healthy: boolean;
+}
+
 export interface DiagnosticsResult {
   path: string;
   size_bytes: number;